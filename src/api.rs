@@ -0,0 +1,971 @@
+//! Fetching posts from a live Ghost instance's Admin or Content API.
+
+use crate::data_model::{
+    self, choose_content, description_or_excerpt, embedded_urls, internal_image,
+    internal_link_regex, non_empty, rename_image_urls, site_link_regex, strip_site_links,
+    AuthorExtra, ContentFormats, ContentSource, Extra, LinkRewriter, Post, PostId, PostLinks,
+    Social, Status, TagExtra, Taxonomies,
+};
+use crate::external::ExternalImages;
+use crate::extract::{
+    claim_output, ensure_indices, output_path, write_authors, write_post, write_tags, Bundles,
+    Report,
+};
+use crate::manifest::{extracted_images, Manifest};
+use crate::progress::Phase;
+use crate::summary::Summary;
+use crate::transcode::transcode_images;
+use crate::{Error, ExtractOptions, ImageSource, KeyMapping};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use path_absolutize::Absolutize;
+use serde::{de::DeserializeOwned, Deserialize};
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const ADMIN_API: &str = "ghost/api/admin";
+const CONTENT_API: &str = "ghost/api/content";
+const ACCEPT_VERSION: &str = "v5.0";
+const PAGE_SIZE: &str = "100";
+
+fn base64url(data: impl AsRef<[u8]>) -> String {
+    use base64::Engine;
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(data)
+}
+
+#[derive(Debug, Deserialize)]
+struct Settings {
+    /// the admin api lists settings as `{key, value}` objects; the content api as a single object
+    settings: serde_json::Value,
+}
+
+impl Settings {
+    fn get(&self, key: &str) -> Option<&str> {
+        match &self.settings {
+            serde_json::Value::Array(settings) => settings
+                .iter()
+                .find(|setting| setting["key"] == key)
+                .and_then(|setting| setting["value"].as_str()),
+            settings => settings[key].as_str(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Pagination {
+    next: Option<u32>,
+    total: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Meta {
+    pagination: Pagination,
+}
+
+#[derive(Debug, Deserialize)]
+struct Tag {
+    id: String,
+    name: String,
+    slug: String,
+    description: Option<String>,
+    feature_image: Option<String>,
+}
+
+impl Tag {
+    fn to_tag(&self) -> data_model::Tag {
+        data_model::Tag {
+            name: self.name.clone(),
+            description: non_empty(self.description.clone()),
+            slug: self.slug.clone(),
+            extra: TagExtra {
+                id: PostId::Text(self.id.clone()),
+                feature_image: non_empty(self.feature_image.clone()),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Author {
+    id: String,
+    name: String,
+    slug: String,
+    bio: Option<String>,
+    website: Option<String>,
+    location: Option<String>,
+    twitter: Option<String>,
+    facebook: Option<String>,
+    profile_image: Option<String>,
+    cover_image: Option<String>,
+}
+
+impl Author {
+    fn to_author(&self) -> data_model::Author {
+        data_model::Author {
+            name: self.name.clone(),
+            bio: non_empty(self.bio.clone()),
+            slug: self.slug.clone(),
+            extra: AuthorExtra {
+                id: PostId::Text(self.id.clone()),
+                website: non_empty(self.website.clone()),
+                location: non_empty(self.location.clone()),
+                twitter: non_empty(self.twitter.clone()),
+                facebook: non_empty(self.facebook.clone()),
+                profile_image: non_empty(self.profile_image.clone()),
+                cover_image: non_empty(self.cover_image.clone()),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiPost {
+    id: String,
+    uuid: Option<String>,
+    title: Option<String>,
+    slug: String,
+    /// only the admin api serves editor documents
+    mobiledoc: Option<String>,
+    lexical: Option<String>,
+    plaintext: Option<String>,
+    html: Option<String>,
+    /// the content api only serves published posts, and omits their status
+    #[serde(default = "published")]
+    status: String,
+    meta_description: Option<String>,
+    custom_excerpt: Option<String>,
+    published_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+    created_at: Option<DateTime<Utc>>,
+    feature_image: Option<String>,
+    #[serde(default)]
+    featured: bool,
+    visibility: Option<String>,
+    canonical_url: Option<String>,
+    codeinjection_head: Option<String>,
+    codeinjection_foot: Option<String>,
+    og_image: Option<String>,
+    og_title: Option<String>,
+    og_description: Option<String>,
+    twitter_image: Option<String>,
+    twitter_title: Option<String>,
+    twitter_description: Option<String>,
+    #[serde(default)]
+    tags: Vec<Tag>,
+    #[serde(default)]
+    authors: Vec<Author>,
+}
+
+fn published() -> String {
+    "published".into()
+}
+
+#[derive(Debug, Deserialize)]
+struct PostsPage {
+    #[serde(alias = "pages")]
+    posts: Vec<ApiPost>,
+    meta: Meta,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagsPage {
+    tags: Vec<Tag>,
+    meta: Meta,
+}
+
+#[derive(Debug, Deserialize)]
+struct UsersPage {
+    #[serde(alias = "authors")]
+    users: Vec<Author>,
+    meta: Meta,
+}
+
+impl ApiPost {
+    /// convert this into a post, in the same way as posts read from a database
+    ///
+    /// Internal links are not yet rewritten.
+    fn into_post(self, language: &str, options: &ExtractOptions) -> Post {
+        let id = PostId::from(self.id);
+        // neither api has a markdown format
+        let (content, source) = choose_content(
+            &id,
+            &self.slug,
+            ContentFormats {
+                markdown: None,
+                mobiledoc: self.mobiledoc,
+                lexical: self.lexical,
+                plaintext: self.plaintext,
+                html: self.html,
+            },
+            options.raw_html,
+            &options.shortcodes,
+        );
+        let excerpt = non_empty(self.custom_excerpt);
+        Post {
+            title: self.title.unwrap_or_default(),
+            content,
+            description: description_or_excerpt(self.meta_description, excerpt.as_deref()),
+            date: self.published_at,
+            updated: self.updated_at,
+            aliases: Vec::new(),
+            created: self.created_at,
+            // set by the caller, which knows from which endpoint the post came
+            page: false,
+            keys: KeyMapping::default(),
+            status: Status::from_str(&self.status).expect("Status::from_str is infallible"),
+            slug: self.slug,
+            extra: Extra {
+                id,
+                uuid: non_empty(self.uuid),
+                language: language.into(),
+                author_name: self
+                    .authors
+                    .first()
+                    .map(|author| author.name.clone())
+                    .unwrap_or_default(),
+                authors: self.authors.into_iter().map(|author| author.name).collect(),
+                feature_image: non_empty(self.feature_image),
+                featured: self.featured,
+                visibility: non_empty(self.visibility),
+                excerpt,
+                canonical_url: non_empty(self.canonical_url),
+                codeinjection_head: non_empty(self.codeinjection_head),
+                codeinjection_foot: non_empty(self.codeinjection_foot),
+                // computed later, if at all
+                words: None,
+                reading_time: None,
+                plaintext_fallback: source == ContentSource::Plaintext,
+                raw_html: source == ContentSource::Html,
+                // separated from the tags later, if at all
+                internal_tags: Vec::new(),
+                custom: BTreeMap::new(),
+                social: Social {
+                    og_image: non_empty(self.og_image),
+                    og_title: non_empty(self.og_title),
+                    og_description: non_empty(self.og_description),
+                    twitter_image: non_empty(self.twitter_image),
+                    twitter_title: non_empty(self.twitter_title),
+                    twitter_description: non_empty(self.twitter_description),
+                },
+            },
+            taxonomies: Taxonomies {
+                tags: self.tags.into_iter().map(|tag| tag.name).collect(),
+                ..Taxonomies::default()
+            },
+        }
+    }
+}
+
+enum Auth {
+    Admin { key_id: String, secret: Vec<u8> },
+    Content { key: String },
+}
+
+/// A client for the Admin or Content API of a live Ghost instance.
+pub struct GhostApi {
+    url: String,
+    auth: Auth,
+    agent: ureq::Agent,
+}
+
+impl GhostApi {
+    /// construct a client for the Admin API of the Ghost instance at `url`
+    ///
+    /// `admin_key` is an Admin API key of the form `id:secret`, as shown on the integrations
+    /// page of Ghost's admin interface.
+    pub fn admin(url: &str, admin_key: &str) -> Result<GhostApi, Error> {
+        let mut parts = admin_key.splitn(2, ':');
+        let (key_id, secret) = match (parts.next(), parts.next()) {
+            (Some(key_id), Some(secret)) => (key_id, secret),
+            _ => return Err(Error::InvalidAdminKey),
+        };
+        let secret = hex::decode(secret).map_err(|_| Error::InvalidAdminKey)?;
+        Ok(GhostApi::new(
+            url,
+            Auth::Admin {
+                key_id: key_id.into(),
+                secret,
+            },
+        ))
+    }
+
+    /// construct a client for the Content API of the Ghost instance at `url`
+    ///
+    /// The Content API is read-only, and serves only published posts.
+    pub fn content(url: &str, content_key: &str) -> GhostApi {
+        GhostApi::new(
+            url,
+            Auth::Content {
+                key: content_key.into(),
+            },
+        )
+    }
+
+    fn new(url: &str, auth: Auth) -> GhostApi {
+        GhostApi {
+            url: url.trim_end_matches('/').into(),
+            auth,
+            agent: ureq::Agent::new(),
+        }
+    }
+
+    /// generate a short-lived token authenticating an admin request
+    ///
+    /// See <https://ghost.org/docs/admin-api/#token-authentication>.
+    fn admin_token(key_id: &str, secret: &[u8]) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system clock is after the epoch")
+            .as_secs();
+        let header = serde_json::json!({ "alg": "HS256", "typ": "JWT", "kid": key_id });
+        let claims = serde_json::json!({ "iat": now, "exp": now + 5 * 60, "aud": "/admin/" });
+        let unsigned = format!(
+            "{}.{}",
+            base64url(header.to_string()),
+            base64url(claims.to_string())
+        );
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(secret).expect("hmac accepts keys of any length");
+        mac.update(unsigned.as_bytes());
+        format!("{}.{}", unsigned, base64url(mac.finalize().into_bytes()))
+    }
+
+    fn get<T: DeserializeOwned>(&self, endpoint: &str, query: &[(&str, &str)]) -> Result<T, Error> {
+        let mut request = match &self.auth {
+            Auth::Admin { key_id, secret } => self
+                .agent
+                .get(&format!("{}/{}/{}/", self.url, ADMIN_API, endpoint))
+                .set(
+                    "Authorization",
+                    &format!("Ghost {}", GhostApi::admin_token(key_id, secret)),
+                ),
+            Auth::Content { key } => self
+                .agent
+                .get(&format!("{}/{}/{}/", self.url, CONTENT_API, endpoint))
+                .query("key", key),
+        }
+        .set("Accept-Version", ACCEPT_VERSION);
+        for (param, value) in query {
+            request = request.query(param, value);
+        }
+        Ok(request.call().map_err(Box::new)?.into_json()?)
+    }
+
+    fn settings(&self) -> Result<Settings, Error> {
+        self.get("settings", &[])
+    }
+
+    /// fetch a page of either `posts` or `pages`
+    fn posts_page(&self, resource: &str, page: u32) -> Result<PostsPage, Error> {
+        self.get(
+            resource,
+            &[
+                ("limit", PAGE_SIZE),
+                ("page", &page.to_string()),
+                (
+                    "formats",
+                    match self.auth {
+                        Auth::Admin { .. } => "mobiledoc,lexical,plaintext,html",
+                        Auth::Content { .. } => "plaintext,html",
+                    },
+                ),
+                ("include", "tags,authors"),
+                ("order", "published_at asc"),
+            ],
+        )
+    }
+
+    /// fetch a page of tags
+    fn tags_page(&self, page: u32) -> Result<TagsPage, Error> {
+        self.get("tags", &[("limit", PAGE_SIZE), ("page", &page.to_string())])
+    }
+
+    /// fetch a page of users: `users` from the admin api, or `authors` from the content api
+    fn users_page(&self, page: u32) -> Result<UsersPage, Error> {
+        let resource = match self.auth {
+            Auth::Admin { .. } => "users",
+            Auth::Content { .. } => "authors",
+        };
+        self.get(
+            resource,
+            &[("limit", PAGE_SIZE), ("page", &page.to_string())],
+        )
+    }
+
+    /// fetch every tag, keyed by id
+    fn tags(&self) -> Result<BTreeMap<String, data_model::Tag>, Error> {
+        let mut tags = BTreeMap::new();
+        let mut page = Some(1);
+        while let Some(current) = page {
+            let tags_page = self.tags_page(current)?;
+            for tag in tags_page.tags {
+                tags.insert(tag.id.clone(), tag.to_tag());
+            }
+            page = tags_page.meta.pagination.next;
+        }
+        log::info!("fetched {} tags", tags.len());
+        Ok(tags)
+    }
+
+    /// fetch every user, keyed by id
+    fn users(&self) -> Result<BTreeMap<String, data_model::Author>, Error> {
+        let mut users = BTreeMap::new();
+        let mut page = Some(1);
+        while let Some(current) = page {
+            let users_page = self.users_page(current)?;
+            for user in users_page.users {
+                users.insert(user.id.clone(), user.to_author());
+            }
+            page = users_page.meta.pagination.next;
+        }
+        log::info!("fetched {} users", users.len());
+        Ok(users)
+    }
+
+    /// download an upload, served from the site-relative `url`, into the extract path
+    ///
+    /// Returns the path of the upload, if it was downloaded. Each path is downloaded once, though
+    /// several urls, i.e. of Ghost's responsive variants, may lead to it; `seen` holds those
+    /// downloaded so far.
+    fn download_image(
+        &self,
+        url: &str,
+        extract_path: &Path,
+        options: &ExtractOptions,
+        seen: &mut HashSet<PathBuf>,
+    ) -> Result<Option<PathBuf>, Error> {
+        let sources = ImageSource::uploads();
+        let (source, relative_path) = match internal_image(url, &sources) {
+            Some(image) => image,
+            None => return Ok(None),
+        };
+        let extract_to = extract_path.join(relative_path).absolutize()?.to_path_buf();
+        if !extract_to.starts_with(extract_path) {
+            log::warn!(
+                "image link attempted to extract past extraction root: {}",
+                relative_path
+            );
+            return Ok(None);
+        }
+        if !seen.insert(extract_to.clone())
+            || !claim_output(&extract_to, options.conflict_policy())?
+        {
+            return Ok(None);
+        }
+        // fetch the original, rather than any responsive variant
+        let url = format!("{}{}/{}", self.url, source.url_prefix, relative_path);
+        let response = match self.agent.get(&url).call() {
+            Ok(response) => response,
+            Err(err) => {
+                log::warn!("failed to download {}: {}", url, err);
+                return Ok(None);
+            }
+        };
+        if let Some(parent) = extract_to.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::File::create(&extract_to)?;
+        std::io::copy(&mut response.into_reader(), &mut file)?;
+        log::trace!("downloaded image: {}", extract_to.display());
+        Ok(Some(extract_to))
+    }
+}
+
+/// Extract the posts of a live Ghost instance into a destination folder, via its Admin API.
+///
+/// Posts are converted exactly as by [`extract_archive`][crate::extract_archive], except:
+///
+/// - `extra.language` is the site's locale setting, as the API has no per-post language
+/// - the API has no markdown format, so each post's content is rendered from its mobiledoc or
+///   lexical document, falling back to its plaintext rendering; only the Content API, which
+///   serves no editor documents, is limited to the plaintext and html renderings
+/// - uploads linked from `/content/images`, `/content/files` and `/content/media`, including
+///   feature images, are downloaded from the instance rather than extracted from an archive
+///
+/// With [`ExtractOptions::extract_content`] unset, posts are fetched only to find the images to
+/// download; none are written.
+///
+/// Options which relate only to archives or databases, such as [`ExtractOptions::sql_filter`],
+/// are ignored.
+pub fn extract_admin_api<EP>(
+    url: &str,
+    admin_key: &str,
+    extract_path: EP,
+    options: &ExtractOptions,
+) -> Result<usize, Error>
+where
+    EP: AsRef<Path>,
+{
+    extract_api(&GhostApi::admin(url, admin_key)?, extract_path, options)
+}
+
+/// Extract the published posts of a live Ghost instance into a destination folder, via its Content API.
+///
+/// This is as [`extract_admin_api`], but requires only a read-only Content API key. As the Content API
+/// serves only published posts, no drafts are extracted; likewise, only public tags, and the
+/// authors of published posts, are fetched.
+pub fn extract_content_api<EP>(
+    url: &str,
+    content_key: &str,
+    extract_path: EP,
+    options: &ExtractOptions,
+) -> Result<usize, Error>
+where
+    EP: AsRef<Path>,
+{
+    extract_api(&GhostApi::content(url, content_key), extract_path, options)
+}
+
+/// note an image served from the blog's uploads, by the url of its original, to be downloaded
+///
+/// Urls served from elsewhere are ignored.
+fn note_image(url: &str, images: &mut HashSet<String>) {
+    if let Some((source, path)) = internal_image(url, &ImageSource::uploads()) {
+        images.insert(format!("{}/{}", source.url_prefix, path));
+    }
+}
+
+/// make an image url served by the api relative, noting the image if it must be downloaded
+fn localize_image(url: &mut String, api: &GhostApi, images: &mut HashSet<String>) {
+    // the api serves absolute urls
+    if let Some(relative) = url.strip_prefix(&api.url) {
+        *url = relative.to_string();
+    }
+    note_image(url, images);
+}
+
+fn extract_api<EP>(
+    api: &GhostApi,
+    extract_path: EP,
+    options: &ExtractOptions,
+) -> Result<usize, Error>
+where
+    EP: AsRef<Path>,
+{
+    let extract_path = extract_path.as_ref().canonicalize()?;
+
+    let settings = api.settings()?;
+    if let Some(title) = settings.get("title") {
+        log::info!("fetching posts from {}", title);
+    }
+    let language = settings.get("locale").unwrap_or_default().to_string();
+
+    // the api serves absolute urls
+    let image_links = LinkRewriter::from_options(options).site_url(Some(api.url.clone()));
+    let image_link_re = internal_link_regex(&ImageSource::uploads());
+    let site_link_re = site_link_regex(Some(&api.url));
+    let mut images = HashSet::new();
+    let mut external = ExternalImages::from_options(&extract_path, options);
+    let mut authors = api.users()?;
+    let mut tags = api.tags()?;
+    let mut report = Report::default();
+    let mut manifest = Manifest::default();
+    let mut posts = Vec::new();
+    // ghost serves pages separately from posts
+    for (resource, is_page) in [("posts", false), ("pages", true)] {
+        if !options.only.includes(is_page) {
+            continue;
+        }
+        let mut page = Some(1);
+        while let Some(current) = page {
+            let posts_page = api.posts_page(resource, current)?;
+            log::info!(
+                "fetched page {} of {} ({} total)",
+                current,
+                resource,
+                posts_page.meta.pagination.total.unwrap_or_default()
+            );
+            for api_post in posts_page.posts {
+                for author in &api_post.authors {
+                    authors
+                        .entry(author.id.clone())
+                        .or_insert_with(|| author.to_author());
+                }
+                // the content api lists only public tags, though posts may have internal ones
+                for tag in &api_post.tags {
+                    tags.entry(tag.id.clone()).or_insert_with(|| tag.to_tag());
+                }
+                let mut post = api_post.into_post(&language, options);
+                post.page = is_page;
+                report.process(&mut post, options)?;
+                if report.skip(&post, options) {
+                    continue;
+                }
+                manifest.ghost_url(&post, options);
+                report.claim_path(&mut post, options);
+                post.content = strip_site_links(&post.content, &site_link_re, Some(&api.url));
+                for link in image_link_re.find_iter(&post.content) {
+                    // skip the leading `](`, and stop before any title or the closing `)`
+                    let url = link.as_str()[2..]
+                        .split(|c: char| c == ')' || c.is_whitespace())
+                        .next()
+                        .unwrap_or_default();
+                    note_image(url, &mut images);
+                }
+                for url in embedded_urls(&post.content) {
+                    note_image(&url, &mut images);
+                }
+                for url in post.image_urls_mut() {
+                    localize_image(url, api, &mut images);
+                }
+                post.rewrite_links(&image_links);
+                // only once the site's own images are relative can the rest be told apart
+                if let Some(external) = &mut external {
+                    external.localize(&mut post)?;
+                }
+                posts.push(post);
+            }
+            page = posts_page.meta.pagination.next;
+        }
+    }
+
+    let mut authors: Vec<_> = authors.into_values().collect();
+    if options.authors_dir.is_some() {
+        for author in &mut authors {
+            for url in author.image_urls_mut() {
+                localize_image(url, api, &mut images);
+            }
+            author.rewrite_links(&image_links);
+        }
+    }
+    let mut tags: Vec<_> = tags.into_values().collect();
+    if options.tags_dir.is_some() {
+        for tag in &mut tags {
+            for url in tag.image_urls_mut() {
+                localize_image(url, api, &mut images);
+            }
+            tag.rewrite_links(&image_links);
+        }
+    }
+
+    // images are downloaded before anything is written, so that any conversions are known
+    let mut downloaded = Vec::new();
+    let mut ghost_urls = HashMap::new();
+    let images_path = options.images_path(&extract_path)?;
+    if options.extract_images {
+        let mut seen = HashSet::new();
+        for image in &images {
+            if let Some(path) = api.download_image(image, &images_path, options, &mut seen)? {
+                ghost_urls.insert(path.clone(), image.clone());
+                if let Some(progress) = &options.progress {
+                    progress.image_extracted(&path);
+                }
+                downloaded.push(path);
+                if let Some(on_progress) = &options.on_progress {
+                    on_progress.call(Phase::Images, downloaded.len(), None);
+                }
+            }
+        }
+        if let Some(on_progress) = &options.on_progress {
+            on_progress.done(Phase::Images, downloaded.len());
+        }
+        log::info!("downloaded {} images", downloaded.len());
+    }
+    if let Some(external) = &external {
+        log::info!("downloaded {} external images", external.len());
+    }
+    let images_url = options.images_url();
+    let renames = match options.image_format {
+        Some(format) => {
+            let renames = transcode_images(
+                &downloaded,
+                &images_path,
+                format,
+                options.keep_original_images,
+                options.conflict_policy(),
+            )?;
+            log::info!("converted {} images", renames.len());
+            renames
+        }
+        None => HashMap::new(),
+    };
+
+    let summary = |report: &Report| Summary {
+        images: downloaded.len(),
+        external_images: external.as_ref().map_or(0, ExternalImages::len),
+        converted_images: renames.len(),
+        ..report.summary(options)
+    };
+
+    if !options.extract_content {
+        summary(&report).write(options)?;
+        return Ok(0);
+    }
+
+    // links between posts can only be resolved once every post's path is known
+    let mut links = PostLinks::new(options.target);
+    if options.link_posts {
+        for post in &posts {
+            links.insert(post.slug.clone(), &output_path(post, options));
+        }
+    }
+    let n_posts = posts.len();
+    let mut bundles = Bundles::default();
+    let mut written = Vec::with_capacity(n_posts);
+    report.read_previous(&extract_path, options)?;
+    for (idx, mut post) in posts.into_iter().enumerate() {
+        post.rename_images(&images_url, &renames);
+        report.check_images(&mut post, &images_path, options);
+        post.link_posts(&links);
+        report.check_alt_text(&post, options);
+        bundles.bundle(&mut post, options);
+        options.transforms.apply(&mut post);
+        if report.changed(&post, options) {
+            write_post(&post, &extract_path, options)?;
+        }
+        manifest.post(&post, options);
+        written.push(output_path(&post, options));
+        if let Some(progress) = &options.progress {
+            progress.post_written(idx, &output_path(&post, options));
+        }
+        if let Some(on_progress) = &options.on_progress {
+            on_progress.call(Phase::Posts, idx + 1, Some(n_posts));
+        }
+    }
+    report.remove_moved(&written, &extract_path)?;
+    if let Some(progress) = &options.progress {
+        progress.posts_done(n_posts);
+    }
+    if let Some(on_progress) = &options.on_progress {
+        on_progress.done(Phase::Posts, n_posts);
+    }
+    log::info!("extracted {} posts", n_posts);
+    report.log(options);
+
+    if let Some(authors_dir) = &options.authors_dir {
+        for author in &mut authors {
+            rename_image_urls(author.image_urls_mut(), &images_url, &renames);
+        }
+        write_authors(&authors, &extract_path.join(authors_dir), options)?;
+        log::info!("extracted {} authors", authors.len());
+    }
+    if let Some(tags_dir) = &options.tags_dir {
+        for tag in &mut tags {
+            rename_image_urls(tag.image_urls_mut(), &images_url, &renames);
+        }
+        write_tags(&tags, &extract_path.join(tags_dir), options)?;
+        log::info!("extracted {} tags", tags.len());
+    }
+
+    // only the images which are linked to are downloaded, so none are orphans
+    let images = extracted_images(&ghost_urls, &images_path, &renames);
+    manifest.images(&images, &bundles, options);
+    if options.page_bundles {
+        let n_bundled = bundles.move_images(&extract_path, options)?;
+        log::info!("moved {} images into page bundles", n_bundled);
+    }
+    manifest.write(options)?;
+    report.check_links(&written, &extract_path, options)?;
+
+    let n_indices = ensure_indices(&extract_path, options)?;
+    log::info!("added {} indices", n_indices);
+    report.validate(&extract_path, options)?;
+    Summary {
+        posts: n_posts,
+        indices: n_indices,
+        ..summary(&report)
+    }
+    .write(options)?;
+    report.check_strict(options)?;
+
+    Ok(n_posts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// serve each body at its path, ignoring any query, from a local server; returns its url
+    ///
+    /// Bodies of paths ending in `.png` are served as images; the rest, as json, in which `{url}`
+    /// is replaced with the server's url.
+    fn serve(routes: Vec<(&'static str, Vec<u8>)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let routes: Vec<_> = routes
+            .into_iter()
+            .map(|(path, body)| {
+                if path.ends_with(".png") {
+                    (path, "image/png", body)
+                } else {
+                    let body = String::from_utf8(body).unwrap().replace("{url}", &url);
+                    (path, "application/json", body.into_bytes())
+                }
+            })
+            .collect();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                reader.read_line(&mut request).unwrap();
+                // skip the headers, up to the blank line which ends them
+                let mut header = String::new();
+                while reader.read_line(&mut header).unwrap() > 2 {
+                    header.clear();
+                }
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+                let path = path.split('?').next().unwrap_or_default();
+                let (status, content_type, body) =
+                    match routes.iter().find(|(route, _, _)| *route == path) {
+                        Some((_, content_type, body)) => ("200 OK", *content_type, body.as_slice()),
+                        None => ("404 Not Found", "text/plain", &b""[..]),
+                    };
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    content_type,
+                    body.len()
+                )
+                .unwrap();
+                stream.write_all(body).unwrap();
+            }
+        });
+        url
+    }
+
+    /// serve a blog via the admin api, whose one post links to its one image by absolute url
+    fn serve_blog() -> String {
+        let mobiledoc = serde_json::json!({
+            "version": "0.3.1",
+            "markups": [],
+            "atoms": [],
+            "cards": [["markdown", {
+                "markdown": "![a]({url}/content/images/2020/01/a.png?w=600 \"A\")"
+            }]],
+            "sections": [[10, 0]],
+        });
+        let meta = serde_json::json!({ "pagination": { "next": null, "total": 1 } });
+        let json = |value: serde_json::Value| value.to_string().into_bytes();
+        serve(vec![
+            (
+                "/ghost/api/admin/settings/",
+                json(serde_json::json!({ "settings": [{ "key": "title", "value": "Blog" }] })),
+            ),
+            (
+                "/ghost/api/admin/posts/",
+                json(serde_json::json!({
+                    "posts": [{
+                        "id": "1",
+                        "title": "Hello",
+                        "slug": "hello",
+                        "status": "published",
+                        "published_at": "2020-01-02T10:00:00.000Z",
+                        "mobiledoc": mobiledoc.to_string(),
+                    }],
+                    "meta": meta,
+                })),
+            ),
+            (
+                "/ghost/api/admin/pages/",
+                json(serde_json::json!({ "pages": [], "meta": meta })),
+            ),
+            (
+                "/ghost/api/admin/tags/",
+                json(serde_json::json!({ "tags": [], "meta": meta })),
+            ),
+            (
+                "/ghost/api/admin/users/",
+                json(serde_json::json!({ "users": [], "meta": meta })),
+            ),
+            (
+                "/content/images/2020/01/a.png",
+                b"not really a png".to_vec(),
+            ),
+        ])
+    }
+
+    #[test]
+    fn downloads_absolute_images() {
+        let dir = tempfile::tempdir().unwrap();
+        let extract_path = dir.path().join("site/content/blog");
+        std::fs::create_dir_all(&extract_path).unwrap();
+        let url = serve_blog();
+        extract_admin_api(
+            &url,
+            "abc123:00ff",
+            &extract_path,
+            &ExtractOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            std::fs::read(extract_path.join("2020/01/a.png")).unwrap(),
+            b"not really a png"
+        );
+        let post = std::fs::read_to_string(extract_path.join("2020/01/02/hello.md")).unwrap();
+        assert!(post.contains("![a](/blog/2020/01/a.png \"A\")"), "{}", post);
+    }
+
+    #[test]
+    fn leaves_own_images_to_uploads() {
+        let dir = tempfile::tempdir().unwrap();
+        let extract_path = dir.path().join("site/content/blog");
+        std::fs::create_dir_all(&extract_path).unwrap();
+        let options = ExtractOptions {
+            download_external_images: true,
+            ..ExtractOptions::default()
+        };
+        extract_admin_api(&serve_blog(), "abc123:00ff", &extract_path, &options).unwrap();
+
+        assert!(extract_path.join("2020/01/a.png").is_file());
+        assert!(!extract_path.join("external").exists());
+        let post = std::fs::read_to_string(extract_path.join("2020/01/02/hello.md")).unwrap();
+        assert!(post.contains("![a](/blog/2020/01/a.png \"A\")"), "{}", post);
+    }
+
+    #[test]
+    fn rejects_malformed_keys() {
+        assert!(GhostApi::admin("https://example.com", "no-secret").is_err());
+        assert!(GhostApi::admin("https://example.com", "id:not-hex").is_err());
+    }
+
+    #[test]
+    fn token_names_key() {
+        let api = GhostApi::admin("https://example.com/", "abc123:00ff").unwrap();
+        assert_eq!(api.url, "https://example.com");
+        let token = GhostApi::admin_token("abc123", &[0x00, 0xff]);
+        let parts: Vec<_> = token.split('.').collect();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(
+            parts[0],
+            base64url(r#"{"alg":"HS256","kid":"abc123","typ":"JWT"}"#)
+        );
+    }
+
+    #[test]
+    fn reads_users_of_either_api() {
+        for resource in &["users", "authors"] {
+            let page: UsersPage = serde_json::from_str(&format!(
+                r#"{{"{}": [{{"id": "1", "name": "Ada", "slug": "ada"}}],
+                    "meta": {{"pagination": {{"next": 2, "total": 101}}}}}}"#,
+                resource
+            ))
+            .unwrap();
+            assert_eq!(page.users.len(), 1);
+            assert_eq!(page.users[0].to_author().slug, "ada");
+            assert_eq!(page.meta.pagination.next, Some(2));
+        }
+    }
+
+    #[test]
+    fn reads_settings_of_either_api() {
+        let admin: Settings = serde_json::from_str(
+            r#"{"settings": [{"key": "title", "value": "Blog"}, {"key": "locale", "value": "de"}]}"#,
+        )
+        .unwrap();
+        let content: Settings =
+            serde_json::from_str(r#"{"settings": {"title": "Blog", "locale": "de"}}"#).unwrap();
+        for settings in &[admin, content] {
+            assert_eq!(settings.get("title"), Some("Blog"));
+            assert_eq!(settings.get("locale"), Some("de"));
+            assert_eq!(settings.get("missing"), None);
+        }
+    }
+}
@@ -1,10 +1,15 @@
-use crate::{log_progress, Error};
+use crate::progress::{Phase, ProgressCallback, ProgressObserver};
+use crate::{log_progress, Error, ImageSource};
+use std::collections::BTreeSet;
 use std::ffi::OsStr;
 use std::io::Read;
 use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
 
+/// The types of file in which a ghost blog may be found.
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
-enum FileType {
+pub enum FileType {
+    /// a bare ghost database
     Sqlite3,
     Tar,
     TarGz,
@@ -12,7 +17,8 @@ enum FileType {
 }
 
 impl FileType {
-    fn try_from_path(path: &Path) -> Option<Self> {
+    /// detect the type of the file at this path from its contents, if it's of a known type
+    pub fn try_from_path(path: &Path) -> Option<Self> {
         match tree_magic::from_filepath(path).as_str() {
             "application/vnd.sqlite3" => Some(FileType::Sqlite3),
             "application/x-tar" => Some(FileType::Tar),
@@ -23,10 +29,21 @@ impl FileType {
     }
 }
 
+impl std::fmt::Display for FileType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FileType::Sqlite3 => "sqlite3 database",
+            FileType::Tar => "tar archive",
+            FileType::TarGz => "gzip-compressed tar archive",
+            FileType::TarBz2 => "bzip2-compressed tar archive",
+        })
+    }
+}
+
 fn try_to_tar_reader(path: &Path) -> Result<Box<dyn Read>, Error> {
-    let reader = std::fs::File::open(&path)?;
+    let reader = std::fs::File::open(path)?;
     let reader = std::io::BufReader::new(reader);
-    match FileType::try_from_path(&path) {
+    match FileType::try_from_path(path) {
         Some(FileType::Tar) => Ok(Box::new(reader)),
         Some(FileType::TarGz) => {
             let reader = libflate::gzip::Decoder::new(reader)?;
@@ -46,6 +63,36 @@ pub fn try_archive(path: &Path) -> Result<tar::Archive<Box<dyn Read>>, Error> {
     Ok(tar::Archive::new(reader))
 }
 
+/// copy a blog's database out of an archive into a temporary file, which is removed once it's
+/// dropped
+///
+/// Every other entry is passed to `f` as the archive is read, i.e. to measure the images.
+pub(crate) fn copy_db<F>(
+    archive_path: &Path,
+    db_path: &Path,
+    mut f: F,
+) -> Result<NamedTempFile, Error>
+where
+    F: FnMut(&Path, &tar::Entry<'_, Box<dyn Read>>),
+{
+    let mut database = NamedTempFile::new()?;
+    let mut found_db = false;
+    for entry in try_archive(archive_path)?.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        if path == db_path {
+            found_db = true;
+            std::io::copy(&mut entry, &mut database)?;
+        } else {
+            f(&path, &entry);
+        }
+    }
+    if !found_db {
+        return Err(Error::GhostDbNotFound);
+    }
+    Ok(database)
+}
+
 /// find all ghost databases within an archive
 pub fn find_ghost_dbs<'a, R>(
     archive: &'a mut tar::Archive<R>,
@@ -58,16 +105,13 @@ where
         .enumerate()
         .filter_map(|(idx, maybe_entry)| {
             log_progress(idx, "inspected");
-            maybe_entry
-                .ok()
-                .map(|entry| {
-                    entry
-                        .path()
-                        .ok()
-                        .filter(|path| path.file_name() == Some(OsStr::new("ghost.db")))
-                        .map(|path| path.into_owned())
-                })
-                .flatten()
+            maybe_entry.ok().and_then(|entry| {
+                entry
+                    .path()
+                    .ok()
+                    .filter(|path| path.file_name() == Some(OsStr::new("ghost.db")))
+                    .map(|path| path.into_owned())
+            })
         }))
 }
 
@@ -112,3 +156,244 @@ pub fn find_ghost_db_in<P: AsRef<Path>>(
     let mut archive = try_archive(path.as_ref())?;
     find_ghost_db(&mut archive, prefix)
 }
+
+/// Paths to the components of a ghost blog within an archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GhostPaths {
+    /// path to the ghost database
+    pub db: PathBuf,
+    /// path to the directory from which `/content/images` was served, if one was found
+    pub images: Option<PathBuf>,
+}
+
+impl GhostPaths {
+    /// the ghost content directory
+    ///
+    /// This is the parent of the images directory if one was found; otherwise the grandparent
+    /// of the database.
+    pub fn content_dir(&self) -> Option<&Path> {
+        match &self.images {
+            Some(images) => images.parent(),
+            None => self.db.parent().and_then(|parent| parent.parent()),
+        }
+    }
+
+    /// the directory within the archive from which `source` was served
+    ///
+    /// The `/content/images` source is the images directory itself, whatever it's named; the
+    /// other sources are found relative to the [content directory][GhostPaths::content_dir].
+    pub fn source_dir(&self, source: &ImageSource) -> Option<PathBuf> {
+        match &self.images {
+            Some(images) if source.url_prefix == ImageSource::default().url_prefix => {
+                Some(images.clone())
+            }
+            _ => self
+                .content_dir()
+                .map(|content_dir| content_dir.join(&source.archive_dir)),
+        }
+    }
+}
+
+/// choose the images directory for a blog whose database is at `db_path`
+///
+/// In a standard configuration, the database is at `content/data/ghost.db` and the images at
+/// `content/images`. When that directory doesn't exist, the shallowest directory named `images`
+/// beneath either the database's grandparent or the search prefix is chosen instead.
+fn choose_images_dir(
+    db_path: &Path,
+    prefix: Option<&Path>,
+    candidates: &BTreeSet<PathBuf>,
+) -> Option<PathBuf> {
+    let grandparent = db_path.parent().and_then(|parent| parent.parent())?;
+    let standard = grandparent.join("images");
+    if candidates.contains(&standard) {
+        return Some(standard);
+    }
+    candidates
+        .iter()
+        .filter(|candidate| {
+            candidate.starts_with(grandparent)
+                || prefix.is_some_and(|prefix| candidate.starts_with(prefix))
+        })
+        .min_by_key(|candidate| (candidate.components().count(), candidate.to_path_buf()))
+        .cloned()
+}
+
+/// find the internal paths to a ghost database and its images in an existing archive
+///
+/// If `db_path` is set, it is used as the database without searching for one, though it must
+/// still exist in the archive. If `images_prefix` is set, it is used as the images directory
+/// without searching for one.
+pub fn find_ghost_paths<R>(
+    archive: &mut tar::Archive<R>,
+    prefix: Option<PathBuf>,
+    db_path: Option<PathBuf>,
+    images_prefix: Option<PathBuf>,
+) -> Result<GhostPaths, Error>
+where
+    R: Read,
+{
+    scan_ghost_paths(archive, prefix, db_path, images_prefix, None, None)
+}
+
+/// as [`find_ghost_paths`], notifying an observer and a callback of each entry inspected
+pub(crate) fn scan_ghost_paths<R>(
+    archive: &mut tar::Archive<R>,
+    prefix: Option<PathBuf>,
+    db_path: Option<PathBuf>,
+    images_prefix: Option<PathBuf>,
+    progress: Option<&dyn ProgressObserver>,
+    on_progress: Option<&ProgressCallback>,
+) -> Result<GhostPaths, Error>
+where
+    R: Read,
+{
+    let is_db = |path: &Path| match &db_path {
+        Some(db_path) => path == db_path,
+        None => {
+            path.file_name() == Some(OsStr::new("ghost.db"))
+                && prefix
+                    .as_ref()
+                    .is_none_or(|prefix| path.starts_with(prefix))
+        }
+    };
+
+    let mut dbs = Vec::new();
+    let mut images_candidates = BTreeSet::new();
+    let mut n_entries = 0;
+    for (idx, maybe_entry) in archive.entries()?.enumerate() {
+        log_progress(idx, "inspected");
+        n_entries = idx + 1;
+        let entry = match maybe_entry {
+            Ok(entry) => entry,
+            Err(_) => continue,
+        };
+        let path = match entry.path() {
+            Ok(path) => path.into_owned(),
+            Err(_) => continue,
+        };
+        if let Some(progress) = progress {
+            progress.archive_entry(Phase::Scanning, idx, &path, entry.size());
+        }
+        if let Some(on_progress) = on_progress {
+            on_progress.call(Phase::Scanning, idx, None);
+        }
+        if is_db(&path) {
+            dbs.push(path);
+        } else if images_prefix.is_none() {
+            images_candidates.extend(
+                path.ancestors()
+                    .skip(1)
+                    .filter(|ancestor| ancestor.file_name() == Some(OsStr::new("images")))
+                    .map(Path::to_path_buf),
+            );
+        }
+    }
+    if let Some(progress) = progress {
+        progress.archive_done(Phase::Scanning, n_entries);
+    }
+    if let Some(on_progress) = on_progress {
+        on_progress.done(Phase::Scanning, n_entries);
+    }
+    let db = match dbs.len() {
+        0 => return Err(Error::GhostDbNotFound),
+        1 => dbs.remove(0),
+        _ => return Err(Error::MultipleGhostDb),
+    };
+    let images =
+        images_prefix.or_else(|| choose_images_dir(&db, prefix.as_deref(), &images_candidates));
+    Ok(GhostPaths { db, images })
+}
+
+/// find the internal paths to a ghost database and its images in an archive
+pub fn find_ghost_paths_in<P: AsRef<Path>>(
+    path: P,
+    prefix: Option<PathBuf>,
+    db_path: Option<PathBuf>,
+    images_prefix: Option<PathBuf>,
+) -> Result<GhostPaths, Error> {
+    scan_ghost_paths_in(path, prefix, db_path, images_prefix, None, None)
+}
+
+/// as [`find_ghost_paths_in`], notifying an observer of each entry inspected
+pub(crate) fn scan_ghost_paths_in<P: AsRef<Path>>(
+    path: P,
+    prefix: Option<PathBuf>,
+    db_path: Option<PathBuf>,
+    images_prefix: Option<PathBuf>,
+    progress: Option<&dyn ProgressObserver>,
+    on_progress: Option<&ProgressCallback>,
+) -> Result<GhostPaths, Error> {
+    log::info!("analyzing archive");
+    let mut archive = try_archive(path.as_ref())?;
+    scan_ghost_paths(
+        &mut archive,
+        prefix,
+        db_path,
+        images_prefix,
+        progress,
+        on_progress,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates(paths: &[&str]) -> BTreeSet<PathBuf> {
+        paths.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn prefers_standard_images_dir() {
+        let candidates = candidates(&[
+            "ghost/content/images",
+            "ghost/content/themes/casper/assets/images",
+        ]);
+        assert_eq!(
+            choose_images_dir(Path::new("ghost/content/data/ghost.db"), None, &candidates),
+            Some(PathBuf::from("ghost/content/images")),
+        );
+    }
+
+    #[test]
+    fn finds_images_outside_grandparent() {
+        let candidates = candidates(&[
+            "backup/content/themes/casper/assets/images",
+            "backup/content/images",
+            "unrelated/images",
+        ]);
+        assert_eq!(
+            choose_images_dir(Path::new("backup/db/ghost.db"), None, &candidates),
+            Some(PathBuf::from("backup/content/images")),
+        );
+    }
+
+    #[test]
+    fn serves_images_from_overridden_dir() {
+        let ghost_paths = GhostPaths {
+            db: "backup/content/data/ghost.db".into(),
+            images: Some("backup/content/pictures".into()),
+        };
+        let sources = ImageSource::uploads();
+        assert_eq!(
+            ghost_paths.source_dir(&sources[0]),
+            Some(PathBuf::from("backup/content/pictures")),
+        );
+        assert_eq!(
+            ghost_paths.source_dir(&sources[1]),
+            Some(PathBuf::from("backup/content/files")),
+        );
+    }
+
+    #[test]
+    fn searches_within_prefix() {
+        let candidates = candidates(&["srv/content/images"]);
+        let db_path = Path::new("srv/blog/data/ghost.db");
+        assert_eq!(choose_images_dir(db_path, None, &candidates), None);
+        assert_eq!(
+            choose_images_dir(db_path, Some(Path::new("srv")), &candidates),
+            Some(PathBuf::from("srv/content/images")),
+        );
+    }
+}
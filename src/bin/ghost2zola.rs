@@ -1,35 +1,967 @@
-use ghost2zola::extract_archive;
+use anyhow::Context;
+use chrono::NaiveDate;
+use ghost2zola::{
+    api::{extract_admin_api, extract_content_api},
+    archive_stats, check_idempotence, convert_ghost_json, diff_archive, diff_extractions,
+    extract_archive, find_ghost_db, find_ghost_dbs,
+    ghost::Top,
+    logging::Verbosity,
+    progress::{ProgressBars, ProgressObserver, StatusLine},
+    query::PostFilter,
+    taxonomies_config, try_archive, verify_extraction, ConflictPolicy, DraftPolicy,
+    ExtraAssignment, ExtractOptions, FileType, FrontmatterFormat, FrontmatterTemplate, ImageFormat,
+    ImageSource, ImagesDest, KeyAssignment, ManifestFormat, PathSanitization, PostTypes,
+    RawHtmlPolicy, ReportFormat, SectionIndex, ShortcodeAssignment, SlugOptions, SortBy, Target,
+    TaxonomyAssignment, UndatedPolicy, UnsplashPolicy,
+};
 use std::path::PathBuf;
+use std::sync::Arc;
 use structopt::StructOpt;
 
+/// Migrate a ghost blog to zola
 #[derive(Debug, StructOpt)]
 struct Opt {
+    #[structopt(flatten)]
+    verbosity: Verbosity,
+
+    #[structopt(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Extract a ghost blog from an archive into a zola site
+    Extract(Box<Extract>),
+    /// Extract a live ghost blog into a zola site through its API
+    Api(Api),
+    /// Find the ghost database within an archive
+    FindDb(FindDb),
+    /// Detect the type of a file, i.e. whether it is an archive which can be extracted
+    Detect(Detect),
+    /// Check that a ghost JSON export parses and converts
+    CheckJson(CheckJson),
+    /// Compare an extracted blog with the ghost database, reporting where they differ
+    Verify(Verify),
+    /// Count the posts, tags, authors, and images of a ghost blog within an archive
+    Stats(Stats),
+    /// Compare two extractions of a blog, or an extraction with a fresh one from an archive
+    Diff(Diff),
+}
+
+/// Options locating a ghost blog within an archive.
+#[derive(Debug, StructOpt)]
+struct ArchiveOpt {
     /// Path to a possibly-compressed tar archiving a ghost blog
     #[structopt(parse(from_os_str))]
     archive_path: PathBuf,
 
+    /// Relative prefix within the archive
+    ///
+    /// In cases where the archive contains only a single blog, this is not necessary.
+    /// When the archive contains several blogs, this can be set to any distinct prefix
+    /// winnowing the selection to a single selection.
+    ///
+    /// If you're not sure what prefixes might be available, consider using `find-db --all`.
+    #[structopt(parse(from_os_str), long)]
+    prefix: Option<PathBuf>,
+}
+
+/// Options shaping the site into which a ghost blog is extracted, from an archive or its API.
+#[derive(Debug, StructOpt)]
+struct SiteOpt {
+    /// Directory, relative to the extract path, into which a section per author is extracted
+    ///
+    /// Each author's name, bio, location, website, and social accounts are written into
+    /// `<slug>/_index.md` within it. As the extract path is normally `content/blog`, this is
+    /// typically `../authors`.
+    #[structopt(parse(from_os_str), long)]
+    authors_dir: Option<PathBuf>,
+
+    /// Directory, relative to the extract path, into which a section per tag is extracted
+    ///
+    /// Each tag's name, description, and feature image are written into `<slug>/_index.md` within
+    /// it, typically `../tags`.
+    #[structopt(parse(from_os_str), long)]
+    tags_dir: Option<PathBuf>,
+
+    /// Leave links between posts as they are, rather than rewriting them into Zola internal links
+    ///
+    /// Otherwise, links such as `](/my-other-post/)` become `](@/blog/yyyy/mm/dd/my-other-post.md)`,
+    /// which `zola build` validates.
+    #[structopt(long)]
+    no_post_links: bool,
+
+    /// Which types of post to extract: `posts`, `pages`, or `all`
+    ///
+    /// To place posts and pages in separate Zola sections, extract each in its own run. For example,
+    /// `--only posts` into `content/blog`, then `--only pages` into `content/pages`.
+    #[structopt(long, default_value = "all")]
+    only: PostTypes,
+
+    /// When to use a post's pre-rendered html as its content, instead of converting it
+    ///
+    /// `never` always converts; `fallback` uses the html of posts which have no markdown, rather than
+    /// their plaintext; `always` uses the html of every post. Such posts are wrapped so that Zola
+    /// passes their html through untouched, and flagged with `extra.raw_html = true`. The API has no
+    /// markdown, so through it, `fallback` and `always` both use the html of every post which has
+    /// any.
+    #[structopt(long, default_value = "never")]
+    raw_html: RawHtmlPolicy,
+}
+
+#[derive(Debug, StructOpt)]
+struct Extract {
+    #[structopt(flatten)]
+    archive: ArchiveOpt,
+
     /// Path to the base directory into which the ghost blog should be expanded.
     ///
     /// Normally, this is the `content/blog` directory of your zola installation.
     #[structopt(parse(from_os_str))]
     extract_path: PathBuf,
 
-    /// Relative prefix within the archive
+    /// Path within the archive to the ghost database
     ///
-    /// In cases where the archive contains only a single blog, this is not necessary.
-    /// When the archive contains several blogs, this can be set to any distinct prefix
-    /// winnowing the selection to a single selection.
+    /// Normally, this is discovered automatically as the only `ghost.db` within the prefix.
+    /// Set this if the database has a different name, or discovery can't choose between several.
+    ///
+    /// If this and `--images-prefix` are both set, discovery is skipped entirely.
+    #[structopt(parse(from_os_str), long)]
+    db_path: Option<PathBuf>,
+
+    /// Path within the archive to the directory containing the blog's images
+    ///
+    /// Normally, this is discovered automatically: it is `images` beside the directory containing
+    /// `ghost.db`, or failing that the shallowest `images` directory near the database or within
+    /// the prefix. Set this if discovery chooses the wrong directory.
+    ///
+    /// If this and `--db-path` are both set, discovery is skipped entirely.
+    #[structopt(parse(from_os_str), long)]
+    images_prefix: Option<PathBuf>,
+
+    /// SQL predicate restricting which posts are extracted
+    ///
+    /// This is appended to the `WHERE` clause of the posts query, and can refer to any column
+    /// of the `posts` or `users` tables. For example: `--where "posts.created_at > '2020-01-01'"`.
+    #[structopt(long = "where")]
+    sql_filter: Option<String>,
+
+    /// Extract only the posts published on or after this date, i.e. `2019-01-01`
+    ///
+    /// Posts without a publication date, such as drafts, are then left out.
+    #[structopt(long)]
+    since: Option<NaiveDate>,
+
+    /// Extract only the posts published on or before this date, i.e. `2019-12-31`
+    ///
+    /// Posts without a publication date, such as drafts, are then left out.
+    #[structopt(long)]
+    until: Option<NaiveDate>,
+
+    /// Extract only the posts with this tag, given by its name or slug
+    ///
+    /// May be repeated, to extract the posts with any of the tags.
+    #[structopt(long = "tag", number_of_values = 1)]
+    tags: Vec<String>,
+
+    /// Extract only the posts by this author, given by their name or slug
+    ///
+    /// May be repeated, to extract the posts by any of the authors.
+    #[structopt(long = "author", number_of_values = 1)]
+    authors: Vec<String>,
+
+    /// Extract only the posts with this status, i.e. `published`, `draft`, or `scheduled`
+    ///
+    /// May be repeated, to extract the posts with any of the statuses.
+    #[structopt(long = "status", number_of_values = 1)]
+    statuses: Vec<String>,
+
+    /// Extract at most this many posts, i.e. to try out the other options on a few of them
+    ///
+    /// These are the first posts which would otherwise be extracted, by id.
+    #[structopt(long, conflicts_with = "sample")]
+    limit: Option<usize>,
+
+    /// Extract this many posts chosen at random, i.e. to try out the other options on a few of them
+    #[structopt(long)]
+    sample: Option<usize>,
+
+    /// Additional location from which the blog served images, as `url_prefix=archive_dir`
+    ///
+    /// Uploads under `/content/images`, `/content/files` and `/content/media` are always handled.
+    /// Legacy blogs may also link images from other locations, i.e. `/assets/images`. The archive
+    /// directory is relative to the ghost content directory: the directory containing
+    /// `data/ghost.db`. For example:
+    /// `--image-source /assets/images=themes/casper/assets/images`.
+    ///
+    /// May be repeated.
+    #[structopt(long = "image-source", number_of_values = 1)]
+    image_sources: Vec<ImageSource>,
+
+    /// Url at which the blog was served, i.e. `https://example.com`
+    ///
+    /// Absolute links to it, over either http or https, are rewritten like relative links.
+    #[structopt(long)]
+    base_url: Option<String>,
+
+    /// Keep the query strings and fragments of rewritten image links, i.e. `?w=600`
+    ///
+    /// By default these are dropped, as Zola serves the extracted images as they are.
+    #[structopt(long)]
+    keep_image_query: bool,
+
+    /// Directory, relative to the extract path, into which pages are extracted
+    ///
+    /// Ghost pages, such as "About", are not part of the blog's chronology, so they are placed
+    /// directly within this directory rather than in the date tree. Defaults to `pages`, or to the
+    /// extract path itself with `--only pages`.
+    #[structopt(parse(from_os_str), long)]
+    pages_dir: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    site: SiteOpt,
+
+    /// What to do with posts which are published but have no publication date
+    ///
+    /// `keep` extracts them as they are, into `undated`; `draft` extracts them as drafts;
+    /// `created-at` dates them by their creation time, or extracts them as drafts if that is also
+    /// unknown; `error` aborts the extraction. Either way, such posts are listed once extraction
+    /// completes.
+    #[structopt(long = "undated", default_value = "keep")]
+    undated_policy: UndatedPolicy,
+
+    /// What to do with drafts: `include`, `separate`, or `skip`
+    ///
+    /// `include` extracts them alongside the published posts, i.e. into `undated`; `separate`
+    /// extracts them into `--drafts-dir`; `skip` leaves them out.
+    #[structopt(long, default_value = "include")]
+    drafts: DraftPolicy,
+
+    /// Directory, relative to the destination, into which to extract drafts with `--drafts separate`
+    #[structopt(parse(from_os_str), long, default_value = "../drafts")]
+    drafts_dir: PathBuf,
+
+    /// Don't write Zola's section indices, `_index.md`, i.e. when the site already has its own
+    #[structopt(long)]
+    no_indices: bool,
+
+    /// Write the contents of this file as the section index at the destination's root
+    ///
+    /// By default, the root index sorts posts by date and paginates them.
+    #[structopt(parse(from_os_str), long)]
+    root_index: Option<PathBuf>,
+
+    /// Write the contents of this file as the section index of each year and month
+    ///
+    /// By default, these indices are titled by their date, i.e. `January 2020`, and transparent, so
+    /// that their posts are listed by the root.
+    #[structopt(parse(from_os_str), long)]
+    branch_index: Option<PathBuf>,
+
+    /// The site's default language, i.e. `en`, to extract posts in other languages as translations
+    ///
+    /// A French post is then written to i.e. `hello.fr.md`, and each section gets an index per
+    /// language. Each language must be declared in the site's configuration. Ghost's language tags
+    /// are reduced to their primary subtag, so `en_US` is `en`.
+    #[structopt(long)]
+    default_language: Option<String>,
+
+    /// Order in which the root section sorts its posts
+    ///
+    /// One of `date`, `update_date`, `title`, `title_bytes`, `weight`, `slug`, or `none`.
+    #[structopt(long, default_value = "date")]
+    root_sort_by: SortBy,
+
+    /// Number of posts per page of the root section, or 0 not to paginate it
+    #[structopt(long, default_value = "10")]
+    root_paginate_by: u32,
+
+    /// Whether the root section's posts are also listed by its parent section
+    #[structopt(long, default_value = "false", parse(try_from_str))]
+    root_transparent: bool,
+
+    /// Order in which each year, month, and day section sorts its posts
+    #[structopt(long, default_value = "date")]
+    branch_sort_by: SortBy,
+
+    /// Number of posts per page of each year, month, and day section, or 0 not to paginate them
+    #[structopt(long, default_value = "0")]
+    branch_paginate_by: u32,
+
+    /// Whether each year, month, and day section's posts are also listed by the root section
+    #[structopt(long, default_value = "true", parse(try_from_str))]
+    branch_transparent: bool,
+
+    /// Static site generator for which to extract content: `zola`, `hugo`, or `jekyll`
+    ///
+    /// For Hugo, frontmatter follows Hugo's conventions, links between posts use its `ref`
+    /// shortcode, and no section indices are written. For Jekyll, the destination is the site's
+    /// root: posts are written into `_posts` with yaml frontmatter, and images into
+    /// `assets/images`.
+    #[structopt(long, default_value = "zola")]
+    target: Target,
+
+    /// Format in which to write frontmatter: `toml` or `yaml`
+    ///
+    /// Ignored for Jekyll, which only reads yaml.
+    #[structopt(long, default_value = "toml")]
+    frontmatter: FrontmatterFormat,
+
+    /// Render each post's frontmatter with this Tera template, instead of the default fields
+    ///
+    /// The template's context holds the fields which would otherwise be written, i.e. `title`,
+    /// `date`, and `extra.author_name`. Its output is placed between the frontmatter's delimiters.
+    #[structopt(parse(from_os_str), long)]
+    frontmatter_template: Option<PathBuf>,
+
+    /// Ghost's permalink structure, i.e. `/{slug}/`, from which to write each post's `aliases`
+    ///
+    /// Each post's url on Ghost then redirects to its new url, so that inbound links keep working.
+    /// The structure may use `{slug}`, `{id}`, `{year}`, `{month}`, `{day}`, `{primary_tag}`, and
+    /// `{primary_author}`, or their older forms, i.e. `:slug`.
+    #[structopt(long)]
+    permalinks: Option<String>,
+
+    /// Insert a summary separator, i.e. `<!-- more -->`, where each post's custom excerpt ends
+    ///
+    /// The separator follows the paragraph in which the excerpt ends, or the first paragraph if
+    /// the excerpt isn't part of the post, so that the post's summary matches what Ghost showed.
+    /// Posts without a custom excerpt are left alone.
+    #[structopt(long)]
+    summary_separator: bool,
+
+    /// Write each post's word count and estimated reading time into its frontmatter
+    ///
+    /// They are written as `extra.words` and `extra.reading_time`, in minutes.
+    #[structopt(long)]
+    reading_time: bool,
+
+    /// Reading speed with which `--reading-time` estimates reading time
+    #[structopt(long, default_value = "275")]
+    words_per_minute: u32,
+
+    /// Render a kind of content with a Zola shortcode, or with none, as `kind=name`
+    ///
+    /// The kinds are `caption`, `gallery`, `bookmark`, `callout`, `toggle`, `button`, `audio`,
+    /// `video`, `file`, `youtube`, `vimeo`, `twitter` and `soundcloud`. The arguments with which
+    /// each is called are documented by the library's `Shortcodes`. By default, `youtube` and `vimeo` use Zola's built-in shortcodes of the same
+    /// names, and other kinds have none. For example, `--shortcode caption=figcaption`, or
+    /// `--shortcode youtube=` to pass embedded YouTube videos through as html.
+    ///
+    /// May be repeated.
+    #[structopt(long = "shortcode", number_of_values = 1)]
+    shortcodes: Vec<ShortcodeAssignment>,
+
+    /// Keep non-ascii letters in the slugs generated for posts without one, i.e. `café`
+    ///
+    /// By default, they're transliterated into ascii, i.e. `cafe`. Zola slugifies paths into ascii
+    /// again unless its `slugify.paths` is `safe`.
+    #[structopt(long)]
+    unicode_slugs: bool,
+
+    /// Character between the words of the slugs generated for posts without one
+    #[structopt(long, default_value = "-")]
+    slug_separator: char,
+
+    /// Greatest number of characters in the slugs generated for posts without one
+    #[structopt(long, default_value = "150")]
+    slug_max_length: usize,
+
+    /// How to make the names of extracted files safe: `none` or `windows`
+    ///
+    /// `windows` replaces the characters which Windows forbids in names with `-`, trims trailing
+    /// dots, and suffixes its reserved names, i.e. `con`, with `_`. Use it when the site will be
+    /// built, or its sources checked out, on Windows.
+    #[structopt(long, default_value = "none")]
+    sanitize_paths: PathSanitization,
+
+    /// Don't extract or download any images, i.e. when they were already migrated
+    ///
+    /// Links to them are rewritten all the same.
+    #[structopt(long, conflicts_with = "images-only")]
+    no_images: bool,
+
+    /// Extract or download only the images, without writing any posts, pages, or sections
+    #[structopt(long)]
+    images_only: bool,
+
+    /// What to do with posts, images, and indices which already exist: `skip`, `overwrite`,
+    /// `backup`, or `error`
+    ///
+    /// `backup` renames each existing file beside itself, i.e. to `hello.md.bak`, before writing
+    /// the new one. By default, posts and images are overwritten, but existing indices are left
+    /// alone.
+    #[structopt(long)]
+    on_conflict: Option<ConflictPolicy>,
+
+    /// Rewrite only the posts which changed since the blog was last extracted into the extract path
     ///
-    /// If you're not sure what prefixes might be available, consider using the `find_ghost_db` tool.
+    /// Each post is matched with the file with its id, and left alone if its path and time of last
+    /// update are unchanged. Posts whose path changed have their old file removed. The posts
+    /// updated are listed. Use the same options as the previous extraction.
+    #[structopt(long)]
+    incremental: bool,
+
+    /// Skip extracting the images which no post, author, or tag links to, i.e. Ghost's resized
+    /// variants
+    ///
+    /// The archive is read twice to find them. They're listed either way. Only the posts which are
+    /// extracted count, so this is best left unset when extracting posts and pages separately
+    /// with `--only`.
+    #[structopt(long, conflicts_with = "images-only")]
+    prune_orphans: bool,
+
+    /// Write a manifest of where each of Ghost's urls went into this file
+    ///
+    /// Each post and image is listed with the url at which Ghost served it, the path into which it
+    /// was extracted, relative to the extract path, and the url at which the site serves it, from
+    /// which redirects can be generated. Ghost's urls for posts follow `--permalinks`, or else its
+    /// default, `/{slug}/`.
+    #[structopt(parse(from_os_str), long)]
+    manifest: Option<PathBuf>,
+
+    /// The format of the manifest: `json` or `csv`
+    #[structopt(long, default_value = "json")]
+    manifest_format: ManifestFormat,
+
+    /// Once extraction completes, write a summary of it in this format: `json`
+    ///
+    /// The summary counts the posts, images, and indices written, and lists the posts which were
+    /// skipped, with their ids and why, the images without alt text in each post, and each
+    /// warning. It's written to stdout, unless `--report-path` is set.
+    #[structopt(long)]
+    report: Option<ReportFormat>,
+
+    /// Write the summary into this file, rather than to stdout
+    #[structopt(parse(from_os_str), long, requires = "report")]
+    report_path: Option<PathBuf>,
+
+    /// Fail if anything was lost along the way, rather than warning about it
+    ///
+    /// That is, if any post was written without its markdown or with cards which couldn't be
+    /// converted, if any image which a post links to was missing, if any internal link doesn't
+    /// resolve, or, with `--validate`, if any frontmatter is invalid. Everything is extracted and
+    /// logged all the same, then the exit code is non-zero.
+    ///
+    /// Posts skipped on purpose don't count: drafts left out by `--drafts skip` aren't lost, as
+    /// they were asked to be skipped. Nor is there any fallback for frontmatter which can't be
+    /// serialized into TOML for this to catch, as such frontmatter fails the extraction outright.
+    #[structopt(long)]
+    strict: bool,
+
+    /// Once extraction completes, check that the target would accept every file's frontmatter
+    ///
+    /// Each markdown file beneath the extract path is parsed: its frontmatter must parse, without
+    /// duplicate keys, and its dates must be dates. For zola, each page must have a title, and
+    /// every key must be one zola knows, of the right type.
+    #[structopt(long)]
+    validate: bool,
+
+    /// Before extracting, check that extracting the archive twice writes byte-identical files
+    ///
+    /// The archive is extracted twice into temporary directories, with these options, and every
+    /// file compared. If any differs, they're listed, and nothing is written to the extract path.
+    #[structopt(long, conflicts_with = "sample")]
+    check_idempotence: bool,
+
+    /// Where to extract the images: `content`, beside the posts, or the site's `static` directory
+    ///
+    /// The static directory is `../../static`, relative to the extract path, as that is normally
+    /// `content/blog`. Links to the images follow them.
+    #[structopt(long, default_value = "content")]
+    images_dest: ImagesDest,
+
+    /// Directory, relative to the images destination, into which images are extracted
+    ///
+    /// By default, they're extracted where Ghost's links to them keep working: beside the posts,
+    /// or into `blog` within the static directory.
+    #[structopt(parse(from_os_str), long)]
+    images_dir: Option<PathBuf>,
+
+    /// Download the images which posts link to by absolute url, i.e. on Ghost's CDN or Unsplash
+    ///
+    /// They're downloaded into `external` beside the extracted images, and the links to them
+    /// rewritten, so that the site no longer depends on them.
+    #[structopt(long)]
+    download_external_images: bool,
+
+    /// What to do with images hotlinked from Unsplash: `keep`, `normalize`, or `download`
+    ///
+    /// `normalize` replaces their long query strings with Ghost's default size; `download` also
+    /// downloads them like `--download-external-images`, keeping the photographer's attribution
+    /// in their alt text.
+    #[structopt(long, default_value = "keep")]
+    unsplash: UnsplashPolicy,
+
+    /// Convert the extracted png, jpeg, bmp, and tiff images to `webp` or `avif`
+    ///
+    /// Links to them are rewritten to the converted images. Gifs are left alone, as they may be
+    /// animated.
+    #[structopt(long)]
+    image_format: Option<ImageFormat>,
+
+    /// Keep the originals of converted images beside them, i.e. as a fallback
+    #[structopt(long)]
+    keep_original_images: bool,
+
+    /// Write each post as a page bundle, i.e. `hello/index.md`, alongside the images it uses
+    ///
+    /// Links to the images become relative. An image used by several posts is copied into each of
+    /// their bundles. Ignored for Jekyll.
+    #[structopt(long)]
+    page_bundles: bool,
+
+    /// Classify posts by author, as well as by tag, in `taxonomies.authors`
+    ///
+    /// Zola only builds sites which declare each taxonomy they use; see `--print-taxonomies`.
+    #[structopt(long)]
+    authors_taxonomy: bool,
+
+    /// Classify posts by their primary tag, as well as by all their tags, in `taxonomies.categories`
+    ///
+    /// Zola only builds sites which declare each taxonomy they use; see `--print-taxonomies`.
+    #[structopt(long)]
+    categories_taxonomy: bool,
+
+    /// Classify posts into a differently named taxonomy, as `tags|categories|authors=name`
+    ///
+    /// For example, `--taxonomy tags=topics` classifies posts by tag in a `topics` taxonomy, and
+    /// `--taxonomy tags=` doesn't classify them by tag at all. Classifications assigned the same
+    /// name are merged. Overrides `--authors-taxonomy` and `--categories-taxonomy`.
+    ///
+    /// May be repeated.
+    #[structopt(long = "taxonomy", number_of_values = 1)]
+    taxonomies: Vec<TaxonomyAssignment>,
+
+    /// Slugify taxonomy terms, i.e. `Rust Tips` to `rust-tips`
+    #[structopt(long)]
+    slugify_terms: bool,
+
+    /// Keep Ghost's internal tags, whose names begin with `#`, in `extra.internal_tags`
+    ///
+    /// Otherwise, they are dropped: Ghost never shows them to readers, so they don't belong in
+    /// the public `tags` taxonomy.
+    #[structopt(long)]
+    keep_internal_tags: bool,
+
+    /// Set a field in every post's `extra`, as `key=value`
+    ///
+    /// The value is read as toml where it can be, and otherwise as a string. For example,
+    /// `--extra template=post.html --extra comments=true`.
+    ///
+    /// May be repeated.
+    #[structopt(long = "extra", number_of_values = 1)]
+    extra_frontmatter: Vec<ExtraAssignment>,
+
+    /// Rename a key of each post's frontmatter, or drop it, as `key=renamed`
+    ///
+    /// Keys are dotted paths into the frontmatter as written for the target. For example,
+    /// `--frontmatter-key extra.author_name=extra.author`, or `--frontmatter-key extra.language=`
+    /// to drop the language.
+    ///
+    /// May be repeated.
+    #[structopt(long = "frontmatter-key", number_of_values = 1)]
+    frontmatter_keys: Vec<KeyAssignment>,
+
+    /// Once extraction completes, print the taxonomies which the site's configuration must declare
+    #[structopt(long)]
+    print_taxonomies: bool,
+
+    /// Keep a status line on the terminal showing the archive entry currently being processed
+    ///
+    /// The line also shows the elapsed time and the rate at which the archive is being read.
+    #[structopt(long)]
+    status_line: bool,
+
+    /// Draw progress bars on the terminal: the archive entries processed, the images extracted,
+    /// and the posts rendered
+    #[structopt(long, conflicts_with = "status-line")]
+    progress: bool,
+}
+
+#[derive(Debug, StructOpt)]
+struct Api {
+    /// Url of a live ghost blog, i.e. `https://example.com/blog`
+    #[structopt(long)]
+    ghost_url: String,
+
+    /// Admin API key for the blog, of the form `id:secret`
+    ///
+    /// Create one by adding a custom integration in the blog's admin interface.
+    #[structopt(
+        long,
+        env = "GHOST_ADMIN_KEY",
+        hide_env_values = true,
+        required_unless = "content-key"
+    )]
+    admin_key: Option<String>,
+
+    /// Content API key for the blog
+    ///
+    /// This read-only key suffices to fetch published posts; drafts are not extracted.
+    #[structopt(
+        long,
+        env = "GHOST_CONTENT_KEY",
+        hide_env_values = true,
+        conflicts_with = "admin-key"
+    )]
+    content_key: Option<String>,
+
+    #[structopt(flatten)]
+    site: SiteOpt,
+
+    /// Draw progress bars on the terminal: the images downloaded, and the posts rendered
+    #[structopt(long)]
+    progress: bool,
+
+    /// Path to the base directory into which the ghost blog should be expanded.
+    ///
+    /// Normally, this is the `content/blog` directory of your zola installation.
+    #[structopt(parse(from_os_str))]
+    extract_path: PathBuf,
+}
+
+#[derive(Debug, StructOpt)]
+struct FindDb {
+    #[structopt(flatten)]
+    archive: ArchiveOpt,
+
+    /// Find all possible DB paths instead of searching for a single one
+    #[structopt(long)]
+    all: bool,
+}
+
+#[derive(Debug, StructOpt)]
+struct Detect {
+    /// Paths to check
+    #[structopt(parse(from_os_str), required = true)]
+    paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+struct CheckJson {
+    /// Path to a JSON export of a ghost blog, from its admin interface's labs settings
+    #[structopt(parse(from_os_str))]
+    path: PathBuf,
+}
+
+#[derive(Debug, StructOpt)]
+struct Verify {
+    /// Path to the ghost database, i.e. as extracted from an archive
+    #[structopt(parse(from_os_str))]
+    db_path: PathBuf,
+
+    /// Path to the directory into which the blog was extracted, i.e. `content/blog`
+    ///
+    /// Each post's file is found by the id in its frontmatter, wherever it is beneath this, and its
+    /// title, date, and tags compared with the database's. Posts missing from it, and posts no
+    /// longer in the database, are listed too. The exit code is non-zero if anything differs.
+    #[structopt(parse(from_os_str))]
+    extract_path: PathBuf,
+
+    /// Static site generator for which the blog was extracted: `zola`, `hugo`, or `jekyll`
+    #[structopt(long, default_value = "zola")]
+    target: Target,
+}
+
+#[derive(Debug, StructOpt)]
+struct Stats {
+    #[structopt(flatten)]
+    archive: ArchiveOpt,
+}
+
+#[derive(Debug, StructOpt)]
+struct Diff {
+    /// Path to the directory into which the blog was extracted before, i.e. `content/blog`
+    #[structopt(parse(from_os_str))]
+    old: PathBuf,
+
+    /// Path to a newer extraction of the blog, or to an archive from which to extract it afresh
+    ///
+    /// An archive is extracted into a temporary directory, with the default options but for
+    /// `--target` and `--prefix`, and without its images. Posts are matched by the id in their
+    /// frontmatter, so a post whose slug changed is listed as moved. The exit code is non-zero if
+    /// anything differs.
+    #[structopt(parse(from_os_str))]
+    new: PathBuf,
+
+    /// Relative prefix within the archive, if `new` is one which contains several blogs
     #[structopt(parse(from_os_str), long)]
     prefix: Option<PathBuf>,
+
+    /// Static site generator for which the blog was extracted: `zola`, `hugo`, or `jekyll`
+    #[structopt(long, default_value = "zola")]
+    target: Target,
 }
 
 fn main() -> Result<(), anyhow::Error> {
-    pretty_env_logger::init_timed();
     let opt = Opt::from_args();
+    opt.verbosity
+        .init_logger(matches!(opt.command, Command::Extract(_) | Command::Api(_)));
+    match opt.command {
+        Command::Extract(extract) => run_extract(*extract),
+        Command::Api(api) => run_api(api),
+        Command::FindDb(find_db) => run_find_db(find_db),
+        Command::Detect(detect) => run_detect(detect),
+        Command::CheckJson(check_json) => run_check_json(check_json),
+        Command::Verify(verify) => run_verify(verify),
+        Command::Stats(stats) => run_stats(stats),
+        Command::Diff(diff) => run_diff(diff),
+    }
+}
+
+fn run_extract(opt: Extract) -> Result<(), anyhow::Error> {
+    let mut options = ExtractOptions {
+        prefix: opt.archive.prefix,
+        db_path: opt.db_path,
+        images_prefix: opt.images_prefix,
+        sql_filter: opt.sql_filter,
+        post_filter: PostFilter {
+            since: opt.since,
+            until: opt.until,
+            tags: opt.tags,
+            authors: opt.authors,
+            statuses: opt.statuses,
+        },
+        limit: opt.limit.or(opt.sample),
+        sample: opt.sample.is_some(),
+        base_url: opt.base_url,
+        keep_image_query: opt.keep_image_query,
+        pages_dir: match (opt.pages_dir, opt.site.only) {
+            (Some(pages_dir), _) => pages_dir,
+            (None, PostTypes::Pages) => PathBuf::new(),
+            (None, _) => "pages".into(),
+        },
+        slugs: SlugOptions {
+            unicode: opt.unicode_slugs,
+            separator: opt.slug_separator,
+            max_length: opt.slug_max_length,
+        },
+        path_sanitization: opt.sanitize_paths,
+        extract_images: !opt.no_images,
+        extract_content: !opt.images_only,
+        on_conflict: opt.on_conflict,
+        incremental: opt.incremental,
+        prune_orphans: opt.prune_orphans,
+        manifest: opt.manifest,
+        manifest_format: opt.manifest_format,
+        report: opt.report,
+        report_path: opt.report_path,
+        strict: opt.strict,
+        validate: opt.validate,
+        images_dest: opt.images_dest,
+        images_dir: opt.images_dir,
+        download_external_images: opt.download_external_images,
+        unsplash: opt.unsplash,
+        image_format: opt.image_format,
+        keep_original_images: opt.keep_original_images,
+        page_bundles: opt.page_bundles,
+        authors_dir: opt.site.authors_dir,
+        tags_dir: opt.site.tags_dir,
+        link_posts: !opt.site.no_post_links,
+        only: opt.site.only,
+        undated_policy: opt.undated_policy,
+        drafts: opt.drafts,
+        drafts_dir: opt.drafts_dir,
+        indices: !opt.no_indices,
+        default_language: opt.default_language,
+        root_section: SectionIndex {
+            sort_by: opt.root_sort_by,
+            paginate_by: Some(opt.root_paginate_by).filter(|&n| n > 0),
+            transparent: opt.root_transparent,
+        },
+        branch_section: SectionIndex {
+            sort_by: opt.branch_sort_by,
+            paginate_by: Some(opt.branch_paginate_by).filter(|&n| n > 0),
+            transparent: opt.branch_transparent,
+        },
+        raw_html: opt.site.raw_html,
+        target: opt.target,
+        frontmatter: opt.frontmatter,
+        words_per_minute: if opt.reading_time {
+            Some(opt.words_per_minute)
+        } else {
+            None
+        },
+        keep_internal_tags: opt.keep_internal_tags,
+        permalinks: opt.permalinks,
+        summary_separator: opt.summary_separator,
+        ..ExtractOptions::default()
+    };
+    options.image_sources.extend(opt.image_sources);
+    for assignment in opt.shortcodes {
+        options.shortcodes.set(&assignment.kind, assignment.name);
+    }
+    if opt.categories_taxonomy {
+        options.taxonomies.categories = Some("categories".into());
+    }
+    if opt.authors_taxonomy {
+        options.taxonomies.authors = Some("authors".into());
+    }
+    for assignment in opt.taxonomies {
+        *options.taxonomies.name_mut(assignment.classification) = assignment.name;
+    }
+    if let Some(path) = opt.root_index {
+        options.root_index = Some(std::fs::read_to_string(path)?);
+    }
+    if let Some(path) = opt.branch_index {
+        options.branch_index = Some(std::fs::read_to_string(path)?);
+    }
+    if let Some(path) = opt.frontmatter_template {
+        options.frontmatter_template =
+            Some(FrontmatterTemplate::new(&std::fs::read_to_string(path)?)?);
+    }
+    for assignment in opt.frontmatter_keys {
+        options
+            .frontmatter_keys
+            .set(&assignment.key, assignment.renamed);
+    }
+    for assignment in opt.extra_frontmatter {
+        options
+            .extra_frontmatter
+            .insert(assignment.key, assignment.value);
+    }
+    options.taxonomies.slugify = opt.slugify_terms;
+    if opt.status_line {
+        options.progress = Some(Arc::new(StatusLine::default()));
+    }
+    if opt.progress {
+        options.progress = Some(Arc::new(ProgressBars::default()));
+    }
+    if opt.check_idempotence {
+        check_idempotence(&opt.archive.archive_path, &options)?;
+    }
+    extract_archive(opt.archive.archive_path, opt.extract_path, &options)?;
+    if opt.print_taxonomies {
+        print!("{}", taxonomies_config(&options));
+    }
+
+    Ok(())
+}
+
+fn run_api(opt: Api) -> Result<(), anyhow::Error> {
+    let options = ExtractOptions {
+        raw_html: opt.site.raw_html,
+        authors_dir: opt.site.authors_dir,
+        tags_dir: opt.site.tags_dir,
+        link_posts: !opt.site.no_post_links,
+        only: opt.site.only,
+        pages_dir: match opt.site.only {
+            PostTypes::Pages => PathBuf::new(),
+            _ => "pages".into(),
+        },
+        progress: opt
+            .progress
+            .then(|| Arc::new(ProgressBars::default()) as Arc<dyn ProgressObserver>),
+        ..ExtractOptions::default()
+    };
+    match (opt.admin_key, opt.content_key) {
+        (Some(admin_key), _) => {
+            extract_admin_api(&opt.ghost_url, &admin_key, opt.extract_path, &options)?
+        }
+        (None, Some(content_key)) => {
+            extract_content_api(&opt.ghost_url, &content_key, opt.extract_path, &options)?
+        }
+        (None, None) => unreachable!("structopt requires one key"),
+    };
+    Ok(())
+}
 
-    extract_archive(opt.archive_path, opt.prefix, opt.extract_path)?;
+fn run_find_db(opt: FindDb) -> Result<(), anyhow::Error> {
+    let mut archive = try_archive(&opt.archive.archive_path)?;
+    if opt.all {
+        for db_path in find_ghost_dbs(&mut archive)? {
+            println!("{}", db_path.display());
+        }
+    } else {
+        let db_path = find_ghost_db(&mut archive, opt.archive.prefix)?;
+        println!("found db path: {}", db_path.display());
+    }
+    Ok(())
+}
+
+fn run_detect(opt: Detect) -> Result<(), anyhow::Error> {
+    for path in opt.paths {
+        if !path.is_file() {
+            anyhow::bail!("{} is not a file", path.display());
+        }
+        match FileType::try_from_path(&path) {
+            Some(file_type) => println!("{}: {}", path.display(), file_type),
+            None => println!("{}: unrecognized", path.display()),
+        }
+    }
+    Ok(())
+}
+
+fn run_check_json(opt: CheckJson) -> Result<(), anyhow::Error> {
+    let file = std::fs::File::open(&opt.path)
+        .with_context(|| format!("opening {}", opt.path.display()))?;
+    let top: Top = serde_json::from_reader(std::io::BufReader::new(file))
+        .with_context(|| format!("parsing {}", opt.path.display()))?;
+    for db in &top.db {
+        let pages = db.data.posts.iter().filter(|post| post.is_page()).count();
+        println!(
+            "ghost {}: {} posts, {} pages, {} tags, {} users",
+            db.meta.version,
+            db.data.posts.len() - pages,
+            pages,
+            db.data.tags.len(),
+            db.data.users.len(),
+        );
+    }
+    let converted = convert_ghost_json(&top, &ExtractOptions::default())?;
+    println!("converted {} posts and pages", converted.len());
+    Ok(())
+}
+
+fn run_verify(opt: Verify) -> Result<(), anyhow::Error> {
+    if !opt.db_path.is_file() {
+        anyhow::bail!("{} is not a file", opt.db_path.display());
+    }
+    let options = ExtractOptions {
+        target: opt.target,
+        ..ExtractOptions::default()
+    };
+    let drift = verify_extraction(&opt.db_path, &opt.extract_path, &options)?;
+    for difference in &drift {
+        println!("{}", difference);
+    }
+    if !drift.is_empty() {
+        anyhow::bail!("the extraction differs from the database");
+    }
+    println!("every post matches the database");
+    Ok(())
+}
+
+fn run_stats(opt: Stats) -> Result<(), anyhow::Error> {
+    let options = ExtractOptions {
+        prefix: opt.archive.prefix,
+        ..ExtractOptions::default()
+    };
+    print!("{}", archive_stats(&opt.archive.archive_path, &options)?);
+    Ok(())
+}
 
+fn run_diff(opt: Diff) -> Result<(), anyhow::Error> {
+    if !opt.old.is_dir() {
+        anyhow::bail!("{} is not a directory", opt.old.display());
+    }
+    let options = ExtractOptions {
+        prefix: opt.prefix,
+        target: opt.target,
+        ..ExtractOptions::default()
+    };
+    let differences = if opt.new.is_dir() {
+        diff_extractions(&opt.old, &opt.new, &options)?
+    } else {
+        diff_archive(&opt.old, &opt.new, &options)?
+    };
+    for difference in &differences {
+        println!("{}", difference);
+    }
+    if !differences.is_empty() {
+        anyhow::bail!("{} files differ", differences.len());
+    }
+    println!("the extractions match");
     Ok(())
 }
@@ -0,0 +1,1114 @@
+//! Conversion of the cards of Ghost's editor into markdown.
+//!
+//! Cards are the editor's rich content, i.e. captioned images and embedded videos. They are stored
+//! in the post's mobiledoc or lexical document, or rendered into html, which is sometimes stored
+//! verbatim in the post's markdown. Left alone, Zola passes that html through untouched, so themes
+//! can't style it and links within it aren't checked.
+
+use std::collections::BTreeMap;
+
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde_json::Value;
+
+use crate::Shortcodes;
+
+lazy_static! {
+    // an image, optionally linked, and its optional caption
+    static ref FIGURE: Regex = Regex::new(
+        r"(?is)<figure\b[^>]*>\s*(?:<a\b([^>]*)>\s*)?(<img\b[^>]*>)\s*(?:</a>\s*)?(?:<figcaption\b[^>]*>(.*?)</figcaption>\s*)?</figure>"
+    )
+    .unwrap();
+    // a gallery of images, laid out in rows
+    static ref GALLERY: Regex =
+        Regex::new(r#"(?is)<figure\b[^>]*\bkg-gallery-card\b[^>]*>(.*?)</figure>"#).unwrap();
+    // a rich preview of a link
+    static ref BOOKMARK: Regex =
+        Regex::new(r#"(?is)<figure\b[^>]*\bkg-bookmark-card\b[^>]*>(.*?)</figure>"#).unwrap();
+    static ref BOOKMARK_CONTAINER: Regex =
+        Regex::new(r"(?is)<a\b([^>]*\bkg-bookmark-container\b[^>]*)>").unwrap();
+    static ref BOOKMARK_FIELD: Regex = Regex::new(
+        r"(?is)<(?:div|span)\b[^>]*\bkg-bookmark-(title|description|author|publisher)\b[^>]*>(.*?)</(?:div|span)>"
+    )
+    .unwrap();
+    static ref BOOKMARK_THUMBNAIL: Regex =
+        Regex::new(r"(?is)\bkg-bookmark-thumbnail\b[^>]*>\s*(<img\b[^>]*>)").unwrap();
+    // a box of text, introduced by an emoji
+    static ref CALLOUT: Regex = Regex::new(
+        r"(?is)<div\b([^>]*\bkg-callout-card\b[^>]*)>\s*(?:<div\b[^>]*\bkg-callout-emoji\b[^>]*>(.*?)</div>\s*)?<div\b[^>]*\bkg-callout-text\b[^>]*>(.*?)</div>\s*</div>"
+    )
+    .unwrap();
+    static ref CALLOUT_COLOR: Regex = Regex::new(r"\bkg-callout-card-([\w-]+)").unwrap();
+    // a heading which reveals its content when clicked
+    static ref TOGGLE: Regex = Regex::new(
+        r"(?is)<div\b[^>]*\bkg-toggle-card\b[^>]*>\s*<div\b[^>]*\bkg-toggle-heading\b[^>]*>\s*<h\d\b[^>]*>(.*?)</h\d>.*?</div>\s*<div\b[^>]*\bkg-toggle-content\b[^>]*>(.*?)</div>\s*</div>"
+    )
+    .unwrap();
+    // a link styled as a call to action
+    static ref BUTTON: Regex = Regex::new(
+        r"(?is)<div\b([^>]*\bkg-button-card\b[^>]*)>\s*<a\b([^>]*)>(.*?)</a>\s*</div>"
+    )
+    .unwrap();
+    static ref BUTTON_ALIGNMENT: Regex = Regex::new(r"\bkg-align-([\w-]+)").unwrap();
+    // a captioned code block
+    static ref CODE_FIGURE: Regex = Regex::new(
+        r"(?is)<figure\b[^>]*\bkg-code-card\b[^>]*>\s*<pre\b[^>]*>\s*<code\b([^>]*)>(.*?)</code>\s*</pre>\s*(?:<figcaption\b[^>]*>(.*?)</figcaption>\s*)?</figure>"
+    )
+    .unwrap();
+    static ref CODE_LANGUAGE: Regex = Regex::new(r"\blang(?:uage)?-([\w+#.-]+)").unwrap();
+    static ref PARAGRAPH: Regex = Regex::new(r"(?is)<p\b[^>]*>(.*?)</p>").unwrap();
+    static ref LINE_BREAK: Regex = Regex::new(r"(?i)<br\s*/?>").unwrap();
+    static ref GALLERY_ROW: Regex = Regex::new(r"(?i)\bkg-gallery-row\b").unwrap();
+    static ref IMG: Regex = Regex::new(r"(?i)<img\b[^>]*>").unwrap();
+    static ref FIGCAPTION: Regex =
+        Regex::new(r"(?is)<figcaption\b[^>]*>(.*?)</figcaption>").unwrap();
+    // the comments with which ghost brackets each card it renders into html, and any whitespace
+    // left behind on their lines
+    static ref CARD_MARKER: Regex =
+        Regex::new(r"(?m)^[ \t]*<!--\s*kg-card-(?:begin|end)\b[^>]*-->[ \t]*(?:\r?\n)?|<!--\s*kg-card-(?:begin|end)\b[^>]*-->").unwrap();
+    // a card which is still html, i.e. `<figure class="kg-card kg-embed-card">`
+    static ref UNCONVERTED_CARD: Regex =
+        Regex::new(r"(?i)<(?:figure|div)\b[^>]*\bkg-(?:[\w-]+-)?card\b").unwrap();
+    static ref ATTRIBUTE: Regex =
+        Regex::new(r#"(?i)\b([\w-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap();
+    static ref INLINE_LINK: Regex = Regex::new(r"(?is)<a\b([^>]*)>(.*?)</a>").unwrap();
+    static ref INLINE_EMPHASIS: Regex = Regex::new(r"(?is)<(?:em|i)\b[^>]*>(.*?)</(?:em|i)>").unwrap();
+    static ref INLINE_STRONG: Regex =
+        Regex::new(r"(?is)<(?:strong|b)\b[^>]*>(.*?)</(?:strong|b)>").unwrap();
+    static ref INLINE_CODE: Regex = Regex::new(r"(?is)<code\b[^>]*>(.*?)</code>").unwrap();
+    static ref TAG: Regex = Regex::new(r"<[^>]*>").unwrap();
+}
+
+/// find the value of the named attribute among a tag's attributes
+pub(crate) fn attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    ATTRIBUTE.captures_iter(attributes).find_map(|captures| {
+        if captures[1].eq_ignore_ascii_case(name) {
+            captures
+                .get(2)
+                .or_else(|| captures.get(3))
+                .map(|value| value.as_str())
+        } else {
+            None
+        }
+    })
+}
+
+/// decode the html entities which commonly appear in text
+pub(crate) fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// escape text which markdown would otherwise interpret
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '<') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// convert inline html, i.e. a caption, into markdown
+///
+/// Links, emphasis and code are preserved; other tags are dropped.
+pub(crate) fn inline_markdown(html: &str) -> String {
+    let text = INLINE_LINK.replace_all(html, |captures: &Captures| {
+        match attribute(&captures[1], "href") {
+            Some(href) => format!("[{}]({})", &captures[2], href),
+            None => captures[2].to_string(),
+        }
+    });
+    let text = INLINE_STRONG.replace_all(&text, "**$1**");
+    let text = INLINE_EMPHASIS.replace_all(&text, "_${1}_");
+    let text = INLINE_CODE.replace_all(&text, "`$1`");
+    let text = TAG.replace_all(&text, "");
+    decode_entities(text.trim())
+}
+
+/// convert html paragraphs, i.e. a card's text, into markdown
+///
+/// Line breaks are preserved; other block elements are reduced to their text.
+fn paragraphs_markdown(html: &str) -> String {
+    let paragraph = |html: &str| {
+        LINE_BREAK
+            .split(html)
+            .map(inline_markdown)
+            .collect::<Vec<_>>()
+            .join("\\\n")
+    };
+    let paragraphs: Vec<_> = PARAGRAPH
+        .captures_iter(html)
+        .map(|captures| paragraph(&captures[1]))
+        .filter(|paragraph| !paragraph.is_empty())
+        .collect();
+    if paragraphs.is_empty() {
+        paragraph(html)
+    } else {
+        paragraphs.join("\n\n")
+    }
+}
+
+/// remove the `<!--kg-card-begin: ...-->` and `<!--kg-card-end: ...-->` markers around cards
+pub(crate) fn strip_card_markers(text: &str) -> String {
+    CARD_MARKER.replace_all(text, "").into_owned()
+}
+
+/// whether any cards are left as html by [`convert_cards`]
+pub(crate) fn has_unconverted_cards(text: &str) -> bool {
+    UNCONVERTED_CARD.is_match(text)
+}
+
+/// quote a string argument to a shortcode
+fn quote(value: &str) -> String {
+    // zola's string arguments have no escapes, but may use any of three quotes
+    let quote = ['"', '\'', '`']
+        .iter()
+        .copied()
+        .find(|quote| !value.contains(*quote))
+        .unwrap_or('"');
+    let value = if value.contains(quote) {
+        value.replace(quote, "'")
+    } else {
+        value.to_string()
+    };
+    format!("{}{}{}", quote, value, quote)
+}
+
+/// render a call to a function with string arguments
+fn call(name: &str, args: &[(&str, &str)]) -> String {
+    let args: Vec<_> = args
+        .iter()
+        .map(|(arg, value)| format!("{}={}", arg, quote(value)))
+        .collect();
+    format!("{}({})", name, args.join(", "))
+}
+
+/// render a call to a shortcode with string arguments
+pub(crate) fn shortcode(name: &str, args: &[(&str, &str)]) -> String {
+    format!("{{{{ {} }}}}", call(name, args))
+}
+
+/// render a call to a shortcode with string arguments and a body
+fn body_shortcode(name: &str, args: &[(&str, &str)], body: &str) -> String {
+    format!("{{% {} %}}\n{}\n{{% end %}}", call(name, args), body)
+}
+
+/// render a caption beneath an image
+///
+/// With a shortcode, the caption is passed to it as `text`; otherwise it is emphasized.
+fn caption(text: &str, shortcodes: &Shortcodes) -> String {
+    match shortcodes.get("caption") {
+        Some(name) => shortcode(name, &[("text", text)]),
+        None => format!("*{}*", text),
+    }
+}
+
+/// render an image, optionally linked, and its caption, which is html
+fn image(
+    src: &str,
+    alt: &str,
+    href: Option<&str>,
+    caption_html: Option<&str>,
+    shortcodes: &Shortcodes,
+) -> String {
+    let mut markdown = format!("![{}]({})", alt, src);
+    if let Some(href) = href.filter(|href| !href.is_empty()) {
+        markdown = format!("[{}]({})", markdown, href);
+    }
+    if let Some(caption_html) = caption_html {
+        let text = inline_markdown(caption_html);
+        if !text.is_empty() {
+            markdown.push('\n');
+            markdown.push_str(&caption(&text, shortcodes));
+        }
+    }
+    markdown
+}
+
+/// render a gallery of images, each row of which is a list of markdown images, and its caption
+///
+/// With a shortcode, the rows are its body; otherwise they're paragraphs of their own.
+fn gallery(rows: &[Vec<String>], caption_html: Option<&str>, shortcodes: &Shortcodes) -> String {
+    let rows: Vec<_> = rows
+        .iter()
+        .filter(|row| !row.is_empty())
+        .map(|row| row.join("\n"))
+        .collect();
+    if rows.is_empty() {
+        return String::new();
+    }
+    let mut markdown = rows.join("\n\n");
+    if let Some(name) = shortcodes.get("gallery") {
+        markdown = body_shortcode(name, &[], &markdown);
+    }
+    if let Some(caption_html) = caption_html {
+        let text = inline_markdown(caption_html);
+        if !text.is_empty() {
+            markdown.push('\n');
+            markdown.push_str(&caption(&text, shortcodes));
+        }
+    }
+    markdown
+}
+
+/// the fields of a bookmark's metadata which are preserved, in order
+const BOOKMARK_FIELDS: &[&str] = &["title", "description", "author", "publisher", "thumbnail"];
+
+/// render a bookmark, a rich preview of a link, given its metadata, and its caption
+///
+/// With a shortcode, the metadata are passed to it alongside the `url`; otherwise the link is
+/// quoted, with its description, author and publisher beneath it.
+fn bookmark(
+    url: &str,
+    metadata: &[(&str, String)],
+    caption_html: Option<&str>,
+    shortcodes: &Shortcodes,
+) -> String {
+    let field = |name: &str| {
+        metadata
+            .iter()
+            .find(|(field, value)| *field == name && !value.is_empty())
+            .map(|(_, value)| value.as_str())
+    };
+    let mut markdown = match shortcodes.get("bookmark") {
+        Some(name) => {
+            let mut args = vec![("url", url)];
+            args.extend(
+                BOOKMARK_FIELDS
+                    .iter()
+                    .filter_map(|name| field(name).map(|value| (*name, value))),
+            );
+            shortcode(name, &args)
+        }
+        None => {
+            let mut lines = vec![format!(
+                "[{}]({})",
+                escape(field("title").unwrap_or(url)),
+                url
+            )];
+            if let Some(description) = field("description") {
+                lines.push(escape(description));
+            }
+            let byline: Vec<_> = ["author", "publisher"]
+                .iter()
+                .filter_map(|name| field(name))
+                .map(escape)
+                .collect();
+            if !byline.is_empty() {
+                lines.push(format!("_{}_", byline.join(" · ")));
+            }
+            lines
+                .iter()
+                .map(|line| format!("> {}", line))
+                .collect::<Vec<_>>()
+                .join("\n>\n")
+        }
+    };
+    if let Some(text) = caption_html.map(inline_markdown) {
+        if !text.is_empty() {
+            markdown.push_str("\n\n");
+            markdown.push_str(&caption(&text, shortcodes));
+        }
+    }
+    markdown
+}
+
+/// render a callout, a box of text introduced by an emoji, given its text as html
+///
+/// With a shortcode, the text is its body, and the `emoji` and background `color` are passed to it
+/// where known; otherwise the text is quoted, behind its emoji.
+fn callout(
+    emoji: Option<&str>,
+    color: Option<&str>,
+    text_html: &str,
+    shortcodes: &Shortcodes,
+) -> String {
+    let emoji = emoji.filter(|emoji| !emoji.is_empty());
+    let lines: Vec<_> = LINE_BREAK.split(text_html).map(inline_markdown).collect();
+    match shortcodes.get("callout") {
+        Some(name) => {
+            let args: Vec<_> = [("emoji", emoji), ("color", color)]
+                .iter()
+                .filter_map(|(arg, value)| value.map(|value| (*arg, value)))
+                .collect();
+            body_shortcode(name, &args, &lines.join("\\\n"))
+        }
+        None => {
+            let text = lines.join("\\\n> ");
+            match emoji {
+                Some(emoji) => format!("> {} {}", emoji, text),
+                None => format!("> {}", text),
+            }
+        }
+    }
+}
+
+/// render a toggle, a heading which reveals its content when clicked, given both as html
+///
+/// With a shortcode, the content is its body, and the `heading` is passed to it; otherwise they
+/// become a `<details>` element, in which the content remains markdown.
+fn toggle(heading_html: &str, content_html: &str, shortcodes: &Shortcodes) -> String {
+    let heading = inline_markdown(heading_html);
+    let content = paragraphs_markdown(content_html);
+    match shortcodes.get("toggle") {
+        Some(name) => body_shortcode(name, &[("heading", &heading)], &content),
+        None => format!(
+            "<details>\n<summary>{}</summary>\n\n{}\n\n</details>",
+            heading_html.trim(),
+            content
+        ),
+    }
+}
+
+/// render a button, a link styled as a call to action
+///
+/// With a shortcode, the `url`, `text` and `alignment` are passed to it; otherwise it's a link.
+fn button(url: &str, text: &str, alignment: Option<&str>, shortcodes: &Shortcodes) -> String {
+    match shortcodes.get("button") {
+        Some(name) => {
+            let mut args = vec![("url", url), ("text", text)];
+            args.extend(alignment.map(|alignment| ("alignment", alignment)));
+            shortcode(name, &args)
+        }
+        None => format!("[{}]({})", escape(text), url),
+    }
+}
+
+/// escape text for an html attribute value
+fn escape_attribute(text: &str) -> String {
+    text.replace('&', "&amp;").replace('"', "&quot;")
+}
+
+/// render an html element with the given attributes, and no content
+///
+/// Attributes without values are rendered bare, i.e. `controls`.
+fn element(tag: &str, attributes: &[(&str, Option<&str>)]) -> String {
+    let attributes: String = attributes
+        .iter()
+        .map(|(name, value)| match value {
+            Some(value) => format!(" {}=\"{}\"", name, escape_attribute(value)),
+            None => format!(" {}", name),
+        })
+        .collect();
+    format!("<{}{}></{}>", tag, attributes, tag)
+}
+
+/// render an audio player, and its title beneath it
+///
+/// With a shortcode, the `src` and `title` are passed to it; otherwise it's an `<audio>` element.
+fn audio(src: &str, title: Option<&str>, shortcodes: &Shortcodes) -> String {
+    match shortcodes.get("audio") {
+        Some(name) => {
+            let mut args = vec![("src", src)];
+            args.extend(title.map(|title| ("title", title)));
+            shortcode(name, &args)
+        }
+        None => {
+            let mut html = element("audio", &[("controls", None), ("src", Some(src))]);
+            if let Some(title) = title {
+                html.push_str("\n\n");
+                html.push_str(&caption(&escape(title), shortcodes));
+            }
+            html
+        }
+    }
+}
+
+/// render a video player, and its caption, which is html
+///
+/// With a shortcode, the `src`, and the `poster`, `width`, `height` and `loop` where known, are
+/// passed to it; otherwise it's a `<video>` element.
+fn video(src: &str, payload: &Value, shortcodes: &Shortcodes) -> String {
+    let field = |field: &str| payload[field].as_str().filter(|value| !value.is_empty());
+    let poster = field("customThumbnailSrc").or_else(|| field("thumbnailSrc"));
+    let width = payload["width"].as_u64().map(|width| width.to_string());
+    let height = payload["height"].as_u64().map(|height| height.to_string());
+    let looped = payload["loop"].as_bool().unwrap_or_default();
+    let optional = [
+        ("poster", poster),
+        ("width", width.as_deref()),
+        ("height", height.as_deref()),
+    ];
+    let mut markdown = match shortcodes.get("video") {
+        Some(name) => {
+            let mut args = vec![("src", src)];
+            args.extend(
+                optional
+                    .iter()
+                    .filter_map(|(arg, value)| value.map(|value| (*arg, value))),
+            );
+            if looped {
+                args.push(("loop", "true"));
+            }
+            shortcode(name, &args)
+        }
+        None => {
+            let mut attributes = vec![("controls", None), ("src", Some(src))];
+            attributes.extend(
+                optional
+                    .iter()
+                    .filter(|(_, value)| value.is_some())
+                    .copied(),
+            );
+            if looped {
+                attributes.push(("loop", None));
+            }
+            element("video", &attributes)
+        }
+    };
+    if let Some(text) = field("caption").map(inline_markdown) {
+        if !text.is_empty() {
+            markdown.push_str("\n\n");
+            markdown.push_str(&caption(&text, shortcodes));
+        }
+    }
+    markdown
+}
+
+/// describe a file's size in bytes as Ghost does, i.e. `1.5 MB`
+fn file_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} bytes", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// render a link to download a file, given its card's payload
+///
+/// With a shortcode, the `src`, and the file's `name`, `size`, `title` and `caption` where known,
+/// are passed to it; otherwise it's a link labelled with the file's name and size, after its title
+/// and above its caption.
+fn file(src: &str, payload: &Value, shortcodes: &Shortcodes) -> String {
+    let field = |field: &str| payload[field].as_str().filter(|value| !value.is_empty());
+    let name = field("fileName")
+        .or_else(|| src.rsplit('/').next())
+        .unwrap_or(src);
+    let size = payload["fileSize"].as_u64().map(file_size);
+    let title = field("fileTitle").map(inline_markdown);
+    let file_caption = field("fileCaption").map(inline_markdown);
+    if let Some(shortcode_name) = shortcodes.get("file") {
+        let optional = [
+            ("size", size.as_deref()),
+            ("title", title.as_deref()),
+            ("caption", file_caption.as_deref()),
+        ];
+        let mut args = vec![("src", src), ("name", name)];
+        args.extend(
+            optional
+                .iter()
+                .filter_map(|(arg, value)| value.map(|value| (*arg, value))),
+        );
+        return shortcode(shortcode_name, &args);
+    }
+    let mut markdown = format!("[{}]({})", escape(name), src);
+    if let Some(size) = size {
+        markdown = format!("{} ({})", markdown, size);
+    }
+    if let Some(title) = title.filter(|title| !title.is_empty() && title != name) {
+        markdown = format!("**{}**: {}", title, markdown);
+    }
+    if let Some(text) = file_caption.filter(|text| !text.is_empty()) {
+        markdown.push('\n');
+        markdown.push_str(&caption(&text, shortcodes));
+    }
+    markdown
+}
+
+/// render a fenced block of code, tagged with its language, and its caption, which is html
+fn code(
+    code: &str,
+    language: Option<&str>,
+    caption_html: Option<&str>,
+    shortcodes: &Shortcodes,
+) -> String {
+    // the fence must be longer than any run of backticks within the code
+    let longest_run = code
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    let mut markdown = format!(
+        "{}{}\n{}\n{}",
+        fence,
+        language.unwrap_or_default(),
+        code.trim_end_matches('\n'),
+        fence
+    );
+    if let Some(text) = caption_html.map(inline_markdown) {
+        if !text.is_empty() {
+            markdown.push('\n');
+            markdown.push_str(&caption(&text, shortcodes));
+        }
+    }
+    markdown
+}
+
+/// convert the html of each card into markdown
+///
+/// Each `<figure>` of an image, and its `<figcaption>`, becomes a markdown image and caption.
+/// Code blocks, galleries, bookmarks, callouts, toggles and buttons are converted likewise; see
+/// [`code`], [`gallery`], [`bookmark`], [`callout`], [`toggle`] and [`button`]. Figures of anything else are left alone.
+pub(crate) fn convert_cards(text: &str, shortcodes: &Shortcodes) -> String {
+    let text = CALLOUT.replace_all(text, |captures: &Captures| {
+        let color = CALLOUT_COLOR
+            .captures(&captures[1])
+            .and_then(|color| color.get(1))
+            .map(|color| color.as_str());
+        let emoji = captures.get(2).map(|emoji| inline_markdown(emoji.as_str()));
+        callout(emoji.as_deref(), color, &captures[3], shortcodes)
+    });
+    let text = CODE_FIGURE.replace_all(&text, |captures: &Captures| {
+        let language = CODE_LANGUAGE
+            .captures(&captures[1])
+            .and_then(|language| language.get(1))
+            .map(|language| language.as_str());
+        code(
+            &decode_entities(&captures[2]),
+            language,
+            captures.get(3).map(|caption| caption.as_str()),
+            shortcodes,
+        )
+    });
+    let text = TOGGLE.replace_all(&text, |captures: &Captures| {
+        toggle(&captures[1], &captures[2], shortcodes)
+    });
+    let text = BUTTON.replace_all(&text, |captures: &Captures| {
+        let url = match attribute(&captures[2], "href") {
+            Some(url) => url,
+            None => return captures[0].to_string(),
+        };
+        let alignment = BUTTON_ALIGNMENT
+            .captures(&captures[1])
+            .and_then(|alignment| alignment.get(1))
+            .map(|alignment| alignment.as_str());
+        let text = decode_entities(&TAG.replace_all(&captures[3], ""));
+        button(url, text.trim(), alignment, shortcodes)
+    });
+    let text = BOOKMARK.replace_all(&text, |captures: &Captures| {
+        let body = &captures[1];
+        let url = match BOOKMARK_CONTAINER
+            .captures(body)
+            .and_then(|container| attribute(container.get(1)?.as_str(), "href"))
+        {
+            Some(url) => url,
+            None => return captures[0].to_string(),
+        };
+        let mut metadata: Vec<_> = BOOKMARK_FIELD
+            .captures_iter(body)
+            .map(|field| (field.get(1).unwrap().as_str(), inline_markdown(&field[2])))
+            .collect();
+        if let Some(src) = BOOKMARK_THUMBNAIL
+            .captures(body)
+            .and_then(|thumbnail| attribute(thumbnail.get(1)?.as_str(), "src"))
+        {
+            metadata.push(("thumbnail", src.to_string()));
+        }
+        let caption = FIGCAPTION
+            .captures(body)
+            .and_then(|caption| caption.get(1))
+            .map(|caption| caption.as_str());
+        bookmark(url, &metadata, caption, shortcodes)
+    });
+    let text = GALLERY.replace_all(&text, |captures: &Captures| {
+        let body = &captures[1];
+        let rows: Vec<Vec<_>> = GALLERY_ROW
+            .split(body)
+            .skip(1)
+            .map(|row| {
+                IMG.find_iter(row)
+                    .filter_map(|img| {
+                        let img = img.as_str();
+                        let src = attribute(img, "src")?;
+                        let alt = decode_entities(attribute(img, "alt").unwrap_or_default());
+                        Some(image(src, &alt, None, None, shortcodes))
+                    })
+                    .collect()
+            })
+            .collect();
+        if rows.iter().all(Vec::is_empty) {
+            return captures[0].to_string();
+        }
+        let caption = FIGCAPTION
+            .captures(body)
+            .and_then(|caption| caption.get(1))
+            .map(|caption| caption.as_str());
+        gallery(&rows, caption, shortcodes)
+    });
+    FIGURE
+        .replace_all(&text, |captures: &Captures| {
+            let img = &captures[2];
+            let src = match attribute(img, "src") {
+                Some(src) => src,
+                None => return captures[0].to_string(),
+            };
+            image(
+                src,
+                &decode_entities(attribute(img, "alt").unwrap_or_default()),
+                captures
+                    .get(1)
+                    .and_then(|attributes| attribute(attributes.as_str(), "href")),
+                captures.get(3).map(|caption| caption.as_str()),
+                shortcodes,
+            )
+        })
+        .into_owned()
+}
+
+/// A card from Ghost's editor which can't be rendered.
+#[derive(Debug, thiserror::Error)]
+#[error("unsupported card: {0}")]
+pub(crate) struct UnsupportedCard(pub(crate) String);
+
+/// render a card from a mobiledoc or lexical document into markdown
+///
+/// Mobiledoc cards and lexical nodes name their cards, and the fields of their payloads, alike.
+pub(crate) fn render_card(
+    name: &str,
+    payload: &Value,
+    shortcodes: &Shortcodes,
+) -> Result<String, UnsupportedCard> {
+    let field = |field: &str| payload[field].as_str().filter(|value| !value.is_empty());
+    match name {
+        "hr" | "horizontalrule" => Ok("---".into()),
+        // markdown is inlined, and html left for zola to pass through, as in the post's markdown
+        "markdown" | "card-markdown" => Ok(field("markdown").unwrap_or_default().trim().into()),
+        "html" => Ok(field("html").unwrap_or_default().trim().into()),
+        "code" | "codeblock" => Ok(code(
+            payload["code"].as_str().unwrap_or_default(),
+            field("language"),
+            field("caption"),
+            shortcodes,
+        )),
+        "image" => Ok(match field("src") {
+            Some(src) => image(
+                src,
+                field("alt").unwrap_or_default(),
+                field("href"),
+                field("caption"),
+                shortcodes,
+            ),
+            None => String::new(),
+        }),
+        "gallery" => {
+            let mut rows = BTreeMap::<u64, Vec<String>>::new();
+            for item in payload["images"].as_array().into_iter().flatten() {
+                let field = |field: &str| item[field].as_str().filter(|value| !value.is_empty());
+                if let Some(src) = field("src") {
+                    rows.entry(item["row"].as_u64().unwrap_or_default())
+                        .or_default()
+                        .push(image(
+                            src,
+                            field("alt").unwrap_or_default(),
+                            field("href"),
+                            None,
+                            shortcodes,
+                        ));
+                }
+            }
+            let rows: Vec<_> = rows.into_values().collect();
+            Ok(gallery(&rows, field("caption"), shortcodes))
+        }
+        "embed" => Ok(embed(payload, shortcodes)),
+        "audio" => Ok(match field("src") {
+            Some(src) => audio(src, field("title"), shortcodes),
+            None => String::new(),
+        }),
+        "video" => Ok(match field("src") {
+            Some(src) => video(src, payload, shortcodes),
+            None => String::new(),
+        }),
+        "file" => Ok(match field("src") {
+            Some(src) => file(src, payload, shortcodes),
+            None => String::new(),
+        }),
+        "button" => Ok(match field("buttonUrl") {
+            Some(url) => button(
+                url,
+                field("buttonText").unwrap_or(url),
+                field("alignment"),
+                shortcodes,
+            ),
+            None => String::new(),
+        }),
+        "toggle" => Ok(toggle(
+            field("heading").unwrap_or_default(),
+            field("content").unwrap_or_default(),
+            shortcodes,
+        )),
+        "callout" => Ok(callout(
+            field("calloutEmoji"),
+            field("backgroundColor"),
+            field("calloutText").unwrap_or_default(),
+            shortcodes,
+        )),
+        "bookmark" => Ok(match field("url") {
+            Some(url) => {
+                let metadata: Vec<_> = BOOKMARK_FIELDS
+                    .iter()
+                    .filter_map(|name| {
+                        let value = payload["metadata"][*name].as_str()?;
+                        Some((*name, value.to_string()))
+                    })
+                    .collect();
+                bookmark(url, &metadata, field("caption"), shortcodes)
+            }
+            None => String::new(),
+        }),
+        _ => Err(UnsupportedCard(name.into())),
+    }
+}
+
+/// the provider of embedded content, i.e. `youtube`
+fn embed_provider(payload: &Value, url: &str) -> Option<String> {
+    if let Some(provider) = payload["metadata"]["provider_name"].as_str() {
+        return Some(provider.to_ascii_lowercase());
+    }
+    let host = url.split("://").nth(1)?.split('/').next()?;
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    Some(match host {
+        "youtu.be" | "youtube.com" | "m.youtube.com" => "youtube".into(),
+        "twitter.com" | "x.com" => "twitter".into(),
+        _ => host.split('.').next()?.to_string(),
+    })
+}
+
+/// find the id of a youtube or vimeo video from its url
+fn video_id<'a>(provider: &str, url: &'a str) -> Option<&'a str> {
+    let id = match provider {
+        "youtube" => {
+            let query_id = url
+                .split(&['?', '&'][..])
+                .find_map(|param| param.strip_prefix("v="));
+            match query_id {
+                Some(id) => id,
+                // youtu.be/ID, youtube.com/embed/ID, youtube.com/shorts/ID
+                None => url.split(&['?', '#'][..]).next()?.rsplit('/').next()?,
+            }
+        }
+        "vimeo" => url
+            .split(&['?', '#'][..])
+            .next()?
+            .rsplit('/')
+            .find(|segment| !segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit()))?,
+        _ => return None,
+    };
+    let id = id.split('#').next().unwrap_or_default();
+    Some(id).filter(|id| !id.is_empty())
+}
+
+/// render an embed card
+///
+/// Content from a provider with a shortcode is rendered by it; otherwise the html which Ghost
+/// embedded is passed through, or failing that, the url is linked.
+fn embed(payload: &Value, shortcodes: &Shortcodes) -> String {
+    let url = payload["url"].as_str().unwrap_or_default();
+    let provider = embed_provider(payload, url).unwrap_or_default();
+    let shortcode_call = shortcodes
+        .get(&provider)
+        .and_then(|name| match provider.as_str() {
+            "youtube" | "vimeo" => {
+                video_id(&provider, url).map(|id| shortcode(name, &[("id", id)]))
+            }
+            _ if !url.is_empty() => Some(shortcode(name, &[("url", url)])),
+            _ => None,
+        });
+    let mut markdown = match (shortcode_call, payload["html"].as_str()) {
+        (Some(call), _) => call,
+        (None, Some(html)) if !html.trim().is_empty() => html.trim().to_string(),
+        _ => format!("<{}>", url),
+    };
+    if let Some(text) = payload["caption"].as_str().map(inline_markdown) {
+        if !text.is_empty() {
+            markdown.push_str("\n\n");
+            markdown.push_str(&caption(&text, shortcodes));
+        }
+    }
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn caption_shortcode() -> Shortcodes {
+        let mut shortcodes = Shortcodes::default();
+        shortcodes.set("caption", Some("caption".into()));
+        shortcodes
+    }
+
+    #[test]
+    fn converts_captioned_figure() {
+        let html = r#"<figure class="kg-card kg-image-card kg-card-hascaption"><img src="/content/images/2020/01/a.jpg" class="kg-image" alt="a &amp; b"><figcaption>By <a href="https://example.com">Pete</a>, <em>2020</em></figcaption></figure>"#;
+        assert_eq!(
+            convert_cards(html, &Shortcodes::default()),
+            "![a & b](/content/images/2020/01/a.jpg)\n*By [Pete](https://example.com), _2020_*",
+        );
+        assert_eq!(
+            convert_cards(html, &caption_shortcode()),
+            "![a & b](/content/images/2020/01/a.jpg)\n{{ caption(text=\"By [Pete](https://example.com), _2020_\") }}",
+        );
+    }
+
+    #[test]
+    fn converts_linked_figure() {
+        let html = "<figure><a href=\"/hello/\"><img src=\"a.jpg\"></a></figure>";
+        assert_eq!(
+            convert_cards(html, &Shortcodes::default()),
+            "[![](a.jpg)](/hello/)"
+        );
+    }
+
+    #[test]
+    fn leaves_other_figures() {
+        let html = "<figure class=\"kg-embed-card\"><iframe src=\"x\"></iframe></figure>";
+        assert_eq!(convert_cards(html, &Shortcodes::default()), html);
+        assert!(has_unconverted_cards(html));
+        assert!(!has_unconverted_cards(
+            "<figure><img src=\"a.png\"></figure> <div class=\"kg-cards\"></div>"
+        ));
+    }
+
+    #[test]
+    fn converts_galleries() {
+        let html = "<figure class=\"kg-card kg-gallery-card kg-width-wide\"><div class=\"kg-gallery-container\"><div class=\"kg-gallery-row\"><div class=\"kg-gallery-image\"><img src=\"/content/images/a.jpg\" alt=\"A\"></div><div class=\"kg-gallery-image\"><img src=\"/content/images/b.jpg\"></div></div><div class=\"kg-gallery-row\"><div class=\"kg-gallery-image\"><img src=\"/content/images/c.jpg\"></div></div></div><figcaption>Three</figcaption></figure>";
+        assert_eq!(
+            convert_cards(html, &Shortcodes::default()),
+            "![A](/content/images/a.jpg)\n![](/content/images/b.jpg)\n\n![](/content/images/c.jpg)\n*Three*",
+        );
+
+        let mut shortcodes = Shortcodes::default();
+        shortcodes.set("gallery", Some("gallery".into()));
+        let card = json!({
+            "images": [
+                { "src": "/content/images/c.jpg", "row": 1 },
+                { "src": "/content/images/a.jpg", "alt": "A", "row": 0 },
+                { "src": "/content/images/b.jpg", "row": 0, "href": "https://example.com" },
+            ],
+        });
+        assert_eq!(
+            render_card("gallery", &card, &shortcodes).unwrap(),
+            "{% gallery() %}\n![A](/content/images/a.jpg)\n[![](/content/images/b.jpg)](https://example.com)\n\n![](/content/images/c.jpg)\n{% end %}",
+        );
+    }
+
+    #[test]
+    fn converts_bookmarks() {
+        let html = r#"<figure class="kg-card kg-bookmark-card"><a class="kg-bookmark-container" href="https://example.com/post"><div class="kg-bookmark-content"><div class="kg-bookmark-title">A *post*</div><div class="kg-bookmark-description">About &amp; things.</div><div class="kg-bookmark-metadata"><img class="kg-bookmark-icon" src="https://example.com/icon.png"><span class="kg-bookmark-author">Pete</span><span class="kg-bookmark-publisher">Example</span></div></div><div class="kg-bookmark-thumbnail"><img src="https://example.com/thumb.png"></div></a><figcaption>Worth a read</figcaption></figure>"#;
+        assert_eq!(
+            convert_cards(html, &Shortcodes::default()),
+            "> [A \\*post\\*](https://example.com/post)\n>\n> About & things.\n>\n> _Pete · Example_\n\n*Worth a read*",
+        );
+
+        let mut shortcodes = Shortcodes::default();
+        shortcodes.set("bookmark", Some("bookmark".into()));
+        let card = json!({
+            "url": "https://example.com/post",
+            "metadata": {
+                "title": "A post",
+                "description": "About things.",
+                "publisher": "",
+                "thumbnail": "https://example.com/thumb.png",
+            },
+        });
+        assert_eq!(
+            render_card("bookmark", &card, &shortcodes).unwrap(),
+            "{{ bookmark(url=\"https://example.com/post\", title=\"A post\", description=\"About things.\", thumbnail=\"https://example.com/thumb.png\") }}",
+        );
+    }
+
+    #[test]
+    fn converts_callouts() {
+        let html = r#"<div class="kg-card kg-callout-card kg-callout-card-blue"><div class="kg-callout-emoji">💡</div><div class="kg-callout-text">Mind <b>the</b> gap<br>twice</div></div>"#;
+        assert_eq!(
+            convert_cards(html, &Shortcodes::default()),
+            "> 💡 Mind **the** gap\\\n> twice",
+        );
+
+        let mut shortcodes = Shortcodes::default();
+        shortcodes.set("callout", Some("callout".into()));
+        assert_eq!(
+            convert_cards(html, &shortcodes),
+            "{% callout(emoji=\"💡\", color=\"blue\") %}\nMind **the** gap\\\ntwice\n{% end %}",
+        );
+        let card =
+            json!({ "calloutText": "<i>Note</i>", "calloutEmoji": "", "backgroundColor": "grey" });
+        assert_eq!(
+            render_card("callout", &card, &Shortcodes::default()).unwrap(),
+            "> _Note_",
+        );
+    }
+
+    #[test]
+    fn converts_toggles() {
+        let html = r#"<div class="kg-card kg-toggle-card" data-kg-toggle-state="close"><div class="kg-toggle-heading"><h4 class="kg-toggle-heading-text">Why <em>Zola</em>?</h4><button class="kg-toggle-card-icon"><svg id="Regular" viewBox="0 0 24 24"><path d="M23.25,7.311"></path></svg></button></div><div class="kg-toggle-content"><p>It's fast.</p><p>And <a href="https://www.getzola.org">simple</a>.</p></div></div>"#;
+        assert_eq!(
+            convert_cards(html, &Shortcodes::default()),
+            "<details>\n<summary>Why <em>Zola</em>?</summary>\n\nIt's fast.\n\nAnd [simple](https://www.getzola.org).\n\n</details>",
+        );
+
+        let mut shortcodes = Shortcodes::default();
+        shortcodes.set("toggle", Some("toggle".into()));
+        let card = json!({ "heading": "Why <em>Zola</em>?", "content": "<p>It's fast.</p>" });
+        assert_eq!(
+            render_card("toggle", &card, &shortcodes).unwrap(),
+            "{% toggle(heading=\"Why _Zola_?\") %}\nIt's fast.\n{% end %}",
+        );
+    }
+
+    #[test]
+    fn converts_buttons() {
+        let html = r#"<div class="kg-card kg-button-card kg-align-center"><a href="https://example.com/signup" class="kg-btn kg-btn-accent">Sign up &amp; save</a></div>"#;
+        assert_eq!(
+            convert_cards(html, &Shortcodes::default()),
+            "[Sign up & save](https://example.com/signup)",
+        );
+
+        let mut shortcodes = Shortcodes::default();
+        shortcodes.set("button", Some("button".into()));
+        assert_eq!(
+            convert_cards(html, &shortcodes),
+            "{{ button(url=\"https://example.com/signup\", text=\"Sign up & save\", alignment=\"center\") }}",
+        );
+        let card = json!({ "buttonUrl": "https://example.com/signup", "buttonText": "" });
+        assert_eq!(
+            render_card("button", &card, &Shortcodes::default()).unwrap(),
+            "[https://example.com/signup](https://example.com/signup)",
+        );
+    }
+
+    #[test]
+    fn renders_media() {
+        let shortcodes = Shortcodes::default();
+        let card = json!({ "src": "/content/media/2022/01/a.mp3", "title": "Episode \"1\"" });
+        assert_eq!(
+            render_card("audio", &card, &shortcodes).unwrap(),
+            "<audio controls src=\"/content/media/2022/01/a.mp3\"></audio>\n\n*Episode \"1\"*",
+        );
+        let card = json!({
+            "src": "/content/media/2022/01/v.mp4",
+            "thumbnailSrc": "/content/media/2022/01/v_thumb.jpg",
+            "width": 1280,
+            "height": 720,
+            "loop": true,
+            "caption": "A <em>clip</em>",
+        });
+        assert_eq!(
+            render_card("video", &card, &shortcodes).unwrap(),
+            "<video controls src=\"/content/media/2022/01/v.mp4\" poster=\"/content/media/2022/01/v_thumb.jpg\" width=\"1280\" height=\"720\" loop></video>\n\n*A _clip_*",
+        );
+
+        let mut shortcodes = Shortcodes::default();
+        shortcodes.set("video", Some("video".into()));
+        assert_eq!(
+            render_card("video", &card, &shortcodes).unwrap(),
+            "{{ video(src=\"/content/media/2022/01/v.mp4\", poster=\"/content/media/2022/01/v_thumb.jpg\", width=\"1280\", height=\"720\", loop=\"true\") }}\n\n*A _clip_*",
+        );
+    }
+
+    #[test]
+    fn renders_files() {
+        let card = json!({
+            "src": "/content/files/2022/01/report-final.pdf",
+            "fileName": "report-final.pdf",
+            "fileSize": 1_572_864,
+            "fileTitle": "Annual report",
+            "fileCaption": "<b>Updated</b> in 2022",
+        });
+        assert_eq!(
+            render_card("file", &card, &Shortcodes::default()).unwrap(),
+            "**Annual report**: [report-final.pdf](/content/files/2022/01/report-final.pdf) (1.5 MB)\n***Updated** in 2022*",
+        );
+        let mut shortcodes = Shortcodes::default();
+        shortcodes.set("file", Some("file".into()));
+        let card = json!({ "src": "/content/files/notes.txt", "fileSize": 12 });
+        assert_eq!(
+            render_card("file", &card, &shortcodes).unwrap(),
+            "{{ file(src=\"/content/files/notes.txt\", name=\"notes.txt\", size=\"12 bytes\") }}",
+        );
+    }
+
+    #[test]
+    fn converts_code() {
+        let html = r#"<figure class="kg-card kg-code-card"><pre><code class="language-rust">fn main() -&gt; () {}
+</code></pre><figcaption>The <em>entry</em> point</figcaption></figure>"#;
+        assert_eq!(
+            convert_cards(html, &Shortcodes::default()),
+            "```rust\nfn main() -> () {}\n```\n*The _entry_ point*",
+        );
+        let card = json!({ "code": "let s = \"```\";\n", "language": "" });
+        assert_eq!(
+            render_card("codeblock", &card, &Shortcodes::default()).unwrap(),
+            "````\nlet s = \"```\";\n````",
+        );
+    }
+
+    #[test]
+    fn strips_card_markers() {
+        let text = "intro\n\n<!--kg-card-begin: html-->\n<p>hi</p>\n<!--kg-card-end: html-->\n\noutro <!--kg-card-begin: markdown-->*md*<!--kg-card-end: markdown-->";
+        assert_eq!(strip_card_markers(text), "intro\n\n<p>hi</p>\n\noutro *md*");
+    }
+
+    #[test]
+    fn renders_embeds() {
+        let shortcodes = Shortcodes::default();
+        let youtube = json!({
+            "url": "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=1",
+            "html": "<iframe src=\"https://www.youtube.com/embed/dQw4w9WgXcQ\"></iframe>",
+            "metadata": { "provider_name": "YouTube" },
+            "caption": "<em>Never</em>",
+        });
+        assert_eq!(
+            render_card("embed", &youtube, &shortcodes).unwrap(),
+            "{{ youtube(id=\"dQw4w9WgXcQ\") }}\n\n*_Never_*",
+        );
+        let vimeo = json!({ "url": "https://vimeo.com/channels/staffpicks/123456" });
+        assert_eq!(
+            render_card("embed", &vimeo, &shortcodes).unwrap(),
+            "{{ vimeo(id=\"123456\") }}",
+        );
+        let tweet = json!({
+            "url": "https://twitter.com/ghost/status/1",
+            "html": "<blockquote class=\"twitter-tweet\"></blockquote>\n",
+        });
+        assert_eq!(
+            render_card("embed", &tweet, &shortcodes).unwrap(),
+            "<blockquote class=\"twitter-tweet\"></blockquote>",
+        );
+        let mut shortcodes = Shortcodes::default();
+        shortcodes.set("twitter", Some("tweet".into()));
+        assert_eq!(
+            render_card("embed", &tweet, &shortcodes).unwrap(),
+            "{{ tweet(url=\"https://twitter.com/ghost/status/1\") }}",
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_cards() {
+        assert!(render_card("product", &json!({}), &Shortcodes::default()).is_err());
+    }
+
+    #[test]
+    fn chooses_unused_quote() {
+        assert_eq!(
+            caption("say \"hi\"", &caption_shortcode()),
+            "{{ caption(text='say \"hi\"') }}"
+        );
+    }
+}
@@ -0,0 +1,666 @@
+use crate::{
+    data_model::{
+        choose_content, description_or_excerpt, is_internal_tag, non_empty, Author, AuthorExtra,
+        ContentFormats, ContentSource, Extra, LinkRewriter, Post, PostLinks, Social, Status, Tag,
+        TagExtra, Taxonomies,
+    },
+    extract::{output_path, render_author, render_post, render_tag, section_path, Report},
+    ghost, Error, ExtractOptions, KeyMapping,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// A post converted into a Zola document.
+#[derive(Debug)]
+pub struct ConvertedPost {
+    /// path of the document, relative to the Zola section into which the blog is extracted
+    pub path: PathBuf,
+    /// the converted post
+    pub post: Post,
+    /// the rendered document: frontmatter followed by content
+    pub document: String,
+}
+
+/// A Ghost user converted into the `_index.md` of a Zola section.
+#[derive(Debug)]
+pub struct ConvertedAuthor {
+    /// path of the document, relative to the authors section
+    pub path: PathBuf,
+    /// the converted user
+    pub author: Author,
+    /// the rendered document: frontmatter only
+    pub document: String,
+}
+
+/// A Ghost tag converted into the `_index.md` of a Zola section.
+#[derive(Debug)]
+pub struct ConvertedTag {
+    /// path of the document, relative to the tags section
+    pub path: PathBuf,
+    /// the converted tag
+    pub tag: Tag,
+    /// the rendered document: frontmatter only
+    pub document: String,
+}
+
+/// Convert a deserialized Ghost JSON export into Zola documents, in memory.
+///
+/// Posts are converted exactly as by [`extract_archive`][crate::extract_archive], but nothing is
+/// read or written: it is up to the caller to store each document at its path, and to copy the
+/// images it links to. Options which relate only to archives or databases, such as
+/// [`ExtractOptions::sql_filter`], are ignored.
+///
+/// Posts are returned in the order in which they appear in the export.
+pub fn convert_ghost_json(
+    top: &ghost::Top,
+    options: &ExtractOptions,
+) -> Result<Vec<ConvertedPost>, Error> {
+    let links = LinkRewriter::from_options(options);
+    let mut report = Report::default();
+    let mut posts = Vec::new();
+    for db in &top.db {
+        let data = &db.data;
+        let tag_names: HashMap<_, _> = data.tags.iter().map(|tag| (&tag.id, &tag.name)).collect();
+        let user_names: HashMap<_, _> = data
+            .users
+            .iter()
+            .map(|user| (&user.id, &user.name))
+            .collect();
+        // index the join tables by post, rather than scanning them for each post
+        let mut posts_meta = HashMap::new();
+        for meta in &data.posts_meta {
+            posts_meta.entry(&meta.post_id).or_insert(meta);
+        }
+        let mut posts_tags: HashMap<_, Vec<_>> = HashMap::new();
+        for post_tag in &data.posts_tags {
+            posts_tags
+                .entry(&post_tag.post_id)
+                .or_default()
+                .push(post_tag);
+        }
+        let mut posts_authors: HashMap<_, Vec<_>> = HashMap::new();
+        for post_author in &data.posts_authors {
+            posts_authors
+                .entry(&post_author.post_id)
+                .or_default()
+                .push(post_author);
+        }
+        for post_tags in posts_tags.values_mut() {
+            post_tags.sort_by_key(|post_tag| post_tag.sort_order);
+        }
+        for post_authors in posts_authors.values_mut() {
+            post_authors.sort_by_key(|post_author| post_author.sort_order);
+        }
+
+        for ghost_post in data
+            .posts
+            .iter()
+            .filter(|ghost_post| options.only.includes(ghost_post.is_page()))
+        {
+            let meta = posts_meta.get(&ghost_post.id).copied();
+            let mut post = convert_post(ghost_post, meta, options);
+
+            post.taxonomies.tags = posts_tags
+                .get(&ghost_post.id)
+                .into_iter()
+                .flatten()
+                .filter_map(|post_tag| tag_names.get(&post_tag.tag_id))
+                .map(|name| name.to_string())
+                .collect();
+            post.extra.authors = posts_authors
+                .get(&ghost_post.id)
+                .into_iter()
+                .flatten()
+                .filter_map(|post_author| user_names.get(&post_author.author_id))
+                .map(|name| name.to_string())
+                .collect();
+            if post.extra.authors.is_empty() {
+                post.extra.authors.extend(
+                    ghost_post
+                        .author_id
+                        .as_ref()
+                        .and_then(|author_id| user_names.get(author_id))
+                        .map(|name| name.to_string()),
+                );
+            }
+            post.extra.author_name = post.extra.authors.first().cloned().unwrap_or_default();
+
+            post.rewrite_links(&links);
+            report.process(&mut post, options)?;
+            if report.skip(&post, options) {
+                continue;
+            }
+            report.claim_path(&mut post, options);
+            posts.push(post);
+        }
+    }
+
+    // links between posts can only be resolved once every post's path is known
+    let mut links = PostLinks::new(options.target);
+    if options.link_posts {
+        for post in &posts {
+            links.insert(post.slug.clone(), &output_path(post, options));
+        }
+    }
+    let mut converted = Vec::with_capacity(posts.len());
+    for mut post in posts {
+        post.link_posts(&links);
+        report.check_alt_text(&post, options);
+        options.transforms.apply(&mut post);
+        let mut document = Vec::new();
+        render_post(&post, &mut document, options)?;
+        converted.push(ConvertedPost {
+            path: output_path(&post, options),
+            post,
+            document: String::from_utf8(document).expect("posts render only from valid utf-8"),
+        });
+    }
+    log::info!("converted {} posts", converted.len());
+    report.log(options);
+    report.check_strict(options)?;
+
+    Ok(converted)
+}
+
+/// Convert the users of a deserialized Ghost JSON export into Zola sections, in memory.
+///
+/// Users are converted exactly as by [`extract_archive`][crate::extract_archive] with
+/// [`ExtractOptions::authors_dir`] set, their images rewritten according to
+/// [`ExtractOptions::image_sources`]. Each is converted, whether or not they wrote any posts.
+pub fn convert_ghost_authors(
+    top: &ghost::Top,
+    options: &ExtractOptions,
+) -> Result<Vec<ConvertedAuthor>, Error> {
+    let links = LinkRewriter::from_options(options);
+    let mut converted = Vec::new();
+    for user in top.db.iter().flat_map(|db| &db.data.users) {
+        let mut author = Author {
+            name: user.name.clone(),
+            bio: non_empty(user.bio.clone()),
+            slug: user.slug.clone(),
+            extra: AuthorExtra {
+                id: user.id.clone(),
+                website: non_empty(user.website.clone()),
+                location: non_empty(user.location.clone()),
+                twitter: non_empty(user.twitter.clone()),
+                facebook: non_empty(user.facebook.clone()),
+                profile_image: non_empty(user.profile_image.clone()),
+                cover_image: non_empty(user.cover_image.clone()),
+            },
+        };
+        author.rewrite_links(&links);
+        let mut document = Vec::new();
+        render_author(&author, &mut document, options)?;
+        converted.push(ConvertedAuthor {
+            path: section_path(author.relative_path(options.path_sanitization), options),
+            author,
+            document: String::from_utf8(document).expect("authors render only from valid utf-8"),
+        });
+    }
+    Ok(converted)
+}
+
+/// Convert the tags of a deserialized Ghost JSON export into Zola sections, in memory.
+///
+/// Tags are converted exactly as by [`extract_archive`][crate::extract_archive] with
+/// [`ExtractOptions::tags_dir`] set, their feature images rewritten according to
+/// [`ExtractOptions::image_sources`]. Ghost's internal tags are skipped.
+pub fn convert_ghost_tags(
+    top: &ghost::Top,
+    options: &ExtractOptions,
+) -> Result<Vec<ConvertedTag>, Error> {
+    let links = LinkRewriter::from_options(options);
+    let mut converted = Vec::new();
+    for ghost_tag in top
+        .db
+        .iter()
+        .flat_map(|db| &db.data.tags)
+        .filter(|ghost_tag| !is_internal_tag(&ghost_tag.name))
+    {
+        let mut tag = Tag {
+            name: ghost_tag.name.clone(),
+            description: non_empty(ghost_tag.description.clone()),
+            slug: ghost_tag.slug.clone(),
+            extra: TagExtra {
+                id: ghost_tag.id.clone(),
+                feature_image: non_empty(ghost_tag.feature_image.clone()),
+            },
+        };
+        tag.rewrite_links(&links);
+        let mut document = Vec::new();
+        render_tag(&tag, &mut document, options)?;
+        converted.push(ConvertedTag {
+            path: section_path(tag.relative_path(options.path_sanitization), options),
+            tag,
+            document: String::from_utf8(document).expect("tags render only from valid utf-8"),
+        });
+    }
+    Ok(converted)
+}
+
+/// convert the fields of a post which don't depend on tags or authors
+///
+/// Metadata is read from the post, or failing that from its `posts_meta` row.
+fn convert_post(
+    ghost_post: &ghost::Post,
+    meta: Option<&ghost::PostMeta>,
+    options: &ExtractOptions,
+) -> Post {
+    let or_meta = |field: &Option<String>, meta_field: fn(&ghost::PostMeta) -> &Option<String>| {
+        non_empty(field.clone())
+            .or_else(|| non_empty(meta.and_then(|meta| meta_field(meta).clone())))
+    };
+    let (content, source) = choose_content(
+        &ghost_post.id,
+        &ghost_post.slug,
+        ContentFormats {
+            markdown: ghost_post.markdown.clone(),
+            mobiledoc: ghost_post.mobiledoc.clone(),
+            lexical: ghost_post.lexical.clone(),
+            plaintext: ghost_post.plaintext.clone(),
+            html: ghost_post.html.clone(),
+        },
+        options.raw_html,
+        &options.shortcodes,
+    );
+    let excerpt = non_empty(ghost_post.custom_excerpt.clone());
+    Post {
+        title: ghost_post.title.clone().unwrap_or_default(),
+        content,
+        description: description_or_excerpt(
+            or_meta(&ghost_post.meta_description, |meta| &meta.meta_description),
+            excerpt.as_deref(),
+        ),
+        date: ghost_post.published_at,
+        updated: ghost_post.updated_at,
+        aliases: Vec::new(),
+        created: ghost_post.created_at,
+        page: ghost_post.is_page(),
+        keys: KeyMapping::default(),
+        status: Status::from_str(&ghost_post.status).expect("Status::from_str is infallible"),
+        slug: ghost_post.slug.clone(),
+        extra: Extra {
+            id: ghost_post.id.clone(),
+            uuid: non_empty(ghost_post.uuid.clone()),
+            language: ghost_post.language.clone().unwrap_or_default(),
+            author_name: String::new(),
+            authors: Vec::new(),
+            feature_image: non_empty(ghost_post.feature_image.clone()),
+            featured: ghost_post.featured,
+            visibility: non_empty(ghost_post.visibility.clone()),
+            excerpt,
+            canonical_url: non_empty(ghost_post.canonical_url.clone()),
+            codeinjection_head: non_empty(ghost_post.codeinjection_head.clone()),
+            codeinjection_foot: non_empty(ghost_post.codeinjection_foot.clone()),
+            // computed later, if at all
+            words: None,
+            reading_time: None,
+            plaintext_fallback: source == ContentSource::Plaintext,
+            raw_html: source == ContentSource::Html,
+            // separated from the tags later, if at all
+            internal_tags: Vec::new(),
+            custom: BTreeMap::new(),
+            social: Social {
+                og_image: or_meta(&ghost_post.og_image, |meta| &meta.og_image),
+                og_title: or_meta(&ghost_post.og_title, |meta| &meta.og_title),
+                og_description: or_meta(&ghost_post.og_description, |meta| &meta.og_description),
+                twitter_image: or_meta(&ghost_post.twitter_image, |meta| &meta.twitter_image),
+                twitter_title: or_meta(&ghost_post.twitter_title, |meta| &meta.twitter_title),
+                twitter_description: or_meta(&ghost_post.twitter_description, |meta| {
+                    &meta.twitter_description
+                }),
+            },
+        },
+        taxonomies: Taxonomies::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DraftPolicy, ExtraAssignment, KeyAssignment, PostTypes, Target};
+
+    const EXPORT: &str = r#"{
+        "db": [{
+            "meta": {"exported_on": 1578000000000, "version": "0.11.14"},
+            "data": {
+                "posts": [
+                    {
+                        "id": 1, "title": "Hello", "slug": "hello", "status": "published",
+                        "markdown": "![](/content/images/2020/01/a.png)",
+                        "image": "/content/images/2020/01/hero.png", "featured": 1,
+                        "language": "en_US", "author_id": 1,
+                        "published_at": "2020-01-02T10:00:00.000Z"
+                    },
+                    {
+                        "id": 2, "title": "Draft", "slug": "draft", "markdown": "see [hello](/hello/)",
+                        "author_id": 1
+                    },
+                    {
+                        "id": 3, "title": "About", "slug": "about", "type": "page",
+                        "status": "published", "markdown": "me", "author_id": 1,
+                        "published_at": "2020-01-03T10:00:00.000Z"
+                    }
+                ],
+                "posts_meta": [
+                    {"post_id": 1, "og_image": "/content/images/2020/01/og.png", "og_title": "Hi!"}
+                ],
+                "tags": [
+                    {
+                        "id": 1, "name": "rust", "slug": "rust", "description": "Crabs.",
+                        "image": "/content/images/2020/01/crab.png"
+                    },
+                    {"id": 2, "name": "zola"}
+                ],
+                "posts_tags": [
+                    {"post_id": 1, "tag_id": 2, "sort_order": 1},
+                    {"post_id": 1, "tag_id": 1, "sort_order": 0}
+                ],
+                "users": [
+                    {
+                        "id": 1, "name": "Pete", "slug": "pete", "bio": "Writes.", "location": "",
+                        "twitter": "@pete", "facebook": null,
+                        "profile_image": "/content/images/2020/01/pete.png"
+                    },
+                    {"id": 2, "name": "Ann"}
+                ],
+                "posts_authors": [
+                    {"post_id": 1, "author_id": 1, "sort_order": 1},
+                    {"post_id": 1, "author_id": 2, "sort_order": 0}
+                ]
+            }
+        }]
+    }"#;
+
+    #[test]
+    fn converts_export() {
+        let top: ghost::Top = serde_json::from_str(EXPORT).unwrap();
+        let converted = convert_ghost_json(&top, &ExtractOptions::default()).unwrap();
+        assert_eq!(converted.len(), 3);
+
+        let hello = &converted[0];
+        assert_eq!(hello.path, PathBuf::from("2020/01/02/hello.md"));
+        assert_eq!(hello.post.content, "![](/blog/2020/01/a.png)");
+        assert_eq!(
+            hello.post.extra.feature_image.as_deref(),
+            Some("/blog/2020/01/hero.png")
+        );
+        assert_eq!(
+            hello.post.extra.social.og_image.as_deref(),
+            Some("/blog/2020/01/og.png")
+        );
+        assert_eq!(hello.post.extra.social.og_title.as_deref(), Some("Hi!"));
+        assert!(hello.post.extra.featured);
+        assert_eq!(hello.post.extra.author_name, "Ann");
+        assert_eq!(hello.post.extra.authors, vec!["Ann", "Pete"]);
+        assert_eq!(hello.post.taxonomies.tags, vec!["rust", "zola"]);
+        assert!(hello.document.starts_with("+++\ntitle = \"Hello\"\n"));
+        assert!(hello
+            .document
+            .contains("\n[extra.social]\nog_image = \"/blog/2020/01/og.png\"\n"));
+        assert!(hello.document.ends_with("![](/blog/2020/01/a.png)\n"));
+
+        let draft = &converted[1];
+        assert_eq!(draft.path, PathBuf::from("undated/draft.md"));
+        assert!(draft.post.status.draft());
+        assert!(!draft.post.extra.featured);
+        assert_eq!(draft.post.extra.authors, vec!["Pete"]);
+        assert_eq!(
+            draft.post.content,
+            "see [hello](@/blog/2020/01/02/hello.md)"
+        );
+
+        let about = &converted[2];
+        assert!(about.post.page);
+        assert_eq!(about.path, PathBuf::from("pages/about.md"));
+    }
+
+    #[test]
+    fn converts_for_hugo() {
+        let top: ghost::Top = serde_json::from_str(EXPORT).unwrap();
+        let options = ExtractOptions {
+            target: Target::Hugo,
+            ..ExtractOptions::default()
+        };
+        let converted = convert_ghost_json(&top, &options).unwrap();
+        let hello = &converted[0].document;
+        assert!(hello.contains("\ntags = [\"rust\", \"zola\"]\n\n[params]\nid = 1\n"));
+        assert!(!hello.contains("[taxonomies]"));
+        assert!(hello.contains("\ndate = 2020-01-02T10:00:00Z\n"));
+        assert_eq!(
+            converted[1].post.content,
+            "see [hello]({{< ref \"/blog/2020/01/02/hello.md\" >}})"
+        );
+
+        let tags = convert_ghost_tags(&top, &options).unwrap();
+        assert_eq!(
+            tags[0].document,
+            "+++\ntitle = \"rust\"\ndescription = \"Crabs.\"\n\n[params]\nid = 1\nfeature_image = \"/blog/2020/01/crab.png\"\n\n+++\n"
+        );
+    }
+
+    #[test]
+    fn converts_for_jekyll() {
+        let top: ghost::Top = serde_json::from_str(EXPORT).unwrap();
+        let options = ExtractOptions {
+            target: Target::Jekyll,
+            ..ExtractOptions::default()
+        };
+        let converted = convert_ghost_json(&top, &options).unwrap();
+        assert_eq!(
+            converted[0].path,
+            PathBuf::from("_posts/2020-01-02-hello.md")
+        );
+        let hello = &converted[0].document;
+        assert!(hello.starts_with("---\nlayout: post\ntitle: Hello\n"));
+        assert!(hello.contains("\ndate: 2020-01-02T10:00:00Z\n"));
+        assert!(hello.contains("\ntags:\n- rust\n- zola\n"));
+        assert_eq!(
+            converted[1].post.content,
+            "see [hello]({% post_url 2020-01-02-hello %})"
+        );
+
+        let tags = convert_ghost_tags(&top, &options).unwrap();
+        assert_eq!(tags[0].path, PathBuf::from("rust/index.md"));
+        assert_eq!(
+            tags[0].document,
+            "---\nlayout: tag\ntitle: rust\ndescription: Crabs.\nextra:\n  id: 1\n  feature_image: /assets/images/2020/01/crab.png\n---\n"
+        );
+    }
+
+    #[test]
+    fn merges_extra_frontmatter() {
+        let top: ghost::Top = serde_json::from_str(EXPORT).unwrap();
+        let mut options = ExtractOptions::default();
+        for assignment in &["template=post.html", "comments=true", "weight=\"3\""] {
+            let assignment: ExtraAssignment = assignment.parse().unwrap();
+            options
+                .extra_frontmatter
+                .insert(assignment.key, assignment.value);
+        }
+        let converted = convert_ghost_json(&top, &options).unwrap();
+        assert!(converted[0]
+            .document
+            .contains("\ncomments = true\ntemplate = \"post.html\"\nweight = \"3\"\n"));
+    }
+
+    #[test]
+    fn runs_transforms() {
+        let top: ghost::Top = serde_json::from_str(EXPORT).unwrap();
+        let mut options = ExtractOptions::default();
+        options.transforms.push(|post| {
+            post.content = post.content.replace("a.png", "b.png");
+        });
+        let mut n_posts = 0;
+        options.transforms.push(move |post| {
+            n_posts += 1;
+            post.extra
+                .custom
+                .insert("n".into(), toml::Value::Integer(n_posts));
+        });
+        let converted = convert_ghost_json(&top, &options).unwrap();
+        assert!(converted[0].document.contains("/2020/01/b.png)"));
+        assert!(converted[0].document.contains("\nn = 1\n"));
+        assert!(converted[1].document.contains("\nn = 2\n"));
+    }
+
+    #[test]
+    fn renders_with_custom_renderers() {
+        struct Titles;
+        impl crate::render::PostRenderer for Titles {
+            fn render(
+                &self,
+                post: &crate::data_model::Post,
+                writer: &mut dyn std::io::Write,
+                _options: &ExtractOptions,
+            ) -> Result<(), crate::Error> {
+                writeln!(writer, "# {}", post.title)?;
+                Ok(())
+            }
+        }
+
+        let top: ghost::Top = serde_json::from_str(EXPORT).unwrap();
+        let options = ExtractOptions {
+            renderer: Some(std::sync::Arc::new(Titles)),
+            ..ExtractOptions::default()
+        };
+        let converted = convert_ghost_json(&top, &options).unwrap();
+        assert_eq!(converted[0].document, "# Hello\n");
+    }
+
+    #[test]
+    fn maps_frontmatter_keys() {
+        let top: ghost::Top = serde_json::from_str(EXPORT).unwrap();
+        let mut options = ExtractOptions::default();
+        for assignment in &[
+            "extra.author_name=extra.author",
+            "extra.language=",
+            "title=extra.headline.text",
+        ] {
+            let assignment: KeyAssignment = assignment.parse().unwrap();
+            options
+                .frontmatter_keys
+                .set(&assignment.key, assignment.renamed);
+        }
+        let converted = convert_ghost_json(&top, &options).unwrap();
+        let hello = &converted[0].document;
+        assert!(hello.starts_with("+++\nslug = \"hello\"\n"));
+        assert!(!hello.contains("language ="));
+        assert!(!hello.contains("author_name ="));
+        assert!(hello.contains("\nauthor = \"Ann\"\n"));
+        assert!(hello.contains("\n[extra.headline]\ntext = \"Hello\"\n"));
+        assert!(hello.find("[extra]") < hello.find("[taxonomies]"));
+
+        assert!("extra..author=x".parse::<KeyAssignment>().is_err());
+        assert!("=extra.author".parse::<KeyAssignment>().is_err());
+    }
+
+    #[test]
+    fn writes_aliases() {
+        let top: ghost::Top = serde_json::from_str(EXPORT).unwrap();
+        let options = ExtractOptions {
+            permalinks: Some("/:year/:month/{slug}/".into()),
+            ..ExtractOptions::default()
+        };
+        let converted = convert_ghost_json(&top, &options).unwrap();
+        assert!(converted[0]
+            .document
+            .contains("\naliases = [\"/2020/01/hello/\"]\n"));
+        let about = converted.iter().find(|c| c.post.page).unwrap();
+        assert_eq!(about.post.aliases, vec!["/about/".to_string()]);
+
+        let hugo = convert_ghost_json(
+            &top,
+            &ExtractOptions {
+                target: Target::Hugo,
+                ..options.clone()
+            },
+        )
+        .unwrap();
+        assert!(hugo[0]
+            .document
+            .contains("\naliases = [\"/2020/01/hello/\"]\n"));
+    }
+
+    #[test]
+    fn separates_or_skips_drafts() {
+        let top: ghost::Top = serde_json::from_str(EXPORT).unwrap();
+        let options = ExtractOptions {
+            drafts: DraftPolicy::Separate,
+            ..ExtractOptions::default()
+        };
+        let converted = convert_ghost_json(&top, &options).unwrap();
+        let draft = converted.iter().find(|c| c.post.slug == "draft").unwrap();
+        assert_eq!(draft.path, PathBuf::from("../drafts/draft.md"));
+
+        let options = ExtractOptions {
+            drafts: DraftPolicy::Skip,
+            ..ExtractOptions::default()
+        };
+        let converted = convert_ghost_json(&top, &options).unwrap();
+        assert!(converted.iter().all(|c| !c.post.status.draft()));
+        assert!(converted.iter().any(|c| c.post.slug == "hello"));
+    }
+
+    #[test]
+    fn names_translations_by_language() {
+        let top: ghost::Top = serde_json::from_str(EXPORT).unwrap();
+        let options = ExtractOptions {
+            default_language: Some("fr".into()),
+            ..ExtractOptions::default()
+        };
+        let converted = convert_ghost_json(&top, &options).unwrap();
+        assert_eq!(converted[0].path, PathBuf::from("2020/01/02/hello.en.md"));
+        assert_eq!(converted[1].path, PathBuf::from("undated/draft.md"));
+
+        let options = ExtractOptions {
+            default_language: Some("en".into()),
+            page_bundles: true,
+            ..ExtractOptions::default()
+        };
+        let converted = convert_ghost_json(&top, &options).unwrap();
+        assert_eq!(
+            converted[0].path,
+            PathBuf::from("2020/01/02/hello/index.md")
+        );
+    }
+
+    #[test]
+    fn converts_only_pages() {
+        let top: ghost::Top = serde_json::from_str(EXPORT).unwrap();
+        let options = ExtractOptions {
+            only: PostTypes::Pages,
+            ..ExtractOptions::default()
+        };
+        let converted = convert_ghost_json(&top, &options).unwrap();
+        assert_eq!(converted.len(), 1);
+        assert_eq!(converted[0].post.slug, "about");
+    }
+
+    #[test]
+    fn converts_authors() {
+        let top: ghost::Top = serde_json::from_str(EXPORT).unwrap();
+        let converted = convert_ghost_authors(&top, &ExtractOptions::default()).unwrap();
+        assert_eq!(converted.len(), 2);
+        assert_eq!(converted[0].path, PathBuf::from("pete/_index.md"));
+        assert_eq!(
+            converted[0].document,
+            "+++\ntitle = \"Pete\"\ndescription = \"Writes.\"\n\n[extra]\nid = 1\ntwitter = \"@pete\"\nprofile_image = \"/blog/2020/01/pete.png\"\n\n+++\n"
+        );
+        assert_eq!(converted[1].path, PathBuf::from("ann/_index.md"));
+    }
+
+    #[test]
+    fn converts_tags() {
+        let top: ghost::Top = serde_json::from_str(EXPORT).unwrap();
+        let converted = convert_ghost_tags(&top, &ExtractOptions::default()).unwrap();
+        assert_eq!(converted.len(), 2);
+        assert_eq!(converted[0].path, PathBuf::from("rust/_index.md"));
+        assert_eq!(
+            converted[0].document,
+            "+++\ntitle = \"rust\"\ndescription = \"Crabs.\"\n\n[extra]\nid = 1\nfeature_image = \"/blog/2020/01/crab.png\"\n\n+++\n"
+        );
+        assert_eq!(converted[1].path, PathBuf::from("zola/_index.md"));
+    }
+}
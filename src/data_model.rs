@@ -1,51 +1,677 @@
 use chrono::{DateTime, Utc};
 use lazy_static::lazy_static;
-use regex::{Regex, RegexBuilder};
+use regex::{Captures, Regex, RegexBuilder};
 use rusqlite::{
     self, params,
-    types::{FromSql, FromSqlResult},
-    Connection,
+    types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef},
+    Connection, ToSql,
 };
-use serde::Serialize;
+use serde::{ser::SerializeMap, Deserialize, Serialize, Serializer};
 use slugify::slugify;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 use std::str::FromStr;
 
+use crate::editor::{render_lexical, render_mobiledoc};
+use crate::query::{has_column, PostQuery, Posts};
+use crate::{
+    ExtractOptions, FrontmatterFormat, FrontmatterTemplate, ImageSource, KeyMapping,
+    PathSanitization, RawHtmlPolicy, Shortcodes, SlugOptions, Target, TaxonomyMapping,
+};
+
 lazy_static! {
-    static ref INTERNAL_LINK_RE: Regex =
-        RegexBuilder::new(r"\]\(/content/images/(\d{4}/\d{2}/[^)]+)\)")
-            .case_insensitive(true)
-            .build()
-            .unwrap();
-    static ref DATE_QUOTE_STRIP_RE: Regex =
-        RegexBuilder::new(r#"^(date|updated) = "([- \w\d:\.]+)"$"#)
-            .multi_line(true)
-            .build()
-            .unwrap();
+    static ref INTERNAL_LINK_RE: Regex = internal_link_regex(&ImageSource::uploads());
+    static ref PERMALINK_PLACEHOLDER: Regex = Regex::new(r"\{(\w+)\}|:(\w+)").unwrap();
+    static ref HTML_TAG: Regex = Regex::new(r"<[^>]*>").unwrap();
+    static ref HTML_MEDIA_TAG: Regex =
+        Regex::new(r"(?i)<(?:img|source|audio|video)\b[^>]*>").unwrap();
+    static ref HTML_MEDIA_ATTR: Regex =
+        Regex::new(r#"(?i)\b(src|srcset|poster)(\s*=\s*)(?:"([^"]*)"|'([^']*)')"#).unwrap();
+    static ref SHORTCODE_CALL: Regex =
+        Regex::new(r"(?s)\{\{\s*[\w-]+\(.*?\)\s*\}\}|\{%\s*[\w-]+\(.*?\)\s*%\}").unwrap();
+    static ref SHORTCODE_ARG: Regex =
+        Regex::new(r#"\b(\w+)(\s*=\s*)(?:"([^"]*)"|'([^']*)'|`([^`]*)`)"#).unwrap();
+    static ref LINK_TARGET: Regex = Regex::new(r"\]\([^)]*\)").unwrap();
+    // a markdown image without alt text, i.e. `![](/blog/a.png "title")`
+    static ref MARKDOWN_IMAGE_WITHOUT_ALT: Regex =
+        Regex::new(r"!\[\s*\]\(\s*(?:<([^>]*)>|([^)\s]*))").unwrap();
+    static ref HTML_IMAGE: Regex = Regex::new(r"(?i)<img\b[^>]*>").unwrap();
+    static ref HTML_ALT: Regex =
+        Regex::new(r#"(?i)\balt\s*=\s*(?:"\s*[^"\s][^"]*"|'\s*[^'\s][^']*'|[^\s"'>]+)"#).unwrap();
     static ref PRE_REIFIED_FOOTNOTES: Regex = Regex::new(r"\[\^(\d+)\]").unwrap();
     static ref FOOTNOTE_FOOT: Regex = RegexBuilder::new(r"^\[\^n\]:")
         .multi_line(true)
         .build()
         .unwrap();
     static ref FOOTNOTE_TEXT: Regex = Regex::new(r"\[\^n\]").unwrap();
+    // the path of one of ghost's responsive variants of an image, i.e. `size/w600/2020/01/a.png`
+    static ref IMAGE_SIZE: Regex = Regex::new(r"^size/w\d+(?:h\d+)?/").unwrap();
+    // a site-relative link to a post, optionally behind a date, i.e. `](/2020/01/02/slug/#part)`
+    static ref POST_LINK: Regex =
+        Regex::new(r"\]\(/(?:\d+/)*([^/()\s#?]+)/?(#[^)\s]*)?\)").unwrap();
+}
+
+/// The Zola section into which posts are extracted, from which images are served.
+pub(crate) const SECTION: &str = "blog";
+
+/// build a regex matching markdown links into any of the given image sources
+///
+/// The first capture group is the path of the image relative to its source; the second, its
+/// query string and fragment, if any; the third, anything else in the link, such as its title.
+pub(crate) fn internal_link_regex(sources: &[ImageSource]) -> Regex {
+    let prefixes = sources
+        .iter()
+        .map(|source| regex::escape(&source.url_prefix))
+        .collect::<Vec<_>>()
+        .join("|");
+    RegexBuilder::new(&format!(
+        r"\]\((?:{})/([^)?#\s]+)([?#][^)\s]*)?([^)]*)\)",
+        prefixes
+    ))
+    .case_insensitive(true)
+    .build()
+    .expect("escaped prefixes always produce a valid regex")
 }
 
-/// replace internal hardlinks with relative links to the parent
-pub(crate) fn relative_internal_links(text: &str) -> String {
-    INTERNAL_LINK_RE
-        .replace_all(text, "](/blog/$1)")
+/// replace internal hardlinks with relative links to the extracted images, at `images_url`
+///
+/// `link_re` should come from [`internal_link_regex`]. Links using Ghost's placeholder for the
+/// site's url should be made relative to the site first; see [`strip_site_links`]. Query strings
+/// and fragments are dropped unless `keep_query` is set.
+pub(crate) fn relative_internal_links(
+    text: &str,
+    link_re: &Regex,
+    images_url: &str,
+    keep_query: bool,
+) -> String {
+    link_re
+        .replace_all(text, |captures: &Captures| {
+            let query = match captures.get(2) {
+                Some(query) if keep_query => query.as_str(),
+                _ => "",
+            };
+            format!(
+                "]({}/{}{}{})",
+                images_url,
+                original_image_path(&captures[1]),
+                query,
+                &captures[3]
+            )
+        })
         .into_owned()
 }
 
-/// strip quotation marks from toml fields named `date` or `updated`
-pub(crate) fn strip_datetime_quotes(text: &str) -> String {
-    DATE_QUOTE_STRIP_RE
-        .replace_all(text, "$1 = $2")
+/// apply `f` to each url embedded in html or in the arguments of a shortcode
+///
+/// These are the urls in the `src`, `srcset` and `poster` attributes of `img`, `source`, `audio`
+/// and `video` tags, and the string arguments of shortcodes, which the markdown link regex can't
+/// see. `f` must leave anything which isn't a url alone.
+fn map_embedded_urls<F>(text: &str, mut f: F) -> String
+where
+    F: FnMut(&str) -> String,
+{
+    let text = HTML_MEDIA_TAG.replace_all(text, |tag: &Captures| {
+        HTML_MEDIA_ATTR
+            .replace_all(&tag[0], |attr: &Captures| {
+                let (quote, value) = match (attr.get(3), attr.get(4)) {
+                    (Some(value), _) => ('"', value.as_str()),
+                    (_, Some(value)) => ('\'', value.as_str()),
+                    _ => unreachable!("the regex requires one of the quoted values"),
+                };
+                let value = if attr[1].eq_ignore_ascii_case("srcset") {
+                    // a comma-separated list of candidates: a url, then optional descriptors
+                    value
+                        .split(',')
+                        .map(|candidate| {
+                            let url_start = candidate.len() - candidate.trim_start().len();
+                            let url_end = candidate[url_start..]
+                                .find(char::is_whitespace)
+                                .map_or(candidate.len(), |end| url_start + end);
+                            format!(
+                                "{}{}{}",
+                                &candidate[..url_start],
+                                f(&candidate[url_start..url_end]),
+                                &candidate[url_end..]
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join(",")
+                } else {
+                    f(value)
+                };
+                format!("{}{}{}{}{}", &attr[1], &attr[2], quote, value, quote)
+            })
+            .into_owned()
+    });
+    SHORTCODE_CALL
+        .replace_all(&text, |call: &Captures| {
+            SHORTCODE_ARG
+                .replace_all(&call[0], |arg: &Captures| {
+                    let (quote, value) = match (arg.get(3), arg.get(4), arg.get(5)) {
+                        (Some(value), _, _) => ('"', value.as_str()),
+                        (_, Some(value), _) => ('\'', value.as_str()),
+                        (_, _, Some(value)) => ('`', value.as_str()),
+                        _ => unreachable!("the regex requires one of the quoted values"),
+                    };
+                    format!("{}{}{}{}{}", &arg[1], &arg[2], quote, f(value), quote)
+                })
+                .into_owned()
+        })
         .into_owned()
 }
 
+/// rewrite the embedded urls of images and media which were served from any of the given sources
+pub(crate) fn relative_embedded_urls(
+    text: &str,
+    sources: &[ImageSource],
+    images_url: &str,
+    keep_query: bool,
+) -> String {
+    map_embedded_urls(text, |url| {
+        relative_image_url(url, sources, images_url, keep_query)
+    })
+}
+
+/// list the urls embedded in html or in the arguments of shortcodes
+///
+/// Shortcode arguments are listed whether or not they're urls.
+pub(crate) fn embedded_urls(text: &str) -> Vec<String> {
+    let mut urls = Vec::new();
+    map_embedded_urls(text, |url| {
+        urls.push(url.to_string());
+        url.to_string()
+    });
+    urls
+}
+
+/// point urls of extracted images which were renamed at their new names; see
+/// [`Post::rename_images`]
+pub(crate) fn rename_image_urls<'a>(
+    urls: impl Iterator<Item = &'a mut String>,
+    images_url: &str,
+    renames: &HashMap<String, String>,
+) {
+    let prefix = format!("{}/", images_url);
+    for url in urls {
+        let renamed = url.strip_prefix(&prefix).and_then(|rest| {
+            let (path, query) = rest.split_at(rest.find(['?', '#']).unwrap_or(rest.len()));
+            renames
+                .get(path)
+                .map(|renamed| format!("{}{}{}", prefix, renamed, query))
+        });
+        if let Some(renamed) = renamed {
+            *url = renamed;
+        }
+    }
+}
+
+/// a relative path as it appears in a url, i.e. `2020/01/a.png`
+pub(crate) fn url_path(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// append a relative path to a url, i.e. `/blog` and `2020/01` to `/blog/2020/01`
+///
+/// A `..` in the path drops the url's last segment.
+pub(crate) fn join_url(mut url: String, path: &Path) -> String {
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                url.truncate(url.rfind('/').unwrap_or_default());
+            }
+            Component::Normal(name) => {
+                url.push('/');
+                url.push_str(&name.to_string_lossy());
+            }
+            _ => {}
+        }
+    }
+    url
+}
+
+/// strip the size segment from the path of a responsive variant of an image
+///
+/// Ghost serves resized variants of each image, i.e. `/content/images/size/w600/2020/01/a.png`,
+/// but only the original is extracted.
+fn original_image_path(path: &str) -> &str {
+    match IMAGE_SIZE.find(path) {
+        Some(size) => &path[size.end()..],
+        None => path,
+    }
+}
+
+/// The placeholder with which Ghost 4.0 and later store the site's own url.
+const GHOST_URL: &str = "__GHOST_URL__";
+
+/// the urls at which the site was served: its placeholder, and `site_url` over http and https
+fn site_urls(site_url: Option<&str>) -> Vec<String> {
+    let mut site_urls = vec![GHOST_URL.to_string()];
+    if let Some(site_url) = site_url {
+        let site_url = site_url.trim_end_matches('/');
+        let without_scheme = site_url
+            .strip_prefix("https://")
+            .or_else(|| site_url.strip_prefix("http://"));
+        match without_scheme {
+            Some(rest) => {
+                site_urls.push(format!("https://{}", rest));
+                site_urls.push(format!("http://{}", rest));
+            }
+            None => site_urls.push(site_url.to_string()),
+        }
+    }
+    site_urls
+}
+
+/// make an absolute url on the site relative to it
+///
+/// i.e. `https://example.com/content/images/a.png` becomes `/content/images/a.png` for the
+/// `site_url` `https://example.com`, over either http or https, as do urls using Ghost's
+/// placeholder for it. Other urls are left alone.
+pub(crate) fn strip_site_url(url: &str, site_url: Option<&str>) -> String {
+    for site_url in site_urls(site_url) {
+        if let Some(rest) = url.strip_prefix(&site_url) {
+            if rest.is_empty() {
+                return "/".into();
+            } else if rest.starts_with('/') {
+                return rest.into();
+            } else if rest.starts_with(['?', '#']) {
+                return format!("/{}", rest);
+            }
+        }
+    }
+    url.to_string()
+}
+
+/// a regex matching the start of absolute links to the site, in markdown links and html `href`
+/// and `src` attributes
+///
+/// Links using Ghost's placeholder for the site's url are matched even without a `site_url`. The
+/// first capture group is everything before the url; the second, the character after the site's
+/// url, if any.
+pub(crate) fn site_link_regex(site_url: Option<&str>) -> Regex {
+    let alternatives = site_urls(site_url)
+        .iter()
+        .map(|site_url| regex::escape(site_url))
+        .collect::<Vec<_>>()
+        .join("|");
+    RegexBuilder::new(&format!(
+        r#"(\]\(<?|\b(?:href|src)\s*=\s*["']?)(?:{})([/?#)"'>\s]|$)"#,
+        alternatives
+    ))
+    .case_insensitive(true)
+    .build()
+    .expect("escaped urls always produce a valid regex")
+}
+
+/// make the absolute links to the site within a post's content relative to it
+///
+/// i.e. `__GHOST_URL__/content/images/a.png` becomes `/content/images/a.png`, and is then
+/// rewritten like any other link. `link_re` should come from [`site_link_regex`] for the same
+/// `site_url`. Only links are rewritten, in markdown, html, and shortcodes; urls elsewhere, i.e.
+/// in code, are left alone.
+pub(crate) fn strip_site_links(text: &str, link_re: &Regex, site_url: Option<&str>) -> String {
+    let text = link_re.replace_all(text, |captures: &Captures| match &captures[2] {
+        "/" => format!("{}/", &captures[1]),
+        after => format!("{}/{}", &captures[1], after),
+    });
+    map_embedded_urls(&text, |url| strip_site_url(url, site_url))
+}
+
+/// The Zola internal links to extracted posts, by slug.
+///
+/// Ghost's posts link to one another by url. Rewritten to Zola's internal links, `zola build`
+/// validates them. For Hugo, they become `ref` shortcodes, which `hugo` validates likewise, and for
+/// Jekyll, `post_url` or `link` tags.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct PostLinks {
+    targets: HashMap<String, String>,
+    target: Target,
+}
+
+impl PostLinks {
+    pub(crate) fn new(target: Target) -> Self {
+        PostLinks {
+            targets: HashMap::new(),
+            target,
+        }
+    }
+
+    /// note the path, relative to the extract path, into which the post with this slug is extracted
+    pub(crate) fn insert(&mut self, slug: String, relative_path: &Path) {
+        let components: Vec<_> = relative_path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect();
+        let path = format!("{}/{}", SECTION, components.join("/"));
+        self.targets.insert(
+            slug,
+            match self.target {
+                Target::Zola => format!("@/{}", path),
+                Target::Hugo => format!("{{{{< ref \"/{}\" >}}}}", path),
+                // jekyll's extract path is the site's root, so the path is used as it is
+                Target::Jekyll => match relative_path.strip_prefix("_posts") {
+                    Ok(post) => format!(
+                        "{{% post_url {} %}}",
+                        post.with_extension("").to_string_lossy()
+                    ),
+                    Err(_) => format!("{{% link {} %}}", components.join("/")),
+                },
+            },
+        );
+    }
+
+    /// rewrite links to the posts' urls into internal links
+    ///
+    /// Links to anything else are left alone.
+    pub(crate) fn rewrite(&self, text: &str) -> String {
+        POST_LINK
+            .replace_all(text, |captures: &Captures| {
+                match self.targets.get(&captures[1]) {
+                    Some(target) => format!(
+                        "]({}{})",
+                        target,
+                        captures.get(2).map_or("", |fragment| fragment.as_str())
+                    ),
+                    None => captures[0].to_string(),
+                }
+            })
+            .into_owned()
+    }
+}
+
+/// find the image source which served an image url, and the path of the image relative to it
+///
+/// Responsive variants of an image resolve to the original, and any query string or fragment is
+/// dropped. Returns `None` if the url was not served from any of the sources.
+pub(crate) fn internal_image<'a, 's>(
+    url: &'a str,
+    sources: &'s [ImageSource],
+) -> Option<(&'s ImageSource, &'a str)> {
+    let url = url.strip_prefix(GHOST_URL).unwrap_or(url);
+    sources.iter().find_map(|source| {
+        let prefix = url.get(..source.url_prefix.len())?;
+        let rest = &url[prefix.len()..];
+        if prefix.eq_ignore_ascii_case(&source.url_prefix) {
+            rest.strip_prefix('/')
+                .map(|path| {
+                    path.split(&['?', '#'][..])
+                        .next()
+                        .unwrap_or_default()
+                        .trim_end()
+                })
+                .map(original_image_path)
+                .filter(|path| !path.is_empty())
+                .map(|path| (source, path))
+        } else {
+            None
+        }
+    })
+}
+
+/// find the path of an image url relative to whichever of the given image sources served it
+///
+/// Returns `None` if the url was not served from any of them.
+pub(crate) fn internal_image_path<'a>(url: &'a str, sources: &[ImageSource]) -> Option<&'a str> {
+    internal_image(url, sources).map(|(_, path)| path)
+}
+
+/// rewrite an image url served from any of the given image sources to point at the extracted image
+///
+/// The extracted images are served at `images_url`, i.e. `/blog`. Urls served from elsewhere are
+/// returned unchanged. Query strings and fragments are dropped unless `keep_query` is set.
+pub(crate) fn relative_image_url(
+    url: &str,
+    sources: &[ImageSource],
+    images_url: &str,
+    keep_query: bool,
+) -> String {
+    match internal_image_path(url, sources) {
+        Some(path) => {
+            let query = match url.find(&['?', '#'][..]) {
+                Some(start) if keep_query => &url[start..],
+                _ => "",
+            };
+            format!("{}/{}{}", images_url, path, query)
+        }
+        None => url.to_string(),
+    }
+}
+
+/// How links to the blog's own images are rewritten to point at the extracted images.
+#[derive(Debug, Clone)]
+pub(crate) struct LinkRewriter {
+    link_re: Regex,
+    sources: Vec<ImageSource>,
+    site_url: Option<String>,
+    /// from [`site_link_regex`] for the site's url
+    site_link_re: Regex,
+    images_url: String,
+    keep_query: bool,
+}
+
+impl LinkRewriter {
+    pub(crate) fn new(sources: Vec<ImageSource>) -> Self {
+        LinkRewriter {
+            link_re: internal_link_regex(&sources),
+            sources,
+            site_url: None,
+            site_link_re: site_link_regex(None),
+            images_url: format!("/{}", SECTION),
+            keep_query: false,
+        }
+    }
+
+    pub(crate) fn from_options(options: &ExtractOptions) -> Self {
+        LinkRewriter::new(options.image_sources.clone())
+            .site_url(options.base_url.clone())
+            .images_url(options.images_url())
+            .keep_query(options.keep_image_query)
+    }
+
+    /// set the url at which the extracted images are served; `/blog` by default
+    pub(crate) fn images_url(mut self, images_url: String) -> Self {
+        self.images_url = images_url;
+        self
+    }
+
+    /// set the url at which the blog was served, absolute links to which are treated as internal
+    pub(crate) fn site_url(mut self, site_url: Option<String>) -> Self {
+        self.site_link_re = site_link_regex(site_url.as_deref());
+        self.site_url = site_url;
+        self
+    }
+
+    /// whether to keep the query strings and fragments of image links
+    pub(crate) fn keep_query(mut self, keep_query: bool) -> Self {
+        self.keep_query = keep_query;
+        self
+    }
+
+    /// rewrite the image links within a post's content, in markdown, html and shortcodes
+    pub(crate) fn content(&self, text: &str) -> String {
+        let text = strip_site_links(text, &self.site_link_re, self.site_url.as_deref());
+        let text = relative_internal_links(&text, &self.link_re, &self.images_url, self.keep_query);
+        relative_embedded_urls(&text, &self.sources, &self.images_url, self.keep_query)
+    }
+
+    /// rewrite a single image url
+    pub(crate) fn image_url(&self, url: &str) -> String {
+        relative_image_url(
+            &strip_site_url(url, self.site_url.as_deref()),
+            &self.sources,
+            &self.images_url,
+            self.keep_query,
+        )
+    }
+}
+
+/// Where a post's content came from.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum ContentSource {
+    Markdown,
+    Plaintext,
+    Html,
+}
+
+/// The formats in which Ghost stored a post's content, any of which may be missing.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct ContentFormats {
+    pub(crate) markdown: Option<String>,
+    pub(crate) mobiledoc: Option<String>,
+    pub(crate) lexical: Option<String>,
+    pub(crate) plaintext: Option<String>,
+    pub(crate) html: Option<String>,
+}
+
+/// choose a post's content
+///
+/// This is its markdown if it has any, otherwise its editor document rendered into markdown,
+/// otherwise its plaintext rendering. Its pre-rendered html is used instead according to the
+/// `raw_html` policy.
+pub(crate) fn choose_content(
+    id: &PostId,
+    slug: &str,
+    formats: ContentFormats,
+    raw_html: RawHtmlPolicy,
+    shortcodes: &Shortcodes,
+) -> (String, ContentSource) {
+    let ContentFormats {
+        markdown,
+        mobiledoc,
+        lexical,
+        plaintext,
+        html,
+    } = formats;
+    // content is possibly null; we want to map that to an empty string
+    let markdown = match non_empty(markdown) {
+        Some(markdown) => markdown,
+        None => {
+            // the lexical document supersedes the mobiledoc, where a post has both
+            let rendered = match (non_empty(lexical), non_empty(mobiledoc)) {
+                (Some(lexical), _) => Some(render_lexical(&lexical, shortcodes)),
+                (None, Some(mobiledoc)) => Some(render_mobiledoc(&mobiledoc, shortcodes)),
+                (None, None) => None,
+            };
+            match rendered {
+                Some(Ok(markdown)) => markdown,
+                Some(Err(err)) => {
+                    log::warn!(
+                        "post {} ({}) could not be rendered from its editor document: {}",
+                        id,
+                        slug,
+                        err
+                    );
+                    String::new()
+                }
+                None => String::new(),
+            }
+        }
+    };
+    let html = html.filter(|html| !html.trim().is_empty());
+    match (raw_html, html) {
+        (RawHtmlPolicy::Always, Some(html)) => return (raw_html_shell(&html), ContentSource::Html),
+        (RawHtmlPolicy::Fallback, Some(html)) if markdown.trim().is_empty() => {
+            log::warn!(
+                "post {} ({}) has no markdown; passing through its html",
+                id,
+                slug
+            );
+            return (raw_html_shell(&html), ContentSource::Html);
+        }
+        _ => {}
+    }
+    match plaintext {
+        Some(plaintext) if markdown.trim().is_empty() && !plaintext.trim().is_empty() => {
+            log::warn!(
+                "post {} ({}) has no markdown; falling back to plaintext",
+                id,
+                slug
+            );
+            (plaintext, ContentSource::Plaintext)
+        }
+        _ => (markdown, ContentSource::Markdown),
+    }
+}
+
+/// whether a tag is internal to Ghost: its name begins with `#`
+pub(crate) fn is_internal_tag(name: &str) -> bool {
+    name.starts_with('#')
+}
+
+/// treat blank text as absent
+pub(crate) fn non_empty(text: Option<String>) -> Option<String> {
+    text.filter(|text| !text.trim().is_empty())
+}
+
+/// choose a post's description: its meta description if it has one, otherwise its custom excerpt
+pub(crate) fn description_or_excerpt(description: Option<String>, excerpt: Option<&str>) -> String {
+    // description is possibly null; we want to map that to an empty string
+    match description.filter(|description| !description.trim().is_empty()) {
+        Some(description) => description,
+        None => excerpt.unwrap_or_default().to_string(),
+    }
+}
+
+/// wrap pre-rendered html so that zola passes it through its markdown renderer untouched
+///
+/// Markdown treats everything from an opening `<div>` up to the next blank line as raw html, so the
+/// blank lines within the html are replaced by empty comments.
+pub(crate) fn raw_html_shell(html: &str) -> String {
+    let body = html
+        .trim()
+        .lines()
+        .map(|line| {
+            if line.trim().is_empty() {
+                "<!-- -->"
+            } else {
+                line
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("<div class=\"ghost-raw-html\">\n{}\n</div>\n", body)
+}
+
+/// count the words of a post's content
+///
+/// Html tags and link targets are not words, nor is markdown punctuation.
+pub(crate) fn count_words(content: &str) -> usize {
+    let text = HTML_TAG.replace_all(content, " ");
+    let text = LINK_TARGET.replace_all(&text, "] ");
+    text.split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .count()
+}
+
+/// insert a summary separator after the paragraph in which the excerpt ends
+///
+/// If the excerpt can't be found in the content, the separator follows the first paragraph
+/// instead. Content which already has a separator, or which has nothing after the summary, is
+/// returned unchanged.
+pub(crate) fn insert_summary_separator(content: &str, excerpt: &str, separator: &str) -> String {
+    if content.contains(separator) {
+        return content.into();
+    }
+    let excerpt = excerpt.trim();
+    let summary_end = match content.find(excerpt) {
+        Some(start) if !excerpt.is_empty() => start + excerpt.len(),
+        _ => content.len() - content.trim_start().len(),
+    };
+    let paragraph_end = content[summary_end..]
+        .find("\n\n")
+        .map_or(content.len(), |end| summary_end + end);
+    let (summary, rest) = content.split_at(paragraph_end);
+    if rest.trim().is_empty() {
+        return content.into();
+    }
+    format!("{}\n\n{}\n\n{}", summary, separator, rest.trim_start())
+}
+
 /// Replace all detected abstract footnotes with numbered ones.
 ///
 /// Ghost has a somewhat more advanced notion of footnotes than Zola does: you can use `[^n]` to insert
@@ -101,9 +727,10 @@ pub(crate) fn reify_footnotes(s: &str) -> String {
     text
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum Status {
     Published,
+    #[default]
     Draft,
 }
 
@@ -120,7 +747,7 @@ impl FromStr for Status {
 }
 
 impl FromSql for Status {
-    fn column_result(value: rusqlite::types::ValueRef<'_>) -> FromSqlResult<Self> {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
         value
             .as_str()
             .map(|str| Status::from_str(str).expect("Status::from_str is infallible"))
@@ -144,14 +771,141 @@ impl Status {
     }
 }
 
+/// The id of a post.
+///
+/// Ghost 0.x used integer ids; later versions use 24-character hex strings.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PostId {
+    Int(i64),
+    Text(String),
+}
+
+impl Default for PostId {
+    fn default() -> Self {
+        PostId::Int(0)
+    }
+}
+
+impl From<i64> for PostId {
+    fn from(id: i64) -> Self {
+        PostId::Int(id)
+    }
+}
+
+impl From<String> for PostId {
+    fn from(id: String) -> Self {
+        PostId::Text(id)
+    }
+}
+
+impl fmt::Display for PostId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PostId::Int(id) => write!(f, "{}", id),
+            PostId::Text(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+impl FromSql for PostId {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Integer(id) => Ok(PostId::Int(id)),
+            ValueRef::Text(_) => value.as_str().map(|id| PostId::Text(id.into())),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+impl ToSql for PostId {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(match self {
+            PostId::Int(id) => ToSqlOutput::from(*id),
+            PostId::Text(id) => ToSqlOutput::from(id.as_str()),
+        })
+    }
+}
+
 #[derive(Debug, Default, Serialize)]
 pub struct Extra {
-    pub id: i64,
+    pub id: PostId,
+    /// Ghost's uuid for the post, by which i.e. comment systems may know it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
     pub language: String,
+    /// the post's primary author
     pub author_name: String,
+    /// every author of the post, in order
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<String>,
+    /// the post's hero image
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feature_image: Option<String>,
+    /// whether the post is featured, i.e. for curation on the homepage
+    pub featured: bool,
+    /// who could read the post on Ghost, i.e. `public`, `members`, or `paid`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<String>,
+    /// the post's hand-written excerpt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excerpt: Option<String>,
+    /// url of the post's canonical version, for `<link rel="canonical">`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canonical_url: Option<String>,
+    /// markup injected into the `<head>` of the post's page, i.e. analytics or custom css
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub codeinjection_head: Option<String>,
+    /// markup injected at the foot of the post's page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub codeinjection_foot: Option<String>,
+    /// number of words in the post
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub words: Option<usize>,
+    /// estimated time to read the post, in minutes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reading_time: Option<usize>,
+    /// set when the post had no markdown, so its content is Ghost's plaintext rendering
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub plaintext_fallback: bool,
+    /// set when the post's content is its pre-rendered html, which should be converted by hand
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub raw_html: bool,
+    /// Ghost's internal tags, whose names begin with `#`, when they are kept
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub internal_tags: Vec<String>,
+    /// site-specific fields, from [`ExtractOptions::extra_frontmatter`]
+    #[serde(flatten)]
+    pub custom: BTreeMap<String, toml::Value>,
+    // tables must follow every plain value in toml, so this must be the last field
+    #[serde(skip_serializing_if = "Social::is_empty")]
+    pub social: Social,
+}
+
+/// Open Graph and Twitter card metadata, with which the post is previewed when shared.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct Social {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub og_image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub og_title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub og_description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub twitter_image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub twitter_title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub twitter_description: Option<String>,
+}
+
+impl Social {
+    pub fn is_empty(&self) -> bool {
+        *self == Social::default()
+    }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize)]
 pub struct Post {
     pub title: String,
     #[serde(skip_serializing_if = "String::is_empty")]
@@ -164,6 +918,17 @@ pub struct Post {
     pub date: Option<DateTime<Utc>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub updated: Option<DateTime<Utc>>,
+    /// paths from which the post is redirected, i.e. its url on Ghost
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    #[serde(skip)]
+    pub created: Option<DateTime<Utc>>,
+    /// whether this is a standalone page, such as "About", rather than a post
+    #[serde(skip)]
+    pub page: bool,
+    /// renames of the keys in the post's frontmatter
+    #[serde(skip)]
+    pub keys: KeyMapping,
     #[serde(
         skip_serializing_if = "Status::published",
         serialize_with = "Status::serialize_as_bool",
@@ -178,65 +943,103 @@ pub struct Post {
     pub content: String,
 }
 
-#[derive(Debug, Default, Serialize)]
+/// The ways in which Ghost classifies a post, and the Zola taxonomies into which they're written.
+#[derive(Debug, Default)]
 pub struct Taxonomies {
-    tags: Vec<String>,
+    pub tags: Vec<String>,
+    /// the post's primary tag
+    pub categories: Vec<String>,
+    pub authors: Vec<String>,
+    pub mapping: TaxonomyMapping,
 }
 
-impl Post {
-    pub fn query(conn: &Connection) -> Result<Vec<Post>, rusqlite::Error> {
-        let mut stmt = conn.prepare(
-            "
-            SELECT
-                posts.id,
-                posts.title,
-                posts.markdown,
-                posts.meta_description,
-                posts.published_at,
-                posts.updated_at,
-                posts.status,
-                posts.slug,
-                posts.language,
-                users.name
-            FROM posts
-            INNER JOIN users
-            ON posts.author_id = users.id
-            ",
-        )?;
-        let mut out: Result<Vec<Post>, rusqlite::Error> = stmt
-            .query_map(params![], |row| {
-                Ok(Post {
-                    // ID: 0
-                    title: row.get(1)?,
-                    // content and description are possibly null; we want to map those to empty strings
-                    content: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
-                    description: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
-                    date: row.get(4)?,
-                    updated: row.get(5)?,
-                    status: row.get(6)?,
-                    slug: row.get(7)?,
-                    extra: Extra {
-                        id: row.get(0)?,
-                        language: row.get(8)?,
-                        author_name: row.get(9)?,
-                    },
-                    taxonomies: Taxonomies::default(),
-                })
-            })?
-            .collect();
-
-        if let Ok(posts) = &mut out {
-            for post in posts.iter_mut() {
-                post.update_tags(conn)?;
-                post.content = relative_internal_links(&post.content);
+impl Taxonomies {
+    /// the terms of each taxonomy, in order
+    ///
+    /// The tags taxonomy is always present, where it is mapped; others only if they have terms.
+    pub fn terms(&self) -> Vec<(&str, Vec<String>)> {
+        let mut terms: Vec<(&str, Vec<String>)> = Vec::new();
+        let classifications = [
+            (&self.mapping.tags, &self.tags, true),
+            (&self.mapping.categories, &self.categories, false),
+            (&self.mapping.authors, &self.authors, false),
+        ];
+        for (name, classification, always) in classifications.iter() {
+            let name = match name {
+                Some(name) if *always || !classification.is_empty() => name.as_str(),
+                _ => continue,
+            };
+            let index = match terms.iter().position(|(existing, _)| *existing == name) {
+                Some(index) => index,
+                None => {
+                    terms.push((name, Vec::new()));
+                    terms.len() - 1
+                }
+            };
+            for term in classification.iter() {
+                let term = if self.mapping.slugify {
+                    slugify!(term)
+                } else {
+                    term.clone()
+                };
+                if !terms[index].1.contains(&term) {
+                    terms[index].1.push(term);
+                }
             }
         }
+        terms
+    }
+}
+
+impl Serialize for Taxonomies {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let terms = self.terms();
+        let mut map = serializer.serialize_map(Some(terms.len()))?;
+        for (name, terms) in &terms {
+            map.serialize_entry(name, terms)?;
+        }
+        map.end()
+    }
+}
+
+impl Post {
+    /// query every post in the database, collecting them into memory
+    ///
+    /// For large blogs, prefer [`Post::iter`], which yields posts one at a time.
+    pub fn query(conn: &Connection) -> Result<Vec<Post>, crate::Error> {
+        Post::iter(conn).collect()
+    }
 
-        out
+    /// iterate over every post in the database, in order of ascending id
+    ///
+    /// For more control over which posts are selected and how, see [`PostQuery`].
+    pub fn iter(conn: &Connection) -> Posts<'_> {
+        PostQuery::new().iter(conn)
     }
 
-    fn update_tags(&mut self, conn: &Connection) -> Result<(), rusqlite::Error> {
-        let mut stmt = conn.prepare(
+    /// read this post's tags, in order if `sorted`
+    ///
+    /// Only `posts_tags` tables with a `sort_order` column can be `sorted`. The first tag is then
+    /// the post's primary tag.
+    pub(crate) fn update_tags(
+        &mut self,
+        conn: &Connection,
+        sorted: bool,
+    ) -> Result<(), rusqlite::Error> {
+        let mut stmt = conn.prepare_cached(if sorted {
+            "
+            SELECT
+                tags.name
+            FROM tags
+            INNER JOIN posts_tags
+            ON tags.id = posts_tags.tag_id
+            WHERE posts_tags.post_id = ?1
+            ORDER BY posts_tags.sort_order
+            "
+        } else {
             "
             SELECT
                 tags.name
@@ -244,72 +1047,915 @@ impl Post {
             INNER JOIN posts_tags
             ON tags.id = posts_tags.tag_id
             WHERE posts_tags.post_id = ?1
+            "
+        })?;
+        self.taxonomies.tags = stmt
+            .query_map(params![self.extra.id], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<String>, rusqlite::Error>>()?;
+        Ok(())
+    }
+
+    /// read this post's authors from `posts_authors`, in order
+    ///
+    /// If it lists any, the first becomes the primary author.
+    pub(crate) fn update_authors(&mut self, conn: &Connection) -> Result<(), rusqlite::Error> {
+        let mut stmt = conn.prepare_cached(
+            "
+            SELECT
+                users.name
+            FROM users
+            INNER JOIN posts_authors
+            ON users.id = posts_authors.author_id
+            WHERE posts_authors.post_id = ?1
+            ORDER BY posts_authors.sort_order
             ",
         )?;
-        self.taxonomies.tags = stmt
-            .query_map(params![self.extra.id], |row| Ok(row.get::<_, String>(0)?))?
+        self.extra.authors = stmt
+            .query_map(params![self.extra.id], |row| row.get::<_, String>(0))?
             .collect::<Result<Vec<String>, rusqlite::Error>>()?;
+        if let Some(primary) = self.extra.authors.first() {
+            self.extra.author_name = primary.clone();
+        }
         Ok(())
     }
 
-    fn render_toml(&self) -> Result<String, crate::Error> {
-        // this is necessary because the TOML library doesn't handle TOML datetimes, emitting strings instead
-        // we have to work around that
-        Ok(strip_datetime_quotes(&toml::to_string(self)?))
+    /// count this post's words, and estimate the minutes it takes to read at the given speed
+    pub(crate) fn compute_reading_time(&mut self, words_per_minute: u32) {
+        let words = count_words(&self.content);
+        let words_per_minute = words_per_minute.max(1) as usize;
+        self.extra.words = Some(words);
+        // round up, so that no post takes no time at all
+        self.extra.reading_time = Some(words.div_ceil(words_per_minute).max(1));
     }
 
-    pub fn render_to<W: Write>(&self, writer: &mut W) -> Result<(), crate::Error> {
-        writeln!(writer, "+++")?;
-        writeln!(writer, "{}", self.render_toml()?)?;
-        writeln!(writer, "+++")?;
-        writeln!(writer, "")?;
-        writeln!(writer, "{}", reify_footnotes(&self.content))?;
-        Ok(())
+    /// the urls of the images describing this post, as opposed to those linked from its content
+    pub(crate) fn image_urls_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        let extra = &mut self.extra;
+        extra
+            .feature_image
+            .iter_mut()
+            .chain(extra.social.og_image.iter_mut())
+            .chain(extra.social.twitter_image.iter_mut())
     }
 
-    /// construct a safe slug for this post
+    /// rewrite the links to the extracted images, at `images_url`, to point into the post's bundle
     ///
-    /// - if a slug has already been set, use that
-    /// - otherwise, construct one from the title
-    /// - unless the title is empty, in which case use a uuidv4
-    pub fn slug(&self) -> String {
-        if self.slug.is_empty() {
-            if self.title.is_empty() {
-                uuid::Uuid::new_v4().to_string()
+    /// Links in the content become relative, i.e. `a.png`; the post's own images are served from
+    /// `bundle_url`. Images from different directories with the same name are told apart by a
+    /// suffix, i.e. `a-2.png`. Returns the path of each image, relative to `images_url`, which
+    /// must be moved into the bundle, with its name there.
+    pub(crate) fn bundle_images(
+        &mut self,
+        images_url: &str,
+        bundle_url: &str,
+    ) -> Vec<(String, String)> {
+        let mut images: Vec<(String, String)> = Vec::new();
+        self.map_extracted_images(images_url, |path, query, in_frontmatter| {
+            let name = match images.iter().find(|(image, _)| image == path) {
+                Some((_, name)) => name.clone(),
+                None => {
+                    let path_ref = Path::new(path);
+                    let file_name = path_ref.file_name()?.to_str()?;
+                    let mut name = file_name.to_string();
+                    let mut n = 1;
+                    while images.iter().any(|(_, bundled)| *bundled == name) {
+                        n += 1;
+                        name = match (
+                            path_ref.file_stem().and_then(|stem| stem.to_str()),
+                            path_ref
+                                .extension()
+                                .and_then(|extension| extension.to_str()),
+                        ) {
+                            (Some(stem), Some(extension)) => {
+                                format!("{}-{}.{}", stem, n, extension)
+                            }
+                            _ => format!("{}-{}", file_name, n),
+                        };
+                    }
+                    images.push((path.to_string(), name.clone()));
+                    name
+                }
+            };
+            if in_frontmatter {
+                Some(format!("{}/{}{}", bundle_url, name, query))
             } else {
-                slugify!(&self.title, max_length = 150)
+                Some(format!("{}{}", name, query))
             }
-        } else {
-            self.slug.clone()
-        }
+        });
+        images
     }
 
-    /// return the relative path to which this post should be rendered
-    pub fn relative_path(&self) -> PathBuf {
-        let base = match self.date {
-            Some(date) => PathBuf::new()
-                .join(date.format("%Y").to_string())
-                .join(date.format("%m").to_string())
-                .join(date.format("%d").to_string()),
-            None => PathBuf::from("undated"),
-        };
-        let name = PathBuf::from(self.slug()).with_extension("md");
-        base.join(name)
+    /// point the links to extracted images which were renamed, i.e. on conversion, at their new
+    /// names
+    ///
+    /// `renames` maps each image's old path, relative to `images_url`, to its new one.
+    pub(crate) fn rename_images(&mut self, images_url: &str, renames: &HashMap<String, String>) {
+        self.map_extracted_images(images_url, |path, query, _| {
+            let renamed = renames.get(path)?;
+            Some(format!("{}/{}{}", images_url, renamed, query))
+        });
     }
-}
 
-impl fmt::Display for Post {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut rendered = Vec::new();
-        self.render_to(&mut rendered).map_err(|_| std::fmt::Error)?;
+    /// the images in the post's content without alt text, by their urls, in order
+    ///
+    /// Markdown images with empty brackets, and html images with an empty or missing `alt`, are
+    /// listed; images in the frontmatter have no alt text to give.
+    pub(crate) fn images_without_alt(&self) -> Vec<String> {
+        let markdown = MARKDOWN_IMAGE_WITHOUT_ALT
+            .captures_iter(&self.content)
+            .map(|image| {
+                let url = image.get(1).or_else(|| image.get(2));
+                (
+                    image.get(0).map_or(0, |image| image.start()),
+                    url.map_or_else(String::new, |url| url.as_str().to_string()),
+                )
+            });
+        let html = HTML_IMAGE
+            .find_iter(&self.content)
+            .filter(|image| !HTML_ALT.is_match(image.as_str()))
+            .map(|image| {
+                let src = HTML_MEDIA_ATTR
+                    .captures_iter(image.as_str())
+                    .find(|attr| attr[1].eq_ignore_ascii_case("src"))
+                    .and_then(|attr| attr.get(3).or_else(|| attr.get(4)))
+                    .map_or_else(String::new, |src| src.as_str().to_string());
+                (image.start(), src)
+            });
+        let mut images: Vec<_> = markdown.chain(html).collect();
+        images.sort();
+        images.into_iter().map(|(_, url)| url).collect()
+    }
+
+    /// the paths of the extracted images, relative to `images_url`, which the post links to
+    ///
+    /// The post is left as it is.
+    pub(crate) fn extracted_images(&mut self, images_url: &str) -> Vec<String> {
+        let mut images = Vec::new();
+        self.map_extracted_images(images_url, |path, _, _| {
+            images.push(path.to_string());
+            None
+        });
+        images
+    }
+
+    /// replace the links to extracted images, in the content and the frontmatter
+    ///
+    /// `f` is given the path of each image relative to `images_url`, its query string and
+    /// fragment, if any, and whether it's in the frontmatter, and returns the url with which to
+    /// replace it, or `None` to leave it alone. Links to posts aren't images.
+    fn map_extracted_images<F>(&mut self, images_url: &str, mut f: F)
+    where
+        F: FnMut(&str, &str, bool) -> Option<String>,
+    {
+        let prefix = format!("{}/", images_url);
+        let mut map = |url: &str, in_frontmatter: bool| -> Option<String> {
+            let rest = url.strip_prefix(&prefix)?;
+            let (path, query) = rest.split_at(rest.find(['?', '#']).unwrap_or(rest.len()));
+            // links to other posts aren't images
+            if Path::new(path)
+                .extension()
+                .is_none_or(|extension| extension == "md")
+            {
+                return None;
+            }
+            f(path, query, in_frontmatter)
+        };
+        let link_re = Regex::new(&format!(r"\]\(({}[^)\s]+)", regex::escape(&prefix)))
+            .expect("an escaped prefix is a valid regex");
+        let content = link_re
+            .replace_all(&self.content, |captures: &Captures| {
+                match map(&captures[1], false) {
+                    Some(url) => format!("]({}", url),
+                    None => captures[0].to_string(),
+                }
+            })
+            .into_owned();
+        self.content = map_embedded_urls(&content, |url| {
+            map(url, false).unwrap_or_else(|| url.to_string())
+        });
+        for url in self.image_urls_mut() {
+            if let Some(mapped) = map(url, true) {
+                *url = mapped;
+            }
+        }
+    }
+
+    /// rewrite links to other posts into Zola internal links
+    pub(crate) fn link_posts(&mut self, links: &PostLinks) {
+        self.content = links.rewrite(&self.content);
+    }
+
+    /// rewrite internal image links, and the post's own images, to point at the extracted images
+    ///
+    /// Images embedded in raw html are rewritten too.
+    pub(crate) fn rewrite_links(&mut self, links: &LinkRewriter) {
+        self.content = links.content(&self.content);
+        for url in self.image_urls_mut() {
+            *url = links.image_url(url);
+        }
+    }
+
+    pub fn render_to<W: Write>(
+        &self,
+        writer: &mut W,
+        format: FrontmatterFormat,
+    ) -> Result<(), crate::Error> {
+        render_mapped_frontmatter(self, &self.keys, format, writer)?;
+        writeln!(writer)?;
+        writeln!(writer, "{}", reify_footnotes(&self.content))?;
+        Ok(())
+    }
+
+    /// render this post with frontmatter from a template, between the format's delimiters
+    pub fn render_template_to<W: Write>(
+        &self,
+        writer: &mut W,
+        template: &FrontmatterTemplate,
+        format: FrontmatterFormat,
+    ) -> Result<(), crate::Error> {
+        write_frontmatter(template.render(self)?.trim_end(), format, writer)?;
+        writeln!(writer)?;
+        writeln!(writer, "{}", reify_footnotes(&self.content))?;
+        Ok(())
+    }
+
+    /// render this post with Hugo's frontmatter
+    pub fn render_hugo_to<W: Write>(
+        &self,
+        writer: &mut W,
+        format: FrontmatterFormat,
+    ) -> Result<(), crate::Error> {
+        let frontmatter = HugoPost {
+            title: &self.title,
+            slug: &self.slug,
+            description: &self.description,
+            date: self.date,
+            lastmod: self.updated,
+            aliases: &self.aliases,
+            draft: self.status.draft(),
+            taxonomies: &self.taxonomies,
+            params: &self.extra,
+        };
+        render_mapped_frontmatter(&frontmatter, &self.keys, format, writer)?;
+        writeln!(writer)?;
+        writeln!(writer, "{}", reify_footnotes(&self.content))?;
+        Ok(())
+    }
+
+    /// render this post with Jekyll's frontmatter, which is always yaml
+    pub fn render_jekyll_to<W: Write>(&self, writer: &mut W) -> Result<(), crate::Error> {
+        let frontmatter = JekyllPost {
+            layout: if self.page { "page" } else { "post" },
+            title: &self.title,
+            slug: &self.slug,
+            description: &self.description,
+            date: self.date,
+            last_modified_at: self.updated,
+            redirect_from: &self.aliases,
+            // draft posts are kept out of the build by the `_drafts` directory, but pages need this
+            published: if self.page && self.status.draft() {
+                Some(false)
+            } else {
+                None
+            },
+            taxonomies: &self.taxonomies,
+            extra: &self.extra,
+        };
+        render_mapped_frontmatter(&frontmatter, &self.keys, FrontmatterFormat::Yaml, writer)?;
+        writeln!(writer)?;
+        writeln!(writer, "{}", reify_footnotes(&self.content))?;
+        Ok(())
+    }
+
+    /// the path at which Ghost served this post, given its permalink structure
+    ///
+    /// The structure may use the placeholders of Ghost's `routes.yaml`, i.e. `/{year}/{slug}/`, or
+    /// of its older settings, i.e. `/:year/:slug/`: `slug`, `id`, `year`, `month`, `day`,
+    /// `primary_tag`, and `primary_author`. Ghost served pages at `/{slug}/` whatever the
+    /// structure. Returns `None` if the structure uses a date, but the post has none, or an unknown
+    /// placeholder. A post without a slug is given one with the slug options, as when it's written.
+    pub fn ghost_path(&self, permalinks: &str, slugs: &SlugOptions) -> Option<String> {
+        let permalinks = if self.page { "/{slug}/" } else { permalinks };
+        let mut unknown = false;
+        let path = PERMALINK_PLACEHOLDER.replace_all(permalinks, |captures: &Captures| {
+            let name = captures
+                .get(1)
+                .or_else(|| captures.get(2))
+                .unwrap()
+                .as_str();
+            let value = match name {
+                "slug" => Some(self.slug_with(slugs)),
+                "id" => Some(self.extra.id.to_string()),
+                "year" => self.date.map(|date| date.format("%Y").to_string()),
+                "month" => self.date.map(|date| date.format("%m").to_string()),
+                "day" => self.date.map(|date| date.format("%d").to_string()),
+                // ghost knew the slugs of tags and authors; these are derived from their names
+                "primary_tag" => self.taxonomies.tags.first().map(|tag| slugify!(tag)),
+                "primary_author" | "author" => {
+                    self.extra.authors.first().map(|author| slugify!(author))
+                }
+                _ => None,
+            };
+            value.unwrap_or_else(|| {
+                unknown = true;
+                String::new()
+            })
+        });
+        if unknown {
+            return None;
+        }
+        if path.starts_with('/') {
+            Some(path.into_owned())
+        } else {
+            Some(format!("/{}", path))
+        }
+    }
+
+    /// construct a safe slug for this post
+    ///
+    /// - if a slug has already been set, use that
+    /// - otherwise, construct one from the title
+    /// - unless that's empty, in which case use the post's uuid, or else its id, so that the
+    ///   same post always gets the same slug
+    pub fn slug(&self) -> String {
+        self.slug_with(&SlugOptions::default())
+    }
+
+    /// construct a safe slug for this post, as [`slug`][Post::slug], with the given options
+    ///
+    /// The options only shape the slugs generated from titles: a slug from Ghost is kept verbatim.
+    pub fn slug_with(&self, options: &SlugOptions) -> String {
+        if !self.slug.is_empty() {
+            return self.slug.clone();
+        }
+        let slug = options.slugify(&self.title);
+        if !slug.is_empty() {
+            return slug;
+        }
+        match &self.extra.uuid {
+            Some(uuid) => uuid.clone(),
+            None => format!("post-{}", self.extra.id),
+        }
+    }
+
+    /// return the name of this post's file, without its extension: its slug, made with the given
+    /// options if it has none, made safe by the given policy
+    pub fn file_stem(&self, slugs: &SlugOptions, sanitization: PathSanitization) -> String {
+        sanitize_file_name(&self.slug_with(slugs), sanitization)
+    }
+
+    /// return the relative path to which this post should be rendered
+    ///
+    /// Posts are placed in a tree by date; pages, relative to their own section, are not.
+    pub fn relative_path(&self, slugs: &SlugOptions, sanitization: PathSanitization) -> PathBuf {
+        let name = PathBuf::from(format!("{}.md", self.file_stem(slugs, sanitization)));
+        if self.page {
+            return name;
+        }
+        let base = match self.date {
+            Some(date) => PathBuf::new()
+                .join(date.format("%Y").to_string())
+                .join(date.format("%m").to_string())
+                .join(date.format("%d").to_string()),
+            None => PathBuf::from("undated"),
+        };
+        base.join(name)
+    }
+}
+
+/// names which Windows reserves for devices, whatever their extension
+const WINDOWS_RESERVED_NAMES: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// make a file or directory name safe according to the given policy
+pub(crate) fn sanitize_file_name(name: &str, sanitization: PathSanitization) -> String {
+    match sanitization {
+        PathSanitization::None => name.to_string(),
+        PathSanitization::Windows => {
+            let name: String = name
+                .chars()
+                .map(|c| match c {
+                    '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '-',
+                    c if c.is_control() => '-',
+                    c => c,
+                })
+                .collect();
+            let mut name = name.trim_end_matches(['.', ' ']).to_string();
+            if name.is_empty() {
+                name.push('_');
+            }
+            let base = name.split('.').next().unwrap_or_default();
+            if WINDOWS_RESERVED_NAMES.contains(&base.to_lowercase().as_str()) {
+                name.insert(base.len(), '_');
+            }
+            name
+        }
+    }
+}
+
+/// A Ghost user, rendered as the `_index.md` of a Zola section describing them.
+#[derive(Debug, Default, Serialize)]
+pub struct Author {
+    #[serde(rename = "title")]
+    pub name: String,
+    /// the author's biography
+    #[serde(rename = "description", skip_serializing_if = "Option::is_none")]
+    pub bio: Option<String>,
+    #[serde(skip)]
+    pub slug: String,
+    pub extra: AuthorExtra,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct AuthorExtra {
+    pub id: PostId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub website: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// twitter handle, i.e. `@ghost`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub twitter: Option<String>,
+    /// facebook username, i.e. `ghost`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub facebook: Option<String>,
+    /// url of the author's avatar
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile_image: Option<String>,
+    /// url of the banner image of the author's page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover_image: Option<String>,
+}
+
+impl Author {
+    /// query every user in the database, in order of ascending id
+    pub fn query(conn: &Connection) -> Result<Vec<Author>, rusqlite::Error> {
+        // choose the first of the candidate columns which exists
+        let choose = |candidates: &[&str]| -> Result<String, rusqlite::Error> {
+            for column in candidates {
+                if has_column(conn, "users", column)? {
+                    return Ok(column.to_string());
+                }
+            }
+            Ok("NULL".into())
+        };
+        let mut stmt = conn.prepare(&format!(
+            "
+            SELECT
+                id, name, slug, bio, website, location,
+                {} AS twitter, {} AS facebook, {} AS profile_image, {} AS cover_image
+            FROM users
+            ORDER BY id
+            ",
+            // social accounts were introduced in Ghost 1.x, which also renamed the images
+            choose(&["twitter"])?,
+            choose(&["facebook"])?,
+            choose(&["profile_image", "image"])?,
+            choose(&["cover_image", "cover"])?,
+        ))?;
+        let authors = stmt
+            .query_map(params![], |row| {
+                Ok(Author {
+                    name: row.get("name")?,
+                    bio: non_empty(row.get("bio")?),
+                    slug: row.get("slug")?,
+                    extra: AuthorExtra {
+                        id: row.get("id")?,
+                        website: non_empty(row.get("website")?),
+                        location: non_empty(row.get("location")?),
+                        twitter: non_empty(row.get("twitter")?),
+                        facebook: non_empty(row.get("facebook")?),
+                        profile_image: non_empty(row.get("profile_image")?),
+                        cover_image: non_empty(row.get("cover_image")?),
+                    },
+                })
+            })?
+            .collect();
+        authors
+    }
+
+    /// the urls of the author's images
+    pub(crate) fn image_urls_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        self.extra
+            .profile_image
+            .iter_mut()
+            .chain(self.extra.cover_image.iter_mut())
+    }
+
+    /// rewrite the author's images to point at the extracted images
+    pub(crate) fn rewrite_links(&mut self, links: &LinkRewriter) {
+        for url in self.image_urls_mut() {
+            *url = links.image_url(url);
+        }
+    }
+
+    pub fn render_to<W: Write>(
+        &self,
+        writer: &mut W,
+        format: FrontmatterFormat,
+    ) -> Result<(), crate::Error> {
+        render_frontmatter(self, format, writer)
+    }
+
+    /// render this author's section with Hugo's frontmatter
+    pub fn render_hugo_to<W: Write>(
+        &self,
+        writer: &mut W,
+        format: FrontmatterFormat,
+    ) -> Result<(), crate::Error> {
+        let frontmatter = HugoSection {
+            title: &self.name,
+            description: self.bio.as_deref(),
+            params: &self.extra,
+        };
+        render_frontmatter(&frontmatter, format, writer)
+    }
+
+    /// render this author's page with Jekyll's frontmatter, using the `author` layout
+    pub fn render_jekyll_to<W: Write>(&self, writer: &mut W) -> Result<(), crate::Error> {
+        let frontmatter = JekyllSection {
+            layout: "author",
+            title: &self.name,
+            description: self.bio.as_deref(),
+            extra: &self.extra,
+        };
+        render_frontmatter(&frontmatter, FrontmatterFormat::Yaml, writer)
+    }
+
+    /// return the path, relative to the authors section, to which this author should be rendered
+    pub fn relative_path(&self, sanitization: PathSanitization) -> PathBuf {
+        let slug = if self.slug.is_empty() {
+            slugify!(&self.name, max_length = 150)
+        } else {
+            self.slug.clone()
+        };
+        PathBuf::from(sanitize_file_name(&slug, sanitization)).join("_index.md")
+    }
+}
+
+/// A Ghost tag, rendered as the `_index.md` of a Zola section describing it.
+#[derive(Debug, Default, Serialize)]
+pub struct Tag {
+    #[serde(rename = "title")]
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip)]
+    pub slug: String,
+    pub extra: TagExtra,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct TagExtra {
+    pub id: PostId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feature_image: Option<String>,
+}
+
+impl Tag {
+    /// query every tag in the database, in order of ascending id
+    ///
+    /// Image urls are returned as Ghost served them; see [`Tag::rewrite_links`].
+    pub fn query(conn: &Connection) -> Result<Vec<Tag>, rusqlite::Error> {
+        // named `image` before Ghost 1.0
+        let feature_image = has_column(conn, "tags", "feature_image")?;
+        let mut stmt = conn.prepare(&format!(
+            "
+            SELECT id, name, slug, description, {} AS feature_image
+            FROM tags
+            ORDER BY id
+            ",
+            if feature_image {
+                "feature_image"
+            } else {
+                "image"
+            },
+        ))?;
+        let tags = stmt
+            .query_map(params![], |row| {
+                Ok(Tag {
+                    name: row.get("name")?,
+                    description: non_empty(row.get("description")?),
+                    slug: row.get("slug")?,
+                    extra: TagExtra {
+                        id: row.get("id")?,
+                        feature_image: non_empty(row.get("feature_image")?),
+                    },
+                })
+            })?
+            .collect();
+        tags
+    }
+
+    /// whether this is one of Ghost's internal tags, which are never shown to readers
+    pub fn is_internal(&self) -> bool {
+        is_internal_tag(&self.name)
+    }
+
+    /// the urls of the tag's images
+    pub(crate) fn image_urls_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        self.extra.feature_image.iter_mut()
+    }
+
+    /// rewrite the tag's feature image to point at the extracted image
+    pub(crate) fn rewrite_links(&mut self, links: &LinkRewriter) {
+        for url in self.image_urls_mut() {
+            *url = links.image_url(url);
+        }
+    }
+
+    pub fn render_to<W: Write>(
+        &self,
+        writer: &mut W,
+        format: FrontmatterFormat,
+    ) -> Result<(), crate::Error> {
+        render_frontmatter(self, format, writer)
+    }
+
+    /// render this tag's section with Hugo's frontmatter
+    pub fn render_hugo_to<W: Write>(
+        &self,
+        writer: &mut W,
+        format: FrontmatterFormat,
+    ) -> Result<(), crate::Error> {
+        let frontmatter = HugoSection {
+            title: &self.name,
+            description: self.description.as_deref(),
+            params: &self.extra,
+        };
+        render_frontmatter(&frontmatter, format, writer)
+    }
+
+    /// render this tag's page with Jekyll's frontmatter, using the `tag` layout
+    pub fn render_jekyll_to<W: Write>(&self, writer: &mut W) -> Result<(), crate::Error> {
+        let frontmatter = JekyllSection {
+            layout: "tag",
+            title: &self.name,
+            description: self.description.as_deref(),
+            extra: &self.extra,
+        };
+        render_frontmatter(&frontmatter, FrontmatterFormat::Yaml, writer)
+    }
+
+    /// return the path, relative to the tags section, to which this tag should be rendered
+    pub fn relative_path(&self, sanitization: PathSanitization) -> PathBuf {
+        let slug = if self.slug.is_empty() {
+            slugify!(&self.name, max_length = 150)
+        } else {
+            self.slug.clone()
+        };
+        PathBuf::from(sanitize_file_name(&slug, sanitization)).join("_index.md")
+    }
+}
+
+/// A post's frontmatter, as Hugo expects it.
+///
+/// Hugo reads taxonomies from the top level, and custom fields from `params`.
+#[derive(Serialize)]
+struct HugoPost<'a> {
+    title: &'a str,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    slug: &'a str,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    description: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lastmod: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    aliases: &'a [String],
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    draft: bool,
+    #[serde(flatten)]
+    taxonomies: &'a Taxonomies,
+    // tables must follow every plain value in toml, so this must be the last field
+    params: &'a Extra,
+}
+
+/// An author's or tag's frontmatter, as Hugo expects it of a taxonomy term's `_index.md`.
+#[derive(Serialize)]
+struct HugoSection<'a, P> {
+    title: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    params: &'a P,
+}
+
+/// A post's frontmatter, as Jekyll expects it.
+///
+/// Jekyll reads `tags` and `categories` from the top level; the other taxonomies, and the custom
+/// fields in `extra`, are available to its layouts all the same.
+#[derive(Serialize)]
+struct JekyllPost<'a> {
+    layout: &'a str,
+    title: &'a str,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    slug: &'a str,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    description: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_modified_at: Option<DateTime<Utc>>,
+    /// the aliases, for the `jekyll-redirect-from` plugin
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    redirect_from: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    published: Option<bool>,
+    #[serde(flatten)]
+    taxonomies: &'a Taxonomies,
+    extra: &'a Extra,
+}
+
+/// An author's or tag's frontmatter, as a Jekyll page rendered with its own layout.
+#[derive(Serialize)]
+struct JekyllSection<'a, E> {
+    layout: &'a str,
+    title: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    extra: &'a E,
+}
+
+/// rename or drop the keys of frontmatter according to the mapping
+///
+/// Every key is removed before any is inserted, so that keys may be swapped.
+fn map_keys<T: Serialize>(frontmatter: &T, keys: &KeyMapping) -> Result<toml::Value, crate::Error> {
+    let mut value = toml::Value::try_from(frontmatter)?;
+    let renamed: Vec<_> = keys
+        .iter()
+        .filter_map(|(key, renamed)| Some((renamed, take_key(&mut value, key)?)))
+        .collect();
+    for (key, taken) in renamed {
+        if let Some(key) = key {
+            insert_key(&mut value, key, taken);
+        }
+    }
+    Ok(value)
+}
+
+/// remove the value at a dotted key, if there is one
+fn take_key(value: &mut toml::Value, key: &str) -> Option<toml::Value> {
+    let (parent, name) = match key.rsplit_once('.') {
+        Some((parent, name)) => (
+            parent
+                .split('.')
+                .try_fold(value, |value, part| value.get_mut(part))?,
+            name,
+        ),
+        None => (value, key),
+    };
+    // the table's own `remove` swaps its last key into the removed key's place, so it's rebuilt
+    // instead, keeping the order of the remaining keys
+    let table = parent.as_table_mut()?;
+    let mut taken = None;
+    *table = std::mem::take(table)
+        .into_iter()
+        .filter_map(|(key, value)| {
+            if key == name {
+                taken = Some(value);
+                None
+            } else {
+                Some((key, value))
+            }
+        })
+        .collect();
+    taken
+}
+
+/// insert a value at a dotted key, creating tables as need be
+///
+/// A value which is in the way of a table is replaced.
+fn insert_key(value: &mut toml::Value, key: &str, inserted: toml::Value) {
+    let mut parts: Vec<_> = key.split('.').collect();
+    let name = parts.pop().expect("split always yields at least one part");
+    let mut table = value;
+    for part in parts {
+        let table_ref = match table {
+            toml::Value::Table(table_ref) => table_ref,
+            _ => return,
+        };
+        let entry = table_ref
+            .entry(part.to_string())
+            .or_insert_with(|| toml::Value::Table(Default::default()));
+        if !entry.is_table() {
+            *entry = toml::Value::Table(Default::default());
+        }
+        table = entry;
+    }
+    if let toml::Value::Table(table) = table {
+        table.insert(name.to_string(), inserted);
+    }
+}
+
+/// render frontmatter in the given format, between its delimiters
+fn render_frontmatter<T: Serialize, W: Write>(
+    frontmatter: &T,
+    format: FrontmatterFormat,
+    writer: &mut W,
+) -> Result<(), crate::Error> {
+    match format {
+        FrontmatterFormat::Toml => {
+            write_frontmatter(&render_toml(frontmatter, DATETIME_FIELDS)?, format, writer)
+        }
+        FrontmatterFormat::Yaml => {
+            write_frontmatter(render_yaml(frontmatter)?.trim_end(), format, writer)
+        }
+    }
+}
+
+/// render frontmatter with its keys renamed according to the mapping, between its delimiters
+///
+/// Datetimes are found wherever the mapping has moved them.
+fn render_mapped_frontmatter<T: Serialize, W: Write>(
+    frontmatter: &T,
+    keys: &KeyMapping,
+    format: FrontmatterFormat,
+    writer: &mut W,
+) -> Result<(), crate::Error> {
+    let frontmatter = map_keys(frontmatter, keys)?;
+    match format {
+        FrontmatterFormat::Toml => {
+            let datetimes: Vec<_> = DATETIME_FIELDS
+                .iter()
+                .filter_map(|field| keys.get(field).unwrap_or(Some(field)))
+                .collect();
+            write_frontmatter(&render_toml(&frontmatter, &datetimes)?, format, writer)
+        }
+        FrontmatterFormat::Yaml => render_frontmatter(&frontmatter, format, writer),
+    }
+}
+
+/// write rendered frontmatter between the format's delimiters
+fn write_frontmatter<W: Write>(
+    rendered: &str,
+    format: FrontmatterFormat,
+    writer: &mut W,
+) -> Result<(), crate::Error> {
+    let delimiter = match format {
+        FrontmatterFormat::Toml => "+++",
+        FrontmatterFormat::Yaml => "---",
+    };
+    writeln!(writer, "{}", delimiter)?;
+    writeln!(writer, "{}", rendered)?;
+    writeln!(writer, "{}", delimiter)?;
+    Ok(())
+}
+
+/// render frontmatter as yaml
+fn render_yaml<T: Serialize>(frontmatter: &T) -> Result<String, crate::Error> {
+    // unlike toml's, the yaml serializer leaves strings unquoted where that's unambiguous, so
+    // datetimes are already written as native timestamps
+    Ok(serde_yaml::to_string(frontmatter)?)
+}
+
+/// the frontmatter fields which hold datetimes, for each target, before their keys are renamed
+const DATETIME_FIELDS: &[&str] = &["date", "updated", "lastmod"];
+
+/// render frontmatter as toml, with the datetimes at the given dotted keys
+fn render_toml<T: Serialize>(frontmatter: &T, datetimes: &[&str]) -> Result<String, crate::Error> {
+    // chrono's datetimes serialize as strings, which toml would quote; they're converted into
+    // native toml datetimes on the way through, leaving every other string alone
+    let mut value = toml::Value::try_from(frontmatter)?;
+    for key in datetimes {
+        let field = match key
+            .split('.')
+            .try_fold(&mut value, |value, part| value.get_mut(part))
+        {
+            Some(field) => field,
+            None => continue,
+        };
+        if let Some(datetime) = field
+            .as_str()
+            .and_then(|datetime| datetime.parse::<toml::value::Datetime>().ok())
+        {
+            *field = toml::Value::Datetime(datetime);
+        }
+    }
+    let mut rendered = String::new();
+    let mut serializer = toml::Serializer::new(&mut rendered);
+    // strings spanning several lines, i.e. code injection, are clearer as multi-line strings
+    serializer.pretty_string(true).pretty_string_literal(false);
+    value.serialize(&mut serializer)?;
+    Ok(rendered)
+}
+
+impl fmt::Display for Post {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rendered = Vec::new();
+        self.render_to(&mut rendered, FrontmatterFormat::default())
+            .map_err(|_| std::fmt::Error)?;
         // this is safe because we just populated the render with only valid utf-8
         write!(f, "{}", unsafe { String::from_utf8_unchecked(rendered) })
     }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
+    use crate::query::PostColumns;
 
     #[test]
     fn can_render() {
@@ -319,19 +1965,25 @@ mod tests {
             description: String::new(),
             date: None,
             updated: None,
+            aliases: Vec::new(),
+            created: None,
             status: Status::Draft,
             slug: "fancy-example-post".into(),
+            page: false,
+            keys: KeyMapping::default(),
             extra: Extra {
-                id: 123,
+                id: 123.into(),
                 language: "en_EN".into(),
                 author_name: "me".into(),
+                ..Extra::default()
             },
             taxonomies: Taxonomies {
                 tags: vec!["tag1".into(), "another".into()],
+                ..Taxonomies::default()
             },
         };
 
-        println!("{}", post.to_string());
+        println!("{}", post);
         println!("=== next post ===");
 
         let post = Post {
@@ -341,14 +1993,463 @@ mod tests {
             ..post
         };
 
-        println!("{}", post.to_string());
+        println!("{}", post);
+    }
+
+    #[test]
+    fn renders_yaml() {
+        let post = Post {
+            title: "Fancy: Example Post".into(),
+            content: "Hi".into(),
+            description: String::new(),
+            date: Some("2020-10-23T20:13:54.069Z".parse().unwrap()),
+            updated: None,
+            aliases: Vec::new(),
+            created: None,
+            status: Status::Published,
+            slug: "fancy-example-post".into(),
+            page: false,
+            keys: KeyMapping::default(),
+            extra: Extra {
+                id: 123.into(),
+                language: "en_EN".into(),
+                author_name: "me".into(),
+                ..Extra::default()
+            },
+            taxonomies: Taxonomies {
+                tags: vec!["tag1".into()],
+                ..Taxonomies::default()
+            },
+        };
+        let mut rendered = Vec::new();
+        post.render_to(&mut rendered, FrontmatterFormat::Yaml)
+            .unwrap();
+        let rendered = String::from_utf8(rendered).unwrap();
+        assert!(
+            rendered.starts_with("---\ntitle: 'Fancy: Example Post'\nslug: fancy-example-post\n")
+        );
+        assert!(rendered.contains("\ndate: 2020-10-23T20:13:54.069Z\n"));
+        assert!(rendered.contains("\ntaxonomies:\n  tags:\n  - tag1\n"));
+        assert!(rendered.ends_with("\n---\n\nHi\n"));
+    }
+
+    /// construct an in-memory database containing a minimal ghost schema
+    pub(crate) fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "
+            CREATE TABLE posts (
+                id integer not null primary key autoincrement,
+                uuid varchar(36) null,
+                title varchar(150) not null,
+                slug varchar(150) not null,
+                markdown text null,
+                plaintext text null,
+                html text null,
+                featured boolean not null default '0',
+                visibility varchar(150) not null default 'public',
+                page boolean not null default '0',
+                status varchar(150) not null default 'draft',
+                language varchar(6) not null default 'en_US',
+                meta_description varchar(200) null,
+                author_id integer not null,
+                image text null,
+                created_at datetime null,
+                updated_at datetime null,
+                published_at datetime null
+            );
+            CREATE TABLE users (
+                id integer not null primary key autoincrement,
+                name varchar(150) not null,
+                slug varchar(150) not null,
+                bio varchar(200) null,
+                website text null,
+                location text null,
+                image text null,
+                cover text null
+            );
+            CREATE TABLE tags (
+                id integer not null primary key autoincrement,
+                name varchar(150) not null,
+                slug varchar(150) not null default '',
+                description varchar(200) null,
+                image text null
+            );
+            CREATE TABLE posts_tags (
+                id integer not null primary key autoincrement,
+                post_id integer not null,
+                tag_id integer not null,
+                sort_order integer not null default '0'
+            );
+
+            INSERT INTO users (id, name, slug, bio, website, image)
+                VALUES (1, 'Pete', 'pete', 'Writes.', 'https://example.com',
+                    '/content/images/2020/01/pete.png');
+            INSERT INTO tags (id, name, slug, description, image)
+                VALUES (1, 'rust', 'rust', 'Crabs.', '/content/images/2020/01/crab.png');
+            INSERT INTO posts (id, title, slug, markdown, status, author_id, image, featured)
+                VALUES (7, 'Second', 'second', '![](/content/images/2020/01/a.png)', 'published', 1,
+                    '/content/images/2020/01/hero.png', 1);
+            INSERT INTO posts (id, title, slug, markdown, status, author_id, visibility)
+                VALUES (3, 'First', 'first', 'hello', 'draft', 1, 'paid');
+            INSERT INTO posts (id, uuid, title, slug, plaintext, html, status, author_id)
+                VALUES (9, '6a1b5c2e-8d4f-4e0a-9c3b-2f7d1e8a4b60', 'Third', 'third', 'only plain', '<p>only</p>
+
+<p>plain</p>', 'published', 1);
+            INSERT INTO posts_tags (post_id, tag_id) VALUES (7, 1);
+            ",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn iterates_posts_in_id_order() {
+        let conn = test_db();
+        let posts = Post::iter(&conn).collect::<Result<Vec<_>, _>>().unwrap();
+        let ids: Vec<_> = posts.iter().map(|post| post.extra.id.clone()).collect();
+        assert_eq!(ids, vec![3.into(), 7.into(), 9.into()]);
+        assert!(posts[0].taxonomies.tags.is_empty());
+        assert_eq!(posts[1].taxonomies.tags, vec!["rust".to_string()]);
+        assert_eq!(posts[1].content, "![](/blog/2020/01/a.png)");
+        assert_eq!(
+            posts[1].extra.feature_image.as_deref(),
+            Some("/blog/2020/01/hero.png")
+        );
+        assert_eq!(posts[0].extra.feature_image, None);
+        assert!(posts[1].extra.featured);
+        assert!(!posts[0].extra.featured);
+        assert_eq!(posts[0].extra.visibility.as_deref(), Some("paid"));
+        assert_eq!(posts[1].extra.visibility.as_deref(), Some("public"));
+        assert_eq!(posts[1].extra.uuid, None);
+        assert_eq!(
+            posts[2].extra.uuid.as_deref(),
+            Some("6a1b5c2e-8d4f-4e0a-9c3b-2f7d1e8a4b60")
+        );
+    }
+
+    #[test]
+    fn reads_authors() {
+        let conn = test_db();
+        let posts = Post::query(&conn).unwrap();
+        assert_eq!(posts[0].extra.authors, vec!["Pete".to_string()]);
+
+        conn.execute_batch(
+            "
+            CREATE TABLE posts_authors (
+                id integer not null primary key autoincrement,
+                post_id integer not null,
+                author_id integer not null,
+                sort_order integer not null default '0'
+            );
+            INSERT INTO users (id, name, slug) VALUES (2, 'Ann', 'ann');
+            INSERT INTO posts_authors (post_id, author_id, sort_order) VALUES (7, 1, 1);
+            INSERT INTO posts_authors (post_id, author_id, sort_order) VALUES (7, 2, 0);
+            INSERT INTO posts (id, title, slug, markdown, status, author_id)
+                VALUES (11, 'Orphan', 'orphan', 'lost', 'published', 99);
+            ",
+        )
+        .unwrap();
+        let posts = Post::query(&conn).unwrap();
+        assert_eq!(posts[0].extra.authors, vec!["Pete".to_string()]);
+        assert_eq!(
+            posts[1].extra.authors,
+            vec!["Ann".to_string(), "Pete".to_string()]
+        );
+        assert_eq!(posts[1].extra.author_name, "Ann");
+        // posts with a stale author are extracted nonetheless
+        assert_eq!(posts[3].extra.id, 11.into());
+        assert!(posts[3].extra.authors.is_empty());
+    }
+
+    #[test]
+    fn lays_out_pages_flat() {
+        let conn = test_db();
+        conn.execute_batch(
+            "INSERT INTO posts (id, title, slug, markdown, status, author_id, page, published_at)
+                VALUES (11, 'About', 'about', 'me', 'published', 1, 1, '2020-01-02 10:00:00');",
+        )
+        .unwrap();
+        let posts = PostQuery::new()
+            .columns(PostColumns::detect(&conn).unwrap())
+            .query(&conn)
+            .unwrap();
+        assert!(!posts[1].page);
+        assert!(posts[3].page);
+        assert_eq!(
+            posts[3].relative_path(&SlugOptions::default(), PathSanitization::default()),
+            PathBuf::from("about.md")
+        );
+    }
+
+    #[test]
+    fn reads_users() {
+        let mut authors = Author::query(&test_db()).unwrap();
+        assert_eq!(authors.len(), 1);
+        authors[0].rewrite_links(&LinkRewriter::new(vec![ImageSource::default()]));
+        assert_eq!(
+            authors[0].relative_path(PathSanitization::default()),
+            PathBuf::from("pete/_index.md")
+        );
+        let mut rendered = Vec::new();
+        authors[0]
+            .render_to(&mut rendered, FrontmatterFormat::Toml)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(rendered).unwrap(),
+            "+++\ntitle = \"Pete\"\ndescription = \"Writes.\"\n\n[extra]\nid = 1\nwebsite = \"https://example.com\"\nprofile_image = \"/blog/2020/01/pete.png\"\n\n+++\n"
+        );
+    }
+
+    #[test]
+    fn reads_tags() {
+        let mut tags = Tag::query(&test_db()).unwrap();
+        assert_eq!(tags.len(), 1);
+        tags[0].rewrite_links(&LinkRewriter::new(vec![ImageSource::default()]));
+        assert_eq!(
+            tags[0].relative_path(PathSanitization::default()),
+            PathBuf::from("rust/_index.md")
+        );
+        assert_eq!(tags[0].description.as_deref(), Some("Crabs."));
+        assert_eq!(
+            tags[0].extra.feature_image.as_deref(),
+            Some("/blog/2020/01/crab.png")
+        );
+    }
+
+    #[test]
+    fn links_posts() {
+        let mut links = PostLinks::default();
+        links.insert("second".into(), Path::new("2020/01/02/second.md"));
+        assert_eq!(
+            links.rewrite(
+                "[a](/second/) [b](/2020/01/02/second/#part) [c](/tag/second/) [d](/missing/) \
+                 [e](/blog/2020/01/a.png)"
+            ),
+            "[a](@/blog/2020/01/02/second.md) [b](@/blog/2020/01/02/second.md#part) \
+             [c](/tag/second/) [d](/missing/) [e](/blog/2020/01/a.png)"
+        );
+    }
+
+    #[test]
+    fn counts_words() {
+        assert_eq!(count_words("Hello, world!\n\n- one\n- two"), 4);
+        assert_eq!(count_words("![a hero](/blog/2020/01/a.png) done"), 3);
+        assert_eq!(count_words("<p>raw <em>html</em></p>"), 2);
+
+        let mut post = Post {
+            content: "word ".repeat(600),
+            ..Post::default()
+        };
+        post.compute_reading_time(275);
+        assert_eq!(post.extra.words, Some(600));
+        assert_eq!(post.extra.reading_time, Some(3));
+    }
+
+    #[test]
+    fn prefers_meta_description_to_excerpt() {
+        assert_eq!(
+            description_or_excerpt(Some("meta".into()), Some("excerpt")),
+            "meta"
+        );
+        assert_eq!(
+            description_or_excerpt(Some(" ".into()), Some("excerpt")),
+            "excerpt"
+        );
+        assert_eq!(description_or_excerpt(None, None), "");
     }
 
     mod replace_links {
         use super::super::*;
 
         fn replace_links(example: &str, expect: &str) {
-            assert_eq!(relative_internal_links(example), expect);
+            assert_eq!(
+                relative_internal_links(example, &INTERNAL_LINK_RE, "/blog", false),
+                expect
+            );
+        }
+
+        #[test]
+        fn test_should_replace_flat_link() {
+            replace_links("![](/content/images/asdf.jpg)", "![](/blog/asdf.jpg)");
+        }
+
+        #[test]
+        fn test_should_strip_ghost_url() {
+            let rewriter = LinkRewriter::new(vec![ImageSource::default()]);
+            assert_eq!(
+                rewriter
+                    .content("![](__GHOST_URL__/content/images/asdf.jpg) [home](__GHOST_URL__)"),
+                "![](/blog/asdf.jpg) [home](/)",
+            );
+            assert_eq!(
+                relative_image_url(
+                    "__GHOST_URL__/content/images/2020/01/a.png",
+                    &[ImageSource::default()],
+                    "/blog",
+                    false
+                ),
+                "/blog/2020/01/a.png"
+            );
+        }
+
+        #[test]
+        fn test_should_replace_responsive_variant() {
+            replace_links(
+                "![](/content/images/size/w600/2020/01/a.jpg) ![](/content/images/size/w600h400/b.jpg)",
+                "![](/blog/2020/01/a.jpg) ![](/blog/b.jpg)",
+            );
+            assert_eq!(
+                relative_image_url(
+                    "/content/images/size/w1000/2020/01/hero.png",
+                    &[ImageSource::default()],
+                    "/blog",
+                    false
+                ),
+                "/blog/2020/01/hero.png"
+            );
+        }
+
+        #[test]
+        fn test_should_replace_html_images() {
+            let sources = ImageSource::uploads();
+            let html = r#"<img src="/content/images/2020/01/a.jpg" srcset="/content/images/size/w600/2020/01/a.jpg 600w, https://example.org/b.jpg 2x" alt="a"><SOURCE SRC='/content/media/c.mp4'>"#;
+            assert_eq!(
+                relative_embedded_urls(html, &sources, "/blog", false),
+                r#"<img src="/blog/2020/01/a.jpg" srcset="/blog/2020/01/a.jpg 600w, https://example.org/b.jpg 2x" alt="a"><SOURCE SRC='/blog/c.mp4'>"#,
+            );
+            assert_eq!(
+                embedded_urls(html),
+                vec![
+                    "/content/images/2020/01/a.jpg",
+                    "/content/images/size/w600/2020/01/a.jpg",
+                    "https://example.org/b.jpg",
+                    "/content/media/c.mp4",
+                ],
+            );
+            // links and text outside of image tags are untouched
+            let text = r#"<a href="/content/images/a.jpg">src="/content/images/a.jpg"</a>"#;
+            assert_eq!(relative_embedded_urls(text, &sources, "/blog", false), text);
+            let media = r#"<video src="/content/media/v.mp4" poster="/content/images/p.jpg"></video> {{ audio(src="/content/media/a.mp3", title="/content/a") }}"#;
+            assert_eq!(
+                relative_embedded_urls(media, &sources, "/blog", false),
+                r#"<video src="/blog/v.mp4" poster="/blog/p.jpg"></video> {{ audio(src="/blog/a.mp3", title="/content/a") }}"#,
+            );
+        }
+
+        #[test]
+        fn test_should_handle_query_strings_and_fragments() {
+            let text =
+                r#"![](/content/images/2020/01/a.jpg?w=600#top "A") ![](/content/images/b.jpg#x)"#;
+            replace_links(text, r#"![](/blog/2020/01/a.jpg "A") ![](/blog/b.jpg)"#);
+            assert_eq!(
+                relative_internal_links(text, &INTERNAL_LINK_RE, "/blog", true),
+                r#"![](/blog/2020/01/a.jpg?w=600#top "A") ![](/blog/b.jpg#x)"#,
+            );
+            let sources = [ImageSource::default()];
+            assert_eq!(
+                internal_image_path("/content/images/a.jpg?w=600", &sources),
+                Some("a.jpg")
+            );
+            assert_eq!(
+                relative_image_url("/content/images/a.jpg?w=600", &sources, "/blog", true),
+                "/blog/a.jpg?w=600"
+            );
+        }
+
+        #[test]
+        fn test_should_replace_files_and_media() {
+            replace_links(
+                "[slides](/content/files/2020/01/talk.pdf) ![](/content/media/2020/01/demo.mp4)",
+                "[slides](/blog/2020/01/talk.pdf) ![](/blog/2020/01/demo.mp4)",
+            );
+        }
+
+        #[test]
+        fn test_should_strip_base_url() {
+            let text = strip_site_links(
+                "![](http://example.com/content/images/a.jpg) [home](https://example.com) \
+                 [away](https://example.org/content/images/b.jpg)",
+                &site_link_regex(Some("https://example.com/")),
+                Some("https://example.com/"),
+            );
+            replace_links(
+                &text,
+                "![](/blog/a.jpg) [home](/) [away](https://example.org/content/images/b.jpg)",
+            );
+            assert_eq!(
+                strip_site_url("https://example.com", Some("https://example.com")),
+                "/"
+            );
+            assert_eq!(
+                strip_site_url("https://example.com.au/a", Some("https://example.com")),
+                "https://example.com.au/a"
+            );
+        }
+
+        #[test]
+        fn test_should_strip_base_url_only_from_links() {
+            let rewriter = LinkRewriter::new(vec![ImageSource::default()])
+                .site_url(Some("https://example.com".into()));
+            let content = "See [hello](https://example.com/hello/) and <a href=\"https://example.com/\">home</a>.\n\n\
+                <img srcset=\"https://example.com/content/images/a.jpg 600w, https://example.com/content/images/b.jpg 1000w\">\n\n\
+                ```sh\ncurl https://example.com/api\n```\n\nOr inline: `https://example.com/api`.";
+            assert_eq!(
+                rewriter.content(content),
+                "See [hello](/hello/) and <a href=\"/\">home</a>.\n\n\
+                <img srcset=\"/blog/a.jpg 600w, /blog/b.jpg 1000w\">\n\n\
+                ```sh\ncurl https://example.com/api\n```\n\nOr inline: `https://example.com/api`."
+            );
+        }
+
+        #[test]
+        fn test_should_strip_ghost_url_only_from_links() {
+            let rewriter = LinkRewriter::new(vec![ImageSource::default()]);
+            let content =
+                "[home](__GHOST_URL__/) <img src=\"__GHOST_URL__/content/images/a.jpg\">\n\n\
+                Themes link to `__GHOST_URL__/about/`, which Ghost fills in.";
+            assert_eq!(
+                rewriter.content(content),
+                "[home](/) <img src=\"/blog/a.jpg\">\n\n\
+                Themes link to `__GHOST_URL__/about/`, which Ghost fills in."
+            );
+        }
+
+        #[test]
+        fn test_should_replace_configured_source() {
+            let sources = [
+                ImageSource::default(),
+                "/assets/images=themes/casper/assets/images"
+                    .parse()
+                    .unwrap(),
+            ];
+            let link_re = internal_link_regex(&sources);
+            assert_eq!(
+                relative_internal_links(
+                    "![](/assets/images/logo.png) ![](/content/images/2020/01/a.png)",
+                    &link_re,
+                    "/blog",
+                    false
+                ),
+                "![](/blog/logo.png) ![](/blog/2020/01/a.png)",
+            );
+        }
+
+        #[test]
+        fn rewrites_image_urls() {
+            let sources = [ImageSource::default()];
+            assert_eq!(
+                relative_image_url("/content/images/2020/01/hero.png", &sources, "/blog", false),
+                "/blog/2020/01/hero.png"
+            );
+            assert_eq!(
+                relative_image_url("https://unsplash.com/photo.jpg", &sources, "/blog", false),
+                "https://unsplash.com/photo.jpg"
+            );
+            assert_eq!(
+                relative_image_url("/content/imagesque/hero.png", &sources, "/blog", false),
+                "/content/imagesque/hero.png"
+            );
         }
 
         #[test]
@@ -400,67 +2501,178 @@ mod tests {
     }
 
     #[test]
-    fn strip_quotes_from_date() {
-        let input = r#"
-title = "Fancy Example Post"
-slug = "fancy-example-post"
-date = "2020-10-23T20:13:54.069963100Z"
-
-[extra]
-id = 123
-language = "en_EN"
-author_name = "me"
-
-[taxonomies]
-tags = ["tag1", "another"]
-"#;
-        let expect = r#"
-title = "Fancy Example Post"
-slug = "fancy-example-post"
-date = 2020-10-23T20:13:54.069963100Z
-
-[extra]
-id = 123
-language = "en_EN"
-author_name = "me"
-
-[taxonomies]
-tags = ["tag1", "another"]
-"#;
-        assert_eq!(strip_datetime_quotes(input), expect);
+    fn bundles_images() {
+        let mut post = Post {
+            content: "![a](/blog/2020/01/a.png?w=600) [other](/blog/2020/01/02/other.md)\n\n<img src=\"/blog/2020/01/b.jpg\"> ![c](/elsewhere/c.png)".into(),
+            extra: Extra {
+                feature_image: Some("/blog/2020/01/hero.png".into()),
+                ..Extra::default()
+            },
+            ..Post::default()
+        };
+        let images = post.bundle_images("/blog", "/blog/2020/01/02/hello");
+        assert_eq!(
+            post.content,
+            "![a](a.png?w=600) [other](/blog/2020/01/02/other.md)\n\n<img src=\"b.jpg\"> ![c](/elsewhere/c.png)"
+        );
+        assert_eq!(
+            post.extra.feature_image.as_deref(),
+            Some("/blog/2020/01/02/hello/hero.png")
+        );
+        let images: Vec<_> = images.iter().map(|(image, _)| image.as_str()).collect();
+        assert_eq!(
+            images,
+            vec!["2020/01/a.png", "2020/01/b.jpg", "2020/01/hero.png"]
+        );
+    }
+
+    #[test]
+    fn bundles_images_with_the_same_name() {
+        let mut post = Post {
+            content: "![a](/blog/2020/01/photo.jpg) ![b](/blog/2020/02/photo.jpg) ![c](/blog/2020/01/photo.jpg)".into(),
+            ..Post::default()
+        };
+        let images = post.bundle_images("/blog", "/blog/2020/02/03/hello");
+        assert_eq!(
+            post.content,
+            "![a](photo.jpg) ![b](photo-2.jpg) ![c](photo.jpg)"
+        );
+        assert_eq!(
+            images,
+            vec![
+                ("2020/01/photo.jpg".to_string(), "photo.jpg".to_string()),
+                ("2020/02/photo.jpg".to_string(), "photo-2.jpg".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn renames_images() {
+        let mut post = Post {
+            content: "![a](/blog/2020/01/a.png?w=600) ![b](/blog/2020/01/b.gif)".into(),
+            extra: Extra {
+                feature_image: Some("/blog/2020/01/a.png".into()),
+                ..Extra::default()
+            },
+            ..Post::default()
+        };
+        let renames = HashMap::from([("2020/01/a.png".to_string(), "2020/01/a.webp".to_string())]);
+        post.rename_images("/blog", &renames);
+        assert_eq!(
+            post.content,
+            "![a](/blog/2020/01/a.webp?w=600) ![b](/blog/2020/01/b.gif)"
+        );
+        assert_eq!(
+            post.extra.feature_image.as_deref(),
+            Some("/blog/2020/01/a.webp")
+        );
+    }
+
+    #[test]
+    fn finds_images_without_alt_text() {
+        let post = Post {
+            content: "![](/blog/a.png \"A\") ![a cat](/blog/b.png) ![ ](<c d.png>)\n\
+                      <img src=\"/blog/e.png\" alt=\"\"> <img alt='a dog' src='/blog/f.png'>\n\
+                      <IMG SRC=\"/blog/g.png\"> <img src=\"/blog/h.png\" alt=hedgehog>"
+                .into(),
+            ..Post::default()
+        };
+        assert_eq!(
+            post.images_without_alt(),
+            vec!["/blog/a.png", "c d.png", "/blog/e.png", "/blog/g.png"]
+        );
+    }
+
+    #[test]
+    fn inserts_summary_separator() {
+        let content = "First paragraph.\n\nThe excerpt is here,\nand continues.\n\nThe rest.";
+        let separator = "<!-- more -->";
+        assert_eq!(
+            insert_summary_separator(content, "The excerpt is here,", separator),
+            "First paragraph.\n\nThe excerpt is here,\nand continues.\n\n<!-- more -->\n\nThe rest."
+        );
+        assert_eq!(
+            insert_summary_separator(content, "Something else entirely.", separator),
+            "First paragraph.\n\n<!-- more -->\n\nThe excerpt is here,\nand continues.\n\nThe rest."
+        );
+        // nothing follows the summary
+        assert_eq!(
+            insert_summary_separator(content, "The rest.", separator),
+            content
+        );
+        let separated = insert_summary_separator(content, "", separator);
+        assert_eq!(
+            insert_summary_separator(&separated, "The rest.", separator),
+            separated
+        );
     }
 
     #[test]
-    fn strip_quotes_from_update() {
-        let input = r#"
-title = "Fancy Example Post"
-slug = "fancy-example-post"
-date = "2020-10-23T20:13:54.069963100Z"
-updated = "2020-10-23T20:13:54.069963101Z"
-
-[extra]
-id = 123
-language = "en_EN"
-author_name = "me"
-
-[taxonomies]
-tags = ["tag1", "another"]
-"#;
-        let expect = r#"
-title = "Fancy Example Post"
-slug = "fancy-example-post"
-date = 2020-10-23T20:13:54.069963100Z
-updated = 2020-10-23T20:13:54.069963101Z
-
-[extra]
-id = 123
-language = "en_EN"
-author_name = "me"
-
-[taxonomies]
-tags = ["tag1", "another"]
-"#;
-        assert_eq!(strip_datetime_quotes(input), expect);
+    fn renders_template() {
+        let post = Post {
+            title: "Fancy \"Example\" Post".into(),
+            date: Some("2020-10-23T20:13:54Z".parse().unwrap()),
+            status: Status::Published,
+            content: "Hi".into(),
+            extra: Extra {
+                author_name: "me".into(),
+                ..Extra::default()
+            },
+            ..Post::default()
+        };
+        let template = FrontmatterTemplate::new(
+            "title = {{ title | json_encode() }}\ndate = {{ date }}\ndraft = {{ draft | default(value=false) }}\n\n[extra]\nauthor = \"{{ extra.author_name }}\"\n",
+        )
+        .unwrap();
+        let mut rendered = Vec::new();
+        post.render_template_to(&mut rendered, &template, FrontmatterFormat::Toml)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(rendered).unwrap(),
+            "+++\ntitle = \"Fancy \\\"Example\\\" Post\"\ndate = 2020-10-23T20:13:54Z\ndraft = false\n\n[extra]\nauthor = \"me\"\n+++\n\nHi\n"
+        );
+    }
+
+    #[test]
+    fn renders_native_datetimes() {
+        let post = Post {
+            title: "Fancy Example Post".into(),
+            description: "Dated\ndate = \"2020-10-23T20:13:54Z\"".into(),
+            date: Some("2020-10-23T20:13:54.069963Z".parse().unwrap()),
+            updated: Some("2020-10-23T20:13:54.069963101Z".parse().unwrap()),
+            extra: Extra {
+                id: 123.into(),
+                ..Extra::default()
+            },
+            ..Post::default()
+        };
+        let rendered = post.to_string();
+        assert!(rendered.contains("\ndate = 2020-10-23T20:13:54.069963Z\n"));
+        assert!(rendered.contains("\nupdated = 2020-10-23T20:13:54.069963101Z\n"));
+        // a string which happens to look like a datetime field is left as it is
+        assert!(rendered.contains("\ndate = \\\"2020-10-23T20:13:54Z\\\""));
+    }
+
+    #[test]
+    fn renders_renamed_datetimes() {
+        let mut keys = KeyMapping::default();
+        keys.set("date", Some("extra.published".into()));
+        keys.set("updated", Some("modified".into()));
+        let post = Post {
+            title: "Fancy Example Post".into(),
+            date: Some("2020-10-23T20:13:54Z".parse().unwrap()),
+            updated: Some("2020-10-24T20:13:54Z".parse().unwrap()),
+            keys,
+            extra: Extra {
+                id: 123.into(),
+                ..Extra::default()
+            },
+            ..Post::default()
+        };
+        let rendered = post.to_string();
+        assert!(rendered.contains("\nmodified = 2020-10-24T20:13:54Z\n"));
+        assert!(rendered.contains("\npublished = 2020-10-23T20:13:54Z\n"));
+        assert!(!rendered.contains("\"2020-10-2"));
     }
 
     #[test]
@@ -499,4 +2711,91 @@ gravida tempor magna. Praesent pretium[^6] bibendum ante, et varius orci ferment
 
         assert_eq!(reify_footnotes(input), expect);
     }
+
+    #[test]
+    fn sanitizes_file_names() {
+        let windows = PathSanitization::Windows;
+        assert_eq!(sanitize_file_name("what-now?", windows), "what-now-");
+        assert_eq!(sanitize_file_name("part: one", windows), "part- one");
+        assert_eq!(sanitize_file_name("etc...", windows), "etc");
+        assert_eq!(sanitize_file_name("con", windows), "con_");
+        assert_eq!(sanitize_file_name("AUX.md", windows), "AUX_.md");
+        assert_eq!(sanitize_file_name("console", windows), "console");
+        assert_eq!(sanitize_file_name("...", windows), "_");
+        assert_eq!(
+            sanitize_file_name("what-now?", PathSanitization::None),
+            "what-now?"
+        );
+    }
+
+    #[test]
+    fn slugifies_titles() {
+        let post = Post {
+            title: "Café au lait, s'il vous plaît!".into(),
+            ..Post::default()
+        };
+        assert_eq!(post.slug(), "cafe-au-lait-s-il-vous-plait");
+        let options = SlugOptions {
+            unicode: true,
+            separator: '_',
+            max_length: 12,
+        };
+        assert_eq!(post.slug_with(&options), "café_au_lait");
+        let options = SlugOptions {
+            separator: '_',
+            max_length: 13,
+            ..SlugOptions::default()
+        };
+        assert_eq!(post.slug_with(&options), "cafe_au_lait");
+    }
+
+    #[test]
+    fn paths_follow_slug_options() {
+        let mut post = Post {
+            title: "Hello World".into(),
+            date: Some("2020-01-02T03:04:05Z".parse().unwrap()),
+            ..Post::default()
+        };
+        let options = SlugOptions {
+            separator: '_',
+            ..SlugOptions::default()
+        };
+        assert_eq!(
+            post.relative_path(&options, PathSanitization::None),
+            PathBuf::from("2020/01/02/hello_world.md")
+        );
+        assert_eq!(
+            post.ghost_path("/{slug}/", &options).as_deref(),
+            Some("/hello_world/")
+        );
+
+        // ghost's own slugs are kept verbatim
+        post.slug = "hello-world".into();
+        assert_eq!(
+            post.file_stem(&options, PathSanitization::None),
+            "hello-world"
+        );
+    }
+
+    #[test]
+    fn falls_back_to_stable_slugs() {
+        let post = Post {
+            title: "!!!".into(),
+            extra: Extra {
+                id: 4.into(),
+                ..Extra::default()
+            },
+            ..Post::default()
+        };
+        assert_eq!(post.slug(), "post-4");
+        assert_eq!(post.slug(), post.slug());
+        let post = Post {
+            extra: Extra {
+                uuid: Some("0c5b4b5c-6b1e-4b8e-9f3a-2f2c0e9f0a11".into()),
+                ..post.extra
+            },
+            ..post
+        };
+        assert_eq!(post.slug(), "0c5b4b5c-6b1e-4b8e-9f3a-2f2c0e9f0a11");
+    }
 }
@@ -0,0 +1,539 @@
+//! Comparisons between two extractions of a blog.
+//!
+//! A blog is often migrated more than once, from successively newer backups, while the new site is
+//! prepared. Comparing each extraction with the last shows what changed on Ghost in the meantime,
+//! and what a change of options did to the output. That's only meaningful if extracting the same
+//! archive twice writes the same files, which can be checked too. An extraction can also be
+//! updated in place, rewriting only the posts which changed since it was made.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::{
+    data_model::{Post, PostId},
+    extract::extract_archive,
+    verify::{file_id, id_key, lookup, markdown_files, parse_frontmatter, updated_key},
+    Error, ExtractOptions,
+};
+
+/// How a file differs between two extractions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Difference {
+    /// the file is only in the new extraction
+    Added { path: PathBuf },
+    /// the file is only in the old extraction
+    Removed { path: PathBuf },
+    /// the file is in both, but moved or changed
+    Changed {
+        old: PathBuf,
+        new: PathBuf,
+        /// the dotted frontmatter keys whose values differ, and `content` if the content does
+        fields: Vec<String>,
+    },
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difference::Added { path } => write!(f, "added {}", path.display()),
+            Difference::Removed { path } => write!(f, "removed {}", path.display()),
+            Difference::Changed { old, new, fields } if fields.is_empty() => {
+                write!(f, "moved {} to {}", old.display(), new.display())
+            }
+            Difference::Changed { old, new, fields } if old != new => write!(
+                f,
+                "changed {}, now {}: {}",
+                old.display(),
+                new.display(),
+                fields.join(", ")
+            ),
+            Difference::Changed { new, fields, .. } => {
+                write!(f, "changed {}: {}", new.display(), fields.join(", "))
+            }
+        }
+    }
+}
+
+/// How a file is matched with its counterpart in the other extraction.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum FileKey {
+    /// by the id of its post, so that a post whose path changed is matched all the same
+    Post(PostId),
+    /// by its path, for indices and anything else without an id
+    Path(PathBuf),
+}
+
+/// A markdown file of an extraction, split into its frontmatter and content.
+#[derive(Debug)]
+struct File {
+    path: PathBuf,
+    frontmatter: BTreeMap<String, toml::Value>,
+    content: String,
+}
+
+/// compare the markdown files beneath two extract paths, listing those added, removed, or changed
+///
+/// Posts are matched by the id in their frontmatter, as [`verify_extraction`](crate::verify_extraction)
+/// matches them, so that a post whose slug or date changed is reported as moved; other files are
+/// matched by their path. The differences are ordered by path.
+pub fn diff_extractions(
+    old: &Path,
+    new: &Path,
+    options: &ExtractOptions,
+) -> Result<Vec<Difference>, Error> {
+    let id_key = id_key(options);
+    let old_files = read_files(old, id_key.as_deref())?;
+    let mut new_files: HashMap<_, _> = read_files(new, id_key.as_deref())?.into_iter().collect();
+
+    let mut differences = Vec::new();
+    for (key, old) in old_files {
+        let new = match new_files.remove(&key) {
+            Some(new) => new,
+            None => {
+                differences.push(Difference::Removed { path: old.path });
+                continue;
+            }
+        };
+        let mut fields: Vec<_> = old
+            .frontmatter
+            .iter()
+            .filter(|(key, value)| new.frontmatter.get(*key) != Some(value))
+            .map(|(key, _)| key.clone())
+            .chain(
+                new.frontmatter
+                    .keys()
+                    .filter(|key| !old.frontmatter.contains_key(*key))
+                    .cloned(),
+            )
+            .collect();
+        fields.sort();
+        if old.content != new.content {
+            fields.push("content".into());
+        }
+        if old.path != new.path || !fields.is_empty() {
+            differences.push(Difference::Changed {
+                old: old.path,
+                new: new.path,
+                fields,
+            });
+        }
+    }
+    differences.extend(
+        new_files
+            .into_values()
+            .map(|new| Difference::Added { path: new.path }),
+    );
+    differences.sort_by(|a, b| path_of(a).cmp(path_of(b)));
+    Ok(differences)
+}
+
+/// compare the markdown files beneath an extract path with a fresh extraction from an archive
+///
+/// The archive is extracted with the options into a temporary directory, which is removed
+/// afterwards, so that nothing beneath the extract path changes. Images aren't extracted, nor
+/// are a manifest or report written, and strict mode is ignored.
+pub fn diff_archive<AP>(
+    old: &Path,
+    archive_path: AP,
+    options: &ExtractOptions,
+) -> Result<Vec<Difference>, Error>
+where
+    AP: AsRef<Path>,
+{
+    let dir = tempfile::tempdir()?;
+    // mirror a zola site, so that directories relative to the extract path stay within it
+    let new = dir.path().join("content").join("blog");
+    std::fs::create_dir_all(&new)?;
+    let options = ExtractOptions {
+        extract_images: false,
+        download_external_images: false,
+        manifest: None,
+        report: None,
+        strict: false,
+        validate: false,
+        ..options.clone()
+    };
+    extract_archive(archive_path, &new, &options)?;
+    diff_extractions(old, &new, &options)
+}
+
+/// check that extracting an archive twice writes byte-identical files
+///
+/// The archive is extracted with the options into each of two temporary directories, which are
+/// removed afterwards, and every file written, including the images, manifest, and report, is
+/// compared. Each file which differs, or was only written once, is logged, and
+/// [`Error::Nondeterministic`] returned. Neither the progress observer nor the progress callback
+/// is notified, and strict mode is ignored. As [`ExtractOptions::sample`] chooses different posts
+/// each time, it always fails the check.
+pub fn check_idempotence<AP>(archive_path: AP, options: &ExtractOptions) -> Result<(), Error>
+where
+    AP: AsRef<Path>,
+{
+    let archive_path = archive_path.as_ref();
+    let mut roots = Vec::new();
+    for _ in 0..2 {
+        let root = tempfile::tempdir()?;
+        // mirror a zola site, so that directories relative to the extract path stay within it
+        let extract_path = root.path().join("content").join("blog");
+        std::fs::create_dir_all(&extract_path)?;
+        let options = ExtractOptions {
+            manifest: options
+                .manifest
+                .as_ref()
+                .map(|_| root.path().join("manifest")),
+            report_path: options.report.map(|_| root.path().join("report")),
+            strict: false,
+            progress: None,
+            on_progress: None,
+            ..options.clone()
+        };
+        extract_archive(archive_path, &extract_path, &options)?;
+        roots.push(root);
+    }
+
+    let (first, second) = (roots[0].path(), roots[1].path());
+    let mut second_files: HashSet<_> = all_files(second)?.into_iter().collect();
+    let mut differing = Vec::new();
+    for path in all_files(first)? {
+        if !second_files.remove(&path)
+            || std::fs::read(first.join(&path))? != std::fs::read(second.join(&path))?
+        {
+            differing.push(path);
+        }
+    }
+    differing.extend(second_files);
+    differing.sort();
+    if differing.is_empty() {
+        log::info!("both extractions wrote identical files");
+        return Ok(());
+    }
+    log::error!(
+        "{} files differed between two extractions of the same archive:",
+        differing.len()
+    );
+    for path in &differing {
+        log::error!("  {}", path.display());
+    }
+    Err(Error::Nondeterministic(differing.len()))
+}
+
+/// every file beneath a directory, relative to it
+fn all_files(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(subdir) = dirs.pop() {
+        for entry in std::fs::read_dir(&subdir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                dirs.push(entry.path());
+            } else {
+                files.push(entry.path().strip_prefix(dir)?.to_path_buf());
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// The posts already extracted beneath the extract path, when updating it incrementally.
+#[derive(Debug, Default)]
+pub(crate) struct PreviousExtraction {
+    /// each post's file, relative to the extract path, and when the post had last been updated
+    posts: HashMap<PostId, (PathBuf, Option<DateTime<FixedOffset>>)>,
+    /// the files of the posts which are written elsewhere this time
+    moved: Vec<PathBuf>,
+    /// the posts which are new or changed, by the path to which each is written
+    pub(crate) updated: Vec<PathBuf>,
+    /// the number of posts left alone, as they hadn't changed
+    pub(crate) unchanged: usize,
+}
+
+impl PreviousExtraction {
+    /// find the id, and the time of the last update, of each post beneath the extract path
+    pub(crate) fn scan(extract_path: &Path, options: &ExtractOptions) -> Result<Self, Error> {
+        let mut previous = PreviousExtraction::default();
+        let id_key = match id_key(options) {
+            Some(id_key) => id_key,
+            None => return Ok(previous),
+        };
+        let updated_key = updated_key(options);
+        for path in markdown_files(extract_path)? {
+            let document = std::fs::read_to_string(extract_path.join(&path))?;
+            let frontmatter = match parse_frontmatter(&document) {
+                Ok(Some(frontmatter)) => frontmatter,
+                _ => continue,
+            };
+            let id = match file_id(&frontmatter, &id_key) {
+                Some(id) => id,
+                None => continue,
+            };
+            let updated = updated_key
+                .as_deref()
+                .and_then(|key| lookup(&frontmatter, key))
+                .and_then(|updated| match updated {
+                    toml::Value::Datetime(updated) => Some(updated.to_string()),
+                    toml::Value::String(updated) => Some(updated.clone()),
+                    _ => None,
+                })
+                .and_then(|updated| DateTime::parse_from_rfc3339(&updated).ok());
+            previous.posts.entry(id).or_insert((path, updated));
+        }
+        log::info!(
+            "found {} posts already extracted, to update",
+            previous.posts.len()
+        );
+        Ok(previous)
+    }
+
+    /// whether a post about to be written to `path` is new, or has changed since it was last
+    /// extracted
+    ///
+    /// A post is unchanged if it was extracted to the same path, and both it and its file have
+    /// the same time of their last update.
+    pub(crate) fn changed(&mut self, post: &Post, path: &Path) -> bool {
+        let previous = self.posts.remove(&post.extra.id);
+        let unchanged = match (&previous, post.updated) {
+            (Some((old, Some(then))), Some(now)) => old == path && *then == now,
+            _ => false,
+        };
+        match previous {
+            Some((old, _)) if old != path => self.moved.push(old),
+            _ => {}
+        }
+        if unchanged {
+            self.unchanged += 1;
+        } else {
+            self.updated.push(path.to_path_buf());
+        }
+        !unchanged
+    }
+
+    /// remove the old files of the posts which were written elsewhere, unless another post has
+    /// since been written in their place
+    pub(crate) fn remove_moved(
+        &self,
+        written: &[PathBuf],
+        extract_path: &Path,
+    ) -> Result<(), Error> {
+        for old in self.moved.iter().filter(|old| !written.contains(old)) {
+            let path = extract_path.join(old);
+            if path.is_file() {
+                std::fs::remove_file(&path)?;
+                log::debug!("removed the old file of a moved post: {}", old.display());
+            }
+        }
+        Ok(())
+    }
+
+    /// log the posts which were updated
+    pub(crate) fn log(&self) {
+        log::info!(
+            "updated {} posts, and left {} unchanged",
+            self.updated.len(),
+            self.unchanged
+        );
+        for path in &self.updated {
+            log::info!("  {}", path.display());
+        }
+    }
+}
+
+/// the path by which a difference is ordered
+fn path_of(difference: &Difference) -> &Path {
+    match difference {
+        Difference::Added { path } | Difference::Removed { path } => path,
+        Difference::Changed { old, .. } => old,
+    }
+}
+
+/// read the markdown files beneath a directory, keyed by the id of their post if they have one
+///
+/// Files whose frontmatter doesn't parse are compared as content alone.
+fn read_files(dir: &Path, id_key: Option<&str>) -> Result<Vec<(FileKey, File)>, Error> {
+    let mut files = Vec::new();
+    let mut ids = HashSet::new();
+    for path in markdown_files(dir)? {
+        let document = std::fs::read_to_string(dir.join(&path))?;
+        let table = parse_frontmatter(&document).ok().flatten();
+        let id = table
+            .as_ref()
+            .zip(id_key)
+            .and_then(|(table, id_key)| file_id(table, id_key))
+            // a duplicated id is matched by path instead
+            .filter(|id| ids.insert(id.clone()));
+        let mut frontmatter = BTreeMap::new();
+        if let Some(table) = table {
+            flatten("", table, &mut frontmatter);
+        }
+        let key = match id {
+            Some(id) => FileKey::Post(id),
+            None => FileKey::Path(path.clone()),
+        };
+        files.push((
+            key,
+            File {
+                path,
+                frontmatter,
+                content: content(&document).to_string(),
+            },
+        ));
+    }
+    Ok(files)
+}
+
+/// flatten a table into its values by dotted key, i.e. `extra.id`
+///
+/// Arrays, including arrays of tables, are values in their own right.
+fn flatten(prefix: &str, table: toml::value::Table, into: &mut BTreeMap<String, toml::Value>) {
+    for (key, value) in table {
+        let key = format!("{}{}", prefix, key);
+        match value {
+            toml::Value::Table(table) => flatten(&format!("{}.", key), table, into),
+            value => {
+                into.insert(key, value);
+            }
+        }
+    }
+}
+
+/// a document's content, after its frontmatter if it has any
+fn content(document: &str) -> &str {
+    let trimmed = document.trim_start_matches('\u{feff}');
+    let delimiter = match trimmed.get(..3) {
+        Some(delimiter @ "+++") | Some(delimiter @ "---") => delimiter,
+        _ => return document,
+    };
+    let mut offset = 0;
+    for (n, line) in trimmed.split_inclusive('\n').enumerate() {
+        offset += line.len();
+        if n > 0 && line.trim_end() == delimiter {
+            return &trimmed[offset..];
+        }
+    }
+    document
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract::tests::ghost_archive;
+    use crate::DraftPolicy;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn checks_idempotence() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = ghost_archive(dir.path());
+        let options = ExtractOptions {
+            drafts: DraftPolicy::Skip,
+            ..ExtractOptions::default()
+        };
+        check_idempotence(&archive_path, &options).unwrap();
+    }
+
+    #[test]
+    fn reports_nondeterminism() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = ghost_archive(dir.path());
+        let mut options = ExtractOptions {
+            drafts: DraftPolicy::Skip,
+            ..ExtractOptions::default()
+        };
+        // stamp each post with the time it was written, as it were
+        let clock = AtomicUsize::new(0);
+        options.transforms.push(move |post| {
+            let tick = clock.fetch_add(1, Ordering::Relaxed) as i64;
+            post.updated = DateTime::from_timestamp(1_600_000_000 + tick, 0);
+        });
+        assert!(matches!(
+            check_idempotence(&archive_path, &options),
+            Err(Error::Nondeterministic(2))
+        ));
+    }
+
+    #[test]
+    fn diffs_extractions() {
+        let dir = tempfile::tempdir().unwrap();
+        let (old, new) = (dir.path().join("old"), dir.path().join("new"));
+        for tree in &[&old, &new] {
+            std::fs::create_dir_all(tree.join("2020")).unwrap();
+            std::fs::write(tree.join("_index.md"), "+++\nsort_by = \"date\"\n+++\n").unwrap();
+        }
+        let post = |title: &str, id: u32, content: &str| {
+            format!(
+                "+++\ntitle = \"{}\"\n\n[extra]\nid = {}\n+++\n\n{}\n",
+                title, id, content
+            )
+        };
+        std::fs::write(old.join("2020/hello.md"), post("Hello", 1, "Hi")).unwrap();
+        std::fs::write(new.join("2020/hello.md"), post("Hello", 1, "Hi")).unwrap();
+        std::fs::write(old.join("2020/again.md"), post("Again", 2, "Hi")).unwrap();
+        std::fs::write(new.join("2020/again.md"), post("Edited", 2, "Hi!")).unwrap();
+        std::fs::write(old.join("2020/old-slug.md"), post("Moved", 3, "Hi")).unwrap();
+        std::fs::write(new.join("2020/new-slug.md"), post("Moved", 3, "Hi")).unwrap();
+        std::fs::write(old.join("2020/gone.md"), post("Gone", 4, "Hi")).unwrap();
+        std::fs::write(new.join("2020/fresh.md"), post("Fresh", 5, "Hi")).unwrap();
+
+        let differences = diff_extractions(&old, &new, &ExtractOptions::default()).unwrap();
+        let differences: Vec<_> = differences.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            differences,
+            vec![
+                "changed 2020/again.md: title, content",
+                "added 2020/fresh.md",
+                "removed 2020/gone.md",
+                "moved 2020/old-slug.md to 2020/new-slug.md",
+            ]
+        );
+    }
+
+    #[test]
+    fn updates_changed_posts() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("2020")).unwrap();
+        let post = |id: u32| {
+            format!(
+                "+++\ntitle = \"Hello\"\nupdated = 2020-01-03T10:00:00Z\n\n[extra]\nid = {}\n+++\n",
+                id
+            )
+        };
+        for (name, id) in &[("same", 1), ("edited", 2), ("moved", 3)] {
+            std::fs::write(dir.path().join(format!("2020/{}.md", name)), post(*id)).unwrap();
+        }
+        let mut previous =
+            PreviousExtraction::scan(dir.path(), &ExtractOptions::default()).unwrap();
+
+        let then = "2020-01-03T10:00:00Z".parse().unwrap();
+        let mut post = Post {
+            updated: Some(then),
+            ..Post::default()
+        };
+        post.extra.id = 1.into();
+        assert!(!previous.changed(&post, Path::new("2020/same.md")));
+        post.extra.id = 2.into();
+        post.updated = Some("2020-02-01T10:00:00Z".parse().unwrap());
+        assert!(previous.changed(&post, Path::new("2020/edited.md")));
+        post.extra.id = 3.into();
+        post.updated = Some(then);
+        assert!(previous.changed(&post, Path::new("2020/renamed.md")));
+        post.extra.id = 4.into();
+        assert!(previous.changed(&post, Path::new("2020/new.md")));
+        assert_eq!(previous.unchanged, 1);
+        assert_eq!(previous.updated.len(), 3);
+
+        previous
+            .remove_moved(&[PathBuf::from("2020/renamed.md")], dir.path())
+            .unwrap();
+        assert!(!dir.path().join("2020/moved.md").exists());
+        assert!(dir.path().join("2020/same.md").exists());
+    }
+
+    #[test]
+    fn splits_content_from_frontmatter() {
+        assert_eq!(content("+++\ntitle = \"a\"\n+++\n\nHi\n"), "\nHi\n");
+        assert_eq!(content("---\ntitle: a\n---\nHi"), "Hi");
+        assert_eq!(content("Hi\n"), "Hi\n");
+    }
+}
@@ -0,0 +1,367 @@
+//! Rendering of the documents of Ghost's editor into markdown.
+//!
+//! From Ghost 1.0, posts are no longer stored as markdown. Instead, the editor stores a mobiledoc
+//! document, and from Ghost 5.0, a lexical document: a tree of JSON nodes. Both describe the same
+//! kinds of content: paragraphs of marked-up text, headings, quotes, lists, and cards, which are
+//! rendered by [`render_card`].
+
+use serde_json::Value;
+
+use crate::cards::{escape, render_card, UnsupportedCard};
+use crate::Shortcodes;
+
+/// A reason for which an editor document couldn't be rendered.
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum EditorError {
+    #[error("malformed document")]
+    Malformed(#[from] serde_json::Error),
+    #[error(transparent)]
+    UnsupportedCard(#[from] UnsupportedCard),
+}
+
+/// the elements of a JSON array, or none
+fn elements(value: &Value) -> &[Value] {
+    value.as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+/// escape the start of each line of a paragraph which markdown would otherwise take for a
+/// heading, quote, or list
+fn escape_block_start(text: String) -> String {
+    text.split('\n')
+        .map(|line| {
+            if line.starts_with(&['#', '>', '-', '+'][..]) {
+                format!("\\{}", line)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// render a block of text with the given tag, i.e. `p` or `h2`
+fn text_block(tag: &str, text: String) -> String {
+    match tag.to_ascii_lowercase().as_str() {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = tag[1..].parse().unwrap_or(1);
+            format!("{} {}", "#".repeat(level), text)
+        }
+        "blockquote" | "aside" | "pull-quote" | "quote" => text
+            .lines()
+            .map(|line| format!("> {}", line).trim_end().to_string())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => escape_block_start(text),
+    }
+}
+
+/// the markdown which opens and closes a mobiledoc markup
+fn markup_delimiters(markup: &Value) -> (String, String) {
+    let tag = markup[0].as_str().unwrap_or_default().to_ascii_lowercase();
+    match tag.as_str() {
+        "b" | "strong" => ("**".into(), "**".into()),
+        "i" | "em" => ("*".into(), "*".into()),
+        "s" | "del" | "strike" => ("~~".into(), "~~".into()),
+        "code" => ("`".into(), "`".into()),
+        "a" => {
+            // attributes are a flat list of names and values
+            let href = elements(&markup[1])
+                .chunks(2)
+                .find(|pair| pair[0].as_str() == Some("href"))
+                .and_then(|pair| pair.get(1))
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            ("[".into(), format!("]({})", href))
+        }
+        "u" | "sup" | "sub" => (format!("<{}>", tag), format!("</{}>", tag)),
+        _ => (String::new(), String::new()),
+    }
+}
+
+/// render a mobiledoc document into markdown
+pub(crate) fn render_mobiledoc(
+    mobiledoc: &str,
+    shortcodes: &Shortcodes,
+) -> Result<String, EditorError> {
+    let doc: Value = serde_json::from_str(mobiledoc)?;
+    let markups = elements(&doc["markups"]);
+    let atoms = elements(&doc["atoms"]);
+    let cards = elements(&doc["cards"]);
+
+    // each marker is `[type, opened markups, number of closed markups, text or atom]`
+    let render_markers = |markers: &Value| {
+        let mut text = String::new();
+        let mut closers = Vec::new();
+        for marker in elements(markers) {
+            for markup in elements(&marker[1]) {
+                let markup = markup.as_u64().and_then(|idx| markups.get(idx as usize));
+                let (open, close) = markup.map(markup_delimiters).unwrap_or_default();
+                text.push_str(&open);
+                closers.push(close);
+            }
+            match marker[0].as_u64() {
+                Some(1) => {
+                    // each atom is `[name, text, payload]`
+                    let atom = marker[3]
+                        .as_u64()
+                        .and_then(|idx| atoms.get(idx as usize))
+                        .unwrap_or(&Value::Null);
+                    match atom[0].as_str() {
+                        Some("soft-return") => text.push_str("\\\n"),
+                        _ => text.push_str(&escape(atom[1].as_str().unwrap_or_default())),
+                    }
+                }
+                _ => text.push_str(&escape(marker[3].as_str().unwrap_or_default())),
+            }
+            for _ in 0..marker[2].as_u64().unwrap_or_default() {
+                text.push_str(&closers.pop().unwrap_or_default());
+            }
+        }
+        while let Some(close) = closers.pop() {
+            text.push_str(&close);
+        }
+        text
+    };
+
+    let mut blocks = Vec::new();
+    for section in elements(&doc["sections"]) {
+        let block = match section[0].as_u64() {
+            // markup section: `[1, tag, markers]`
+            Some(1) => {
+                let text = render_markers(&section[2]);
+                if text.trim().is_empty() {
+                    continue;
+                }
+                text_block(section[1].as_str().unwrap_or("p"), text)
+            }
+            // image section: `[2, src]`
+            Some(2) => format!("![]({})", section[1].as_str().unwrap_or_default()),
+            // list section: `[3, tag, items]`, where each item is a list of markers
+            Some(3) => {
+                let ordered = section[1].as_str() == Some("ol");
+                elements(&section[2])
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, item)| {
+                        let bullet = if ordered {
+                            format!("{}.", idx + 1)
+                        } else {
+                            "-".into()
+                        };
+                        format!("{} {}", bullet, render_markers(item))
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            // card section: `[10, card]`, where each card is `[name, payload]`
+            Some(10) => {
+                let card = section[1]
+                    .as_u64()
+                    .and_then(|idx| cards.get(idx as usize))
+                    .unwrap_or(&Value::Null);
+                render_card(card[0].as_str().unwrap_or_default(), &card[1], shortcodes)?
+            }
+            _ => continue,
+        };
+        if !block.is_empty() {
+            blocks.push(block);
+        }
+    }
+    Ok(blocks.join("\n\n"))
+}
+
+// the bits of a lexical text node's format
+const BOLD: u64 = 1;
+const ITALIC: u64 = 1 << 1;
+const STRIKETHROUGH: u64 = 1 << 2;
+const UNDERLINE: u64 = 1 << 3;
+const CODE: u64 = 1 << 4;
+const SUBSCRIPT: u64 = 1 << 5;
+const SUPERSCRIPT: u64 = 1 << 6;
+
+/// render the inline children of a lexical node
+fn lexical_inline(nodes: &Value) -> String {
+    let mut text = String::new();
+    for node in elements(nodes) {
+        match node["type"].as_str().unwrap_or_default() {
+            "text" | "extended-text" => {
+                let content = node["text"].as_str().unwrap_or_default();
+                let format = node["format"].as_u64().unwrap_or_default();
+                let mut content = if format & CODE != 0 {
+                    format!("`{}`", content)
+                } else {
+                    escape(content)
+                };
+                for (bit, open, close) in [
+                    (SUBSCRIPT, "<sub>", "</sub>"),
+                    (SUPERSCRIPT, "<sup>", "</sup>"),
+                    (UNDERLINE, "<u>", "</u>"),
+                    (STRIKETHROUGH, "~~", "~~"),
+                    (ITALIC, "*", "*"),
+                    (BOLD, "**", "**"),
+                ] {
+                    if format & bit != 0 {
+                        content = format!("{}{}{}", open, content, close);
+                    }
+                }
+                text.push_str(&content);
+            }
+            "link" | "autolink" => text.push_str(&format!(
+                "[{}]({})",
+                lexical_inline(&node["children"]),
+                node["url"].as_str().unwrap_or_default()
+            )),
+            "linebreak" => text.push_str("\\\n"),
+            "tab" => text.push('\t'),
+            _ => text.push_str(&lexical_inline(&node["children"])),
+        }
+    }
+    text
+}
+
+/// render a lexical list, indenting any nested lists
+fn lexical_list(node: &Value, depth: usize) -> String {
+    let ordered = node["listType"].as_str() == Some("number");
+    let indent = "    ".repeat(depth);
+    let mut lines = Vec::new();
+    let mut number = node["start"].as_u64().unwrap_or(1);
+    for item in elements(&node["children"]) {
+        // a nested list is an item containing only that list
+        let (nested, inline): (Vec<&Value>, Vec<&Value>) = elements(&item["children"])
+            .iter()
+            .partition(|child| child["type"].as_str() == Some("list"));
+        for list in &nested {
+            lines.push(lexical_list(list, depth + 1));
+        }
+        if nested.is_empty() || !inline.is_empty() {
+            let inline = Value::Array(inline.into_iter().cloned().collect());
+            let bullet = if ordered {
+                let bullet = format!("{}.", number);
+                number += 1;
+                bullet
+            } else {
+                "-".into()
+            };
+            lines.push(format!("{}{} {}", indent, bullet, lexical_inline(&inline)));
+        }
+    }
+    lines.join("\n")
+}
+
+/// render a lexical document into markdown
+pub(crate) fn render_lexical(
+    lexical: &str,
+    shortcodes: &Shortcodes,
+) -> Result<String, EditorError> {
+    let doc: Value = serde_json::from_str(lexical)?;
+    let mut blocks = Vec::new();
+    for node in elements(&doc["root"]["children"]) {
+        let kind = node["type"].as_str().unwrap_or_default();
+        let block = match kind {
+            "paragraph" => {
+                let text = lexical_inline(&node["children"]);
+                if text.trim().is_empty() {
+                    continue;
+                }
+                text_block("p", text)
+            }
+            "heading" | "extended-heading" => text_block(
+                node["tag"].as_str().unwrap_or("h1"),
+                lexical_inline(&node["children"]),
+            ),
+            "quote" | "extended-quote" | "aside" => {
+                text_block("blockquote", lexical_inline(&node["children"]))
+            }
+            "list" => lexical_list(node, 0),
+            // everything else is a card, whose payload is the node itself
+            _ => render_card(kind, node, shortcodes)?,
+        };
+        if !block.is_empty() {
+            blocks.push(block);
+        }
+    }
+    Ok(blocks.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_mobiledoc() {
+        let mobiledoc = r##"{
+            "version": "0.3.1",
+            "atoms": [["soft-return", "", {}]],
+            "cards": [
+                ["embed", {"url": "https://youtu.be/abc", "metadata": {"provider_name": "YouTube"}}],
+                ["hr", {}]
+            ],
+            "markups": [["strong"], ["a", ["href", "/hello/"]]],
+            "sections": [
+                [1, "h2", [[0, [], 0, "Hello"]]],
+                [1, "p", [[0, [0], 1, "bold"], [0, [], 0, " and "], [0, [1], 1, "a_link"], [1, [], 0, 0], [0, [], 0, "# more"]]],
+                [1, "p", []],
+                [10, 0],
+                [3, "ol", [[[0, [], 0, "one"]], [[0, [], 0, "two"]]]],
+                [1, "blockquote", [[0, [], 0, "quoted"]]],
+                [10, 1]
+            ]
+        }"##;
+        assert_eq!(
+            render_mobiledoc(mobiledoc, &Shortcodes::default()).unwrap(),
+            "## Hello\n\n**bold** and [a\\_link](/hello/)\\\n\\# more\n\n{{ youtube(id=\"abc\") }}\n\n1. one\n2. two\n\n> quoted\n\n---",
+        );
+    }
+
+    #[test]
+    fn renders_lexical() {
+        let lexical = r#"{"root": {"children": [
+            {"type": "heading", "tag": "h3", "children": [{"type": "text", "text": "Hi", "format": 0}]},
+            {"type": "paragraph", "children": [
+                {"type": "text", "text": "it", "format": 3},
+                {"type": "text", "text": " is ", "format": 0},
+                {"type": "link", "url": "https://example.com", "children": [{"type": "text", "text": "code", "format": 16}]}
+            ]},
+            {"type": "list", "listType": "bullet", "children": [
+                {"type": "listitem", "children": [{"type": "text", "text": "a", "format": 0}]},
+                {"type": "listitem", "children": [{"type": "list", "listType": "number", "children": [
+                    {"type": "listitem", "children": [{"type": "text", "text": "b", "format": 0}]}
+                ]}]}
+            ]},
+            {"type": "image", "src": "/content/images/a.png", "alt": "a", "caption": "An <b>a</b>"}
+        ]}}"#;
+        assert_eq!(
+            render_lexical(lexical, &Shortcodes::default()).unwrap(),
+            "### Hi\n\n***it*** is [`code`](https://example.com)\n\n- a\n    1. b\n\n![a](/content/images/a.png)\n*An **a***",
+        );
+    }
+
+    #[test]
+    fn inlines_markdown_and_html_cards() {
+        let mobiledoc = r#"{
+            "cards": [
+                ["card-markdown", {"markdown": "Some *markdown*[^1]\n\n[^1]: a note\n"}],
+                ["html", {"html": "<div class=\"kg-card kg-callout-card\">\n<p>hi</p>\n</div>"}],
+                ["markdown", {"markdown": ""}]
+            ],
+            "sections": [[10, 0], [1, "p", [[0, [], 0, "between"]]], [10, 1], [10, 2]]
+        }"#;
+        assert_eq!(
+            render_mobiledoc(mobiledoc, &Shortcodes::default()).unwrap(),
+            "Some *markdown*[^1]\n\n[^1]: a note\n\nbetween\n\n<div class=\"kg-card kg-callout-card\">\n<p>hi</p>\n</div>",
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_cards() {
+        let mobiledoc = r#"{"cards": [["product", {}]], "sections": [[10, 0]]}"#;
+        assert!(matches!(
+            render_mobiledoc(mobiledoc, &Shortcodes::default()),
+            Err(EditorError::UnsupportedCard(_))
+        ));
+        assert!(matches!(
+            render_lexical("not json", &Shortcodes::default()),
+            Err(EditorError::Malformed(_))
+        ));
+    }
+}
@@ -0,0 +1,327 @@
+//! Downloads of the images which posts link to, but which the blog didn't host.
+//!
+//! Ghost's CDN, Unsplash, and other sites serve images by absolute url. None of them are in a
+//! backup archive, so a migrated site keeps linking to them, until they move or vanish.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    data_model::Post, extract::claim_output, ConflictPolicy, Error, ExtractOptions, UnsplashPolicy,
+};
+
+lazy_static! {
+    // the target of a markdown image, i.e. `![alt](https://example.com/a.png "title")`
+    static ref MARKDOWN_IMAGE: Regex = Regex::new(r"(!\[[^\]]*\]\()(https?://[^)\s]+)").unwrap();
+    // the `src` of an html image, i.e. `<img src="https://example.com/a.png">`
+    static ref HTML_IMAGE: Regex =
+        Regex::new(r#"(?i)(<img\b[^>]*\bsrc\s*=\s*["'])(https?://[^"'\s]+)"#).unwrap();
+    // a markdown image hotlinked from unsplash, followed by its caption
+    static ref UNSPLASH_IMAGE: Regex = Regex::new(
+        r"!\[([^\]]*)\]\((https://images\.unsplash\.com/[^)\s]*)\)(\n[^\n]*)?"
+    )
+    .unwrap();
+    // unsplash's attribution of a photo, i.e. `Photo by [Ann](...) / [Unsplash](...)`
+    static ref UNSPLASH_ATTRIBUTION: Regex = Regex::new(
+        r"Photo by \[?([^\]\n/]+?)\]?(?:\([^)\s]*\))?\s*(?:on|/)\s*\[?Unsplash"
+    )
+    .unwrap();
+}
+
+/// The host from which Ghost's Unsplash integration hotlinks images.
+const UNSPLASH_URL: &str = "https://images.unsplash.com/";
+
+/// The query with which unsplash images are normalized: Ghost's default, 2000 pixels wide.
+const UNSPLASH_QUERY: &str = "w=2000&q=80&fm=jpg&fit=max";
+
+/// The directory, relative to the images directory, into which external images are downloaded.
+const EXTERNAL_DIR: &str = "external";
+
+/// Images hosted elsewhere, downloaded into the images directory.
+#[derive(Debug)]
+pub(crate) struct ExternalImages {
+    agent: ureq::Agent,
+    /// where the images are downloaded to
+    images_path: PathBuf,
+    /// the url from which the downloaded images are served
+    images_url: String,
+    /// whether to download every external image, or only those from unsplash
+    all: bool,
+    /// what to do with images which were already downloaded
+    on_conflict: ConflictPolicy,
+    /// the local url of each image requested so far, or `None` if it couldn't be downloaded
+    localized: HashMap<String, Option<String>>,
+}
+
+impl ExternalImages {
+    pub(crate) fn new(images_path: PathBuf, images_url: String) -> Self {
+        ExternalImages {
+            agent: ureq::Agent::new(),
+            images_path,
+            images_url,
+            all: true,
+            on_conflict: ConflictPolicy::default(),
+            localized: HashMap::new(),
+        }
+    }
+
+    /// the downloads which the options call for, if any
+    pub(crate) fn from_options(extract_path: &Path, options: &ExtractOptions) -> Option<Self> {
+        let unsplash = options.unsplash == UnsplashPolicy::Download;
+        if !options.extract_images || !(options.download_external_images || unsplash) {
+            return None;
+        }
+        Some(ExternalImages {
+            all: options.download_external_images,
+            on_conflict: options.conflict_policy(),
+            ..ExternalImages::new(
+                extract_path.join(options.extracted_images_dir()),
+                options.images_url(),
+            )
+        })
+    }
+
+    /// the number of images downloaded
+    pub(crate) fn len(&self) -> usize {
+        self.localized.values().filter(|url| url.is_some()).count()
+    }
+
+    /// download the external images which a post links to, and point its links at them
+    ///
+    /// Images which can't be downloaded are logged, and their links left alone.
+    pub(crate) fn localize(&mut self, post: &mut Post) -> Result<(), Error> {
+        map_external_images(post, |url| {
+            if !self.all && !url.starts_with(UNSPLASH_URL) {
+                return Ok(None);
+            }
+            if let Some(local) = self.localized.get(url) {
+                return Ok(local.clone());
+            }
+            let local = self.download(url)?;
+            self.localized.insert(url.to_string(), local.clone());
+            Ok(local)
+        })
+    }
+
+    /// download an image, returning the url from which it's served once extracted
+    fn download(&self, url: &str) -> Result<Option<String>, Error> {
+        let response = match self.agent.get(url).call() {
+            Ok(response) => response,
+            Err(err) => {
+                log::warn!("failed to download {}: {}", url, err);
+                return Ok(None);
+            }
+        };
+        let extension = match image_extension(url, response.content_type()) {
+            Some(extension) => extension,
+            None => {
+                log::warn!(
+                    "not downloading {}, served as {}",
+                    url,
+                    response.content_type()
+                );
+                return Ok(None);
+            }
+        };
+        let name = format!("{}.{}", url_hash(url), extension);
+        let extract_to = self.images_path.join(EXTERNAL_DIR).join(&name);
+        if claim_output(&extract_to, self.on_conflict)? {
+            std::fs::create_dir_all(self.images_path.join(EXTERNAL_DIR))?;
+            let mut file = std::fs::File::create(&extract_to)?;
+            std::io::copy(&mut response.into_reader(), &mut file)?;
+            log::trace!("downloaded image: {}", extract_to.display());
+        }
+        Ok(Some(format!(
+            "{}/{}/{}",
+            self.images_url, EXTERNAL_DIR, name
+        )))
+    }
+}
+
+/// replace the urls of a post's external images, in its content and its frontmatter
+///
+/// `f` returns the url with which to replace each, or `None` to leave it alone.
+fn map_external_images<F>(post: &mut Post, mut f: F) -> Result<(), Error>
+where
+    F: FnMut(&str) -> Result<Option<String>, Error>,
+{
+    let mut result = Ok(());
+    let mut replace = |prefix: &str, url: &str| match f(url) {
+        Ok(local) => format!("{}{}", prefix, local.as_deref().unwrap_or(url)),
+        Err(err) => {
+            result = Err(err);
+            format!("{}{}", prefix, url)
+        }
+    };
+    let content = MARKDOWN_IMAGE
+        .replace_all(&post.content, |image: &Captures| {
+            replace(&image[1], &image[2])
+        })
+        .into_owned();
+    post.content = HTML_IMAGE
+        .replace_all(&content, |image: &Captures| replace(&image[1], &image[2]))
+        .into_owned();
+    for url in post.image_urls_mut() {
+        if url.starts_with("http://") || url.starts_with("https://") {
+            *url = replace("", url);
+        }
+    }
+    result
+}
+
+/// apply the unsplash policy to the images which a post hotlinks from unsplash
+///
+/// They are downloaded later, with the other external images.
+pub(crate) fn apply_unsplash_policy(post: &mut Post, policy: UnsplashPolicy) {
+    if policy == UnsplashPolicy::Keep {
+        return;
+    }
+    let normalize = |url: &str| match url.strip_prefix(UNSPLASH_URL) {
+        Some(path) => format!(
+            "{}{}?{}",
+            UNSPLASH_URL,
+            path.split(['?', '#']).next().unwrap_or_default(),
+            UNSPLASH_QUERY
+        ),
+        None => url.to_string(),
+    };
+    let download = policy == UnsplashPolicy::Download;
+    post.content = UNSPLASH_IMAGE
+        .replace_all(&post.content, |image: &Captures| {
+            let caption = image.get(3).map_or("", |caption| caption.as_str());
+            let mut alt = image[1].to_string();
+            if let Some(attribution) = UNSPLASH_ATTRIBUTION
+                .captures(caption)
+                .filter(|_| download)
+                .map(|attribution| format!("Photo by {} on Unsplash", attribution[1].trim()))
+            {
+                alt = if alt.is_empty() {
+                    attribution
+                } else {
+                    format!("{} ({})", alt, attribution)
+                };
+            }
+            format!("![{}]({}){}", alt, normalize(&image[2]), caption)
+        })
+        .into_owned();
+    post.content = HTML_IMAGE
+        .replace_all(&post.content, |image: &Captures| {
+            format!("{}{}", &image[1], normalize(&image[2]))
+        })
+        .into_owned();
+    for url in post.image_urls_mut() {
+        *url = normalize(url);
+    }
+}
+
+/// a short name for an image, which differs for each url
+fn url_hash(url: &str) -> String {
+    Sha256::digest(url.as_bytes())
+        .iter()
+        .take(8)
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// the extension of an image, from its url or else its content type
+///
+/// `None` if the url isn't of an image.
+fn image_extension(url: &str, content_type: &str) -> Option<String> {
+    let subtype = content_type.strip_prefix("image/")?;
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let from_url = Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .filter(|extension| extension.len() <= 5)
+        .map(str::to_lowercase);
+    let from_type = match subtype {
+        "jpeg" => "jpg",
+        "svg+xml" => "svg",
+        subtype => subtype,
+    };
+    Some(from_url.unwrap_or_else(|| from_type.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_external_images() {
+        let mut post = Post {
+            content: "![a](https://cdn.example.com/a.png \"A\") ![b](/blog/b.png) \
+                      <img src=\"https://cdn.example.com/c.jpg\"> [d](https://example.com/)"
+                .into(),
+            ..Post::default()
+        };
+        post.extra.feature_image = Some("https://cdn.example.com/hero.png".into());
+        map_external_images(&mut post, |url| {
+            Ok(url
+                .strip_prefix("https://cdn.example.com/")
+                .map(|name| format!("/blog/external/{}", name)))
+        })
+        .unwrap();
+        assert_eq!(
+            post.content,
+            "![a](/blog/external/a.png \"A\") ![b](/blog/b.png) \
+             <img src=\"/blog/external/c.jpg\"> [d](https://example.com/)"
+        );
+        assert_eq!(
+            post.extra.feature_image.as_deref(),
+            Some("/blog/external/hero.png")
+        );
+    }
+
+    #[test]
+    fn applies_unsplash_policy() {
+        let content = "![](https://images.unsplash.com/photo-1?ixlib=rb-1.2.1&q=80&fm=jpg&crop=entropy&cs=tinysrgb&w=2000&fit=max&ixid=abc)\n\
+                       *Photo by [Ann Lee](https://unsplash.com/@ann?utm_source=ghost) / [Unsplash](https://unsplash.com/?utm_source=ghost)*";
+        let mut post = Post {
+            content: content.into(),
+            ..Post::default()
+        };
+        apply_unsplash_policy(&mut post, UnsplashPolicy::Keep);
+        assert_eq!(post.content, content);
+
+        apply_unsplash_policy(&mut post, UnsplashPolicy::Download);
+        assert!(post.content.starts_with(
+            "![Photo by Ann Lee on Unsplash](https://images.unsplash.com/photo-1?w=2000&q=80&fm=jpg&fit=max)\n*Photo by"
+        ));
+
+        let mut post = Post {
+            content: "![a hill](https://images.unsplash.com/photo-2?w=600)".into(),
+            ..Post::default()
+        };
+        post.extra.feature_image = Some("https://images.unsplash.com/photo-3?w=100".into());
+        apply_unsplash_policy(&mut post, UnsplashPolicy::Normalize);
+        assert_eq!(
+            post.content,
+            "![a hill](https://images.unsplash.com/photo-2?w=2000&q=80&fm=jpg&fit=max)"
+        );
+        assert_eq!(
+            post.extra.feature_image.as_deref(),
+            Some("https://images.unsplash.com/photo-3?w=2000&q=80&fm=jpg&fit=max")
+        );
+    }
+
+    #[test]
+    fn names_images_by_type() {
+        assert_eq!(
+            image_extension("https://example.com/a.PNG?w=600", "image/png").as_deref(),
+            Some("png")
+        );
+        assert_eq!(
+            image_extension("https://images.unsplash.com/photo-1?w=2000", "image/jpeg").as_deref(),
+            Some("jpg")
+        );
+        assert_eq!(image_extension("https://example.com/", "text/html"), None);
+        assert_ne!(
+            url_hash("https://a.com/1.png"),
+            url_hash("https://a.com/2.png")
+        );
+    }
+}
@@ -1,7 +1,27 @@
-use crate::{data_model::Post, find_ghost_db_in, log_progress, try_archive, Error};
-use log;
+use crate::{
+    archive::{copy_db, find_ghost_paths_in, scan_ghost_paths_in},
+    cards::{convert_cards, has_unconverted_cards, strip_card_markers},
+    data_model::{
+        insert_summary_separator, is_internal_tag, join_url, rename_image_urls, url_path, Author,
+        LinkRewriter, Post, PostId, PostLinks, Status, Tag,
+    },
+    diff::PreviousExtraction,
+    external::{apply_unsplash_policy, ExternalImages},
+    log_progress,
+    manifest::{extracted_images, Manifest},
+    progress::Phase,
+    query::{PostColumns, PostQuery, Posts},
+    render::{DefaultRenderer, PostRenderer},
+    summary::{NotedPost, PostWithoutAlt, Reason, Summary},
+    transcode::transcode_images,
+    try_archive,
+    verify::{validate_tree, LinkChecker},
+    ConflictPolicy, DraftPolicy, Error, ExtractOptions, GhostPaths, PostTypes, RawHtmlPolicy,
+    SectionIndex, Target, UndatedPolicy,
+};
 use path_absolutize::Absolutize;
 use rusqlite::Connection;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use tempfile::NamedTempFile;
@@ -9,6 +29,12 @@ use tempfile::NamedTempFile;
 struct PartialExtraction {
     database: NamedTempFile,
     images: Vec<PathBuf>,
+    /// the url at which Ghost served each of the images
+    ghost_urls: HashMap<PathBuf, String>,
+    /// the images left in the archive as nothing links to them, relative to the images url
+    pruned: Vec<String>,
+    /// the posts chosen by any limit, if they were chosen before the images were extracted
+    ids: Option<Vec<PostId>>,
 }
 
 impl PartialExtraction {
@@ -16,6 +42,9 @@ impl PartialExtraction {
         Ok(PartialExtraction {
             database: NamedTempFile::new()?,
             images: Vec::new(),
+            ghost_urls: HashMap::new(),
+            pruned: Vec::new(),
+            ids: None,
         })
     }
 }
@@ -35,40 +64,103 @@ macro_rules! contextualize {
 ///
 /// Assuming that the ghost DB is located in `a/b/c/data/ghost.db`, in a standard configuration,
 /// the images will be located in `a/b/c/images/yyyy/mm/*`. They will be extracted into
-/// `extract_path/yyyy/mm/*`.
+/// `extract_path/yyyy/mm/*`. If there is no such directory, the shallowest directory named `images`
+/// beneath `a/b` or the search prefix is used instead; [`ExtractOptions::images_prefix`] overrides
+/// the search entirely. Images within the archive directory of any other image source are extracted
+/// relative to `extract_path`.
 ///
 /// # Database Handling
 ///
+/// The database is normally the unique `ghost.db` within the search prefix; [`ExtractOptions::db_path`]
+/// overrides the search. When both it and [`ExtractOptions::images_prefix`] are set, the archive is
+/// read only once.
+///
 /// To avoid memory issues with large databases, the database is extracted into a temporary file.
 /// This file will be automatically removed by the OS when it is closed.
+///
+/// Either half may be skipped, with [`ExtractOptions::extract_images`] and
+/// [`ExtractOptions::extract_content`].
+///
+/// With [`ExtractOptions::prune_orphans`], the database is copied out first, and the images which
+/// nothing links to are left in the archive.
 fn extract_images_and_db<AP>(
     archive_path: AP,
-    prefix: Option<PathBuf>,
     extract_path: &Path,
+    options: &ExtractOptions,
 ) -> Result<PartialExtraction, Error>
 where
     AP: AsRef<Path>,
 {
     let archive_path = archive_path.as_ref();
     let extract_path = contextualize!(extract_path.canonicalize())?;
-    let db_path = contextualize!(find_ghost_db_in(archive_path, prefix))?;
-    let images_base = db_path
-        .parent()
-        .and_then(|parent| parent.parent())
-        .map(|grandparent| grandparent.join("images"));
+    let images_path = contextualize!(options.images_path(&extract_path))?;
+    let ghost_paths = match (&options.db_path, &options.images_prefix) {
+        // with both paths known, there's no need for a discovery pass over the archive
+        (Some(db), Some(images)) => GhostPaths {
+            db: db.clone(),
+            images: Some(images.clone()),
+        },
+        _ => contextualize!(scan_ghost_paths_in(
+            archive_path,
+            options.prefix.clone(),
+            options.db_path.clone(),
+            options.images_prefix.clone(),
+            options.progress.as_deref(),
+            options.on_progress.as_ref(),
+        ))?,
+    };
+    let db_path = &ghost_paths.db;
+    match &ghost_paths.images {
+        Some(images) => log::info!("using images directory {}", images.display()),
+        None => log::warn!("no images directory found"),
+    }
+    let images_bases: Vec<_> = if options.extract_images {
+        options
+            .image_sources
+            .iter()
+            .filter_map(|source| Some((ghost_paths.source_dir(source)?, source)))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let mut out = contextualize!(PartialExtraction::new())?;
+    // orphans can only be told apart from the rest once the posts are read
+    let linked = if options.prune_orphans && options.extract_content && !images_bases.is_empty() {
+        out.database = copy_db(archive_path, db_path, |_, _| {})?;
+        let (linked, ids) = linked_images(out.database.path(), options)?;
+        out.ids = ids;
+        Some(linked)
+    } else {
+        None
+    };
 
     log::info!("processing archive");
     let mut archive = contextualize!(try_archive(archive_path))?;
-    let mut out = contextualize!(PartialExtraction::new())?;
+    let mut found_db = false;
+    let mut n_entries = 0;
     for (idx, entry) in contextualize!(archive.entries())?.enumerate() {
         log_progress(idx, "processed");
+        n_entries = idx + 1;
 
         let mut entry = contextualize!(entry)?;
-        let path = contextualize!(entry.path())?;
-        if path == db_path {
+        let path = contextualize!(entry.path())?.into_owned();
+        if let Some(progress) = &options.progress {
+            progress.archive_entry(Phase::Extracting, idx, &path, entry.size());
+        }
+        if let Some(on_progress) = &options.on_progress {
+            on_progress.call(Phase::Extracting, idx, None);
+        }
+        if path == *db_path {
             // handle the database itself
-            contextualize!(std::io::copy(&mut entry, &mut out.database))?;
-            log::info!("extracted database at entry {}", idx);
+            found_db = true;
+            if options.extract_content && linked.is_none() {
+                contextualize!(std::io::copy(&mut entry, &mut out.database))?;
+                log::info!("extracted database at entry {}", idx);
+            }
+            if !options.extract_images {
+                break;
+            }
         } else if entry.header().entry_type() == tar::EntryType::Directory
             || path
                 .extension()
@@ -79,132 +171,1598 @@ where
             // don't waste time on directories; we can unpack them on demand later
             // likewise, it's more trouble than it's worth to copy over markdown files
             continue;
-        } else if let Some(images_base) = &images_base {
-            if path.starts_with(images_base) {
-                // handle an image
-                let subpath = contextualize!(path.strip_prefix(images_base))?;
-                let extract_to =
-                    contextualize!((&extract_path).join(subpath).absolutize())?.to_path_buf();
-                if !extract_to.starts_with(&extract_path) {
-                    log::warn!(
-                        "malicious file in tar attempted to extract past extraction root: {}",
-                        subpath.display(),
-                    );
+        } else if let Some((images_base, source)) = images_bases
+            .iter()
+            .find(|(images_base, _)| path.starts_with(images_base))
+        {
+            let subpath = contextualize!(path.strip_prefix(images_base))?;
+            if let Some(linked) = &linked {
+                let image = url_path(subpath);
+                if !linked.contains(&image) {
+                    out.pruned.push(image);
                     continue;
                 }
-                if let Some(parent) = extract_to.parent() {
-                    contextualize!(std::fs::create_dir_all(parent))?;
+            }
+            if let Some(extracted) = extract_image(&mut entry, subpath, &images_path, options)? {
+                let ghost_url = format!("{}/{}", source.url_prefix, url_path(subpath));
+                out.ghost_urls.insert(extracted.clone(), ghost_url);
+                if let Some(progress) = &options.progress {
+                    progress.image_extracted(&extracted);
+                }
+                out.images.push(extracted);
+                if let Some(on_progress) = &options.on_progress {
+                    on_progress.call(Phase::Images, out.images.len(), None);
                 }
-                log::trace!("extracting image: {}", extract_to.display());
-                contextualize!(entry.unpack(&extract_to))?;
-                out.images.push(extract_to);
             }
         }
     }
-    log::info!("extracted {} images", out.images.len());
+    if let Some(progress) = &options.progress {
+        progress.archive_done(Phase::Extracting, n_entries);
+    }
+    if let Some(on_progress) = &options.on_progress {
+        on_progress.done(Phase::Extracting, n_entries);
+        if options.extract_images {
+            on_progress.done(Phase::Images, out.images.len());
+        }
+    }
+    if options.extract_images {
+        log::info!("extracted {} images", out.images.len());
+    }
+    if !out.pruned.is_empty() {
+        log::info!("pruned {} images which nothing links to:", out.pruned.len());
+        for image in &out.pruned {
+            log::info!("  {}/{}", options.images_url(), image);
+        }
+    }
+
+    if !found_db && options.extract_content {
+        log::error!("database not found at {}", db_path.display());
+        return Err(Error::GhostDbNotFound);
+    }
 
     Ok(out)
 }
 
+/// the images, relative to the images url, which the posts, authors, and tags which the options
+/// extract link to, with the posts chosen by any limit
+///
+/// Only the links which Ghost wrote count: the images which transforms add links to are pruned too.
+fn linked_images(
+    database: &Path,
+    options: &ExtractOptions,
+) -> Result<(BTreeSet<String>, Option<Vec<PostId>>), Error> {
+    let conn = Connection::open_with_flags(database, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut query = select_posts(PostColumns::detect(&conn)?, options);
+    let ids = match options.limit {
+        Some(limit) => Some(query.choose_ids(&conn, limit, options.sample)?),
+        None => None,
+    };
+    if let Some(ids) = &ids {
+        query = query.ids(ids);
+    }
+    let images_url = options.images_url();
+    let mut report = Report::default();
+    for post in query.iter(&conn) {
+        let mut post = post?;
+        report.process(&mut post, options)?;
+        if report.skip(&post, options) {
+            continue;
+        }
+        report
+            .linked_images
+            .extend(post.extracted_images(&images_url));
+    }
+    let image_links = LinkRewriter::from_options(options);
+    if options.authors_dir.is_some() {
+        for mut author in Author::query(&conn)? {
+            author.rewrite_links(&image_links);
+            report.link_images(author.image_urls_mut(), options);
+        }
+    }
+    if options.tags_dir.is_some() {
+        for mut tag in Tag::query(&conn)? {
+            tag.rewrite_links(&image_links);
+            report.link_images(tag.image_urls_mut(), options);
+        }
+    }
+    Ok((report.linked_images, ids))
+}
+
+/// extract an image from the archive into the images directory, returning its path
+///
+/// `None` if the image would be extracted outside the images directory. An image which already
+/// exists is left alone if the conflict policy says to, but its path is returned all the same.
+fn extract_image<R: std::io::Read>(
+    entry: &mut tar::Entry<R>,
+    subpath: &Path,
+    images_path: &Path,
+    options: &ExtractOptions,
+) -> Result<Option<PathBuf>, Error> {
+    let extract_to = contextualize!(images_path.join(subpath).absolutize())?.to_path_buf();
+    if !extract_to.starts_with(images_path) {
+        log::warn!(
+            "malicious file in tar attempted to extract past extraction root: {}",
+            subpath.display(),
+        );
+        return Ok(None);
+    }
+    if !claim_output(&extract_to, options.conflict_policy())? {
+        return Ok(Some(extract_to));
+    }
+    if let Some(parent) = extract_to.parent() {
+        contextualize!(std::fs::create_dir_all(parent))?;
+    }
+    log::trace!("extracting image: {}", extract_to.display());
+    contextualize!(entry.unpack(&extract_to))?;
+    Ok(Some(extract_to))
+}
+
 /// Extract an archive into a destination folder.
 ///
 /// # Image Handling
 ///
 /// Assuming that the ghost DB is located in `a/b/c/data/ghost.db`, in a standard configuration,
 /// the images will be located in `a/b/c/images/yyyy/mm/*`. They will be extracted into
-/// `extract_path/yyyy/mm/*`.
+/// `extract_path/yyyy/mm/*`. With [`ExtractOptions::page_bundles`], the images which posts use
+/// are then moved alongside them. They may instead be extracted into their own directory, or the
+/// site's static directory, with [`ExtractOptions::images_dir`] and
+/// [`ExtractOptions::images_dest`]; the links to them follow.
+///
+/// Links to images which weren't in the archive are logged once the extraction is complete, so
+/// that they can be found by hand. Conversely, so are the extracted images which nothing links
+/// to; with [`ExtractOptions::prune_orphans`], they aren't extracted at all.
+///
+/// Images already migrated can be left alone with [`ExtractOptions::extract_images`]; conversely,
+/// only the images are extracted with [`ExtractOptions::extract_content`] unset.
 ///
 /// # Post Handling
 ///
 /// Posts are extracted from the Ghost-format sqlite DB and converted into Zola-compatible format.
 ///
-/// **WARN: if the post's original markdown has been lost, i.e. from a previous Ghost import, it will be skipped!**
-/// In that circumstance, consider regenerating the markdown from the rendered post content within the database
-/// with a different tool.
+/// **WARN: if the post's original markdown has been lost, i.e. from a previous Ghost import, its plaintext
+/// rendering is used instead, and `extra.plaintext_fallback` is set!** In that circumstance, consider
+/// regenerating the markdown from the rendered post content within the database with a different tool.
+/// Such posts are listed once extraction completes, as are the posts which were skipped, and both
+/// are included in the summary written with [`ExtractOptions::report`].
+///
+/// Alternately, [`ExtractOptions::raw_html`] passes such posts', or every post's, pre-rendered `html`
+/// through to Zola verbatim, setting `extra.raw_html` to flag them for later manual conversion.
+///
+/// Ghost doesn't require alt text, so many images lack it. The posts with such images are listed
+/// with their urls, and included in the summary, so that they can be described once migrated.
 ///
-/// Each post will be extracted into `extract_path/yyyy/mm/dd/slug`.
+/// Each post will be extracted into `extract_path/yyyy/mm/dd/slug`. Pages, such as "About", are
+/// extracted into a flat section instead: `extract_path/pages/slug`, by default; see
+/// [`ExtractOptions::pages_dir`]. Slugs can be made safe to use as file names on Windows; see
+/// [`ExtractOptions::path_sanitization`].
+///
+/// Each of these directories is made a Zola section by an `_index.md`, unless it already has one.
+/// The root index sorts the posts by date and paginates them. The year, month, and day indices are
+/// titled by their date, i.e. `January 2020`, and sort their posts by date too; they are
+/// transparent, so that their posts are also listed by the root. How they sort and paginate their
+/// posts is configured by [`ExtractOptions::root_section`] and [`ExtractOptions::branch_section`].
+/// Their contents can be replaced with
+/// [`ExtractOptions::root_index`] and [`ExtractOptions::branch_index`], or the indices left out
+/// entirely with [`ExtractOptions::indices`].
+///
+/// With [`ExtractOptions::default_language`] set, posts in other languages are extracted as
+/// translations, i.e. `extract_path/yyyy/mm/dd/slug.fr.md`, and each section gets an index in
+/// each of their languages, i.e. `_index.fr.md`.
+///
+/// Posts can be restricted to a subset with [`ExtractOptions::sql_filter`].
+///
+/// When [`ExtractOptions::authors_dir`] is set, each Ghost user is also extracted, as a Zola
+/// section describing them, into `authors_dir/slug/_index.md`. Their profile and cover images are
+/// rewritten like feature images. Likewise, when
+/// [`ExtractOptions::tags_dir`] is set, each tag's description and feature image are extracted
+/// into `tags_dir/slug/_index.md`.
+///
+/// Posts which are published but have no `published_at` are handled according to
+/// [`ExtractOptions::undated_policy`], and listed in a warning once extraction is complete.
+///
+/// A post which would be written to the same path as an earlier one, i.e. as it has the same slug
+/// and date, is renamed to `slug-2`, and so on, and likewise listed in a warning.
+///
+/// Drafts are extracted into `undated` alongside published posts by default. With
+/// [`ExtractOptions::drafts`] they can instead be extracted into their own section,
+/// [`ExtractOptions::drafts_dir`], or skipped entirely.
 ///
 /// ## Self-hosted images
 ///
 /// Within each post's markdown, things which look like image links, i.e. things which match the regex
-/// `\]\(/content/images/\d{4}/\d{2}/[^)]+\)`, will have the `/content/images` portion stripped out and
-/// replaced with `/blog`, ending up as `](/blog/dddd/mm/$1)`. This should preserve the links.
+/// `\]\(/content/images/[^)]+\)`, will have the `/content/images` portion stripped out and
+/// replaced with `/blog`, ending up as `](/blog/$1)`. This should preserve the links. Links to
+/// Ghost's responsive variants of an image, i.e. `/content/images/size/w600/2020/01/a.jpg`, point
+/// at the original image instead, as only it is extracted. Query strings and fragments, i.e.
+/// `?w=600`, are dropped unless [`ExtractOptions::keep_image_query`] is set. Images and media
+/// embedded in raw html, in the `src`, `srcset` and `poster` attributes of `img`, `source`, `audio`
+/// and `video` tags, and in the arguments of shortcodes, are rewritten likewise.
+///
+/// Other uploads are handled the same way: files under `/content/files`, and audio and video under
+/// `/content/media`, are extracted from the `files` and `media` directories beside `images`, and
+/// links to them are rewritten to `/blog`. See [`ImageSource::uploads`][crate::ImageSource::uploads].
+///
+/// Legacy blogs may have served images from other locations, i.e. `/assets/images`. Additional
+/// [`ImageSource`][crate::ImageSource]s can be configured in [`ExtractOptions::image_sources`]; links
+/// to their url prefixes are rewritten in the same way.
+///
+/// Each post's feature image is rewritten likewise, into `extra.feature_image`, as are its Open Graph
+/// and Twitter card images. As they are served from an image source, they are extracted along with
+/// the rest of that source's images.
+///
+/// With [`ExtractOptions::image_format`], the extracted raster images are converted into WebP or
+/// AVIF, and the links to them rewritten; [`ExtractOptions::keep_original_images`] keeps the
+/// originals beside them.
+///
+/// Images linked by absolute url, such as those on Ghost's CDN or Unsplash, aren't in the archive,
+/// so links to them are left alone. With [`ExtractOptions::download_external_images`], they are
+/// downloaded into `/blog/external` instead, and the links to them rewritten, as are feature and
+/// social images. Those which can't be downloaded are logged, and their links left alone.
+///
+/// Images from Ghost's Unsplash integration are handled according to [`ExtractOptions::unsplash`]:
+/// they may be left alone, have their query strings normalized to one size, or be downloaded with
+/// the photographer's attribution, from their caption, kept in their alt text.
+///
+/// Where the database has a `posts_meta` table, as from Ghost 3.0, metadata absent from `posts` is
+/// read from it instead.
+///
+/// ## Editor cards
+///
+/// Ghost's editor stores some of its cards as html within the post's markdown, bracketed by
+/// `<!--kg-card-begin: ...-->` and `<!--kg-card-end: ...-->` comments, which are removed.
+///
+/// Captioned images, `<figure>`s containing an `<img>` and its `<figcaption>`, are converted into
+/// a markdown image followed by its caption, code blocks into fenced code tagged with its language,
+/// galleries into rows of markdown images, bookmarks into quoted links, callouts into quotes,
+/// toggles into `<details>`, and buttons into links; see [`ExtractOptions::shortcodes`]. Posts
+/// whose content is raw html are otherwise left alone.
+///
+/// Posts without markdown are rendered from their mobiledoc or lexical editor document. Markdown
+/// cards are inlined verbatim, and html cards passed through as raw html, both then converted like
+/// any other post's markdown. Embed cards for YouTube and Vimeo become Zola shortcodes; other
+/// providers keep their embed html. Audio and video cards become `<audio>` and `<video>` players,
+/// and file cards become links to download the file, labelled with its name and size. A post
+/// containing a card which can't be rendered falls back to its plaintext or html.
+///
+/// ## Links between posts
+///
+/// Links from one post to another, i.e. `](/my-other-post/)`, are rewritten into Zola internal links,
+/// i.e. `](@/blog/2020/01/02/my-other-post.md)`, so that `zola build` validates them. Links to posts
+/// which weren't extracted are left alone. See [`ExtractOptions::link_posts`].
+///
+/// Absolute links to the blog, i.e. `](https://example.com/my-other-post/)`, are treated the same way
+/// when [`ExtractOptions::base_url`] is set.
+///
+/// Once everything is written, the internal links of each post, to images and to other posts, are
+/// checked, and any which don't resolve to an extracted file are logged, and listed in the report.
+/// [`ExtractOptions::strict`] fails the extraction if there are any. Links to images are only checked
+/// when [`ExtractOptions::extract_images`] is set.
+///
+/// ## Hugo
+///
+/// With [`ExtractOptions::target`] set to [`Target::Hugo`], content is extracted the same way for
+/// Hugo instead, into i.e. `content/blog`. Posts' frontmatter follows Hugo's conventions: `updated`
+/// becomes `lastmod`, each taxonomy is set at the top level rather than beneath `taxonomies`, and
+/// `extra` becomes `params`; authors' and tags' sections likewise. Links between posts become
+/// `{{< ref "/blog/2020/01/02/my-other-post.md" >}}`. No section indices are written, as Hugo
+/// lists the posts beneath a section without them. Raw html requires Goldmark's `unsafe` renderer.
+///
+/// ## Jekyll
+///
+/// With [`Target::Jekyll`], the extract path is the root of a Jekyll site instead. Published posts
+/// are written to `_posts/2020-01-02-my-post.md`, and drafts and undated posts to
+/// `_drafts/my-post.md`; pages are placed in the pages directory as usual. Frontmatter is always
+/// yaml, with `layout: post`, or `page` for pages, and `updated` becomes `last_modified_at`.
+/// Images are extracted into `assets/images`, and links to them rewritten to `/assets/images/...`.
+/// Links between posts become `{% post_url 2020-01-02-my-other-post %}`. Authors and tags are
+/// written as pages, i.e. `authors/jane/index.md`, with the `author` and `tag` layouts, which the
+/// site must provide. No indices are written.
 ///
 /// ## Metadata
 ///
-/// Zola expects post metadata to exist in TOML front matter prepended to each post. The following metadata
-/// is extracted from the DB and rendered into the frontmatter:
+/// Zola expects post metadata to exist in TOML front matter prepended to each post, or in YAML with
+/// [`ExtractOptions::frontmatter`]. The following metadata is extracted from the DB and rendered into
+/// the frontmatter, or made available to [`ExtractOptions::frontmatter_template`]:
 ///
 /// | Ghost Sql Field | Zola Frontmatter Key | Notes |
 /// | --- | --- | --- |
 /// | `title` | `title` | |
-/// | `meta_description` | `description` | not set if empty |
-/// | `published_at` | `date` | not set if empty |
+/// | `meta_description` | `description` | `custom_excerpt` if empty; not set if both are empty |
+/// | `custom_excerpt` | `extra.excerpt` | not set if empty |
+/// | `published_at` | `date` | not set if empty; see [`UndatedPolicy`] for published posts |
 /// | `updated_at` | `updated` | not set if empty |
+/// | `slug`, `published_at` | `aliases` | the post's url on Ghost; only set with [`ExtractOptions::permalinks`] |
 /// | `status` | `draft` | `"published"` => `false`; anything else => `true`; not set if false |
 /// | `slug` | `slug` | |
+/// | `id` | `extra.id` | |
+/// | `uuid` | `extra.uuid` | |
 /// | `language` | `extra.language` | |
-/// | `users.name` | `extra.author_name` | `posts inner join users on posts.author_id = users.id` |
-/// | `tags.name` | `taxonomies.tags` | `select tags.name from posts_tags inner join tags on posts_tags.tag_id = tags.id where posts_tags.post_id = %` |
+/// | `image` | `extra.feature_image` | rewritten like image links; not set if empty |
+/// | `featured` | `extra.featured` | always set, so that templates can filter on it |
+/// | `canonical_url` | `extra.canonical_url` | not set if empty |
+/// | `visibility` | `extra.visibility` | i.e. `public`, `members`, or `paid`; non-public posts are counted once extraction is complete |
+/// | `codeinjection_head` | `extra.codeinjection_head` | multi-line string; not set if empty |
+/// | `codeinjection_foot` | `extra.codeinjection_foot` | multi-line string; not set if empty |
+/// | `og_image`, `og_title`, `og_description` | `extra.social.og_*` | images rewritten like image links; each not set if empty |
+/// | `twitter_image`, `twitter_title`, `twitter_description` | `extra.social.twitter_*` | images rewritten like image links; each not set if empty |
+/// | `users.name` | `extra.author_name` | the first of `extra.authors`; empty if it has none |
+/// | `users.name` | `extra.authors` | `select users.name from users inner join posts_authors on users.id = posts_authors.author_id where posts_authors.post_id = % order by posts_authors.sort_order`; where that table doesn't exist or lists no authors, `posts left join users on posts.author_id = users.id` |
+/// | | `extra.words` | words in the content; only set with [`ExtractOptions::words_per_minute`] |
+/// | | `extra.reading_time` | estimated minutes to read the content; only set with [`ExtractOptions::words_per_minute`] |
+/// | | `extra.plaintext_fallback` | `true` if the post had no markdown; not set otherwise |
+/// | | `extra.raw_html` | `true` if the post's content is its `html`; not set otherwise |
+/// | `tags.name` | `taxonomies.tags` | `select tags.name from posts_tags inner join tags on posts_tags.tag_id = tags.id where posts_tags.post_id = % order by posts_tags.sort_order`, less tags beginning with `#` |
+/// | `tags.name` | `extra.internal_tags` | tags beginning with `#`; only set with [`ExtractOptions::keep_internal_tags`] |
+/// | | `extra.*` | any site-specific fields set with [`ExtractOptions::extra_frontmatter`] |
+/// | | `taxonomies.*` | the primary tag and authors may be classified too, and every taxonomy renamed; see [`ExtractOptions::taxonomies`] |
+///
+/// Any of these keys may be renamed, or dropped, with [`ExtractOptions::frontmatter_keys`].
 pub fn extract_archive<AP, EP>(
     archive_path: AP,
-    prefix: Option<PathBuf>,
     extract_path: EP,
+    options: &ExtractOptions,
 ) -> Result<usize, Error>
 where
     AP: AsRef<Path>,
     EP: AsRef<Path>,
 {
     let extract_path = extract_path.as_ref();
-    extract_images_and_db(archive_path, prefix, extract_path)?.extract_database(extract_path)
+    let partial = extract_images_and_db(archive_path, extract_path, options)?;
+    if !options.extract_content {
+        Summary {
+            images: partial.images.len(),
+            ..Summary::default()
+        }
+        .write(options)?;
+        return Ok(0);
+    }
+    partial.extract_database(extract_path, options)
+}
+
+/// read the posts of a ghost blog within an archive, converted as [`extract_archive`] would
+/// convert them, without writing anything
+///
+/// The database is found as [`extract_archive`] finds it, and copied into a temporary file, which
+/// is removed once the iterator is dropped. The posts are yielded one at a time, as they're read,
+/// after every transformation up to rendering: their cards are converted, their slugs made unique,
+/// their links to other posts rewritten if the options say to, and the options'
+/// [`transforms`](ExtractOptions::transforms) run. Their images aren't extracted, downloaded, or
+/// converted, and drafts are left out if [`ExtractOptions::drafts`] says to skip them. It's then up
+/// to the caller to index, render, or store them.
+pub fn posts_in_archive<AP>(
+    archive_path: AP,
+    options: &ExtractOptions,
+) -> Result<impl Iterator<Item = Result<Post, Error>>, Error>
+where
+    AP: AsRef<Path>,
+{
+    let archive_path = archive_path.as_ref();
+    let db_path = match &options.db_path {
+        Some(db_path) => db_path.clone(),
+        None => {
+            find_ghost_paths_in(
+                archive_path,
+                options.prefix.clone(),
+                None,
+                options.images_prefix.clone(),
+            )?
+            .db
+        }
+    };
+    let database = copy_db(archive_path, &db_path, |_, _| {})?;
+    let conn =
+        Connection::open_with_flags(database.path(), rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let columns = PostColumns::detect(&conn)?;
+    let mut query = select_posts(columns.clone(), options);
+    if let Some(limit) = options.limit {
+        let ids = query.choose_ids(&conn, limit, options.sample)?;
+        query = query.ids(&ids);
+    }
+    let links = if options.link_posts {
+        Some(post_links(&conn, &query, columns, options)?)
+    } else {
+        None
+    };
+    Ok(ArchivePosts {
+        posts: query.into_posts(conn),
+        _database: database,
+        report: Report::default(),
+        links,
+        options: options.clone(),
+    })
+}
+
+/// The posts of a blog within an archive, converted as they're read; see [`posts_in_archive`].
+struct ArchivePosts {
+    /// the cursor over the posts, which owns the connection to the database
+    posts: Posts<'static>,
+    /// the database, which is removed once it's dropped, after the connection
+    _database: NamedTempFile,
+    /// the paths claimed by the posts so far, to keep each unique
+    report: Report,
+    links: Option<PostLinks>,
+    options: ExtractOptions,
+}
+
+impl Iterator for ArchivePosts {
+    type Item = Result<Post, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for post in &mut self.posts {
+            let mut post = match post {
+                Ok(post) => post,
+                Err(err) => return Some(Err(err)),
+            };
+            if let Err(err) = self.report.process(&mut post, &self.options) {
+                return Some(Err(err));
+            }
+            if self.report.skip(&post, &self.options) {
+                continue;
+            }
+            self.report.claim_path(&mut post, &self.options);
+            if let Some(links) = &self.links {
+                post.link_posts(links);
+            }
+            self.options.transforms.apply(&mut post);
+            return Some(Ok(post));
+        }
+        None
+    }
 }
 
 impl PartialExtraction {
-    fn extract_database(self, extract_path: &Path) -> Result<usize, Error> {
+    fn extract_database(
+        self,
+        extract_path: &Path,
+        options: &ExtractOptions,
+    ) -> Result<usize, Error> {
         let conn = Connection::open_with_flags(
             self.database.path(),
             rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY,
         )?;
-        let posts = Post::query(&conn)?;
-        for post in posts.iter() {
-            let relative_path = post.relative_path();
-            let path = extract_path.join(&relative_path);
-            if let Some(parent) = path.parent() {
-                std::fs::create_dir_all(parent)?;
-            }
-            let file = std::fs::OpenOptions::new()
-                .write(true)
-                .create(true)
-                .open(path)?;
-            let mut writer = std::io::BufWriter::new(file);
-            post.render_to(&mut writer)?;
-            log::trace!("generated {}", relative_path.display());
+        let mut n_posts = 0;
+        let columns = PostColumns::detect(&conn)?;
+        let mut query = select_posts(columns.clone(), options);
+        if let Some(limit) = options.limit {
+            let ids = match &self.ids {
+                Some(ids) => ids.clone(),
+                None => query.choose_ids(&conn, limit, options.sample)?,
+            };
+            log::info!("extracting only {} posts", ids.len());
+            query = query.ids(&ids);
         }
-        log::info!("extracted {} posts", posts.len());
+        let links = if options.link_posts {
+            Some(post_links(&conn, &query, columns, options)?)
+        } else {
+            None
+        };
+        let images_url = options.images_url();
+        let images_path = options.images_path(&extract_path.canonicalize()?)?;
+        let renames = match options.image_format {
+            Some(format) => {
+                let renames = transcode_images(
+                    &self.images,
+                    &images_path,
+                    format,
+                    options.keep_original_images,
+                    options.conflict_policy(),
+                )?;
+                log::info!("converted {} images", renames.len());
+                renames
+            }
+            None => HashMap::new(),
+        };
+
+        let mut report = Report::default();
+        let mut bundles = Bundles::default();
+        let mut manifest = Manifest::default();
+        let mut external = ExternalImages::from_options(extract_path, options);
+        let mut written = Vec::new();
+        report.read_previous(extract_path, options)?;
+        for post in query.iter(&conn) {
+            let mut post = post?;
+            report.process(&mut post, options)?;
+            if report.skip(&post, options) {
+                continue;
+            }
+            manifest.ghost_url(&post, options);
+            report.claim_path(&mut post, options);
+            if let Some(external) = &mut external {
+                external.localize(&mut post)?;
+            }
+            post.rename_images(&images_url, &renames);
+            report.check_images(&mut post, &images_path, options);
+            if let Some(links) = &links {
+                post.link_posts(links);
+            }
+            report.check_alt_text(&post, options);
+            bundles.bundle(&mut post, options);
+            options.transforms.apply(&mut post);
+            if report.changed(&post, options) {
+                write_post(&post, extract_path, options)?;
+            }
+            manifest.post(&post, options);
+            written.push(output_path(&post, options));
+            if let Some(progress) = &options.progress {
+                progress.post_written(n_posts, &output_path(&post, options));
+            }
+            n_posts += 1;
+            if let Some(on_progress) = &options.on_progress {
+                on_progress.call(Phase::Posts, n_posts, None);
+            }
+        }
+        report.remove_moved(&written, extract_path)?;
+        if let Some(progress) = &options.progress {
+            progress.posts_done(n_posts);
+        }
+        if let Some(on_progress) = &options.on_progress {
+            on_progress.done(Phase::Posts, n_posts);
+        }
+        log::info!("extracted {} posts", n_posts);
+        if let Some(external) = &external {
+            log::info!("downloaded {} external images", external.len());
+        }
+        report.log(options);
+
+        let image_links = LinkRewriter::from_options(options);
+        if let Some(authors_dir) = &options.authors_dir {
+            let mut authors = Author::query(&conn)?;
+            for author in &mut authors {
+                author.rewrite_links(&image_links);
+                rename_image_urls(author.image_urls_mut(), &images_url, &renames);
+                report.link_images(author.image_urls_mut(), options);
+            }
+            write_authors(&authors, &extract_path.join(authors_dir), options)?;
+            log::info!("extracted {} authors", authors.len());
+        }
+        if let Some(tags_dir) = &options.tags_dir {
+            let mut tags = Tag::query(&conn)?;
+            for tag in &mut tags {
+                tag.rewrite_links(&image_links);
+                rename_image_urls(tag.image_urls_mut(), &images_url, &renames);
+                report.link_images(tag.image_urls_mut(), options);
+            }
+            write_tags(&tags, &extract_path.join(tags_dir), options)?;
+            log::info!("extracted {} tags", tags.len());
+        }
+
+        // the images as they were extracted, or converted
+        let images = extracted_images(&self.ghost_urls, &images_path, &renames);
+        let extracted: Vec<_> = images.iter().map(|(_, image)| image.clone()).collect();
+        log_orphans(&report.orphans(&extracted), options);
+        manifest.images(&images, &bundles, options);
+
+        if options.page_bundles {
+            let n_bundled = bundles.move_images(extract_path, options)?;
+            log::info!("moved {} images into page bundles", n_bundled);
+        }
+        manifest.write(options)?;
+        report.check_links(&written, extract_path, options)?;
 
         // now ensure that appropriate indices exist
-        let n_indices = ensure_indices(extract_path)?;
+        let n_indices = ensure_indices(extract_path, options)?;
         log::info!("added {} indices", n_indices);
+        report.validate(extract_path, options)?;
+
+        let mut summary = report.summary(options);
+        summary.posts = n_posts;
+        summary.images = self.images.len();
+        summary.external_images = external.as_ref().map_or(0, ExternalImages::len);
+        summary.converted_images = renames.len();
+        summary.pruned_images = self.pruned.len();
+        summary.indices = n_indices;
+        summary.write(options)?;
+        report.check_strict(options)?;
+
+        Ok(n_posts)
+    }
+}
+
+/// the query selecting the posts which the options extract, but for any limit
+pub(crate) fn select_posts(columns: PostColumns, options: &ExtractOptions) -> PostQuery {
+    let is_page = format!("COALESCE({}, 0)", columns.page);
+    let mut query = PostQuery::new()
+        .columns(columns)
+        .image_sources(options.image_sources.clone())
+        .base_url(options.base_url.clone())
+        .images_url(options.images_url())
+        .keep_image_query(options.keep_image_query)
+        .raw_html(options.raw_html)
+        .shortcodes(options.shortcodes.clone())
+        .post_filter(options.post_filter.clone());
+    match options.only {
+        PostTypes::Posts => query = query.filter(format!("NOT {}", is_page)),
+        PostTypes::Pages => query = query.filter(is_page),
+        PostTypes::All => {}
+    }
+    if let Some(sql_filter) = &options.sql_filter {
+        query = query.filter(sql_filter.as_str());
+    }
+    query
+}
+
+/// find where each post which `query` selects will be extracted
+///
+/// Posts are written as they are read, so this takes a preliminary pass over the posts, without
+/// their content.
+fn post_links(
+    conn: &Connection,
+    query: &PostQuery,
+    columns: PostColumns,
+    options: &ExtractOptions,
+) -> Result<PostLinks, Error> {
+    let columns = columns.without_content();
+    let mut links = PostLinks::new(options.target);
+    // paths are claimed in the same order as they are when the posts are written, so that they
+    // are disambiguated the same way
+    let mut report = Report::default();
+    for post in query
+        .clone()
+        .columns(columns)
+        .join_authors(false)
+        .iter(conn)
+    {
+        let mut post = post?;
+        resolve_undated(&mut post, options.undated_policy)?;
+        if is_skipped(&post, options) {
+            continue;
+        }
+        let path = report.claim_path(&mut post, options);
+        links.insert(post.slug.clone(), &path);
+    }
+    Ok(links)
+}
 
-        Ok(posts.len())
+/// apply the undated policy to a post which is published but has no publication date
+///
+/// Returns whether the post was such a post.
+pub(crate) fn resolve_undated(post: &mut Post, policy: UndatedPolicy) -> Result<bool, Error> {
+    if post.date.is_some() || post.status.draft() {
+        return Ok(false);
     }
+    match policy {
+        UndatedPolicy::Keep => {}
+        UndatedPolicy::Draft => post.status = Status::Draft,
+        UndatedPolicy::CreatedAt => match post.created {
+            Some(created) => post.date = Some(created),
+            None => post.status = Status::Draft,
+        },
+        UndatedPolicy::Error => {
+            return Err(Error::UndatedPost {
+                id: post.extra.id.clone(),
+                slug: post.slug.clone(),
+            })
+        }
+    }
+    Ok(true)
+}
+
+/// whether a post is left out of the extraction, as a draft when drafts are skipped
+pub(crate) fn is_skipped(post: &Post, options: &ExtractOptions) -> bool {
+    options.drafts == DraftPolicy::Skip && post.status.draft()
 }
 
-const ROOT_INDEX_DATA: &[u8] = include_bytes!("../templates/root._index.md");
-const BRANCH_INDEX_DATA: &[u8] = include_bytes!("../templates/branch._index.md");
+/// Notable posts found during an extraction, which are summarized once it is complete.
+#[derive(Debug, Default)]
+pub(crate) struct Report {
+    /// published posts without a publication date, as `id (slug)`
+    undated: Vec<String>,
+    /// the number of posts with each visibility other than `public`
+    non_public: BTreeMap<String, usize>,
+    /// the posts left out of the extraction
+    skipped: Vec<NotedPost>,
+    /// the posts extracted without their markdown
+    without_markdown: Vec<NotedPost>,
+    /// the posts with cards which couldn't be converted
+    unconverted_cards: Vec<NotedPost>,
+    /// the posts with images which have no alt text
+    without_alt_text: Vec<PostWithoutAlt>,
+    /// the paths claimed by the posts so far
+    paths: BTreeSet<PathBuf>,
+    /// posts renamed because another post already had their path
+    collisions: Vec<String>,
+    /// the links to images which weren't extracted, with the slugs of the posts using each
+    missing_images: BTreeMap<String, BTreeSet<String>>,
+    /// the paths of the extracted images which are linked to, relative to the images url
+    linked_images: BTreeSet<String>,
+    /// the posts' internal links which don't resolve, by the path of each post
+    dangling_links: Vec<(PathBuf, Vec<String>)>,
+    /// the problems with each generated file's frontmatter, by its path
+    invalid_frontmatter: Vec<(PathBuf, Vec<String>)>,
+    /// the posts already beneath the extract path, when it's updated incrementally
+    previous: Option<PreviousExtraction>,
+}
 
-fn ensure_indices(extract_path: &Path) -> Result<u32, Error> {
+impl Report {
+    /// apply the options which adjust a post about to be written, and note anything notable about it
+    pub(crate) fn process(
+        &mut self,
+        post: &mut Post,
+        options: &ExtractOptions,
+    ) -> Result<(), Error> {
+        if resolve_undated(post, options.undated_policy)? {
+            self.undated
+                .push(format!("{} ({})", post.extra.id, post.slug));
+        }
+        post.content = strip_card_markers(&post.content);
+        // raw html is otherwise passed through untouched
+        if !post.extra.raw_html {
+            post.content = convert_cards(&post.content, &options.shortcodes);
+        }
+        apply_unsplash_policy(post, options.unsplash);
+        if options.summary_separator && !post.extra.raw_html {
+            if let Some(excerpt) = &post.extra.excerpt {
+                post.content = insert_summary_separator(
+                    &post.content,
+                    excerpt,
+                    options.target.summary_separator(),
+                );
+            }
+        }
+        if let Some(words_per_minute) = options.words_per_minute {
+            post.compute_reading_time(words_per_minute);
+        }
+        let (internal_tags, tags) = std::mem::take(&mut post.taxonomies.tags)
+            .into_iter()
+            .partition(|tag| is_internal_tag(tag));
+        post.taxonomies.tags = tags;
+        if options.keep_internal_tags {
+            post.extra.internal_tags = internal_tags;
+        }
+        post.extra.custom.extend(
+            options
+                .extra_frontmatter
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
+        post.taxonomies.categories = post.taxonomies.tags.iter().take(1).cloned().collect();
+        if let Some(permalinks) = &options.permalinks {
+            post.aliases
+                .extend(post.ghost_path(permalinks, &options.slugs));
+        }
+        post.taxonomies.authors = post.extra.authors.clone();
+        post.taxonomies.mapping = options.taxonomies.clone();
+        post.keys = options.frontmatter_keys.clone();
+        if let Some(visibility) = &post.extra.visibility {
+            if visibility != "public" {
+                *self.non_public.entry(visibility.clone()).or_default() += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// whether a post, already processed, is left out of the extraction; see [`is_skipped`]
+    ///
+    /// Posts which aren't left out are noted if they're extracted without their markdown, or with
+    /// cards which couldn't be converted.
+    pub(crate) fn skip(&mut self, post: &Post, options: &ExtractOptions) -> bool {
+        let note = |reason| NotedPost {
+            id: post.extra.id.clone(),
+            slug: post.slug_with(&options.slugs),
+            title: post.title.clone(),
+            reason,
+        };
+        let skipped = is_skipped(post, options);
+        if skipped {
+            self.skipped.push(note(Reason::Draft));
+        } else if post.extra.plaintext_fallback {
+            self.without_markdown.push(note(Reason::Plaintext));
+        } else if post.extra.raw_html && options.raw_html == RawHtmlPolicy::Fallback {
+            self.without_markdown.push(note(Reason::RawHtml));
+        } else if post.content.trim().is_empty() {
+            self.without_markdown.push(note(Reason::Empty));
+        }
+        if !skipped && !post.extra.raw_html && has_unconverted_cards(&post.content) {
+            self.unconverted_cards.push(note(Reason::UnconvertedCards));
+        }
+        skipped
+    }
+
+    /// claim a path which no other post has, renaming the post's slug to i.e. `slug-2` if need be
+    ///
+    /// Posts are renamed in the order in which they're claimed, so that the first post keeps its
+    /// slug.
+    pub(crate) fn claim_path(&mut self, post: &mut Post, options: &ExtractOptions) -> PathBuf {
+        // an empty slug would otherwise be generated anew for each path
+        post.slug = post.slug_with(&options.slugs);
+        let slug = post.slug.clone();
+        let mut path = output_path(post, options);
+        let mut n = 1;
+        while self.paths.contains(&path) {
+            n += 1;
+            post.slug = format!("{}-{}", slug, n);
+            path = output_path(post, options);
+        }
+        if n > 1 {
+            self.collisions
+                .push(format!("{} ({}) => {}", post.extra.id, slug, post.slug));
+        }
+        self.paths.insert(path.clone());
+        path
+    }
+
+    /// note the images which a post links to, but which weren't extracted into `images_path`
+    ///
+    /// Nothing is noted when images aren't extracted at all.
+    pub(crate) fn check_images(
+        &mut self,
+        post: &mut Post,
+        images_path: &Path,
+        options: &ExtractOptions,
+    ) {
+        if !options.extract_images {
+            return;
+        }
+        let images_url = options.images_url();
+        for image in post.extracted_images(&images_url) {
+            if !images_path.join(&image).is_file() {
+                self.missing_images
+                    .entry(format!("{}/{}", images_url, image))
+                    .or_default()
+                    .insert(post.slug.clone());
+            }
+            self.linked_images.insert(image);
+        }
+    }
+
+    /// find the posts already extracted beneath the extract path, if the options say to update it
+    /// incrementally
+    ///
+    /// This must be done before any post is written.
+    pub(crate) fn read_previous(
+        &mut self,
+        extract_path: &Path,
+        options: &ExtractOptions,
+    ) -> Result<(), Error> {
+        if options.incremental {
+            self.previous = Some(PreviousExtraction::scan(extract_path, options)?);
+        }
+        Ok(())
+    }
+
+    /// whether to write a post, as it's new or has changed since the extract path was last
+    /// updated; always, unless it's updated incrementally
+    pub(crate) fn changed(&mut self, post: &Post, options: &ExtractOptions) -> bool {
+        let path = output_path(post, options);
+        self.previous
+            .as_mut()
+            .is_none_or(|previous| previous.changed(post, &path))
+    }
+
+    /// remove the files of the posts which an incremental update wrote elsewhere
+    pub(crate) fn remove_moved(
+        &self,
+        written: &[PathBuf],
+        extract_path: &Path,
+    ) -> Result<(), Error> {
+        match &self.previous {
+            Some(previous) => previous.remove_moved(written, extract_path),
+            None => Ok(()),
+        }
+    }
+
+    /// note the images in a post without alt text, as it's about to be written
+    pub(crate) fn check_alt_text(&mut self, post: &Post, options: &ExtractOptions) {
+        let images = post.images_without_alt();
+        if images.is_empty() {
+            return;
+        }
+        self.without_alt_text.push(PostWithoutAlt {
+            id: post.extra.id.clone(),
+            slug: post.slug_with(&options.slugs),
+            title: post.title.clone(),
+            images,
+        });
+    }
+
+    /// note the extracted images which an author or tag links to
+    pub(crate) fn link_images<'a>(
+        &mut self,
+        urls: impl Iterator<Item = &'a mut String>,
+        options: &ExtractOptions,
+    ) {
+        let prefix = format!("{}/", options.images_url());
+        for url in urls {
+            if let Some(rest) = url.strip_prefix(&prefix) {
+                let path = rest.split(['?', '#']).next().unwrap_or_default();
+                self.linked_images.insert(path.to_string());
+            }
+        }
+    }
+
+    /// the extracted images, given as paths relative to the images url, which nothing links to
+    pub(crate) fn orphans<'a>(&self, extracted: &'a [String]) -> Vec<&'a str> {
+        extracted
+            .iter()
+            .filter(|image| !self.linked_images.contains(*image))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// log a summary of the notable posts
+    pub(crate) fn log(&self, options: &ExtractOptions) {
+        if let Some(previous) = &self.previous {
+            previous.log();
+        }
+        if !self.skipped.is_empty() {
+            log::info!("skipped {} posts:", self.skipped.len());
+            for post in &self.skipped {
+                log::info!("  {}", post);
+            }
+        }
+        if !self.without_markdown.is_empty() {
+            log::warn!(
+                "{} posts were extracted without their markdown:",
+                self.without_markdown.len()
+            );
+            for post in &self.without_markdown {
+                log::warn!("  {}", post);
+            }
+        }
+        if !self.unconverted_cards.is_empty() {
+            log::warn!(
+                "{} posts have cards which couldn't be converted, left as html:",
+                self.unconverted_cards.len()
+            );
+            for post in &self.unconverted_cards {
+                log::warn!("  {}", post);
+            }
+        }
+        if !self.without_alt_text.is_empty() {
+            let n_images: usize = self
+                .without_alt_text
+                .iter()
+                .map(|post| post.images.len())
+                .sum();
+            log::info!(
+                "{} images in {} posts have no alt text:",
+                n_images,
+                self.without_alt_text.len()
+            );
+            for post in &self.without_alt_text {
+                log::info!("  {}", post);
+            }
+        }
+        if !self.undated.is_empty() {
+            let resolution = match options.undated_policy {
+                UndatedPolicy::Keep => "extracted without a date",
+                UndatedPolicy::Draft => "extracted as drafts",
+                UndatedPolicy::CreatedAt => {
+                    "dated by creation time where known, else extracted as drafts"
+                }
+                UndatedPolicy::Error => {
+                    unreachable!("the error policy aborts on the first undated post")
+                }
+            };
+            log::warn!(
+                "{} published posts had no publication date; {}:",
+                self.undated.len(),
+                resolution
+            );
+            for post in &self.undated {
+                log::warn!("  {}", post);
+            }
+        }
+
+        if !self.collisions.is_empty() {
+            log::warn!(
+                "{} posts had the same path as another post; renamed:",
+                self.collisions.len()
+            );
+            for post in &self.collisions {
+                log::warn!("  {}", post);
+            }
+        }
+
+        if !self.missing_images.is_empty() {
+            log::warn!(
+                "{} images which posts link to were not in the archive:",
+                self.missing_images.len()
+            );
+            for (image, posts) in &self.missing_images {
+                let posts: Vec<_> = posts.iter().map(String::as_str).collect();
+                log::warn!("  {} ({})", image, posts.join(", "));
+            }
+        }
+
+        for (visibility, n_posts) in &self.non_public {
+            log::warn!(
+                "{} posts had visibility {:?}; see extra.visibility",
+                n_posts,
+                visibility
+            );
+        }
+    }
+
+    /// check that the internal links of the written posts, given relative to the extract path,
+    /// resolve, logging those which don't
+    ///
+    /// This must be done once every file is written, and every image moved.
+    pub(crate) fn check_links(
+        &mut self,
+        posts: &[PathBuf],
+        extract_path: &Path,
+        options: &ExtractOptions,
+    ) -> Result<(), Error> {
+        self.dangling_links = LinkChecker::new(extract_path, options)?.check(posts)?;
+        let n_links: usize = self
+            .dangling_links
+            .iter()
+            .map(|(_, links)| links.len())
+            .sum();
+        if n_links == 0 {
+            log::debug!("every internal link resolves");
+            return Ok(());
+        }
+        log::warn!(
+            "{} internal links in {} posts don't resolve to an extracted file:",
+            n_links,
+            self.dangling_links.len()
+        );
+        for (post, links) in &self.dangling_links {
+            log::warn!("  {}: {}", post.display(), links.join(", "));
+        }
+        Ok(())
+    }
+
+    /// check that the target would accept the frontmatter of every file beneath the extract path,
+    /// if the options say to, logging those which it wouldn't
+    ///
+    /// This must be done once every file, including the indices, is written.
+    pub(crate) fn validate(
+        &mut self,
+        extract_path: &Path,
+        options: &ExtractOptions,
+    ) -> Result<(), Error> {
+        if !options.validate {
+            return Ok(());
+        }
+        self.invalid_frontmatter = validate_tree(extract_path, options)?;
+        if self.invalid_frontmatter.is_empty() {
+            log::info!("the frontmatter of every file is valid");
+            return Ok(());
+        }
+        log::warn!(
+            "{} files have frontmatter which {:?} would reject:",
+            self.invalid_frontmatter.len(),
+            options.target
+        );
+        for (path, problems) in &self.invalid_frontmatter {
+            log::warn!("  {}: {}", path.display(), problems.join("; "));
+        }
+        Ok(())
+    }
+
+    /// fail if the options are strict, and any post was degraded, image missing, link left
+    /// dangling, or frontmatter invalid
+    ///
+    /// Posts skipped by the options, i.e. drafts with [`DraftPolicy::Skip`], don't count, as
+    /// nothing was lost which wasn't asked to be. Each dangling link counts, not each post with
+    /// one. This is checked once the extraction is complete, so that each of them is logged first.
+    pub(crate) fn check_strict(&self, options: &ExtractOptions) -> Result<(), Error> {
+        let n_problems = self.without_markdown.len()
+            + self.unconverted_cards.len()
+            + self.missing_images.len()
+            + self
+                .dangling_links
+                .iter()
+                .map(|(_, links)| links.len())
+                .sum::<usize>()
+            + self.invalid_frontmatter.len();
+        if options.strict && n_problems > 0 {
+            return Err(Error::Incomplete(n_problems));
+        }
+        Ok(())
+    }
+
+    /// a summary of the notable posts, to which the caller adds its counts
+    ///
+    /// Its warnings are those which [`Report::log`] logs, one per post or image concerned.
+    pub(crate) fn summary(&self, options: &ExtractOptions) -> Summary {
+        let mut warnings = Vec::new();
+        for post in &self.undated {
+            warnings.push(match options.undated_policy {
+                UndatedPolicy::Keep => format!(
+                    "published post {} had no publication date; extracted without one",
+                    post
+                ),
+                UndatedPolicy::Draft => format!(
+                    "published post {} had no publication date; extracted as a draft",
+                    post
+                ),
+                _ => format!(
+                    "published post {} had no publication date; dated by creation time if known",
+                    post
+                ),
+            });
+        }
+        for post in &self.collisions {
+            warnings.push(format!(
+                "post {} had the same path as another post; renamed",
+                post
+            ));
+        }
+        for (image, posts) in &self.missing_images {
+            let posts: Vec<_> = posts.iter().map(String::as_str).collect();
+            warnings.push(format!(
+                "image {} was not in the archive; linked by {}",
+                image,
+                posts.join(", ")
+            ));
+        }
+        for (visibility, n_posts) in &self.non_public {
+            warnings.push(format!(
+                "{} posts had visibility {:?}; see extra.visibility",
+                n_posts, visibility
+            ));
+        }
+        Summary {
+            skipped: self.skipped.clone(),
+            without_markdown: self.without_markdown.clone(),
+            unconverted_cards: self.unconverted_cards.clone(),
+            without_alt_text: self.without_alt_text.clone(),
+            dangling_links: self
+                .dangling_links
+                .iter()
+                .map(|(post, links)| (url_path(post), links.clone()))
+                .collect(),
+            invalid_frontmatter: self
+                .invalid_frontmatter
+                .iter()
+                .map(|(path, problems)| (url_path(path), problems.clone()))
+                .collect(),
+            updated: self
+                .previous
+                .iter()
+                .flat_map(|previous| previous.updated.iter().map(|path| url_path(path)))
+                .collect(),
+            unchanged: self
+                .previous
+                .as_ref()
+                .map_or(0, |previous| previous.unchanged),
+            warnings,
+            ..Summary::default()
+        }
+    }
+}
+
+/// list the extracted images which nothing links to
+fn log_orphans(orphans: &[&str], options: &ExtractOptions) {
+    if orphans.is_empty() {
+        return;
+    }
+    log::info!("{} extracted images are not linked to:", orphans.len());
+    for orphan in orphans {
+        log::info!("  {}/{}", options.images_url(), orphan);
+    }
+}
+
+/// the taxonomies which Zola's `config.toml` must declare for the extracted posts
+///
+/// Zola refuses to build a site whose content uses an undeclared taxonomy, so this snippet should
+/// be merged into the site's configuration. For Hugo, it declares them in Hugo's form instead, so
+/// that those other than its default `tags` and `categories` are recognized. Jekyll needs no
+/// declaration, so for it this is empty.
+pub fn taxonomies_config(options: &ExtractOptions) -> String {
+    if options.target == Target::Jekyll {
+        return String::new();
+    }
+    if options.target == Target::Hugo {
+        let mut config = String::from("[taxonomies]\n");
+        for name in options.taxonomies.names() {
+            // hugo keys each taxonomy by its singular name
+            let singular = match name {
+                "tags" => "tag",
+                "categories" => "category",
+                "authors" => "author",
+                name => name,
+            };
+            config.push_str(&format!("{} = {:?}\n", singular, name));
+        }
+        return config;
+    }
+    let mut config = String::from("taxonomies = [\n");
+    for name in options.taxonomies.names() {
+        config.push_str(&format!("    {{ name = {:?} }},\n", name));
+    }
+    config.push_str("]\n");
+    config
+}
+
+/// the path of a post relative to the extract path
+///
+/// Pages are placed in the pages directory; posts, in the date tree. Drafts are placed in the
+/// drafts directory when they are [separate][DraftPolicy::Separate]. Translations are named by
+/// their language, i.e. `slug.fr.md`; see [`translation`]. For Jekyll, posts are instead
+/// named by date in `_posts`, or placed in `_drafts` if they're drafts or undated.
+pub(crate) fn output_path(post: &Post, options: &ExtractOptions) -> PathBuf {
+    let path = untranslated_path(post, options);
+    match translation(post, options) {
+        Some(language) => {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            path.with_file_name(format!("{}.{}.md", stem, language))
+        }
+        None => path,
+    }
+}
+
+/// the site-relative url at which the target serves a post, i.e. `/blog/2020/01/02/hello/`
+///
+/// Translations are served beneath their language, i.e. `/fr/blog/...`. `None` for Jekyll, which
+/// serves posts according to its `permalink` setting.
+pub(crate) fn post_url(post: &Post, options: &ExtractOptions) -> Option<String> {
+    if options.target == Target::Jekyll {
+        return None;
+    }
+    let mut path = untranslated_path(post, options).with_extension("");
+    if path.file_name().is_some_and(|name| name == "index") {
+        path.pop();
+    }
+    let root = match translation(post, options) {
+        Some(language) => format!("/{}{}", language, options.target.content_url()),
+        None => options.target.content_url(),
+    };
+    Some(format!("{}/", join_url(root, &path)))
+}
+
+/// the language into which a post is translated, if it isn't in the default language
+///
+/// Only Ghost's primary language subtag is kept, i.e. `fr` of `fr_CA`, as is usual for Zola's
+/// and Hugo's language codes. Jekyll has no translations.
+fn translation(post: &Post, options: &ExtractOptions) -> Option<String> {
+    let default_language = options.default_language.as_deref()?;
+    if options.target == Target::Jekyll {
+        return None;
+    }
+    let language = language_code(&post.extra.language);
+    (!language.is_empty() && language != language_code(default_language)).then_some(language)
+}
+
+/// the primary subtag of a language tag, i.e. `en` of `en_US` or `en-US`
+fn language_code(language: &str) -> String {
+    language
+        .split(['_', '-'])
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase()
+}
+
+/// the path of a post in the default language
+fn untranslated_path(post: &Post, options: &ExtractOptions) -> PathBuf {
+    if options.drafts == DraftPolicy::Separate
+        && post.status.draft()
+        && options.target != Target::Jekyll
+    {
+        let name = PathBuf::from(format!(
+            "{}.md",
+            post.file_stem(&options.slugs, options.path_sanitization)
+        ));
+        return bundle_path(options.drafts_dir.join(name), options);
+    }
+    if post.page {
+        return bundle_path(
+            options
+                .pages_dir
+                .join(post.relative_path(&options.slugs, options.path_sanitization)),
+            options,
+        );
+    }
+    match (options.target, post.date) {
+        (Target::Jekyll, Some(date)) if !post.status.draft() => {
+            PathBuf::from("_posts").join(format!(
+                "{}-{}.md",
+                date.format("%Y-%m-%d"),
+                post.file_stem(&options.slugs, options.path_sanitization)
+            ))
+        }
+        (Target::Jekyll, _) => PathBuf::from("_drafts").join(format!(
+            "{}.md",
+            post.file_stem(&options.slugs, options.path_sanitization)
+        )),
+        _ => bundle_path(
+            post.relative_path(&options.slugs, options.path_sanitization),
+            options,
+        ),
+    }
+}
+
+/// with page bundles, a post's `slug.md` becomes `slug/index.md`
+fn bundle_path(path: PathBuf, options: &ExtractOptions) -> PathBuf {
+    if options.page_bundles && options.target != Target::Jekyll {
+        path.with_extension("").join("index.md")
+    } else {
+        path
+    }
+}
+
+/// The images to move into each post's page bundle.
+///
+/// Links to the images are rewritten as each post is written, but the images can only be moved
+/// once every post is written, as several posts may use the same image.
+#[derive(Debug, Default)]
+pub(crate) struct Bundles {
+    /// each image's path relative to the images directory, and its path in the bundle relative
+    /// to the extract path
+    images: Vec<(String, PathBuf)>,
+}
+
+impl Bundles {
+    /// colocate a post's images with it, if posts are written as page bundles
+    pub(crate) fn bundle(&mut self, post: &mut Post, options: &ExtractOptions) {
+        if !options.page_bundles || options.target == Target::Jekyll {
+            return;
+        }
+        let path = output_path(post, options);
+        let dir = path.parent().unwrap_or_else(|| Path::new("")).to_path_buf();
+        let components: Vec<_> = dir
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect();
+        let images_url = options.images_url();
+        let bundle_url = format!("{}/{}", options.target.content_url(), components.join("/"));
+        for (image, name) in post.bundle_images(&images_url, &bundle_url) {
+            self.images.push((image, dir.join(name)));
+        }
+    }
+
+    /// the paths in the bundles, relative to the extract path, to which an image, given relative
+    /// to the images directory, is moved
+    pub(crate) fn paths(&self, image: &str) -> Vec<&Path> {
+        self.images
+            .iter()
+            .filter(|(bundled, _)| bundled == image)
+            .map(|(_, path)| path.as_path())
+            .collect()
+    }
+
+    /// move the images into their bundles, returning the number moved
+    pub(crate) fn move_images(
+        self,
+        extract_path: &Path,
+        options: &ExtractOptions,
+    ) -> Result<usize, Error> {
+        let images_path = options.images_path(extract_path)?;
+        let mut moved = std::collections::BTreeSet::new();
+        for (image, bundled) in &self.images {
+            let from = images_path.join(image).absolutize()?.to_path_buf();
+            if !from.starts_with(&images_path) {
+                log::warn!(
+                    "image link attempted to move a file from outside the images directory: {}",
+                    image
+                );
+                continue;
+            }
+            if !from.is_file() {
+                log::warn!(
+                    "image {} not found for the bundle {}",
+                    image,
+                    bundled.parent().unwrap_or_else(|| Path::new("")).display()
+                );
+                continue;
+            }
+            let to = extract_path.join(bundled);
+            if claim_output(&to, options.conflict_policy())? {
+                std::fs::copy(&from, to)?;
+            }
+            moved.insert(from);
+        }
+        for image in &moved {
+            std::fs::remove_file(image)?;
+        }
+        Ok(moved.len())
+    }
+}
+
+/// the path of an author's or tag's section relative to its directory
+///
+/// Jekyll has no sections, so each is a page of its own instead.
+pub(crate) fn section_path(relative_path: PathBuf, options: &ExtractOptions) -> PathBuf {
+    match options.target {
+        Target::Jekyll => relative_path.with_file_name("index.md"),
+        Target::Zola | Target::Hugo => relative_path,
+    }
+}
+
+/// render a post with the options' renderer, or else for the target site generator
+pub(crate) fn render_post<W: Write>(
+    post: &Post,
+    writer: &mut W,
+    options: &ExtractOptions,
+) -> Result<(), Error> {
+    match &options.renderer {
+        Some(renderer) => renderer.render(post, writer, options),
+        None => DefaultRenderer.render(post, writer, options),
+    }
+}
+
+/// render an author's section for the target site generator
+pub(crate) fn render_author<W: Write>(
+    author: &Author,
+    writer: &mut W,
+    options: &ExtractOptions,
+) -> Result<(), Error> {
+    match options.target {
+        Target::Zola => author.render_to(writer, options.frontmatter),
+        Target::Hugo => author.render_hugo_to(writer, options.frontmatter),
+        Target::Jekyll => author.render_jekyll_to(writer),
+    }
+}
+
+/// render a tag's section for the target site generator
+pub(crate) fn render_tag<W: Write>(
+    tag: &Tag,
+    writer: &mut W,
+    options: &ExtractOptions,
+) -> Result<(), Error> {
+    match options.target {
+        Target::Zola => tag.render_to(writer, options.frontmatter),
+        Target::Hugo => tag.render_hugo_to(writer, options.frontmatter),
+        Target::Jekyll => tag.render_jekyll_to(writer),
+    }
+}
+
+/// render a post into its file beneath the extract path
+pub(crate) fn write_post(
+    post: &Post,
+    extract_path: &Path,
+    options: &ExtractOptions,
+) -> Result<(), Error> {
+    let relative_path = output_path(post, options);
+    write_document(&extract_path.join(&relative_path), options, |writer| {
+        render_post(post, writer, options)
+    })?;
+    log::trace!("generated {}", relative_path.display());
+    Ok(())
+}
+
+/// create or replace a document, and any directories containing it
+fn write_document<F>(path: &Path, options: &ExtractOptions, render: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut std::io::BufWriter<std::fs::File>) -> Result<(), Error>,
+{
+    if !claim_output(path, options.conflict_policy())? {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?;
+    render(&mut std::io::BufWriter::new(file))
+}
+
+/// whether to write a file which may already exist, according to the conflict policy
+///
+/// An existing file is first renamed beside itself, i.e. to `hello.md.bak`, if the policy says to
+/// back it up.
+pub(crate) fn claim_output(path: &Path, policy: ConflictPolicy) -> Result<bool, Error> {
+    if !path.exists() {
+        return Ok(true);
+    }
+    match policy {
+        ConflictPolicy::Overwrite => Ok(true),
+        ConflictPolicy::Skip => {
+            log::debug!("leaving existing file alone: {}", path.display());
+            Ok(false)
+        }
+        ConflictPolicy::Backup => {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let mut backup = path.with_file_name(format!("{}.bak", name));
+            let mut n = 1;
+            while backup.exists() {
+                n += 1;
+                backup = path.with_file_name(format!("{}.bak{}", name, n));
+            }
+            std::fs::rename(path, &backup)?;
+            log::debug!("backed up existing file: {}", backup.display());
+            Ok(true)
+        }
+        ConflictPolicy::Error => Err(Error::OutputExists(path.to_path_buf())),
+    }
+}
+
+const AUTHORS_INDEX_DATA: &[u8] = include_bytes!("../templates/authors._index.md");
+const TAGS_INDEX_DATA: &[u8] = include_bytes!("../templates/tags._index.md");
+
+/// render a section per author into the authors directory
+pub(crate) fn write_authors(
+    authors: &[Author],
+    authors_dir: &Path,
+    options: &ExtractOptions,
+) -> Result<(), Error> {
+    for author in authors {
+        let path = authors_dir.join(section_path(
+            author.relative_path(options.path_sanitization),
+            options,
+        ));
+        write_document(&path, options, |writer| {
+            render_author(author, writer, options)
+        })?;
+    }
+    ensure_index(authors_dir, AUTHORS_INDEX_DATA, options)
+}
+
+/// render a section per public tag into the tags directory
+pub(crate) fn write_tags(
+    tags: &[Tag],
+    tags_dir: &Path,
+    options: &ExtractOptions,
+) -> Result<(), Error> {
+    for tag in tags.iter().filter(|tag| !tag.is_internal()) {
+        let path = tags_dir.join(section_path(
+            tag.relative_path(options.path_sanitization),
+            options,
+        ));
+        write_document(&path, options, |writer| render_tag(tag, writer, options))?;
+    }
+    ensure_index(tags_dir, TAGS_INDEX_DATA, options)
+}
+
+/// write a section's index, unless the target has no sections
+fn ensure_index(dir: &Path, data: &[u8], options: &ExtractOptions) -> Result<(), Error> {
+    let index = dir.join("_index.md");
+    if options.indices
+        && options.target != Target::Jekyll
+        && dir.is_dir()
+        && claim_output(&index, options.index_conflict_policy())?
+    {
+        std::fs::write(index, data)?;
+    }
+    Ok(())
+}
+
+const PAGES_INDEX_DATA: &[u8] = include_bytes!("../templates/pages._index.md");
+
+pub(crate) fn ensure_indices(extract_path: &Path, options: &ExtractOptions) -> Result<u32, Error> {
     let mut n = 0;
 
-    let index = extract_path.join("_index.md");
-    if !index.exists() {
-        let mut file = std::fs::OpenOptions::new()
-            .write(true)
-            .create(true)
-            .open(index)?;
-        file.write_all(ROOT_INDEX_DATA)?;
-        n += 1;
+    // the indices configure zola's sections; hugo lists the pages beneath a section without them,
+    // and jekyll has no sections at all
+    if !options.indices || options.target != Target::Zola {
+        return Ok(n);
     }
+    let branch_index = options.branch_index.as_ref().map(String::as_bytes);
+
+    // each section in which there are translations needs an index in each of their languages
+    let mut languages = BTreeSet::new();
+    if options.default_language.is_some() {
+        find_languages(extract_path, &mut languages)?;
+    }
+
+    // the pages and drafts sections are not part of the date tree, so they get their own indices
+    let mut flat_dirs = vec![&options.pages_dir];
+    if options.drafts == DraftPolicy::Separate {
+        flat_dirs.push(&options.drafts_dir);
+    }
+    // neither they nor images extracted into their own directory are part of the date tree
+    let mut skip = vec![options.images_path(extract_path)?];
+    for dir in flat_dirs {
+        let dir = extract_path.join(dir);
+        if dir.is_dir() {
+            n += write_index(
+                &dir,
+                PAGES_INDEX_DATA,
+                &languages,
+                options.index_conflict_policy(),
+            )?;
+        }
+        skip.push(dir.absolutize()?.to_path_buf());
+    }
+
+    let root_index = match &options.root_index {
+        Some(data) => data.clone(),
+        None => section_index_data(None, &options.root_section),
+    };
+    n += write_index(
+        extract_path,
+        root_index.as_bytes(),
+        &languages,
+        options.index_conflict_policy(),
+    )?;
 
     for subdir in extract_path.read_dir()?.filter(|maybe_dir_entry| {
         maybe_dir_entry
@@ -229,23 +1787,47 @@ fn ensure_indices(extract_path: &Path) -> Result<u32, Error> {
             }
         };
 
-        n += ensure_indices_recursive(&subdir.path())?;
+        n += ensure_indices_recursive(
+            extract_path,
+            &subdir.path(),
+            branch_index,
+            &options.branch_section,
+            &languages,
+            &skip,
+            options.index_conflict_policy(),
+        )?;
     }
 
     /// Recursive mode on!
-    fn ensure_indices_recursive(path: &Path) -> Result<u32, Error> {
+    fn ensure_indices_recursive(
+        extract_path: &Path,
+        path: &Path,
+        data: Option<&[u8]>,
+        section: &SectionIndex,
+        languages: &BTreeSet<String>,
+        skip: &[PathBuf],
+        on_conflict: ConflictPolicy,
+    ) -> Result<u32, Error> {
         let mut n = 0;
 
-        let index = path.join("_index.md");
-        if !index.exists() {
-            let mut file = std::fs::OpenOptions::new()
-                .write(true)
-                .create(true)
-                .open(index)?;
-            file.write_all(BRANCH_INDEX_DATA)?;
-            n += 1;
+        // a page bundle is a page, not a section
+        if is_bundle(path)? || skip.contains(&path.absolutize()?.to_path_buf()) {
+            return Ok(n);
         }
 
+        n += match data {
+            Some(data) => write_index(path, data, languages, on_conflict)?,
+            None => {
+                let relative = path.strip_prefix(extract_path).unwrap_or(path);
+                write_index(
+                    path,
+                    branch_index_data(relative, section).as_bytes(),
+                    languages,
+                    on_conflict,
+                )?
+            }
+        };
+
         for subdir in path.read_dir()?.filter(|maybe_dir_entry| {
             maybe_dir_entry
                 .as_ref()
@@ -269,7 +1851,15 @@ fn ensure_indices(extract_path: &Path) -> Result<u32, Error> {
                 }
             };
 
-            n += ensure_indices_recursive(&subdir.path())?;
+            n += ensure_indices_recursive(
+                extract_path,
+                &subdir.path(),
+                data,
+                section,
+                languages,
+                skip,
+                on_conflict,
+            )?;
         }
 
         Ok(n)
@@ -277,3 +1867,810 @@ fn ensure_indices(extract_path: &Path) -> Result<u32, Error> {
 
     Ok(n)
 }
+
+/// write a section's index, and its index in each language, returning the number written
+fn write_index(
+    dir: &Path,
+    data: &[u8],
+    languages: &BTreeSet<String>,
+    on_conflict: ConflictPolicy,
+) -> Result<u32, Error> {
+    let mut n = 0;
+    let names = std::iter::once("_index.md".to_string()).chain(
+        languages
+            .iter()
+            .map(|language| format!("_index.{}.md", language)),
+    );
+    for name in names {
+        let index = dir.join(name);
+        if claim_output(&index, on_conflict)? {
+            let mut file = std::fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(index)?;
+            file.write_all(data)?;
+            n += 1;
+        }
+    }
+    Ok(n)
+}
+
+/// whether a directory is a page bundle, i.e. contains `index.md` or a translation of it
+fn is_bundle(dir: &Path) -> Result<bool, Error> {
+    for entry in dir.read_dir()? {
+        let name = entry?.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with("index.") && name.ends_with(".md") {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// collect the languages of the translations beneath a directory, i.e. `fr` of `hello.fr.md`
+fn find_languages(dir: &Path, languages: &mut BTreeSet<String>) -> Result<(), Error> {
+    for entry in dir.read_dir()? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_languages(&path, languages)?;
+        } else if path.extension().is_some_and(|extension| extension == "md") {
+            if let Some(language) = path
+                .file_stem()
+                .map(Path::new)
+                .and_then(Path::extension)
+                .and_then(|language| language.to_str())
+            {
+                languages.insert(language.to_string());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// the default index of a branch of the date tree, i.e. `2020/01`, titled by its date
+///
+/// Branches outside the date tree, such as `undated`, are left untitled.
+fn branch_index_data(relative: &Path, section: &SectionIndex) -> String {
+    let parts: Vec<_> = relative
+        .iter()
+        .map(|part| part.to_str().and_then(|part| part.parse::<u32>().ok()))
+        .collect();
+    let title = match parts[..] {
+        [Some(year)] => Some(year.to_string()),
+        [Some(year), Some(month)] => chrono::NaiveDate::from_ymd_opt(year as i32, month, 1)
+            .map(|date| date.format("%B %Y").to_string()),
+        [Some(year), Some(month), Some(day)] => {
+            chrono::NaiveDate::from_ymd_opt(year as i32, month, day)
+                .map(|date| date.format("%B %-d, %Y").to_string())
+        }
+        _ => None,
+    };
+
+    section_index_data(title, section)
+}
+
+/// the default index of a section, configured by its [`SectionIndex`]
+fn section_index_data(title: Option<String>, section: &SectionIndex) -> String {
+    let mut data = String::from("+++\n");
+    if let Some(title) = title {
+        data.push_str(&format!("title = {}\n", toml::Value::String(title)));
+    }
+    data.push_str(&format!("sort_by = \"{}\"\n", section.sort_by.as_str()));
+    if let Some(paginate_by) = section.paginate_by {
+        data.push_str(&format!("paginate_by = {}\n", paginate_by));
+    }
+    if section.transparent {
+        data.push_str("transparent = true\n");
+    }
+    data.push_str("+++\n");
+    data
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::data_model::{Extra, Taxonomies};
+    use crate::progress::ProgressCallback;
+    use crate::{ImagesDest, KeyMapping, SortBy, TaxonomyMapping};
+    use chrono::{TimeZone, Utc};
+    use std::sync::{Arc, Mutex};
+
+    fn undated_post(created: Option<chrono::DateTime<Utc>>) -> Post {
+        Post {
+            title: "Lost in Time".into(),
+            content: String::new(),
+            description: String::new(),
+            date: None,
+            updated: None,
+            aliases: Vec::new(),
+            created,
+            status: Status::Published,
+            slug: "lost-in-time".into(),
+            page: false,
+            keys: KeyMapping::default(),
+            extra: Extra {
+                id: 5.into(),
+                ..Extra::default()
+            },
+            taxonomies: Taxonomies::default(),
+        }
+    }
+
+    #[test]
+    fn resolves_undated_posts() {
+        let created = Utc.with_ymd_and_hms(2020, 1, 2, 3, 4, 5).unwrap();
+
+        let mut post = undated_post(Some(created));
+        assert!(resolve_undated(&mut post, UndatedPolicy::CreatedAt).unwrap());
+        assert_eq!(post.date, Some(created));
+        assert!(post.status.published());
+
+        let mut post = undated_post(None);
+        assert!(resolve_undated(&mut post, UndatedPolicy::CreatedAt).unwrap());
+        assert!(post.status.draft());
+
+        let mut post = undated_post(Some(created));
+        assert!(resolve_undated(&mut post, UndatedPolicy::Keep).unwrap());
+        assert_eq!(post.date, None);
+        assert!(post.status.published());
+
+        let mut post = undated_post(Some(created));
+        assert!(resolve_undated(&mut post, UndatedPolicy::Draft).unwrap());
+        assert_eq!(post.date, None);
+        assert!(post.status.draft());
+
+        let mut post = undated_post(Some(created));
+        assert!(matches!(
+            resolve_undated(&mut post, UndatedPolicy::Error),
+            Err(Error::UndatedPost { .. })
+        ));
+    }
+
+    #[test]
+    fn ignores_dated_and_draft_posts() {
+        let mut post = undated_post(None);
+        post.status = Status::Draft;
+        assert!(!resolve_undated(&mut post, UndatedPolicy::Error).unwrap());
+
+        let mut post = undated_post(None);
+        post.date = Some(Utc::now());
+        assert!(!resolve_undated(&mut post, UndatedPolicy::Error).unwrap());
+    }
+
+    #[test]
+    fn counts_non_public_posts() {
+        let mut report = Report::default();
+        for visibility in &["public", "paid", "members", "paid"] {
+            let mut post = undated_post(None);
+            post.extra.visibility = Some(visibility.to_string());
+            report
+                .process(&mut post, &ExtractOptions::default())
+                .unwrap();
+        }
+        assert_eq!(report.non_public.get("paid"), Some(&2));
+        assert_eq!(report.non_public.get("members"), Some(&1));
+        assert_eq!(report.non_public.get("public"), None);
+    }
+
+    #[test]
+    fn classifies_by_author() {
+        let mut options = ExtractOptions::default();
+        options.taxonomies.authors = Some("authors".into());
+        let mut post = undated_post(None);
+        post.extra.authors = vec!["Ann".into(), "Pete".into()];
+        Report::default().process(&mut post, &options).unwrap();
+        assert_eq!(post.taxonomies.authors, vec!["Ann", "Pete"]);
+        assert!(post
+            .to_string()
+            .contains("[taxonomies]\ntags = []\nauthors = [\"Ann\", \"Pete\"]\n"));
+        assert_eq!(
+            taxonomies_config(&options),
+            "taxonomies = [\n    { name = \"tags\" },\n    { name = \"authors\" },\n]\n"
+        );
+    }
+
+    #[test]
+    fn separates_internal_tags() {
+        let mut post = undated_post(None);
+        post.taxonomies.tags = vec!["rust".into(), "#hidden".into()];
+        Report::default()
+            .process(&mut post, &ExtractOptions::default())
+            .unwrap();
+        assert_eq!(post.taxonomies.tags, vec!["rust"]);
+        assert!(post.extra.internal_tags.is_empty());
+
+        let options = ExtractOptions {
+            keep_internal_tags: true,
+            ..ExtractOptions::default()
+        };
+        let mut post = undated_post(None);
+        post.taxonomies.tags = vec!["rust".into(), "#hidden".into()];
+        Report::default().process(&mut post, &options).unwrap();
+        assert_eq!(post.taxonomies.tags, vec!["rust"]);
+        assert_eq!(post.extra.internal_tags, vec!["#hidden"]);
+    }
+
+    #[test]
+    fn classifies_by_primary_tag() {
+        let mut options = ExtractOptions::default();
+        options.taxonomies.categories = Some("categories".into());
+        let mut post = undated_post(None);
+        post.taxonomies.tags = vec!["#hidden".into(), "rust".into(), "zola".into()];
+        Report::default().process(&mut post, &options).unwrap();
+        assert_eq!(post.taxonomies.categories, vec!["rust"]);
+
+        let mut post = undated_post(None);
+        Report::default().process(&mut post, &options).unwrap();
+        assert!(post.taxonomies.categories.is_empty());
+    }
+
+    #[test]
+    fn maps_taxonomies() {
+        let options = ExtractOptions {
+            taxonomies: TaxonomyMapping {
+                tags: Some("topics".into()),
+                categories: Some("topics".into()),
+                authors: None,
+                slugify: true,
+            },
+            ..ExtractOptions::default()
+        };
+        let mut post = undated_post(None);
+        post.taxonomies.tags = vec!["Rust Tips".into(), "zola".into()];
+        post.extra.authors = vec!["Ann".into()];
+        Report::default().process(&mut post, &options).unwrap();
+        assert!(post
+            .to_string()
+            .contains("[taxonomies]\ntopics = [\"rust-tips\", \"zola\"]\n\n+++"));
+        assert_eq!(
+            taxonomies_config(&options),
+            "taxonomies = [\n    { name = \"topics\" },\n]\n"
+        );
+        let options = ExtractOptions {
+            target: Target::Hugo,
+            ..options
+        };
+        assert_eq!(
+            taxonomies_config(&options),
+            "[taxonomies]\ntopics = \"topics\"\n"
+        );
+    }
+
+    #[test]
+    fn writes_custom_indices() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("2020/01")).unwrap();
+        let options = ExtractOptions {
+            root_index: Some("+++\ntitle = \"Blog\"\n+++\n".into()),
+            ..ExtractOptions::default()
+        };
+        assert_eq!(ensure_indices(dir.path(), &options).unwrap(), 3);
+        let root = std::fs::read_to_string(dir.path().join("_index.md")).unwrap();
+        assert_eq!(root, "+++\ntitle = \"Blog\"\n+++\n");
+        let branch = std::fs::read_to_string(dir.path().join("2020/01/_index.md")).unwrap();
+        assert_eq!(
+            branch,
+            branch_index_data(Path::new("2020/01"), &SectionIndex::branch())
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("2020/01")).unwrap();
+        let options = ExtractOptions {
+            indices: false,
+            ..ExtractOptions::default()
+        };
+        assert_eq!(ensure_indices(dir.path(), &options).unwrap(), 0);
+        assert!(!dir.path().join("_index.md").exists());
+    }
+
+    #[test]
+    fn keeps_existing_indices() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = ghost_archive(dir.path());
+        let extract_path = dir.path().join("site/content/blog");
+        std::fs::create_dir_all(&extract_path).unwrap();
+        let index = extract_path.join("_index.md");
+        std::fs::write(&index, "+++\ntitle = \"Mine\"\n+++\n").unwrap();
+
+        extract_archive(&archive_path, &extract_path, &ExtractOptions::default()).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&index).unwrap(),
+            "+++\ntitle = \"Mine\"\n+++\n"
+        );
+        // the other indices are still written where there were none
+        assert!(extract_path.join("2020/_index.md").exists());
+
+        let options = ExtractOptions {
+            on_conflict: Some(ConflictPolicy::Overwrite),
+            ..ExtractOptions::default()
+        };
+        extract_archive(&archive_path, &extract_path, &options).unwrap();
+        assert_ne!(
+            std::fs::read_to_string(&index).unwrap(),
+            "+++\ntitle = \"Mine\"\n+++\n"
+        );
+    }
+
+    #[test]
+    fn titles_branch_indices() {
+        assert_eq!(
+            branch_index_data(Path::new("2020"), &SectionIndex::branch()),
+            "+++\ntitle = \"2020\"\nsort_by = \"date\"\ntransparent = true\n+++\n"
+        );
+        assert!(
+            branch_index_data(Path::new("2020/01"), &SectionIndex::branch())
+                .contains("title = \"January 2020\"\n")
+        );
+        assert!(
+            branch_index_data(Path::new("2020/01/02"), &SectionIndex::branch())
+                .contains("title = \"January 2, 2020\"\n")
+        );
+        assert!(
+            !branch_index_data(Path::new("undated"), &SectionIndex::branch()).contains("title")
+        );
+    }
+
+    #[test]
+    fn configures_section_indices() {
+        assert_eq!(
+            section_index_data(None, &SectionIndex::root()),
+            "+++\nsort_by = \"date\"\npaginate_by = 10\n+++\n"
+        );
+        let section = SectionIndex {
+            sort_by: SortBy::Weight,
+            paginate_by: Some(5),
+            transparent: false,
+        };
+        assert_eq!(
+            branch_index_data(Path::new("2020"), &section),
+            "+++\ntitle = \"2020\"\nsort_by = \"weight\"\npaginate_by = 5\n+++\n"
+        );
+    }
+
+    #[test]
+    fn writes_indices_per_language() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("2020/01/hello")).unwrap();
+        std::fs::write(dir.path().join("2020/01/hello/index.fr.md"), "").unwrap();
+        let options = ExtractOptions {
+            default_language: Some("en".into()),
+            ..ExtractOptions::default()
+        };
+        assert_eq!(ensure_indices(dir.path(), &options).unwrap(), 6);
+        assert!(dir.path().join("_index.fr.md").exists());
+        assert!(dir.path().join("2020/01/_index.fr.md").exists());
+        assert!(!dir.path().join("2020/01/hello/_index.md").exists());
+    }
+
+    #[test]
+    fn disambiguates_colliding_paths() {
+        let options = ExtractOptions::default();
+        let mut report = Report::default();
+        let mut first = undated_post(None);
+        let mut second = undated_post(None);
+        assert_eq!(
+            report.claim_path(&mut first, &options),
+            PathBuf::from("undated/lost-in-time.md")
+        );
+        assert_eq!(
+            report.claim_path(&mut second, &options),
+            PathBuf::from("undated/lost-in-time-2.md")
+        );
+        assert_eq!(first.slug, "lost-in-time");
+        assert_eq!(second.slug, "lost-in-time-2");
+        assert_eq!(report.collisions.len(), 1);
+    }
+
+    #[test]
+    fn follows_images_dest() {
+        let mut options = ExtractOptions::default();
+        assert_eq!(options.extracted_images_dir(), PathBuf::from(""));
+        assert_eq!(options.images_url(), "/blog");
+
+        options.images_dir = Some("media".into());
+        assert_eq!(options.images_url(), "/blog/media");
+        options.images_dir = Some("../media".into());
+        assert_eq!(options.images_url(), "/media");
+
+        options.images_dest = ImagesDest::Static;
+        options.images_dir = None;
+        assert_eq!(
+            options.extracted_images_dir(),
+            PathBuf::from("../../static/blog")
+        );
+        assert_eq!(options.images_url(), "/blog");
+        options.images_dir = Some("images/blog".into());
+        assert_eq!(options.images_url(), "/images/blog");
+
+        options.target = Target::Jekyll;
+        options.images_dir = None;
+        assert_eq!(
+            options.extracted_images_dir(),
+            PathBuf::from("assets/images")
+        );
+        assert_eq!(options.images_url(), "/assets/images");
+    }
+
+    #[test]
+    fn reports_missing_images() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("2020/01")).unwrap();
+        std::fs::write(dir.path().join("2020/01/a.png"), "").unwrap();
+        let mut post = Post {
+            content: "![a](/blog/2020/01/a.png) ![b](/blog/2020/01/b.png?w=600) \
+                      [c](/blog/2020/01/02/c.md)"
+                .into(),
+            slug: "hello".into(),
+            ..Post::default()
+        };
+        post.extra.feature_image = Some("/blog/2020/01/hero.jpg".into());
+
+        let mut report = Report::default();
+        report.check_images(&mut post, dir.path(), &ExtractOptions::default());
+        let missing: Vec<_> = report.missing_images.keys().map(String::as_str).collect();
+        assert_eq!(missing, ["/blog/2020/01/b.png", "/blog/2020/01/hero.jpg"]);
+        assert!(report.missing_images["/blog/2020/01/b.png"].contains("hello"));
+
+        let mut report = Report::default();
+        let options = ExtractOptions {
+            extract_images: false,
+            ..ExtractOptions::default()
+        };
+        report.check_images(&mut post, dir.path(), &options);
+        assert!(report.missing_images.is_empty());
+    }
+
+    #[test]
+    fn notes_skipped_posts_and_posts_without_markdown() {
+        let options = ExtractOptions {
+            drafts: DraftPolicy::Skip,
+            ..ExtractOptions::default()
+        };
+        let post = |id: i64, slug: &str, content: &str| {
+            let mut post = Post {
+                title: slug.to_uppercase(),
+                slug: slug.into(),
+                content: content.into(),
+                status: Status::Published,
+                ..Post::default()
+            };
+            post.extra.id = id.into();
+            post
+        };
+        let mut draft = post(1, "draft", "");
+        draft.status = Status::Draft;
+        let mut plain = post(2, "plain", "only plain");
+        plain.extra.plaintext_fallback = true;
+
+        let mut report = Report::default();
+        assert!(report.skip(&draft, &options));
+        assert!(!report.skip(&plain, &options));
+        assert!(!report.skip(&post(3, "empty", " \n"), &options));
+        assert!(!report.skip(&post(4, "fine", "hello"), &options));
+
+        let summary = report.summary(&options);
+        assert_eq!(summary.skipped.len(), 1);
+        assert_eq!(
+            summary.skipped[0].to_string(),
+            "1 (draft) \"DRAFT\": a draft"
+        );
+        let reasons: Vec<_> = summary
+            .without_markdown
+            .iter()
+            .map(|post| (post.slug.as_str(), post.reason))
+            .collect();
+        assert_eq!(
+            reasons,
+            [("plain", Reason::Plaintext), ("empty", Reason::Empty)]
+        );
+
+        assert!(report.check_strict(&options).is_ok());
+        let strict = ExtractOptions {
+            strict: true,
+            ..options
+        };
+        // the skipped draft was skipped by choice
+        assert!(matches!(
+            report.check_strict(&strict),
+            Err(Error::Incomplete(2))
+        ));
+        assert!(Report::default().check_strict(&strict).is_ok());
+        let dangling = Report {
+            dangling_links: vec![(
+                PathBuf::from("2020/01/02/hello.md"),
+                vec!["/blog/a/".into(), "/blog/b/".into()],
+            )],
+            ..Report::default()
+        };
+        assert!(matches!(
+            dangling.check_strict(&strict),
+            Err(Error::Incomplete(2))
+        ));
+    }
+
+    #[test]
+    fn finds_orphaned_images() {
+        let options = ExtractOptions::default();
+        let mut post = Post {
+            content: "![a](/blog/2020/01/a.png?w=600)".into(),
+            ..Post::default()
+        };
+        let mut profile_image = String::from("/blog/2020/01/me.jpg");
+
+        let mut report = Report::default();
+        report.check_images(&mut post, Path::new("/nonexistent"), &options);
+        report.link_images(std::iter::once(&mut profile_image), &options);
+        let extracted = [
+            "2020/01/a.png".to_string(),
+            "2020/01/me.jpg".to_string(),
+            "2020/01/unused.png".to_string(),
+            "size/w600/2020/01/a.png".to_string(),
+        ];
+        assert_eq!(
+            report.orphans(&extracted),
+            ["2020/01/unused.png", "size/w600/2020/01/a.png"]
+        );
+    }
+
+    #[test]
+    fn resolves_conflicts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hello.md");
+        assert!(claim_output(&path, ConflictPolicy::Error).unwrap());
+
+        std::fs::write(&path, "old").unwrap();
+        assert!(!claim_output(&path, ConflictPolicy::Skip).unwrap());
+        assert!(claim_output(&path, ConflictPolicy::Overwrite).unwrap());
+        assert!(matches!(
+            claim_output(&path, ConflictPolicy::Error),
+            Err(Error::OutputExists(_))
+        ));
+
+        assert!(claim_output(&path, ConflictPolicy::Backup).unwrap());
+        assert!(!path.exists());
+        std::fs::write(&path, "new").unwrap();
+        assert!(claim_output(&path, ConflictPolicy::Backup).unwrap());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("hello.md.bak")).unwrap(),
+            "old"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("hello.md.bak2")).unwrap(),
+            "new"
+        );
+    }
+
+    /// write an archive of a ghost blog with three posts, one a draft, and an image
+    pub(crate) fn ghost_archive(dir: &Path) -> PathBuf {
+        ghost_archive_with(dir, "")
+    }
+
+    /// as [`ghost_archive`], running some more sql on its database, i.e. to add posts
+    pub(crate) fn ghost_archive_with(dir: &Path, sql: &str) -> PathBuf {
+        let db_path = dir.join("ghost.db");
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch(
+                "
+                CREATE TABLE posts (
+                    id integer not null primary key,
+                    title varchar(150) not null,
+                    slug varchar(150) not null,
+                    markdown text null,
+                    status varchar(150) not null default 'draft',
+                    author_id integer not null,
+                    published_at datetime null
+                );
+                CREATE TABLE users (id integer not null primary key, name varchar(150) not null);
+                CREATE TABLE tags (id integer not null primary key, name varchar(150) not null);
+                CREATE TABLE posts_tags (post_id integer not null, tag_id integer not null);
+                INSERT INTO users (id, name) VALUES (1, 'Pete');
+                INSERT INTO posts (id, title, slug, markdown, status, author_id, published_at) VALUES
+                    (1, 'Hello', 'hello', 'Hi', 'published', 1, '2020-01-02 10:00:00'),
+                    (2, 'Again', 'hello', 'Hi', 'published', 1, '2020-01-02 11:00:00'),
+                    (3, 'Draft', 'draft', 'Hi', 'draft', 1, NULL);
+                ",
+            )
+            .unwrap();
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch(sql)
+            .unwrap();
+        let image_path = dir.join("a.png");
+        std::fs::write(&image_path, b"not really a png").unwrap();
+        let archive_path = dir.join("blog.tar");
+        let mut archive = tar::Builder::new(std::fs::File::create(&archive_path).unwrap());
+        archive
+            .append_path_with_name(&db_path, "ghost/content/data/ghost.db")
+            .unwrap();
+        archive
+            .append_path_with_name(&image_path, "ghost/content/images/2020/01/a.png")
+            .unwrap();
+        archive.finish().unwrap();
+        archive_path
+    }
+
+    #[test]
+    fn skips_orphaned_images() {
+        let options = |extract_path: &Path| ExtractOptions {
+            prune_orphans: true,
+            report: Some(crate::ReportFormat::Json),
+            report_path: Some(extract_path.join("report.json")),
+            ..ExtractOptions::default()
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = ghost_archive(dir.path());
+        let extract_path = dir.path().join("out");
+        std::fs::create_dir(&extract_path).unwrap();
+        extract_archive(&archive_path, &extract_path, &options(&extract_path)).unwrap();
+        assert!(!extract_path.join("2020/01/a.png").exists());
+        let report: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(extract_path.join("report.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(report["pruned_images"], 1);
+
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = ghost_archive_with(
+            dir.path(),
+            "UPDATE posts SET markdown = '![](/content/images/2020/01/a.png)' WHERE id = 3;",
+        );
+        let extract_path = dir.path().join("out");
+        std::fs::create_dir(&extract_path).unwrap();
+        extract_archive(&archive_path, &extract_path, &options(&extract_path)).unwrap();
+        assert!(extract_path.join("2020/01/a.png").is_file());
+    }
+
+    #[test]
+    fn extracts_posts_without_images() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = ghost_archive(dir.path());
+        let extract_path = dir.path().join("site/content/blog");
+        std::fs::create_dir_all(&extract_path).unwrap();
+        let options = ExtractOptions {
+            extract_images: false,
+            ..ExtractOptions::default()
+        };
+        extract_archive(&archive_path, &extract_path, &options).unwrap();
+        assert!(extract_path.join("2020/01/02/hello.md").is_file());
+        assert!(!extract_path.join("2020/01/a.png").exists());
+    }
+
+    #[test]
+    fn extracts_images_without_posts() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = ghost_archive(dir.path());
+        let extract_path = dir.path().join("site/content/blog");
+        std::fs::create_dir_all(&extract_path).unwrap();
+        let options = ExtractOptions {
+            extract_content: false,
+            ..ExtractOptions::default()
+        };
+        extract_archive(&archive_path, &extract_path, &options).unwrap();
+        assert!(extract_path.join("2020/01/a.png").is_file());
+        let entries: Vec<_> = std::fs::read_dir(extract_path.join("2020/01"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(entries, vec!["a.png"]);
+        assert!(!extract_path.join("_index.md").exists());
+    }
+
+    #[test]
+    fn reads_posts_in_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = ghost_archive(dir.path());
+        let options = ExtractOptions {
+            drafts: DraftPolicy::Skip,
+            ..ExtractOptions::default()
+        };
+        let posts: Vec<_> = posts_in_archive(&archive_path, &options)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let slugs: Vec<_> = posts.iter().map(|post| post.slug.as_str()).collect();
+        assert_eq!(slugs, vec!["hello", "hello-2"]);
+        assert_eq!(posts[0].extra.authors, vec!["Pete"]);
+    }
+
+    #[test]
+    fn reports_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = ghost_archive(dir.path());
+        let extract_path = dir.path().join("site/content/blog");
+        std::fs::create_dir_all(&extract_path).unwrap();
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let options = ExtractOptions {
+            drafts: DraftPolicy::Skip,
+            on_progress: Some(ProgressCallback::new({
+                let calls = calls.clone();
+                move |phase, done, total| calls.lock().unwrap().push((phase, done, total))
+            })),
+            ..ExtractOptions::default()
+        };
+        extract_archive(&archive_path, &extract_path, &options).unwrap();
+
+        let calls = calls.lock().unwrap();
+        let phase = |phase| -> Vec<_> {
+            calls
+                .iter()
+                .filter(|(called, _, _)| *called == phase)
+                .map(|(_, done, total)| (*done, *total))
+                .collect()
+        };
+        assert_eq!(
+            phase(Phase::Scanning),
+            vec![(0, None), (1, None), (2, Some(2))]
+        );
+        assert_eq!(phase(Phase::Extracting).last(), Some(&(2, Some(2))));
+        assert_eq!(phase(Phase::Images), vec![(1, None), (1, Some(1))]);
+        assert_eq!(
+            phase(Phase::Posts),
+            vec![(1, None), (2, None), (2, Some(2))]
+        );
+    }
+
+    #[test]
+    fn bundles_images_with_the_same_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = ExtractOptions {
+            page_bundles: true,
+            ..ExtractOptions::default()
+        };
+        let images_path = options.images_path(dir.path()).unwrap();
+        for month in &["01", "02"] {
+            let month_path = images_path.join("2020").join(month);
+            std::fs::create_dir_all(&month_path).unwrap();
+            std::fs::write(month_path.join("photo.jpg"), month).unwrap();
+        }
+        let images_url = options.images_url();
+        let mut post = Post {
+            slug: "hello".into(),
+            content: format!(
+                "![a]({0}/2020/01/photo.jpg) ![b]({0}/2020/02/photo.jpg)",
+                images_url
+            ),
+            ..Post::default()
+        };
+
+        let mut bundles = Bundles::default();
+        bundles.bundle(&mut post, &options);
+        write_post(&post, dir.path(), &options).unwrap();
+        assert_eq!(bundles.move_images(dir.path(), &options).unwrap(), 2);
+
+        let bundle = dir
+            .path()
+            .join(output_path(&post, &options).parent().unwrap());
+        assert_eq!(
+            std::fs::read_to_string(bundle.join("photo.jpg")).unwrap(),
+            "01"
+        );
+        assert_eq!(
+            std::fs::read_to_string(bundle.join("photo-2.jpg")).unwrap(),
+            "02"
+        );
+    }
+
+    #[test]
+    fn extracts_untitled_posts_idempotently() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = ghost_archive_with(
+            dir.path(),
+            "INSERT INTO posts (id, title, slug, markdown, status, author_id, published_at)
+                VALUES (4, '', '', 'Untitled', 'published', 1, '2020-01-03 10:00:00');",
+        );
+        let options = ExtractOptions {
+            drafts: DraftPolicy::Skip,
+            ..ExtractOptions::default()
+        };
+        crate::check_idempotence(&archive_path, &options).unwrap();
+
+        let extract_path = dir.path().join("site/content/blog");
+        std::fs::create_dir_all(&extract_path).unwrap();
+        extract_archive(&archive_path, &extract_path, &options).unwrap();
+        assert!(extract_path.join("2020/01/03/post-4.md").exists());
+    }
+}
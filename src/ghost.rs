@@ -0,0 +1,285 @@
+//! Types describing Ghost's JSON export format.
+//!
+//! Ghost's admin interface can export a blog's content as a single JSON document, which mirrors the
+//! tables of its database. These types deserialize the parts of it which are converted; unknown
+//! fields are ignored, and the fields which vary between Ghost versions accept each variant.
+
+use crate::data_model::PostId;
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Deserializer};
+
+/// The top level of a Ghost JSON export.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Top {
+    pub db: Vec<Db>,
+}
+
+/// A single exported database.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Db {
+    pub meta: Meta,
+    pub data: Data,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Meta {
+    /// version of Ghost which produced the export
+    pub version: String,
+}
+
+/// The exported tables.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Data {
+    #[serde(default)]
+    pub posts: Vec<Post>,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    #[serde(default)]
+    pub posts_tags: Vec<PostTag>,
+    #[serde(default)]
+    pub users: Vec<User>,
+    /// only exported from Ghost 2.0
+    #[serde(default)]
+    pub posts_authors: Vec<PostAuthor>,
+    /// only exported from Ghost 3.0
+    #[serde(default)]
+    pub posts_meta: Vec<PostMeta>,
+}
+
+/// A row of the `posts` table.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Post {
+    pub id: PostId,
+    #[serde(default)]
+    pub uuid: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    pub slug: String,
+    #[serde(default)]
+    pub markdown: Option<String>,
+    /// the editor's document, from Ghost 1.0
+    #[serde(default)]
+    pub mobiledoc: Option<String>,
+    /// the editor's document, from Ghost 5.0
+    #[serde(default)]
+    pub lexical: Option<String>,
+    #[serde(default)]
+    pub plaintext: Option<String>,
+    #[serde(default)]
+    pub html: Option<String>,
+    /// named `image` before Ghost 1.0
+    #[serde(default, alias = "image")]
+    pub feature_image: Option<String>,
+    #[serde(default, deserialize_with = "flag")]
+    pub featured: bool,
+    #[serde(default)]
+    pub visibility: Option<String>,
+    /// whether the post is a page, before Ghost 2.0
+    #[serde(default, deserialize_with = "flag")]
+    pub page: bool,
+    /// `post` or `page`, from Ghost 2.0
+    #[serde(default, rename = "type")]
+    pub post_type: Option<String>,
+    #[serde(default)]
+    pub canonical_url: Option<String>,
+    #[serde(default)]
+    pub codeinjection_head: Option<String>,
+    #[serde(default)]
+    pub codeinjection_foot: Option<String>,
+    #[serde(default = "draft")]
+    pub status: String,
+    /// named `locale` in some versions of Ghost
+    #[serde(default, alias = "locale")]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub meta_description: Option<String>,
+    #[serde(default)]
+    pub custom_excerpt: Option<String>,
+    #[serde(default)]
+    pub og_image: Option<String>,
+    #[serde(default)]
+    pub og_title: Option<String>,
+    #[serde(default)]
+    pub og_description: Option<String>,
+    #[serde(default)]
+    pub twitter_image: Option<String>,
+    #[serde(default)]
+    pub twitter_title: Option<String>,
+    #[serde(default)]
+    pub twitter_description: Option<String>,
+    #[serde(default)]
+    pub author_id: Option<PostId>,
+    #[serde(default, deserialize_with = "timestamp")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "timestamp")]
+    pub updated_at: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "timestamp")]
+    pub published_at: Option<DateTime<Utc>>,
+}
+
+impl Post {
+    /// whether this is a standalone page, rather than a post
+    pub fn is_page(&self) -> bool {
+        self.page || self.post_type.as_deref() == Some("page")
+    }
+}
+
+fn draft() -> String {
+    "draft".into()
+}
+
+/// A row of the `tags` table.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Tag {
+    pub id: PostId,
+    pub name: String,
+    #[serde(default)]
+    pub slug: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// named `image` before Ghost 1.0
+    #[serde(default, alias = "image")]
+    pub feature_image: Option<String>,
+}
+
+/// A row of the `posts_tags` table, associating a tag with a post.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PostTag {
+    pub post_id: PostId,
+    pub tag_id: PostId,
+    #[serde(default)]
+    pub sort_order: i64,
+}
+
+/// A row of the `posts_authors` table, associating an author with a post.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PostAuthor {
+    pub post_id: PostId,
+    pub author_id: PostId,
+    #[serde(default)]
+    pub sort_order: i64,
+}
+
+/// A row of the `posts_meta` table, to which Ghost 3.0 moved the metadata columns of `posts`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PostMeta {
+    pub post_id: PostId,
+    #[serde(default)]
+    pub meta_description: Option<String>,
+    #[serde(default)]
+    pub og_image: Option<String>,
+    #[serde(default)]
+    pub og_title: Option<String>,
+    #[serde(default)]
+    pub og_description: Option<String>,
+    #[serde(default)]
+    pub twitter_image: Option<String>,
+    #[serde(default)]
+    pub twitter_title: Option<String>,
+    #[serde(default)]
+    pub twitter_description: Option<String>,
+}
+
+/// A row of the `users` table.
+#[derive(Debug, Clone, Deserialize)]
+pub struct User {
+    pub id: PostId,
+    pub name: String,
+    #[serde(default)]
+    pub slug: String,
+    #[serde(default)]
+    pub bio: Option<String>,
+    #[serde(default)]
+    pub website: Option<String>,
+    #[serde(default)]
+    pub location: Option<String>,
+    #[serde(default)]
+    pub twitter: Option<String>,
+    #[serde(default)]
+    pub facebook: Option<String>,
+    /// named `image` before Ghost 1.0
+    #[serde(default, alias = "image")]
+    pub profile_image: Option<String>,
+    /// named `cover` before Ghost 1.0
+    #[serde(default, alias = "cover")]
+    pub cover_image: Option<String>,
+}
+
+/// deserialize a boolean, which exports from sqlite databases represent as `0` or `1`
+fn flag<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Flag {
+        Bool(bool),
+        Int(i64),
+    }
+
+    Ok(match Option::<Flag>::deserialize(deserializer)? {
+        Some(Flag::Bool(flag)) => flag,
+        Some(Flag::Int(flag)) => flag != 0,
+        None => false,
+    })
+}
+
+/// deserialize an optional timestamp
+///
+/// Exports have variously represented these as milliseconds since the epoch, RFC 3339 strings,
+/// and sqlite's `yyyy-mm-dd hh:mm:ss`.
+fn timestamp<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Timestamp {
+        Millis(i64),
+        Text(String),
+    }
+
+    let text = match Option::<Timestamp>::deserialize(deserializer)? {
+        None => return Ok(None),
+        Some(Timestamp::Millis(millis)) => {
+            return Utc
+                .timestamp_millis_opt(millis)
+                .single()
+                .map(Some)
+                .ok_or_else(|| serde::de::Error::custom("timestamp out of range"))
+        }
+        Some(Timestamp::Text(text)) => text,
+    };
+    DateTime::parse_from_rfc3339(&text)
+        .map(|datetime| datetime.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(&text, "%Y-%m-%d %H:%M:%S")
+                .map(|datetime| Utc.from_utc_datetime(&datetime))
+        })
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_timestamps_of_any_version() {
+        let posts: Vec<Post> = serde_json::from_str(
+            r#"[
+                {"id": 1, "slug": "a", "published_at": 1577959200000},
+                {"id": "5e0c", "slug": "b", "published_at": "2020-01-02T10:00:00.000Z"},
+                {"id": 3, "slug": "c", "published_at": "2020-01-02 10:00:00"},
+                {"id": 4, "slug": "d", "published_at": null}
+            ]"#,
+        )
+        .unwrap();
+        let expect = Utc.with_ymd_and_hms(2020, 1, 2, 10, 0, 0).unwrap();
+        assert_eq!(posts[0].published_at, Some(expect));
+        assert_eq!(posts[1].published_at, Some(expect));
+        assert_eq!(posts[2].published_at, Some(expect));
+        assert_eq!(posts[3].published_at, None);
+        assert_eq!(posts[1].id, PostId::Text("5e0c".into()));
+    }
+}
@@ -1,9 +1,43 @@
+pub mod api;
 pub mod data_model;
+pub mod ghost;
+pub mod logging;
+pub mod progress;
+pub mod query;
+pub mod render;
 
 mod archive;
+mod cards;
+mod convert;
+mod diff;
+mod editor;
+mod external;
 mod extract;
-pub use archive::{find_ghost_db, find_ghost_db_in, find_ghost_dbs, try_archive};
-pub use extract::extract_archive;
+mod manifest;
+mod options;
+mod stats;
+mod summary;
+mod transcode;
+mod verify;
+pub use archive::{
+    find_ghost_db, find_ghost_db_in, find_ghost_dbs, find_ghost_paths, find_ghost_paths_in,
+    try_archive, FileType, GhostPaths,
+};
+pub use convert::{
+    convert_ghost_authors, convert_ghost_json, convert_ghost_tags, ConvertedAuthor, ConvertedPost,
+    ConvertedTag,
+};
+pub use diff::{check_idempotence, diff_archive, diff_extractions, Difference};
+pub use extract::{extract_archive, posts_in_archive, taxonomies_config};
+pub use options::{
+    Classification, ConflictPolicy, DraftPolicy, ExtraAssignment, ExtractOptions,
+    FrontmatterFormat, FrontmatterTemplate, ImageFormat, ImageSource, ImagesDest, KeyAssignment,
+    KeyMapping, ManifestFormat, PathSanitization, PostTypes, RawHtmlPolicy, ReportFormat,
+    SectionIndex, ShortcodeAssignment, Shortcodes, SlugOptions, SortBy, Target, TaxonomyAssignment,
+    TaxonomyMapping, Transforms, UndatedPolicy, UnsplashPolicy,
+};
+pub use stats::{archive_stats, PostSize, Stats};
+pub use verify::{verify_extraction, Drift};
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -19,8 +53,77 @@ pub enum Error {
     StripPrefix(#[from] std::path::StripPrefixError),
     #[error("reading ghost database")]
     Sql(#[from] rusqlite::Error),
+    #[error("post {id} ({slug}): {stage}")]
+    Post {
+        id: data_model::PostId,
+        slug: String,
+        stage: query::Stage,
+        #[source]
+        source: rusqlite::Error,
+    },
     #[error("generating frontmatter toml")]
     Frontmatter(#[from] toml::ser::Error),
+    #[error("generating frontmatter yaml")]
+    FrontmatterYaml(#[from] serde_yaml::Error),
+    #[error("generating json")]
+    Json(#[from] serde_json::Error),
+    #[error("rendering frontmatter template")]
+    FrontmatterTemplate(#[from] tera::Error),
+    #[error("admin api key must have the form `id:secret`, where the secret is hexadecimal")]
+    InvalidAdminKey,
+    #[error("requesting ghost api")]
+    Api(#[from] Box<ureq::Error>),
+    #[error("image source must have the form `/url/prefix=archive/dir`: {0}")]
+    InvalidImageSource(String),
+    #[error("taxonomy must have the form `tags|categories|authors=name`: {0}")]
+    InvalidTaxonomy(String),
+    #[error("shortcode must have the form `kind=name`, for a known kind: {0}")]
+    InvalidShortcode(String),
+    #[error("frontmatter key mapping must have the form `key=renamed`, with dotted keys: {0}")]
+    InvalidKeyMapping(String),
+    #[error("extra frontmatter must have the form `key=value`: {0}")]
+    InvalidExtra(String),
+    #[error("post types must be one of `posts`, `pages`, or `all`: {0}")]
+    InvalidPostTypes(String),
+    #[error("undated policy must be one of `keep`, `draft`, `created-at`, or `error`: {0}")]
+    InvalidUndatedPolicy(String),
+    #[error("draft policy must be one of `include`, `separate`, or `skip`: {0}")]
+    InvalidDraftPolicy(String),
+    #[error("raw html policy must be one of `never`, `fallback`, or `always`: {0}")]
+    InvalidRawHtmlPolicy(String),
+    #[error("target must be one of `zola`, `hugo`, or `jekyll`: {0}")]
+    InvalidTarget(String),
+    #[error("frontmatter format must be one of `toml` or `yaml`: {0}")]
+    InvalidFrontmatterFormat(String),
+    #[error("image format must be one of `webp` or `avif`: {0}")]
+    InvalidImageFormat(String),
+    #[error("conflict policy must be one of `skip`, `overwrite`, `backup`, or `error`: {0}")]
+    InvalidConflictPolicy(String),
+    #[error("output file already exists: {}", .0.display())]
+    OutputExists(std::path::PathBuf),
+    #[error("manifest format must be one of `json` or `csv`: {0}")]
+    InvalidManifestFormat(String),
+    #[error("report format must be `json`: {0}")]
+    InvalidReportFormat(String),
+    #[error(
+        "{0} posts, images, links, or files were degraded or missing, which strict mode forbids"
+    )]
+    Incomplete(usize),
+    #[error("{0} files differed between two extractions of the same archive")]
+    Nondeterministic(usize),
+    #[error("images destination must be one of `content` or `static`: {0}")]
+    InvalidImagesDest(String),
+    #[error("unsplash policy must be one of `keep`, `normalize`, or `download`: {0}")]
+    InvalidUnsplashPolicy(String),
+    #[error("path sanitization must be one of `none` or `windows`: {0}")]
+    InvalidPathSanitization(String),
+    #[error("sort order must be one of `date`, `update_date`, `title`, `title_bytes`, `weight`, `slug`, or `none`: {0}")]
+    InvalidSortBy(String),
+    #[error("post {id} ({slug}) is published, but has no publication date")]
+    UndatedPost {
+        id: data_model::PostId,
+        slug: String,
+    },
 }
 
 pub(crate) fn log_progress(idx: usize, verb: &str) {
@@ -0,0 +1,76 @@
+//! Choosing how much the command-line tools log.
+
+use log::LevelFilter;
+use structopt::StructOpt;
+
+// structopt would show a doc comment here as the about of each tool into which this is flattened
+#[cfg_attr(
+    doc,
+    doc = "Flags choosing how much is logged, shared by each of the command-line tools.\n\n\
+           Flatten this into a tool's options with `#[structopt(flatten)]`."
+)]
+#[derive(Debug, Default, StructOpt)]
+pub struct Verbosity {
+    /// Log only errors
+    #[structopt(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Log more: `-v` for the details of each step, `-vv` for each file
+    ///
+    /// By default, warnings and a summary of each step are logged. `RUST_LOG`, if set, overrides
+    /// this and `--quiet`.
+    #[structopt(short, long, global = true, parse(from_occurrences))]
+    verbose: u8,
+}
+
+impl Verbosity {
+    /// the level at which this crate logs
+    pub fn level(&self) -> LevelFilter {
+        if self.quiet {
+            return LevelFilter::Error;
+        }
+        match self.verbose {
+            0 => LevelFilter::Info,
+            1 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        }
+    }
+
+    /// initialize the logger, with the time of each message if `timed`
+    ///
+    /// Other crates log only their warnings, unless `RUST_LOG` says otherwise.
+    pub fn init_logger(&self, timed: bool) {
+        let mut builder = if timed {
+            pretty_env_logger::formatted_timed_builder()
+        } else {
+            pretty_env_logger::formatted_builder()
+        };
+        builder
+            .filter_level(self.level().min(LevelFilter::Warn))
+            .filter_module(env!("CARGO_CRATE_NAME"), self.level());
+        if let Ok(filters) = std::env::var("RUST_LOG") {
+            builder.parse_filters(&filters);
+        }
+        builder.init();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_verbosity() {
+        let level = |args: &[&str]| {
+            Verbosity::from_iter_safe(std::iter::once("tool").chain(args.iter().copied()))
+                .unwrap()
+                .level()
+        };
+        assert_eq!(level(&[]), LevelFilter::Info);
+        assert_eq!(level(&["-q"]), LevelFilter::Error);
+        assert_eq!(level(&["-v"]), LevelFilter::Debug);
+        assert_eq!(level(&["-vv"]), LevelFilter::Trace);
+        assert_eq!(level(&["--verbose", "--verbose", "-v"]), LevelFilter::Trace);
+        assert!(Verbosity::from_iter_safe(["tool", "-q", "-v"].iter().copied()).is_err());
+    }
+}
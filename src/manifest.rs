@@ -0,0 +1,264 @@
+//! A record of where an extraction put each of Ghost's posts and images.
+//!
+//! Redirects from the old site's urls, and checks of the links to them, can be generated from it.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::{
+    data_model::{join_url, url_path, Post, PostId},
+    extract::{output_path, post_url, Bundles},
+    Error, ExtractOptions, ManifestFormat,
+};
+
+/// The structure of the urls at which Ghost serves posts by default.
+const DEFAULT_PERMALINKS: &str = "/{slug}/";
+
+/// What an entry of the manifest locates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum EntryKind {
+    Post,
+    Page,
+    Image,
+}
+
+impl EntryKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            EntryKind::Post => "post",
+            EntryKind::Page => "page",
+            EntryKind::Image => "image",
+        }
+    }
+}
+
+/// Where one of Ghost's urls went.
+#[derive(Debug, Serialize)]
+struct Entry {
+    kind: EntryKind,
+    /// the url at which Ghost served it, i.e. `/hello/`
+    ghost_url: String,
+    /// the path of the extracted file, relative to the extract path, with `/` separators
+    path: String,
+    /// the site-relative url at which the target serves it, if that can be known
+    url: Option<String>,
+}
+
+/// The entries of the manifest, gathered as the extraction proceeds.
+#[derive(Debug, Default)]
+pub(crate) struct Manifest {
+    /// the url at which Ghost served each post, before it was renamed
+    ghost_urls: HashMap<PostId, String>,
+    entries: Vec<Entry>,
+}
+
+impl Manifest {
+    /// note the url at which Ghost served a post
+    ///
+    /// This must be noted before the post's slug is changed, i.e. by [`Report::claim_path`].
+    /// Drafts weren't served at all.
+    ///
+    /// [`Report::claim_path`]: crate::extract::Report::claim_path
+    pub(crate) fn ghost_url(&mut self, post: &Post, options: &ExtractOptions) {
+        if options.manifest.is_none() || post.status.draft() {
+            return;
+        }
+        let permalinks = options.permalinks.as_deref().unwrap_or(DEFAULT_PERMALINKS);
+        if let Some(ghost_url) = post.ghost_path(permalinks, &options.slugs) {
+            self.ghost_urls.insert(post.extra.id.clone(), ghost_url);
+        }
+    }
+
+    /// note where a post was written
+    pub(crate) fn post(&mut self, post: &Post, options: &ExtractOptions) {
+        let ghost_url = match self.ghost_urls.remove(&post.extra.id) {
+            Some(ghost_url) => ghost_url,
+            None => return,
+        };
+        self.entries.push(Entry {
+            kind: if post.page {
+                EntryKind::Page
+            } else {
+                EntryKind::Post
+            },
+            ghost_url,
+            path: url_path(&output_path(post, options)),
+            url: post_url(post, options),
+        });
+    }
+
+    /// note where the extracted images ended up
+    ///
+    /// `images` pairs the url at which Ghost served each image with its path relative to the
+    /// images directory, after any conversion. Pruned images are left out; bundled images are
+    /// noted in each bundle into which they were copied.
+    pub(crate) fn images(
+        &mut self,
+        images: &[(String, String)],
+        bundles: &Bundles,
+        options: &ExtractOptions,
+    ) {
+        if options.manifest.is_none() {
+            return;
+        }
+        let images_dir = options.extracted_images_dir();
+        let images_url = options.images_url();
+        for (ghost_url, image) in images {
+            let bundled = bundles.paths(image);
+            if bundled.is_empty() {
+                self.entries.push(Entry {
+                    kind: EntryKind::Image,
+                    ghost_url: ghost_url.clone(),
+                    path: url_path(&images_dir.join(image)),
+                    url: Some(format!("{}/{}", images_url, image)),
+                });
+                continue;
+            }
+            for path in bundled {
+                self.entries.push(Entry {
+                    kind: EntryKind::Image,
+                    ghost_url: ghost_url.clone(),
+                    path: url_path(path),
+                    url: Some(join_url(options.target.content_url(), path)),
+                });
+            }
+        }
+    }
+
+    /// write the manifest to the file which the options name, if any
+    pub(crate) fn write(&self, options: &ExtractOptions) -> Result<(), Error> {
+        let path = match &options.manifest {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        match options.manifest_format {
+            ManifestFormat::Json => {
+                serde_json::to_writer_pretty(&mut file, &self.entries)?;
+                writeln!(file)?;
+            }
+            ManifestFormat::Csv => {
+                writeln!(file, "kind,ghost_url,path,url")?;
+                for entry in &self.entries {
+                    writeln!(
+                        file,
+                        "{},{},{},{}",
+                        entry.kind.as_str(),
+                        csv_field(&entry.ghost_url),
+                        csv_field(&entry.path),
+                        csv_field(entry.url.as_deref().unwrap_or_default()),
+                    )?;
+                }
+            }
+        }
+        file.flush()?;
+        log::info!(
+            "wrote a manifest of {} urls to {}",
+            self.entries.len(),
+            path.display()
+        );
+        Ok(())
+    }
+}
+
+/// the paths of the images extracted from the archive, paired with the urls at which Ghost
+/// served them; see [`Manifest::images`]
+pub(crate) fn extracted_images(
+    ghost_urls: &HashMap<PathBuf, String>,
+    images_path: &Path,
+    renames: &HashMap<String, String>,
+) -> Vec<(String, String)> {
+    let mut images: Vec<_> = ghost_urls
+        .iter()
+        .filter_map(|(path, ghost_url)| {
+            let image = url_path(path.strip_prefix(images_path).ok()?);
+            let image = renames.get(&image).cloned().unwrap_or(image);
+            Some((ghost_url.clone(), image))
+        })
+        .collect();
+    images.sort();
+    images
+}
+
+/// quote a field of a csv record, if need be
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_model::Status;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn maps_ghost_urls() {
+        let options = ExtractOptions {
+            manifest: Some("manifest.json".into()),
+            ..ExtractOptions::default()
+        };
+        let mut post = Post {
+            title: "Hello".into(),
+            slug: "hello".into(),
+            date: Some(Utc.with_ymd_and_hms(2020, 1, 2, 3, 4, 5).unwrap()),
+            status: Status::Published,
+            ..Post::default()
+        };
+        post.extra.id = 1.into();
+        let mut draft = Post {
+            slug: "draft".into(),
+            status: Status::Draft,
+            ..Post::default()
+        };
+        draft.extra.id = 2.into();
+
+        let mut manifest = Manifest::default();
+        manifest.ghost_url(&post, &options);
+        manifest.ghost_url(&draft, &options);
+        // i.e. renamed on a collision
+        post.slug = "hello-2".into();
+        manifest.post(&post, &options);
+        manifest.post(&draft, &options);
+        manifest.images(
+            &[(
+                "/content/images/2020/01/a.png".into(),
+                "2020/01/a.png".into(),
+            )],
+            &Bundles::default(),
+            &options,
+        );
+
+        assert_eq!(manifest.entries.len(), 2);
+        assert_eq!(manifest.entries[0].kind, EntryKind::Post);
+        assert_eq!(manifest.entries[0].ghost_url, "/hello/");
+        assert_eq!(manifest.entries[0].path, "2020/01/02/hello-2.md");
+        assert_eq!(
+            manifest.entries[0].url.as_deref(),
+            Some("/blog/2020/01/02/hello-2/")
+        );
+        assert_eq!(
+            manifest.entries[1].ghost_url,
+            "/content/images/2020/01/a.png"
+        );
+        assert_eq!(manifest.entries[1].path, "2020/01/a.png");
+        assert_eq!(
+            manifest.entries[1].url.as_deref(),
+            Some("/blog/2020/01/a.png")
+        );
+    }
+
+    #[test]
+    fn quotes_csv_fields() {
+        assert_eq!(csv_field("/hello/"), "/hello/");
+        assert_eq!(csv_field("/a,b/"), "\"/a,b/\"");
+        assert_eq!(csv_field("/\"a\"/"), "\"/\"\"a\"\"/\"");
+    }
+}
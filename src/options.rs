@@ -0,0 +1,1390 @@
+use crate::data_model::Post;
+use crate::progress::{ProgressCallback, ProgressObserver};
+use crate::query::PostFilter;
+use crate::render::PostRenderer;
+use crate::Error;
+use path_absolutize::Absolutize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Options controlling how an archive is extracted.
+#[derive(Debug, Clone)]
+pub struct ExtractOptions {
+    /// Relative prefix within the archive to search for the ghost db.
+    ///
+    /// Only necessary when the archive contains more than one blog.
+    pub prefix: Option<PathBuf>,
+    /// Path within the archive of the ghost database.
+    ///
+    /// When unset, this is discovered: it is the unique `ghost.db` within `prefix`.
+    pub db_path: Option<PathBuf>,
+    /// Path within the archive of the directory from which `/content/images` was served.
+    ///
+    /// When unset, this is discovered: see [`find_ghost_paths`][crate::find_ghost_paths].
+    pub images_prefix: Option<PathBuf>,
+    /// SQL predicate restricting which posts are extracted.
+    ///
+    /// This is appended verbatim to the `WHERE` clause of the posts query,
+    /// e.g. `posts.created_at > '2020-01-01'`.
+    pub sql_filter: Option<String>,
+    /// Restrictions on which posts are extracted, by date, tag, author, or status.
+    ///
+    /// These are applied to the posts query, along with [`ExtractOptions::sql_filter`]. They
+    /// apply only to archives; posts imported through the API are not filtered.
+    pub post_filter: PostFilter,
+    /// The greatest number of posts to extract, i.e. to try out the options on a few posts.
+    ///
+    /// The posts are those which the query would otherwise select first, or a random selection
+    /// of them with [`ExtractOptions::sample`]. Posts left out afterwards, i.e. skipped drafts,
+    /// count towards the limit. Like [`ExtractOptions::post_filter`], this applies only to
+    /// archives.
+    pub limit: Option<usize>,
+    /// Whether the posts extracted with [`ExtractOptions::limit`] are chosen at random.
+    pub sample: bool,
+    /// Locations from which the blog served images.
+    ///
+    /// Images found in each source's archive directory are extracted, and links to each
+    /// source's url prefix are rewritten to point at them. By default, these are Ghost's
+    /// [uploads][ImageSource::uploads].
+    pub image_sources: Vec<ImageSource>,
+    /// Url at which the blog was served, i.e. `https://example.com`.
+    ///
+    /// Posts written in Ghost's editor often link to the blog by its absolute url. When set, such
+    /// links, over either http or https, are treated like the blog's relative links: links to
+    /// images are rewritten to point at the extracted images, and links to posts into internal
+    /// links.
+    pub base_url: Option<String>,
+    /// Whether to keep the query strings and fragments of rewritten image links.
+    ///
+    /// Ghost resizes images according to some query strings, i.e. `?w=600`, but Zola serves the
+    /// extracted images as they are, so by default these are dropped.
+    pub keep_image_query: bool,
+    /// Whether to extract images, or download them from the API.
+    ///
+    /// Unset this when the images were already migrated; links to them are rewritten all the same.
+    pub extract_images: bool,
+    /// Whether to extract posts, pages, and their sections.
+    ///
+    /// Unset this to extract only the images. The database is then not extracted at all.
+    pub extract_content: bool,
+    /// What to do with the posts, images, and indices which already exist in the output.
+    ///
+    /// By default, posts and images are overwritten, but existing indices are left alone, as
+    /// they're often edited by hand once extracted.
+    pub on_conflict: Option<ConflictPolicy>,
+    /// Whether to rewrite only the posts which changed since the extract path was last extracted.
+    ///
+    /// Each post is matched with the file which has its id, and left alone if it would be written
+    /// to the same path, and its time of last update matches the file's. Other posts are written
+    /// according to [`ExtractOptions::on_conflict`], and the old files of posts whose path changed
+    /// are removed. The posts updated are logged, and listed in the report. This assumes the
+    /// extract path was extracted with the same options.
+    pub incremental: bool,
+    /// Whether to skip extracting the images which no post, author, or tag links to.
+    ///
+    /// The database is then copied out of the archive, and its posts read, before any image is
+    /// extracted, so the archive is read twice. The images skipped are listed, as are the extracted
+    /// images which nothing links to otherwise. Only the posts which are extracted count, so this
+    /// is best left unset when extracting posts and pages separately, i.e. with
+    /// [`ExtractOptions::only`].
+    pub prune_orphans: bool,
+    /// File into which to write a manifest of where each of Ghost's urls went.
+    ///
+    /// Each post and image is listed with the url at which Ghost served it, the path into which it
+    /// was extracted, relative to the extract path, and the url at which the target serves it.
+    /// Ghost's urls for posts follow [`ExtractOptions::permalinks`], or else its default,
+    /// `/{slug}/`. Drafts, which Ghost didn't serve, aren't listed.
+    pub manifest: Option<PathBuf>,
+    /// The format of the manifest.
+    pub manifest_format: ManifestFormat,
+    /// The format in which to write a summary of the extraction once it completes, if any.
+    ///
+    /// It counts the posts, images, and indices written, and lists the posts which were skipped,
+    /// the images without alt text, and everything which was logged as a warning.
+    pub report: Option<ReportFormat>,
+    /// File into which to write the summary; by default, it's written to stdout.
+    pub report_path: Option<PathBuf>,
+    /// Whether to fail once the extraction completes if anything was lost along the way.
+    ///
+    /// That is, if any post was written without its markdown or with cards which couldn't be
+    /// converted, if any image which a post links to was missing, if any internal link doesn't
+    /// resolve, or, with [`ExtractOptions::validate`], if any file's frontmatter is invalid. Each
+    /// dangling link counts separately. Posts skipped by choice, i.e. drafts with
+    /// [`DraftPolicy::Skip`], don't count. Everything is extracted, and logged, before
+    /// [`Error::Incomplete`] is returned.
+    ///
+    /// Frontmatter which can't be serialized into TOML isn't covered: there's no fallback for it
+    /// to forbid, as the extraction fails on it regardless.
+    pub strict: bool,
+    /// Whether to check, once the extraction completes, that the target would accept every
+    /// generated file's frontmatter.
+    ///
+    /// Each markdown file beneath the extract path is parsed as the target would parse it: its
+    /// frontmatter must parse, without duplicate keys, and its dates must be dates. For zola, each
+    /// page must have a title, and every key must be one which zola knows, of the type it
+    /// expects. Problems are logged, and listed in the report.
+    pub validate: bool,
+    /// Where to extract the images: beside the posts, or into the site's static directory.
+    pub images_dest: ImagesDest,
+    /// Directory, relative to [`ExtractOptions::images_dest`], into which images are extracted.
+    ///
+    /// Links to the images follow it. By default, images are extracted where Ghost's links to
+    /// them keep working: beside the posts, or into `blog` within the static directory.
+    pub images_dir: Option<PathBuf>,
+    /// A format into which to convert the extracted raster images, i.e. png and jpeg.
+    ///
+    /// Each image is converted beside its original, with the format's extension, and the links to
+    /// it rewritten. Gifs, which may be animated, and external images are left alone.
+    pub image_format: Option<ImageFormat>,
+    /// Whether to keep the original of each converted image, i.e. as a fallback.
+    pub keep_original_images: bool,
+    /// Whether to download the images which posts link to by absolute url, such as those on
+    /// Ghost's CDN or Unsplash, and point the links at the downloaded images.
+    ///
+    /// These aren't in the archive, so by default the posts keep linking to them. The images are
+    /// downloaded into `external` within the images directory, named by a hash of their url.
+    pub download_external_images: bool,
+    /// What to do with the images which Ghost's Unsplash integration hotlinks.
+    pub unsplash: UnsplashPolicy,
+    /// Directory, relative to the extract path, into which pages are extracted.
+    ///
+    /// Unlike posts, which are placed in a tree by date, pages are placed directly within it.
+    pub pages_dir: PathBuf,
+    /// How slugs are generated for the posts which don't have one.
+    pub slugs: SlugOptions,
+    /// How the names of the extracted files and directories are made safe for the filesystem.
+    ///
+    /// By default, they are used as they are; Windows users may opt into making them safe for it.
+    pub path_sanitization: PathSanitization,
+    /// Whether to write each post as a page bundle, i.e. `hello/index.md` rather than `hello.md`.
+    ///
+    /// The images which a post links to are then moved into its bundle, and its links to them
+    /// made relative, so that each post is self-contained. An image used by several posts is
+    /// copied into each of their bundles. Jekyll has no page bundles, so this is ignored for it.
+    pub page_bundles: bool,
+    /// Directory, relative to the extract path, into which a section per author is extracted.
+    ///
+    /// When set, each Ghost user's profile, including their website and social accounts, is
+    /// written into `<authors_dir>/<slug>/_index.md`. As the extract path is normally
+    /// `content/blog`, this is typically `../authors`. Note that Zola places an `authors`
+    /// [taxonomy][TaxonomyMapping::authors] at `/authors` too.
+    pub authors_dir: Option<PathBuf>,
+    /// Directory, relative to the extract path, into which a section per tag is extracted.
+    ///
+    /// When set, each tag's name, description, and feature image are written into
+    /// `<tags_dir>/<slug>/_index.md`, typically `../tags`, for the use of Zola's templates. Note
+    /// that Zola places the `tags` taxonomy at `/tags` too.
+    pub tags_dir: Option<PathBuf>,
+    /// Whether to rewrite links between posts into Zola internal links, i.e. `@/blog/...`.
+    ///
+    /// Like the links to images, these assume that posts are extracted into `content/blog`.
+    pub link_posts: bool,
+    /// Which types of post to extract.
+    pub only: PostTypes,
+    /// What to do with posts which are published but have no publication date.
+    pub undated_policy: UndatedPolicy,
+    /// What to do with drafts.
+    pub drafts: DraftPolicy,
+    /// Directory, relative to the extract path, into which drafts are extracted when they are
+    /// [separate][DraftPolicy::Separate].
+    ///
+    /// Like pages, drafts are placed directly within it. As the extract path is normally
+    /// `content/blog`, this is `../drafts` by default. Jekyll keeps its drafts in `_drafts`
+    /// regardless.
+    pub drafts_dir: PathBuf,
+    /// Whether to write Zola's section indices, `_index.md`.
+    ///
+    /// Unset this when the site already has its own section files.
+    pub indices: bool,
+    /// Contents of the section index written at the root of the extract path, in place of the
+    /// default, which is configured by [`root_section`][ExtractOptions::root_section].
+    pub root_index: Option<String>,
+    /// Contents of the section index written into each year and month of the date tree, in place
+    /// of the default, which titles them by their date and is configured by
+    /// [`branch_section`][ExtractOptions::branch_section].
+    pub branch_index: Option<String>,
+    /// The site's default language, i.e. `en`, for a multilingual extraction.
+    ///
+    /// When set, posts in any other language are extracted as translations, following Zola's
+    /// and Hugo's filename convention: a French post is written to `hello.fr.md`, or
+    /// `hello/index.fr.md` in a page bundle, and each section gets an index per language, i.e.
+    /// `_index.fr.md`. Each language must be declared in the site's configuration. Ghost's
+    /// language tags are reduced to their primary subtag, so `en_US` is `en`.
+    pub default_language: Option<String>,
+    /// How the root section sorts and paginates its posts.
+    pub root_section: SectionIndex,
+    /// How each year, month, and day section sorts and paginates its posts.
+    pub branch_section: SectionIndex,
+    /// When to use a post's pre-rendered html as its content.
+    pub raw_html: RawHtmlPolicy,
+    /// The static site generator for which content is extracted.
+    pub target: Target,
+    /// The format in which frontmatter is written.
+    pub frontmatter: FrontmatterFormat,
+    /// A template with which each post's frontmatter is rendered, in place of the default fields.
+    pub frontmatter_template: Option<FrontmatterTemplate>,
+    /// Reading speed with which to estimate each post's reading time.
+    ///
+    /// When set, each post's word count and reading time in minutes are written into its
+    /// frontmatter. Ghost assumed 275 words per minute.
+    pub words_per_minute: Option<u32>,
+    /// Ghost's permalink structure, i.e. `/{slug}/`, its default.
+    ///
+    /// When set, each post's url on Ghost is written into its `aliases`, so that links to the
+    /// blog from elsewhere keep working. See [`Post::ghost_path`][crate::data_model::Post::ghost_path]
+    /// for the placeholders it may use.
+    pub permalinks: Option<String>,
+    /// Whether to mark the end of each post's summary where Ghost's excerpt ends.
+    ///
+    /// Where a post has a hand-written excerpt, a summary separator, i.e. `<!-- more -->`, is
+    /// inserted after the paragraph in which the excerpt ends, or after the first paragraph if the
+    /// excerpt isn't part of the post. The summary then matches what Ghost showed on its index
+    /// pages.
+    pub summary_separator: bool,
+    /// The Zola shortcodes with which content from Ghost's editor is rendered.
+    pub shortcodes: Shortcodes,
+    /// The Zola taxonomies into which posts are classified.
+    ///
+    /// Zola's `config.toml` must declare each of them; see
+    /// [`taxonomies_config`][crate::taxonomies_config].
+    pub taxonomies: TaxonomyMapping,
+    /// Whether to keep Ghost's internal tags, whose names begin with `#`.
+    ///
+    /// Internal tags are never shown to readers, so they are dropped from `taxonomies.tags`.
+    /// When set, they are kept in `extra.internal_tags` instead.
+    pub keep_internal_tags: bool,
+    /// Renames of the keys in posts' frontmatter, i.e. from `extra.author_name` to `extra.author`.
+    pub frontmatter_keys: KeyMapping,
+    /// Fields merged into every post's `extra`, i.e. `template = "post.html"`.
+    ///
+    /// These are for the fields which a site expects of each post, but which Ghost doesn't
+    /// know about. They should not share the names of the fields extracted from Ghost.
+    pub extra_frontmatter: BTreeMap<String, toml::Value>,
+    /// Observer notified as each archive entry is processed, and each image and post written.
+    pub progress: Option<Arc<dyn ProgressObserver>>,
+    /// Callback reporting the number of archive entries, images, and posts processed so far.
+    pub on_progress: Option<ProgressCallback>,
+    /// Site-specific rewrites of each post, run after the built-in transforms, before it's
+    /// rendered.
+    pub transforms: Transforms,
+    /// Renderer of each post's document, in place of the built-in
+    /// [`DefaultRenderer`](crate::render::DefaultRenderer).
+    pub renderer: Option<Arc<dyn PostRenderer>>,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        ExtractOptions {
+            prefix: None,
+            db_path: None,
+            images_prefix: None,
+            sql_filter: None,
+            post_filter: PostFilter::default(),
+            limit: None,
+            sample: false,
+            image_sources: ImageSource::uploads(),
+            base_url: None,
+            keep_image_query: false,
+            extract_images: true,
+            extract_content: true,
+            on_conflict: None,
+            incremental: false,
+            prune_orphans: false,
+            manifest: None,
+            manifest_format: ManifestFormat::default(),
+            report: None,
+            report_path: None,
+            strict: false,
+            validate: false,
+            images_dest: ImagesDest::default(),
+            images_dir: None,
+            image_format: None,
+            keep_original_images: false,
+            download_external_images: false,
+            unsplash: UnsplashPolicy::default(),
+            pages_dir: "pages".into(),
+            slugs: SlugOptions::default(),
+            path_sanitization: PathSanitization::default(),
+            page_bundles: false,
+            authors_dir: None,
+            tags_dir: None,
+            link_posts: true,
+            only: PostTypes::default(),
+            undated_policy: UndatedPolicy::default(),
+            drafts: DraftPolicy::default(),
+            drafts_dir: "../drafts".into(),
+            indices: true,
+            root_index: None,
+            branch_index: None,
+            default_language: None,
+            root_section: SectionIndex::root(),
+            branch_section: SectionIndex::branch(),
+            raw_html: RawHtmlPolicy::default(),
+            target: Target::default(),
+            frontmatter: FrontmatterFormat::default(),
+            frontmatter_template: None,
+            words_per_minute: None,
+            permalinks: None,
+            summary_separator: false,
+            shortcodes: Shortcodes::default(),
+            taxonomies: TaxonomyMapping::default(),
+            keep_internal_tags: false,
+            frontmatter_keys: KeyMapping::default(),
+            extra_frontmatter: BTreeMap::new(),
+            progress: None,
+            on_progress: None,
+            transforms: Transforms::default(),
+            renderer: None,
+        }
+    }
+}
+
+impl ExtractOptions {
+    /// what to do with the posts, images, and other documents which already exist
+    pub(crate) fn conflict_policy(&self) -> ConflictPolicy {
+        self.on_conflict.unwrap_or_default()
+    }
+
+    /// what to do with the section indices which already exist
+    pub(crate) fn index_conflict_policy(&self) -> ConflictPolicy {
+        self.on_conflict.unwrap_or(ConflictPolicy::Skip)
+    }
+
+    /// the directory, relative to the extract path, into which images are extracted
+    pub(crate) fn extracted_images_dir(&self) -> PathBuf {
+        match self.images_dest {
+            ImagesDest::Content => self
+                .images_dir
+                .clone()
+                .unwrap_or_else(|| self.target.images_dir().into()),
+            ImagesDest::Static => {
+                let dir = match &self.images_dir {
+                    Some(dir) => dir.clone(),
+                    // served at the same url as they would be from beside the posts
+                    None => Path::new(self.target.content_url().trim_start_matches('/'))
+                        .join(self.target.images_dir()),
+                };
+                self.target.static_dir().join(dir)
+            }
+        }
+    }
+
+    /// the absolute path into which images are extracted, given the extract path
+    pub(crate) fn images_path(&self, extract_path: &Path) -> Result<PathBuf, Error> {
+        Ok(extract_path
+            .join(self.extracted_images_dir())
+            .absolutize()?
+            .to_path_buf())
+    }
+
+    /// the url at which the extracted images are served
+    pub(crate) fn images_url(&self) -> String {
+        let (url, dir) = match self.images_dest {
+            ImagesDest::Content => (self.target.content_url(), self.extracted_images_dir()),
+            ImagesDest::Static => (
+                String::new(),
+                self.extracted_images_dir()
+                    .strip_prefix(self.target.static_dir())
+                    .map(Path::to_path_buf)
+                    .unwrap_or_default(),
+            ),
+        };
+        crate::data_model::join_url(url, &dir)
+    }
+}
+
+/// A url prefix from which Ghost served images, and the archive directory containing them.
+///
+/// For example, the standard source serves `/content/images/2020/01/foo.jpg` from
+/// `images/2020/01/foo.jpg`, relative to the ghost content directory: normally the directory which
+/// contains `data/ghost.db`, or otherwise the parent of the discovered images directory.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImageSource {
+    /// url path prefix, e.g. `/content/images`
+    pub url_prefix: String,
+    /// directory containing the images, relative to the ghost content directory
+    pub archive_dir: PathBuf,
+}
+
+impl Default for ImageSource {
+    fn default() -> Self {
+        ImageSource {
+            url_prefix: "/content/images".into(),
+            archive_dir: "images".into(),
+        }
+    }
+}
+
+impl ImageSource {
+    /// the sources from which Ghost serves uploads
+    ///
+    /// Ghost stores images under `/content/images`, other files under `/content/files`, and audio
+    /// and video under `/content/media`.
+    pub fn uploads() -> Vec<ImageSource> {
+        vec![
+            ImageSource::default(),
+            ImageSource {
+                url_prefix: "/content/files".into(),
+                archive_dir: "files".into(),
+            },
+            ImageSource {
+                url_prefix: "/content/media".into(),
+                archive_dir: "media".into(),
+            },
+        ]
+    }
+}
+
+impl FromStr for ImageSource {
+    type Err = Error;
+
+    /// parse an image source of the form `url_prefix=archive_dir`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(url_prefix), Some(archive_dir))
+                if url_prefix.starts_with('/') && !archive_dir.is_empty() =>
+            {
+                Ok(ImageSource {
+                    url_prefix: url_prefix.trim_end_matches('/').into(),
+                    archive_dir: archive_dir.into(),
+                })
+            }
+            _ => Err(Error::InvalidImageSource(s.into())),
+        }
+    }
+}
+
+/// How Ghost's classifications of posts map onto Zola taxonomies.
+///
+/// Each classification is written into the taxonomy of the given name, or not at all if that is
+/// `None`. Classifications mapped to the same name are merged.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TaxonomyMapping {
+    /// taxonomy of each post's public tags; `tags` by default
+    pub tags: Option<String>,
+    /// taxonomy of each post's primary tag: its first public tag, which many themes present as its
+    /// category
+    pub categories: Option<String>,
+    /// taxonomy of each post's authors, with which Zola can generate a page per author
+    pub authors: Option<String>,
+    /// whether to slugify terms, i.e. `Rust Tips` to `rust-tips`
+    pub slugify: bool,
+}
+
+impl Default for TaxonomyMapping {
+    fn default() -> Self {
+        TaxonomyMapping {
+            tags: Some("tags".into()),
+            categories: None,
+            authors: None,
+            slugify: false,
+        }
+    }
+}
+
+impl TaxonomyMapping {
+    /// the taxonomy into which a classification is written
+    pub fn name_mut(&mut self, classification: Classification) -> &mut Option<String> {
+        match classification {
+            Classification::Tags => &mut self.tags,
+            Classification::Categories => &mut self.categories,
+            Classification::Authors => &mut self.authors,
+        }
+    }
+
+    /// the names of the taxonomies, in order and without repetition
+    pub fn names(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        for name in [&self.tags, &self.categories, &self.authors]
+            .iter()
+            .filter_map(|name| name.as_deref())
+        {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names
+    }
+}
+
+/// A way in which Ghost classifies posts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Classification {
+    Tags,
+    Categories,
+    Authors,
+}
+
+/// An assignment of a classification to a taxonomy, or to none, as `classification=name`.
+///
+/// For example, `tags=topics` classifies posts by tag in a `topics` taxonomy, and `authors=`
+/// doesn't classify them by author at all.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TaxonomyAssignment {
+    pub classification: Classification,
+    pub name: Option<String>,
+}
+
+impl FromStr for TaxonomyAssignment {
+    type Err = Error;
+
+    /// parse an assignment of the form `classification=name`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '=');
+        let classification = match parts.next() {
+            Some("tags") => Classification::Tags,
+            Some("categories") => Classification::Categories,
+            Some("authors") => Classification::Authors,
+            _ => return Err(Error::InvalidTaxonomy(s.into())),
+        };
+        let name = match parts.next() {
+            Some(name) if !name.contains(char::is_whitespace) => name,
+            _ => return Err(Error::InvalidTaxonomy(s.into())),
+        };
+        Ok(TaxonomyAssignment {
+            classification,
+            name: if name.is_empty() {
+                None
+            } else {
+                Some(name.into())
+            },
+        })
+    }
+}
+
+/// The Zola shortcodes with which content from Ghost's editor is rendered, by kind.
+///
+/// Content of a kind without a shortcode is rendered as plain markdown, or as html where markdown
+/// can't express it. The kinds are:
+///
+/// - `caption`: the caption of an image, called with the caption as `text`, i.e.
+///   `{{ caption(text="...") }}`. Without a shortcode, captions are emphasized.
+/// - `gallery`: a gallery of images, called with a body of markdown images, one paragraph per row
+///   of the gallery, i.e. `{% gallery() %}...{% end %}`. Its template might render the body with
+///   `body | markdown`. Without a shortcode, each row is a paragraph of images.
+/// - `bookmark`: a rich preview of a link, called with its `url`, and its `title`, `description`,
+///   `author`, `publisher` and `thumbnail` where known. Without a shortcode, the link is quoted
+///   with its description and byline.
+/// - `callout`: a box of text, called with a body of markdown, and its `emoji` and background
+///   `color` where known, i.e. `{% callout(emoji="💡", color="blue") %}...{% end %}`. Without a
+///   shortcode, the text is quoted behind its emoji.
+/// - `toggle`: a heading which reveals its content when clicked, called with a body of markdown
+///   and its `heading`. Without a shortcode, it becomes a `<details>` element.
+/// - `button`: a link styled as a call to action, called with its `url`, `text`, and `alignment`
+///   where known. Without a shortcode, it's a plain link.
+/// - `audio`: an audio player, called with its `src`, and its `title` where known.
+/// - `video`: a video player, called with its `src`, and its `poster`, `width`, `height` and `loop`
+///   where known. Without a shortcode, players are `<audio>` and `<video>` elements.
+/// - `file`: a link to download a file, called with its `src` and `name`, and its `size`, `title`
+///   and `caption` where known. Without a shortcode, it's a link labelled with the file's name and
+///   size.
+/// - `youtube`, `vimeo`: an embedded video, called with the video's `id`, as are Zola's built-in
+///   shortcodes of the same names. These are the defaults.
+/// - `twitter`, `soundcloud`: an embedded tweet or track, called with its `url`. Without a
+///   shortcode, the html which Ghost embedded is passed through.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Shortcodes {
+    names: BTreeMap<String, String>,
+}
+
+impl Default for Shortcodes {
+    fn default() -> Self {
+        let mut shortcodes = Shortcodes {
+            names: BTreeMap::new(),
+        };
+        shortcodes.set("youtube", Some("youtube".into()));
+        shortcodes.set("vimeo", Some("vimeo".into()));
+        shortcodes
+    }
+}
+
+impl Shortcodes {
+    /// the kinds of content which can be rendered by shortcode
+    pub const KINDS: &'static [&'static str] = &[
+        "caption",
+        "gallery",
+        "bookmark",
+        "callout",
+        "toggle",
+        "button",
+        "audio",
+        "video",
+        "file",
+        "youtube",
+        "vimeo",
+        "twitter",
+        "soundcloud",
+    ];
+
+    /// the shortcode with which to render this kind of content, if any
+    pub fn get(&self, kind: &str) -> Option<&str> {
+        self.names.get(kind).map(String::as_str)
+    }
+
+    /// set the shortcode with which to render this kind of content, or unset it with `None`
+    pub fn set(&mut self, kind: &str, name: Option<String>) {
+        match name {
+            Some(name) => self.names.insert(kind.into(), name),
+            None => self.names.remove(kind),
+        };
+    }
+}
+
+/// An assignment of a shortcode to a kind of content, or of none, as `kind=name`.
+///
+/// For example, `caption=figcaption` renders captions with the `figcaption` shortcode, and
+/// `youtube=` passes embedded YouTube videos through as html. See [`Shortcodes`] for the kinds.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ShortcodeAssignment {
+    pub kind: String,
+    pub name: Option<String>,
+}
+
+impl FromStr for ShortcodeAssignment {
+    type Err = Error;
+
+    /// parse an assignment of the form `kind=name`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(kind), Some(name))
+                if Shortcodes::KINDS.contains(&kind) && !name.contains(char::is_whitespace) =>
+            {
+                Ok(ShortcodeAssignment {
+                    kind: kind.into(),
+                    name: if name.is_empty() {
+                        None
+                    } else {
+                        Some(name.into())
+                    },
+                })
+            }
+            _ => Err(Error::InvalidShortcode(s.into())),
+        }
+    }
+}
+
+/// A [Tera](https://keats.github.io/tera/) template for posts' frontmatter.
+///
+/// The template is rendered with the fields which would otherwise be written as its context, i.e.
+/// `title`, `date`, `extra.author_name`, or `taxonomies.tags`, and its output is placed between
+/// the frontmatter's delimiters. Fields which are unset, such as `draft` for published posts, are
+/// missing from the context, so use Tera's `default` filter or an `if` for them.
+///
+/// For example, `title = {{ title | json_encode() }}` writes a post's title as a toml string.
+#[derive(Debug, Clone)]
+pub struct FrontmatterTemplate {
+    tera: tera::Tera,
+}
+
+impl FrontmatterTemplate {
+    const NAME: &'static str = "frontmatter";
+
+    /// compile a template from its source
+    pub fn new(source: &str) -> Result<Self, Error> {
+        let mut tera = tera::Tera::default();
+        tera.add_raw_template(Self::NAME, source)?;
+        Ok(FrontmatterTemplate { tera })
+    }
+
+    /// render the template with the given context
+    pub(crate) fn render<T: serde::Serialize>(&self, context: &T) -> Result<String, Error> {
+        let context = tera::Context::from_serialize(context)?;
+        Ok(self.tera.render(Self::NAME, &context)?)
+    }
+}
+
+/// A site-specific rewrite of each post.
+type Transform = dyn FnMut(&mut Post) + Send;
+
+/// Site-specific rewrites of each post, i.e. of custom shortcodes, or of legacy urls.
+///
+/// Each is run on every post, in the order in which they were added, once the built-in transforms
+/// are done: its cards are converted, its links and images rewritten, and its path chosen. It's
+/// then rendered, as it's left. Changing a post's slug or date moves its file, but links to it from
+/// other posts are already rewritten, and won't follow.
+#[derive(Clone, Default)]
+pub struct Transforms {
+    transforms: Vec<Arc<Mutex<Transform>>>,
+}
+
+impl Transforms {
+    /// add a rewrite, to run after those already added
+    pub fn push<F>(&mut self, transform: F)
+    where
+        F: FnMut(&mut Post) + Send + 'static,
+    {
+        self.transforms.push(Arc::new(Mutex::new(transform)));
+    }
+
+    /// the number of rewrites
+    pub fn len(&self) -> usize {
+        self.transforms.len()
+    }
+
+    /// whether there are no rewrites
+    pub fn is_empty(&self) -> bool {
+        self.transforms.is_empty()
+    }
+
+    /// rewrite a post with each of the rewrites in turn
+    pub(crate) fn apply(&self, post: &mut Post) {
+        for transform in &self.transforms {
+            // a rewrite which panicked, if the panic was caught, is still run on later posts
+            let mut transform = transform.lock().unwrap_or_else(PoisonError::into_inner);
+            transform(post);
+        }
+    }
+}
+
+impl fmt::Debug for Transforms {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Transforms({})", self.transforms.len())
+    }
+}
+
+/// Renames of the keys in posts' frontmatter, or their removal.
+///
+/// Many themes expect particular keys of each post, such as `extra.author` rather than
+/// `extra.author_name`. Keys are given as dotted paths into the frontmatter as the target writes
+/// it, so for Hugo, `params.author_name` rather than `extra.author_name`. A key may be renamed into
+/// another table, which is created if need be, and a key renamed to none is dropped.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct KeyMapping {
+    renames: BTreeMap<String, Option<String>>,
+}
+
+impl KeyMapping {
+    /// rename a key, or drop it with `None`
+    pub fn set(&mut self, key: &str, renamed: Option<String>) {
+        self.renames.insert(key.into(), renamed);
+    }
+
+    /// the key to which this key is renamed, or `None` if it's dropped
+    ///
+    /// Keys which aren't mapped are written as they are.
+    pub fn get(&self, key: &str) -> Option<Option<&str>> {
+        self.renames.get(key).map(Option::as_deref)
+    }
+
+    /// each mapped key, and the key to which it's renamed, if any
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.renames
+            .iter()
+            .map(|(key, renamed)| (key.as_str(), renamed.as_deref()))
+    }
+}
+
+/// A renaming of a frontmatter key, or its removal, as `key=renamed`.
+///
+/// For example, `extra.author_name=extra.author` renames the author's name, and `extra.language=`
+/// drops the language. See [`KeyMapping`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyAssignment {
+    pub key: String,
+    pub renamed: Option<String>,
+}
+
+impl FromStr for KeyAssignment {
+    type Err = Error;
+
+    /// parse an assignment of the form `key=renamed`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let is_key = |key: &str| {
+            !key.is_empty()
+                && key
+                    .split('.')
+                    .all(|part| !part.is_empty() && !part.contains(char::is_whitespace))
+        };
+        let mut parts = s.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(key), Some(renamed))
+                if is_key(key) && (renamed.is_empty() || is_key(renamed)) =>
+            {
+                Ok(KeyAssignment {
+                    key: key.into(),
+                    renamed: if renamed.is_empty() {
+                        None
+                    } else {
+                        Some(renamed.into())
+                    },
+                })
+            }
+            _ => Err(Error::InvalidKeyMapping(s.into())),
+        }
+    }
+}
+
+/// A field to merge into every post's `extra`, as `key=value`.
+///
+/// The value is read as toml where it can be, so `comments=true` is a boolean, and otherwise as a
+/// string, so `template=post.html` is the string `"post.html"`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtraAssignment {
+    pub key: String,
+    pub value: toml::Value,
+}
+
+impl FromStr for ExtraAssignment {
+    type Err = Error;
+
+    /// parse an assignment of the form `key=value`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(key), Some(value)) if !key.is_empty() && !key.contains(char::is_whitespace) => {
+                let value = toml::from_str::<toml::value::Table>(&format!("value = {}", value))
+                    .ok()
+                    .and_then(|mut table| table.remove("value"))
+                    .unwrap_or_else(|| toml::Value::String(value.into()));
+                Ok(ExtraAssignment {
+                    key: key.into(),
+                    value,
+                })
+            }
+            _ => Err(Error::InvalidExtra(s.into())),
+        }
+    }
+}
+
+/// Which types of post to extract.
+///
+/// Ghost distinguishes posts from standalone pages, such as "About". Extracting each type in a
+/// separate run allows them to be placed in different Zola sections.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PostTypes {
+    /// extract only posts
+    Posts,
+    /// extract only pages
+    Pages,
+    /// extract both posts and pages
+    #[default]
+    All,
+}
+
+impl PostTypes {
+    /// whether posts of this type are extracted
+    pub fn includes(self, page: bool) -> bool {
+        match self {
+            PostTypes::Posts => !page,
+            PostTypes::Pages => page,
+            PostTypes::All => true,
+        }
+    }
+}
+
+impl FromStr for PostTypes {
+    type Err = Error;
+
+    /// parse one of `posts`, `pages`, or `all`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "posts" => Ok(PostTypes::Posts),
+            "pages" => Ok(PostTypes::Pages),
+            "all" => Ok(PostTypes::All),
+            _ => Err(Error::InvalidPostTypes(s.into())),
+        }
+    }
+}
+
+/// What to do with a post which is published, but has no publication date.
+///
+/// Ghost occasionally contains such posts, typically after an import gone wrong. Left alone, they
+/// would be extracted as published posts into the `undated` directory.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UndatedPolicy {
+    /// extract the post as it is, as a published post into the `undated` directory
+    #[default]
+    Keep,
+    /// extract the post as a draft
+    Draft,
+    /// date the post by its creation time; if that is also unknown, extract it as a draft
+    CreatedAt,
+    /// abort the extraction with [`Error::UndatedPost`]
+    Error,
+}
+
+impl FromStr for UndatedPolicy {
+    type Err = Error;
+
+    /// parse one of `keep`, `draft`, `created-at`, or `error`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(UndatedPolicy::Keep),
+            "draft" => Ok(UndatedPolicy::Draft),
+            "created-at" => Ok(UndatedPolicy::CreatedAt),
+            "error" => Ok(UndatedPolicy::Error),
+            _ => Err(Error::InvalidUndatedPolicy(s.into())),
+        }
+    }
+}
+
+/// What to do with drafts, including posts which the [`UndatedPolicy`] made drafts.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DraftPolicy {
+    /// extract drafts alongside the published posts, i.e. into `undated`
+    #[default]
+    Include,
+    /// extract drafts into [`ExtractOptions::drafts_dir`]
+    Separate,
+    /// don't extract drafts at all
+    Skip,
+}
+
+impl FromStr for DraftPolicy {
+    type Err = Error;
+
+    /// parse one of `include`, `separate`, or `skip`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "include" => Ok(DraftPolicy::Include),
+            "separate" => Ok(DraftPolicy::Separate),
+            "skip" => Ok(DraftPolicy::Skip),
+            _ => Err(Error::InvalidDraftPolicy(s.into())),
+        }
+    }
+}
+
+/// The static site generator for which content is extracted.
+///
+/// Ghost's content is read the same way for each; only the frontmatter, the layout of the extracted
+/// files, and the links between them differ.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Target {
+    /// Zola
+    #[default]
+    Zola,
+    /// Hugo: updates are written as `lastmod`, taxonomies at the top level of the frontmatter, and
+    /// `extra` as `params`; links between posts use the `ref` shortcode
+    Hugo,
+    /// Jekyll: the extract path is the site's root; posts are written into `_posts`, with yaml
+    /// frontmatter, and images into `assets/images`; links between posts use the `post_url` tag
+    Jekyll,
+}
+
+impl Target {
+    /// the url at which the extract path is served
+    pub(crate) fn content_url(self) -> String {
+        match self {
+            Target::Zola | Target::Hugo => format!("/{}", crate::data_model::SECTION),
+            Target::Jekyll => String::new(),
+        }
+    }
+
+    /// the marker which ends a post's summary
+    pub(crate) fn summary_separator(self) -> &'static str {
+        match self {
+            Target::Zola => "<!-- more -->",
+            // jekyll's is configurable; this is its conventional `excerpt_separator`
+            Target::Hugo | Target::Jekyll => "<!--more-->",
+        }
+    }
+
+    /// the directory, relative to the extract path, into which images are extracted by default
+    pub(crate) fn images_dir(self) -> &'static Path {
+        match self {
+            Target::Zola | Target::Hugo => Path::new(""),
+            Target::Jekyll => Path::new("assets/images"),
+        }
+    }
+
+    /// the site's static directory, relative to the extract path
+    ///
+    /// Zola's and Hugo's are beside `content`, of which the extract path is normally a section,
+    /// i.e. `content/blog`. Jekyll has none; it copies whatever isn't content from the site's root.
+    pub(crate) fn static_dir(self) -> &'static Path {
+        match self {
+            Target::Zola | Target::Hugo => Path::new("../../static"),
+            Target::Jekyll => Path::new(""),
+        }
+    }
+}
+
+impl FromStr for Target {
+    type Err = Error;
+
+    /// parse one of `zola`, `hugo`, or `jekyll`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "zola" => Ok(Target::Zola),
+            "hugo" => Ok(Target::Hugo),
+            "jekyll" => Ok(Target::Jekyll),
+            _ => Err(Error::InvalidTarget(s.into())),
+        }
+    }
+}
+
+/// The format in which frontmatter is written.
+///
+/// Zola reads either, distinguishing them by their delimiters: `+++` for toml, `---` for yaml.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FrontmatterFormat {
+    /// toml, between `+++` lines
+    #[default]
+    Toml,
+    /// yaml, between `---` lines
+    Yaml,
+}
+
+impl FromStr for FrontmatterFormat {
+    type Err = Error;
+
+    /// parse one of `toml` or `yaml`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "toml" => Ok(FrontmatterFormat::Toml),
+            "yaml" => Ok(FrontmatterFormat::Yaml),
+            _ => Err(Error::InvalidFrontmatterFormat(s.into())),
+        }
+    }
+}
+
+/// How a Zola section sorts its pages.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SortBy {
+    /// by `date`, newest first
+    #[default]
+    Date,
+    /// by `updated`, or `date` where that is unset, newest first
+    UpdateDate,
+    /// by `title`, in natural order
+    Title,
+    /// by `title`, bytewise
+    TitleBytes,
+    /// by `weight`, lightest first
+    Weight,
+    /// by `slug`, in natural order
+    Slug,
+    /// not at all
+    None,
+}
+
+impl SortBy {
+    /// the name of this order in a section's frontmatter
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            SortBy::Date => "date",
+            SortBy::UpdateDate => "update_date",
+            SortBy::Title => "title",
+            SortBy::TitleBytes => "title_bytes",
+            SortBy::Weight => "weight",
+            SortBy::Slug => "slug",
+            SortBy::None => "none",
+        }
+    }
+}
+
+impl FromStr for SortBy {
+    type Err = Error;
+
+    /// parse one of `date`, `update_date`, `title`, `title_bytes`, `weight`, `slug`, or `none`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "date" => Ok(SortBy::Date),
+            "update_date" => Ok(SortBy::UpdateDate),
+            "title" => Ok(SortBy::Title),
+            "title_bytes" => Ok(SortBy::TitleBytes),
+            "weight" => Ok(SortBy::Weight),
+            "slug" => Ok(SortBy::Slug),
+            "none" => Ok(SortBy::None),
+            _ => Err(Error::InvalidSortBy(s.into())),
+        }
+    }
+}
+
+/// How a generated Zola section index sorts and paginates its pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SectionIndex {
+    /// the order of the section's pages
+    pub sort_by: SortBy,
+    /// the number of pages per page of the section's listing, if it is paginated
+    pub paginate_by: Option<u32>,
+    /// whether the section's pages are also listed by its parent
+    pub transparent: bool,
+}
+
+impl SectionIndex {
+    /// The default root section: sorted by date, ten posts per page.
+    pub fn root() -> Self {
+        SectionIndex {
+            sort_by: SortBy::Date,
+            paginate_by: Some(10),
+            transparent: false,
+        }
+    }
+
+    /// The default year, month, or day section: sorted by date, unpaginated, and transparent.
+    pub fn branch() -> Self {
+        SectionIndex {
+            sort_by: SortBy::Date,
+            paginate_by: None,
+            transparent: true,
+        }
+    }
+}
+
+/// How a slug is generated from a post's title, for posts which don't have one.
+///
+/// Posts which have a slug in Ghost keep it verbatim, so that their urls don't change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SlugOptions {
+    /// whether to keep non-ascii letters and digits, i.e. `café`, rather than transliterate them
+    /// into ascii, i.e. `cafe`
+    ///
+    /// Zola handles utf-8 paths, but by default slugifies them into ascii again; set its
+    /// `slugify.paths` to `safe` to keep them.
+    pub unicode: bool,
+    /// the character between the words of a slug; `-` by default
+    pub separator: char,
+    /// the greatest number of characters in a slug; 150 by default
+    pub max_length: usize,
+}
+
+impl Default for SlugOptions {
+    fn default() -> Self {
+        SlugOptions {
+            unicode: false,
+            separator: '-',
+            max_length: 150,
+        }
+    }
+}
+
+impl SlugOptions {
+    /// make a slug of some text, i.e. `Hello, World!` to `hello-world`
+    pub fn slugify(&self, text: &str) -> String {
+        let separator = self.separator.to_string();
+        if !self.unicode {
+            let slug = slugify::slugify(text, "", "-", Some(self.max_length));
+            return slug.replace('-', &separator);
+        }
+
+        let mut slug = String::new();
+        for c in text.chars().flat_map(char::to_lowercase) {
+            if c.is_alphanumeric() {
+                slug.push(c);
+            } else if !slug.is_empty() && !slug.ends_with(self.separator) {
+                slug.push(self.separator);
+            }
+        }
+        let slug: String = slug.chars().take(self.max_length).collect();
+        slug.trim_end_matches(self.separator).to_string()
+    }
+}
+
+/// What to do when a file to be written already exists.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConflictPolicy {
+    /// leave the existing file alone
+    Skip,
+    /// replace the existing file
+    #[default]
+    Overwrite,
+    /// rename the existing file beside itself, i.e. to `hello.md.bak`, then write the new one
+    Backup,
+    /// abort the extraction
+    Error,
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = Error;
+
+    /// parse one of `skip`, `overwrite`, `backup`, or `error`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(ConflictPolicy::Skip),
+            "overwrite" => Ok(ConflictPolicy::Overwrite),
+            "backup" => Ok(ConflictPolicy::Backup),
+            "error" => Ok(ConflictPolicy::Error),
+            _ => Err(Error::InvalidConflictPolicy(s.into())),
+        }
+    }
+}
+
+/// The format in which the manifest of an extraction is written.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ManifestFormat {
+    /// an array of objects
+    #[default]
+    Json,
+    /// a header, then a record per url
+    Csv,
+}
+
+impl FromStr for ManifestFormat {
+    type Err = Error;
+
+    /// parse one of `json` or `csv`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ManifestFormat::Json),
+            "csv" => Ok(ManifestFormat::Csv),
+            _ => Err(Error::InvalidManifestFormat(s.into())),
+        }
+    }
+}
+
+/// The format in which the summary of an extraction is written.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReportFormat {
+    /// an object of counts and lists
+    #[default]
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = Error;
+
+    /// parse `json`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ReportFormat::Json),
+            _ => Err(Error::InvalidReportFormat(s.into())),
+        }
+    }
+}
+
+/// Where the extracted images are written.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImagesDest {
+    /// within the extract path, beside the posts which use them
+    #[default]
+    Content,
+    /// within the site's static directory, which is served as it is
+    Static,
+}
+
+impl FromStr for ImagesDest {
+    type Err = Error;
+
+    /// parse one of `content` or `static`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "content" => Ok(ImagesDest::Content),
+            "static" => Ok(ImagesDest::Static),
+            _ => Err(Error::InvalidImagesDest(s.into())),
+        }
+    }
+}
+
+/// A modern image format into which extracted images are converted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    /// lossless WebP
+    Webp,
+    /// AVIF, which is smaller, but much slower to encode
+    Avif,
+}
+
+impl ImageFormat {
+    /// the extension of an image in this format
+    pub(crate) fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Webp => "webp",
+            ImageFormat::Avif => "avif",
+        }
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = Error;
+
+    /// parse one of `webp` or `avif`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "webp" => Ok(ImageFormat::Webp),
+            "avif" => Ok(ImageFormat::Avif),
+            _ => Err(Error::InvalidImageFormat(s.into())),
+        }
+    }
+}
+
+/// What to do with the images which Ghost's Unsplash integration hotlinks from
+/// `images.unsplash.com`, with long query strings selecting their size and format.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnsplashPolicy {
+    /// link to them as they are
+    #[default]
+    Keep,
+    /// normalize their query strings to the size Ghost serves by default, 2000 pixels wide
+    Normalize,
+    /// normalize, then download them like [other external
+    /// images][ExtractOptions::download_external_images], keeping the photographer's attribution
+    /// from the caption in the image's alt text
+    Download,
+}
+
+impl FromStr for UnsplashPolicy {
+    type Err = Error;
+
+    /// parse one of `keep`, `normalize`, or `download`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(UnsplashPolicy::Keep),
+            "normalize" => Ok(UnsplashPolicy::Normalize),
+            "download" => Ok(UnsplashPolicy::Download),
+            _ => Err(Error::InvalidUnsplashPolicy(s.into())),
+        }
+    }
+}
+
+/// How the names of the files and directories which are extracted are made safe.
+///
+/// Names come from slugs, which Ghost mostly restricts to portable characters, but imported
+/// posts and generated slugs may use characters which Windows forbids, i.e. `:` or `?`, end in a
+/// dot, or be one of its reserved names, i.e. `con` or `aux`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PathSanitization {
+    /// use names as they are
+    #[default]
+    None,
+    /// replace each forbidden character with `-`, trim trailing dots and spaces, and suffix
+    /// reserved names with `_`, i.e. `con_`
+    Windows,
+}
+
+impl FromStr for PathSanitization {
+    type Err = Error;
+
+    /// parse one of `none` or `windows`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(PathSanitization::None),
+            "windows" => Ok(PathSanitization::Windows),
+            _ => Err(Error::InvalidPathSanitization(s.into())),
+        }
+    }
+}
+
+/// When to use a post's pre-rendered html as its content, instead of converting it.
+///
+/// Html content is wrapped so that Zola passes it through untouched, and flagged with
+/// `extra.raw_html` for later manual conversion.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RawHtmlPolicy {
+    /// never use the html
+    #[default]
+    Never,
+    /// use the html of posts which have no markdown, rather than their plaintext
+    Fallback,
+    /// use the html of every post which has any
+    Always,
+}
+
+impl FromStr for RawHtmlPolicy {
+    type Err = Error;
+
+    /// parse one of `never`, `fallback`, or `always`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "never" => Ok(RawHtmlPolicy::Never),
+            "fallback" => Ok(RawHtmlPolicy::Fallback),
+            "always" => Ok(RawHtmlPolicy::Always),
+            _ => Err(Error::InvalidRawHtmlPolicy(s.into())),
+        }
+    }
+}
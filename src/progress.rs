@@ -0,0 +1,293 @@
+//! Observing the progress of an extraction.
+
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A stage of an extraction.
+///
+/// Only the passes over the archive, `Scanning` and `Extracting`, are reported to a
+/// [`ProgressObserver`]'s archive methods.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Phase {
+    /// searching the archive for the database and images
+    Scanning,
+    /// extracting the database and images from the archive
+    Extracting,
+    /// extracting images from the archive, or downloading them from the api
+    Images,
+    /// rendering posts
+    Posts,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Phase::Scanning => write!(f, "scanning"),
+            Phase::Extracting => write!(f, "extracting"),
+            Phase::Images => write!(f, "images"),
+            Phase::Posts => write!(f, "posts"),
+        }
+    }
+}
+
+/// Observes the progress of an extraction.
+///
+/// All methods do nothing by default, so implementors need only override those they care about.
+pub trait ProgressObserver: Send + Sync {
+    /// an archive entry is about to be processed
+    fn archive_entry(&self, _phase: Phase, _idx: usize, _path: &Path, _size: u64) {}
+
+    /// every entry of the archive has been processed
+    fn archive_done(&self, _phase: Phase, _n_entries: usize) {}
+
+    /// an image has been extracted or downloaded to `path`
+    fn image_extracted(&self, _path: &Path) {}
+
+    /// a post has been rendered to `path`, relative to the extract path
+    fn post_written(&self, _idx: usize, _path: &Path) {}
+
+    /// every post has been rendered
+    fn posts_done(&self, _n_posts: usize) {}
+}
+
+impl fmt::Debug for dyn ProgressObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ProgressObserver")
+    }
+}
+
+type Callback = dyn Fn(Phase, usize, Option<usize>) + Send + Sync;
+
+/// A callback reporting how far an extraction has come, i.e. for a frontend to draw its own
+/// progress bars.
+///
+/// It's called with a phase, the number of archive entries, images, or posts which that phase has
+/// processed so far, and their total, if it's known. Once a phase is complete, it's called with
+/// its total.
+#[derive(Clone)]
+pub struct ProgressCallback(Arc<Callback>);
+
+impl ProgressCallback {
+    /// construct a callback from a closure of the phase, the number processed, and the total
+    pub fn new<F>(callback: F) -> ProgressCallback
+    where
+        F: Fn(Phase, usize, Option<usize>) + Send + Sync + 'static,
+    {
+        ProgressCallback(Arc::new(callback))
+    }
+
+    pub(crate) fn call(&self, phase: Phase, done: usize, total: Option<usize>) {
+        (self.0)(phase, done, total)
+    }
+
+    /// report a phase as complete
+    pub(crate) fn done(&self, phase: Phase, total: usize) {
+        self.call(phase, total, Some(total))
+    }
+}
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ProgressCallback")
+    }
+}
+
+/// format a number of bytes with a binary unit suffix
+pub(crate) fn human_bytes(bytes: f64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+struct StatusLineState {
+    phase: Option<Phase>,
+    phase_start: Instant,
+    bytes: u64,
+    last_draw: Option<Instant>,
+}
+
+/// Keeps a single updating line on stderr describing the archive entry currently being processed.
+pub struct StatusLine {
+    start: Instant,
+    width: usize,
+    state: Mutex<StatusLineState>,
+}
+
+impl StatusLine {
+    const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+    /// construct a status line no wider than `width` characters
+    pub fn new(width: usize) -> StatusLine {
+        let now = Instant::now();
+        StatusLine {
+            start: now,
+            width,
+            state: Mutex::new(StatusLineState {
+                phase: None,
+                phase_start: now,
+                bytes: 0,
+                last_draw: None,
+            }),
+        }
+    }
+}
+
+impl Default for StatusLine {
+    fn default() -> Self {
+        StatusLine::new(100)
+    }
+}
+
+impl ProgressObserver for StatusLine {
+    fn archive_entry(&self, phase: Phase, idx: usize, path: &Path, size: u64) {
+        let mut state = self
+            .state
+            .lock()
+            .expect("status line lock is never poisoned");
+        let now = Instant::now();
+        if state.phase != Some(phase) {
+            state.phase = Some(phase);
+            state.phase_start = now;
+            state.bytes = 0;
+        }
+        state.bytes += size;
+        if state
+            .last_draw
+            .is_some_and(|last_draw| now.duration_since(last_draw) < Self::REDRAW_INTERVAL)
+        {
+            return;
+        }
+        state.last_draw = Some(now);
+
+        let phase_secs = now.duration_since(state.phase_start).as_secs_f64();
+        let rate = if phase_secs > 0.0 {
+            state.bytes as f64 / phase_secs
+        } else {
+            0.0
+        };
+        let elapsed = now.duration_since(self.start).as_secs();
+        let line = format!(
+            "[{:02}:{:02}:{:02}] {} #{} {}/s {}",
+            elapsed / 3600,
+            (elapsed / 60) % 60,
+            elapsed % 60,
+            phase,
+            idx,
+            human_bytes(rate),
+            path.display(),
+        );
+        let line: String = line.chars().take(self.width).collect();
+        // clear the line before drawing, in case the previous line was longer
+        eprint!("\r\x1b[2K{}", line);
+        let _ = std::io::stderr().flush();
+    }
+
+    fn archive_done(&self, phase: Phase, n_entries: usize) {
+        let mut state = self
+            .state
+            .lock()
+            .expect("status line lock is never poisoned");
+        state.last_draw = None;
+        let elapsed = Instant::now().duration_since(state.phase_start);
+        eprintln!(
+            "\r\x1b[2K{} {} entries ({}) in {:.1}s",
+            phase,
+            n_entries,
+            human_bytes(state.bytes as f64),
+            elapsed.as_secs_f64(),
+        );
+    }
+}
+
+/// Draws progress bars on stderr: one for the archive entries processed, one for the images
+/// extracted, and one for the posts rendered.
+///
+/// The entries are counted against the number found by the scan of the archive, if there was
+/// one; the images and posts can't be known in advance, so they're only counted.
+pub struct ProgressBars {
+    entries: ProgressBar,
+    images: ProgressBar,
+    posts: ProgressBar,
+}
+
+impl ProgressBars {
+    const TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+    /// construct the bars, which are drawn only if stderr is a terminal
+    pub fn new() -> ProgressBars {
+        let bars = MultiProgress::with_draw_target(ProgressDrawTarget::stderr());
+        let spinner = |template: &str| {
+            let bar = bars.add(ProgressBar::new_spinner());
+            bar.set_style(
+                ProgressStyle::with_template(template).expect("progress templates are valid"),
+            );
+            bar.enable_steady_tick(Self::TICK_INTERVAL);
+            bar
+        };
+        ProgressBars {
+            entries: spinner("{spinner} [{elapsed_precise}] {prefix:>10} {pos} entries {wide_msg}"),
+            images: spinner("{spinner} [{elapsed_precise}] {pos:>10} images extracted"),
+            posts: spinner("{spinner} [{elapsed_precise}] {pos:>10} posts rendered {wide_msg}"),
+        }
+    }
+}
+
+impl Default for ProgressBars {
+    fn default() -> Self {
+        ProgressBars::new()
+    }
+}
+
+impl ProgressObserver for ProgressBars {
+    fn archive_entry(&self, phase: Phase, idx: usize, path: &Path, _size: u64) {
+        self.entries.set_prefix(phase.to_string());
+        self.entries.set_position(idx as u64 + 1);
+        self.entries.set_message(path.display().to_string());
+    }
+
+    fn archive_done(&self, phase: Phase, n_entries: usize) {
+        match phase {
+            // the extraction reads the same entries again
+            Phase::Scanning => {
+                self.entries.set_style(
+                    ProgressStyle::with_template(
+                        "{spinner} [{elapsed_precise}] {prefix:>10} {wide_bar} {pos}/{len} entries",
+                    )
+                    .expect("progress templates are valid"),
+                );
+                self.entries.set_length(n_entries as u64);
+                self.entries.set_position(0);
+            }
+            Phase::Extracting => {
+                self.entries.set_prefix(phase.to_string());
+                self.entries.finish_with_message("");
+                self.images.finish();
+            }
+            // not passes over the archive
+            Phase::Images | Phase::Posts => {}
+        }
+    }
+
+    fn image_extracted(&self, _path: &Path) {
+        self.images.inc(1);
+    }
+
+    fn post_written(&self, _idx: usize, path: &Path) {
+        self.posts.inc(1);
+        self.posts.set_message(path.display().to_string());
+    }
+
+    fn posts_done(&self, _n_posts: usize) {
+        self.images.finish();
+        self.posts.finish_with_message("");
+    }
+}
@@ -0,0 +1,1091 @@
+//! Configurable queries for posts within a Ghost database.
+
+use crate::data_model::{
+    choose_content, description_or_excerpt, non_empty, ContentFormats, ContentSource, Extra,
+    LinkRewriter, Post, PostId, Social, Taxonomies,
+};
+use crate::{Error, ExtractOptions, ImageSource, KeyMapping, RawHtmlPolicy, Shortcodes};
+use chrono::NaiveDate;
+use rusqlite::{self, params, Connection, OptionalExtension};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+/// SQL expressions from which each field of a [`Post`] is selected.
+///
+/// The defaults match the Ghost schema; override individual expressions to adapt to
+/// other schema versions, i.e. `posts.locale` instead of `posts.language`, or use
+/// [`PostColumns::detect`] to do so automatically.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PostColumns {
+    /// Ghost's uuid for the post, which unlike its id is stable across imports
+    pub uuid: String,
+    pub title: String,
+    /// markdown content of the post
+    pub content: String,
+    /// mobiledoc document of the post, rendered when it has no markdown
+    pub mobiledoc: String,
+    /// lexical document of the post, rendered in preference to its mobiledoc
+    pub lexical: String,
+    /// plaintext rendering of the post, used when it has no markdown
+    pub plaintext: String,
+    /// pre-rendered html of the post, used according to the [`RawHtmlPolicy`]
+    pub html: String,
+    pub description: String,
+    /// hand-written excerpt, which is the description when there is no meta description
+    pub excerpt: String,
+    pub date: String,
+    pub updated: String,
+    pub created: String,
+    pub status: String,
+    pub slug: String,
+    pub language: String,
+    /// url of the post's hero image
+    pub feature_image: String,
+    /// whether the post is featured
+    pub featured: String,
+    /// who may read the post, i.e. `public`, `members`, or `paid`
+    pub visibility: String,
+    /// whether the post is a standalone page, rather than a post
+    pub page: String,
+    /// url of the post's canonical version, i.e. where it was first published
+    pub canonical_url: String,
+    pub og_image: String,
+    pub og_title: String,
+    pub og_description: String,
+    pub twitter_image: String,
+    pub twitter_title: String,
+    pub twitter_description: String,
+    /// markup injected into the `<head>` of the post's page
+    pub codeinjection_head: String,
+    /// markup injected at the foot of the post's page
+    pub codeinjection_foot: String,
+    /// only used when authors are joined
+    pub author_name: String,
+    /// subquery selecting the ids of the post's authors; only used to filter posts by author
+    pub author_ids: String,
+}
+
+impl Default for PostColumns {
+    fn default() -> Self {
+        PostColumns {
+            uuid: "posts.uuid".into(),
+            title: "posts.title".into(),
+            content: "posts.markdown".into(),
+            // Ghost 0.x has no editor documents, nor plaintext column
+            mobiledoc: "NULL".into(),
+            lexical: "NULL".into(),
+            plaintext: "NULL".into(),
+            html: "posts.html".into(),
+            description: "posts.meta_description".into(),
+            // custom excerpts were introduced in Ghost 1.x
+            excerpt: "NULL".into(),
+            date: "posts.published_at".into(),
+            updated: "posts.updated_at".into(),
+            created: "posts.created_at".into(),
+            status: "posts.status".into(),
+            slug: "posts.slug".into(),
+            language: "posts.language".into(),
+            feature_image: "posts.image".into(),
+            featured: "posts.featured".into(),
+            visibility: "posts.visibility".into(),
+            page: "posts.page".into(),
+            // canonical urls were introduced in Ghost 2.x
+            canonical_url: "NULL".into(),
+            // social metadata was introduced in Ghost 1.x
+            og_image: "NULL".into(),
+            og_title: "NULL".into(),
+            og_description: "NULL".into(),
+            twitter_image: "NULL".into(),
+            twitter_title: "NULL".into(),
+            twitter_description: "NULL".into(),
+            // code injection was introduced in Ghost 1.x
+            codeinjection_head: "NULL".into(),
+            codeinjection_foot: "NULL".into(),
+            author_name: "users.name".into(),
+            author_ids: "SELECT posts.author_id".into(),
+        }
+    }
+}
+
+impl PostColumns {
+    /// choose column expressions matching the schema of this database
+    ///
+    /// Ghost's schema has varied between versions. Each field is selected from the first of
+    /// its known columns which exists in `posts`, or failing that in `posts_meta`, to which
+    /// Ghost 3.0 moved the metadata columns; if none do, it is `NULL`.
+    pub fn detect(conn: &Connection) -> Result<PostColumns, rusqlite::Error> {
+        let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1)")?;
+        let mut columns_of = |table: &str| {
+            stmt.query_map(params![table], |row| row.get::<_, String>(0))?
+                .collect::<Result<HashSet<_>, _>>()
+        };
+        let posts = columns_of("posts")?;
+        let posts_meta = columns_of("posts_meta")?;
+        let posts_authors = !columns_of("posts_authors")?.is_empty();
+        let choose = |candidates: &[&str]| {
+            candidates
+                .iter()
+                .find_map(|column| {
+                    if posts.contains(*column) {
+                        Some(format!("posts.{}", column))
+                    } else if posts_meta.contains(*column) {
+                        Some(format!(
+                            "(SELECT posts_meta.{} FROM posts_meta WHERE posts_meta.post_id = posts.id)",
+                            column
+                        ))
+                    } else {
+                        None
+                    }
+                })
+                .unwrap_or_else(|| "NULL".into())
+        };
+
+        Ok(PostColumns {
+            uuid: choose(&["uuid"]),
+            title: choose(&["title"]),
+            content: choose(&["markdown"]),
+            mobiledoc: choose(&["mobiledoc"]),
+            lexical: choose(&["lexical"]),
+            plaintext: choose(&["plaintext"]),
+            html: choose(&["html"]),
+            description: choose(&["meta_description"]),
+            excerpt: choose(&["custom_excerpt"]),
+            date: choose(&["published_at"]),
+            updated: choose(&["updated_at"]),
+            created: choose(&["created_at"]),
+            status: choose(&["status"]),
+            slug: choose(&["slug"]),
+            language: choose(&["language", "locale"]),
+            feature_image: choose(&["feature_image", "image"]),
+            featured: choose(&["featured"]),
+            visibility: choose(&["visibility"]),
+            // Ghost 2.0 replaced the `page` flag with a `type` column
+            page: if posts.contains("type") {
+                "posts.type = 'page'".into()
+            } else {
+                choose(&["page"])
+            },
+            canonical_url: choose(&["canonical_url"]),
+            og_image: choose(&["og_image"]),
+            og_title: choose(&["og_title"]),
+            og_description: choose(&["og_description"]),
+            twitter_image: choose(&["twitter_image"]),
+            twitter_title: choose(&["twitter_title"]),
+            twitter_description: choose(&["twitter_description"]),
+            codeinjection_head: choose(&["codeinjection_head"]),
+            codeinjection_foot: choose(&["codeinjection_foot"]),
+            author_name: "users.name".into(),
+            // Ghost 2.0 introduced multiple authors, keeping `author_id` as the primary author
+            author_ids: if posts_authors {
+                "SELECT posts_authors.author_id FROM posts_authors \
+                 WHERE posts_authors.post_id = posts.id \
+                 UNION SELECT posts.author_id"
+                    .into()
+            } else {
+                "SELECT posts.author_id".into()
+            },
+        })
+    }
+
+    /// these columns, but selecting none of the posts' content, for a pass which reads only their
+    /// metadata
+    pub fn without_content(self) -> PostColumns {
+        PostColumns {
+            content: "NULL".into(),
+            mobiledoc: "NULL".into(),
+            lexical: "NULL".into(),
+            plaintext: "NULL".into(),
+            html: "NULL".into(),
+            ..self
+        }
+    }
+}
+
+/// Restrictions on which posts are queried, for partial migrations.
+///
+/// Each restriction which is set must match. A post matches a list of tags, authors, or statuses
+/// if it matches any of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PostFilter {
+    /// only posts published on or after this date
+    pub since: Option<NaiveDate>,
+    /// only posts published on or before this date
+    pub until: Option<NaiveDate>,
+    /// only posts with any of these tags, by name or slug
+    pub tags: Vec<String>,
+    /// only posts by any of these authors, by name or slug
+    pub authors: Vec<String>,
+    /// only posts with any of these statuses, i.e. `published`, `draft`, or `scheduled`
+    pub statuses: Vec<String>,
+}
+
+impl PostFilter {
+    /// the SQL predicates, over the posts selected as by `columns`, which a post must match
+    ///
+    /// Posts without a publication date, i.e. drafts, don't match a date range.
+    fn predicates(&self, columns: &PostColumns) -> Vec<String> {
+        let mut predicates = Vec::new();
+        if let Some(since) = self.since {
+            predicates.push(format!("date({}) >= '{}'", columns.date, since));
+        }
+        if let Some(until) = self.until {
+            predicates.push(format!("date({}) <= '{}'", columns.date, until));
+        }
+        if !self.tags.is_empty() {
+            predicates.push(format!(
+                "EXISTS (SELECT 1 FROM posts_tags INNER JOIN tags ON posts_tags.tag_id = tags.id \
+                 WHERE posts_tags.post_id = posts.id AND {})",
+                any_name("tags", &self.tags)
+            ));
+        }
+        if !self.authors.is_empty() {
+            predicates.push(format!(
+                "EXISTS (SELECT 1 FROM users WHERE users.id IN ({}) AND {})",
+                columns.author_ids,
+                any_name("users", &self.authors)
+            ));
+        }
+        if !self.statuses.is_empty() {
+            let statuses: Vec<_> = self.statuses.iter().map(|s| sql_string(s)).collect();
+            predicates.push(format!("{} IN ({})", columns.status, statuses.join(", ")));
+        }
+        predicates
+    }
+}
+
+/// an SQL predicate matching rows of `table` with any of these names or slugs
+///
+/// Names are matched regardless of ascii case, as they're typed by hand.
+fn any_name(table: &str, names: &[String]) -> String {
+    let names: Vec<_> = names.iter().map(|name| sql_string(name)).collect();
+    let names = names.join(", ");
+    format!(
+        "({table}.slug IN ({names}) OR {table}.name COLLATE NOCASE IN ({names}))",
+        table = table,
+        names = names
+    )
+}
+
+/// quote a string as an SQL literal
+fn sql_string(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+/// A builder for queries over the posts in a Ghost database.
+///
+/// ```no_run
+/// # use ghost2zola::query::PostQuery;
+/// # let conn = rusqlite::Connection::open("ghost.db").unwrap();
+/// let published = PostQuery::new()
+///     .filter("posts.status = 'published'")
+///     .rewrite_links(false)
+///     .query(&conn)
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct PostQuery {
+    columns: PostColumns,
+    join_authors: bool,
+    filters: Vec<String>,
+    post_filter: PostFilter,
+    image_sources: Vec<ImageSource>,
+    base_url: Option<String>,
+    images_url: String,
+    keep_image_query: bool,
+    rewrite_links: bool,
+    raw_html: RawHtmlPolicy,
+    shortcodes: Shortcodes,
+}
+
+impl Default for PostQuery {
+    fn default() -> Self {
+        PostQuery {
+            columns: PostColumns::default(),
+            join_authors: true,
+            filters: Vec::new(),
+            post_filter: PostFilter::default(),
+            image_sources: ImageSource::uploads(),
+            base_url: None,
+            images_url: ExtractOptions::default().images_url(),
+            keep_image_query: false,
+            rewrite_links: true,
+            raw_html: RawHtmlPolicy::default(),
+            shortcodes: Shortcodes::default(),
+        }
+    }
+}
+
+impl PostQuery {
+    pub fn new() -> PostQuery {
+        PostQuery::default()
+    }
+
+    /// set the SQL expressions from which each field is selected
+    pub fn columns(mut self, columns: PostColumns) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// whether to join the `users` table to find each post's authors
+    ///
+    /// Defaults to `true`. Where the database has a `posts_authors` table, as from Ghost 2.0, each
+    /// post's authors are read from it in order; otherwise, or if it lists none, the author is
+    /// `posts.author_id`. Posts whose author can't be found have no authors. When authors are not
+    /// joined, every post's authors are empty.
+    pub fn join_authors(mut self, join_authors: bool) -> Self {
+        self.join_authors = join_authors;
+        self
+    }
+
+    /// restrict the query to posts matching an SQL predicate
+    ///
+    /// The predicate is inserted verbatim into the `WHERE` clause of the posts query, so it can
+    /// refer to any column of `posts` or `users`, e.g. `posts.created_at > '2020-01-01'`.
+    /// Multiple filters must all match.
+    pub fn filter(mut self, predicate: impl Into<String>) -> Self {
+        self.filters.push(predicate.into());
+        self
+    }
+
+    /// restrict the query to posts matching a filter, as well as any SQL predicates
+    pub fn post_filter(mut self, post_filter: PostFilter) -> Self {
+        self.post_filter = post_filter;
+        self
+    }
+
+    /// set the image sources whose links are rewritten
+    pub fn image_sources(mut self, image_sources: Vec<ImageSource>) -> Self {
+        self.image_sources = image_sources;
+        self
+    }
+
+    /// set the url at which the blog was served, absolute links to which are treated as internal
+    pub fn base_url(mut self, base_url: Option<String>) -> Self {
+        self.base_url = base_url;
+        self
+    }
+
+    /// set the url at which the extracted images are served
+    ///
+    /// Defaults to `/blog`, where Zola serves the images alongside the posts.
+    pub fn images_url(mut self, images_url: impl Into<String>) -> Self {
+        self.images_url = images_url.into();
+        self
+    }
+
+    /// whether to keep the query strings and fragments of rewritten image links
+    ///
+    /// Defaults to `false`.
+    pub fn keep_image_query(mut self, keep_image_query: bool) -> Self {
+        self.keep_image_query = keep_image_query;
+        self
+    }
+
+    /// whether to rewrite internal image links, and feature images, to point at the extracted images
+    ///
+    /// Defaults to `true`.
+    pub fn rewrite_links(mut self, rewrite_links: bool) -> Self {
+        self.rewrite_links = rewrite_links;
+        self
+    }
+
+    /// when to use each post's pre-rendered html as its content
+    ///
+    /// Defaults to [`RawHtmlPolicy::Never`].
+    pub fn raw_html(mut self, raw_html: RawHtmlPolicy) -> Self {
+        self.raw_html = raw_html;
+        self
+    }
+
+    /// set the shortcodes with which posts' editor documents are rendered
+    pub fn shortcodes(mut self, shortcodes: Shortcodes) -> Self {
+        self.shortcodes = shortcodes;
+        self
+    }
+
+    /// restrict the query to the posts with these ids
+    pub fn ids(self, ids: &[PostId]) -> Self {
+        let ids: Vec<_> = ids
+            .iter()
+            .map(|id| match id {
+                PostId::Int(id) => id.to_string(),
+                PostId::Text(id) => sql_string(id),
+            })
+            .collect();
+        self.filter(format!("posts.id IN ({})", ids.join(", ")))
+    }
+
+    /// the ids of up to `n` matching posts: the first `n` by id, or else `n` chosen at random
+    ///
+    /// Restricting the query to these [`ids`][PostQuery::ids] gives a small sample of the blog,
+    /// i.e. for a trial run.
+    pub fn choose_ids(
+        &self,
+        conn: &Connection,
+        n: usize,
+        random: bool,
+    ) -> Result<Vec<PostId>, rusqlite::Error> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT posts.id FROM posts {join} WHERE 1 {filters} ORDER BY {order} LIMIT ?1",
+            join = self.join(),
+            filters = self.filters(),
+            order = if random { "RANDOM()" } else { "posts.id" },
+        ))?;
+        let mut ids = stmt
+            .query_map(params![n as i64], |row| row.get(0))?
+            .collect::<Result<Vec<PostId>, _>>()?;
+        ids.sort();
+        Ok(ids)
+    }
+
+    fn join(&self) -> &'static str {
+        if self.join_authors {
+            "LEFT JOIN users ON posts.author_id = users.id"
+        } else {
+            ""
+        }
+    }
+
+    fn filters(&self) -> String {
+        self.filters
+            .iter()
+            .cloned()
+            .chain(self.post_filter.predicates(&self.columns))
+            .map(|filter| format!("AND ({})\n", filter))
+            .collect()
+    }
+
+    fn sql(&self) -> String {
+        let columns = &self.columns;
+        let author_name = if self.join_authors {
+            columns.author_name.as_str()
+        } else {
+            "NULL"
+        };
+        format!(
+            "
+            SELECT
+                posts.id AS id,
+                {uuid} AS uuid,
+                {title} AS title,
+                {content} AS content,
+                {mobiledoc} AS mobiledoc,
+                {lexical} AS lexical,
+                {plaintext} AS plaintext,
+                {html} AS html,
+                {description} AS description,
+                {excerpt} AS excerpt,
+                {date} AS date,
+                {updated} AS updated,
+                {created} AS created,
+                {status} AS status,
+                {slug} AS slug,
+                {language} AS language,
+                {feature_image} AS feature_image,
+                {featured} AS featured,
+                {visibility} AS visibility,
+                {page} AS page,
+                {canonical_url} AS canonical_url,
+                {og_image} AS og_image,
+                {og_title} AS og_title,
+                {og_description} AS og_description,
+                {twitter_image} AS twitter_image,
+                {twitter_title} AS twitter_title,
+                {twitter_description} AS twitter_description,
+                {codeinjection_head} AS codeinjection_head,
+                {codeinjection_foot} AS codeinjection_foot,
+                {author_name} AS author_name
+            FROM posts
+            {join}
+            WHERE posts.id > ?1
+            {filters}
+            ORDER BY posts.id
+            LIMIT 1
+            ",
+            uuid = columns.uuid,
+            title = columns.title,
+            content = columns.content,
+            mobiledoc = columns.mobiledoc,
+            lexical = columns.lexical,
+            plaintext = columns.plaintext,
+            html = columns.html,
+            description = columns.description,
+            excerpt = columns.excerpt,
+            date = columns.date,
+            updated = columns.updated,
+            created = columns.created,
+            status = columns.status,
+            slug = columns.slug,
+            language = columns.language,
+            feature_image = columns.feature_image,
+            featured = columns.featured,
+            visibility = columns.visibility,
+            page = columns.page,
+            canonical_url = columns.canonical_url,
+            og_image = columns.og_image,
+            og_title = columns.og_title,
+            og_description = columns.og_description,
+            twitter_image = columns.twitter_image,
+            twitter_title = columns.twitter_title,
+            twitter_description = columns.twitter_description,
+            codeinjection_head = columns.codeinjection_head,
+            codeinjection_foot = columns.codeinjection_foot,
+            author_name = author_name,
+            join = self.join(),
+            filters = self.filters(),
+        )
+    }
+
+    /// iterate over the matching posts, in order of ascending id
+    pub fn iter<'conn>(&self, conn: &'conn Connection) -> Posts<'conn> {
+        self.posts(Conn::Borrowed(conn))
+    }
+
+    /// iterate over the matching posts, as [`PostQuery::iter`] does, taking the connection with it
+    ///
+    /// The iterator can then outlive the scope which opened the database.
+    pub fn into_posts(self, conn: Connection) -> Posts<'static> {
+        self.posts(Conn::Owned(conn))
+    }
+
+    fn posts<'conn>(&self, conn: Conn<'conn>) -> Posts<'conn> {
+        Posts {
+            conn,
+            sql: self.sql(),
+            links: if self.rewrite_links {
+                Some(
+                    LinkRewriter::new(self.image_sources.clone())
+                        .site_url(self.base_url.clone())
+                        .images_url(self.images_url.clone())
+                        .keep_query(self.keep_image_query),
+                )
+            } else {
+                None
+            },
+            raw_html: self.raw_html,
+            shortcodes: self.shortcodes.clone(),
+            posts_authors: if self.join_authors { None } else { Some(false) },
+            tags_sorted: None,
+            // sqlite orders all integers before all text, so this precedes every id
+            last_id: PostId::Int(i64::MIN),
+            done: false,
+        }
+    }
+
+    /// collect every matching post into memory
+    ///
+    /// For large blogs, prefer [`PostQuery::iter`], which yields posts one at a time.
+    pub fn query(&self, conn: &Connection) -> Result<Vec<Post>, Error> {
+        self.iter(conn).collect()
+    }
+}
+
+/// The stage of reading a post from the database at which it failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stage {
+    /// reading the named column of its row, i.e. `updated`
+    Column(String),
+    /// reading its row, at a column which sqlite didn't identify
+    Row,
+    /// reading its tags
+    Tags,
+    /// reading its authors
+    Authors,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::Column(column) => write!(f, "invalid {}", column),
+            Stage::Row => write!(f, "unreadable row"),
+            Stage::Tags => write!(f, "unreadable tags"),
+            Stage::Authors => write!(f, "unreadable authors"),
+        }
+    }
+}
+
+impl Stage {
+    /// the stage at which reading a post's row failed with this error
+    fn of_row(row: &rusqlite::Row, err: &rusqlite::Error) -> Stage {
+        let idx = match err {
+            rusqlite::Error::FromSqlConversionFailure(idx, ..)
+            | rusqlite::Error::IntegralValueOutOfRange(idx, _)
+            | rusqlite::Error::InvalidColumnType(idx, ..) => *idx,
+            rusqlite::Error::InvalidColumnName(column) => return Stage::Column(column.clone()),
+            _ => return Stage::Row,
+        };
+        match row.column_name(idx) {
+            Ok(column) => Stage::Column(column.to_string()),
+            Err(_) => Stage::Row,
+        }
+    }
+}
+
+/// attach the post's id and slug to an error reading its row, if they can be read themselves
+fn row_error(row: &rusqlite::Row, err: rusqlite::Error) -> Error {
+    let stage = Stage::of_row(row, &err);
+    match row.get("id") {
+        Ok(id) => Error::Post {
+            id,
+            slug: row
+                .get::<_, Option<String>>("slug")
+                .ok()
+                .flatten()
+                .unwrap_or_default(),
+            stage,
+            source: err,
+        },
+        Err(_) => Error::Sql(err),
+    }
+}
+
+/// attach a post's id and slug to an error completing it
+fn post_error(post: &Post, stage: Stage, err: rusqlite::Error) -> Error {
+    Error::Post {
+        id: post.extra.id.clone(),
+        slug: post.slug.clone(),
+        stage,
+        source: err,
+    }
+}
+
+fn post_from_row(
+    row: &rusqlite::Row,
+    raw_html: RawHtmlPolicy,
+    shortcodes: &Shortcodes,
+) -> Result<Post, rusqlite::Error> {
+    let (content, source) = choose_content(
+        &row.get("id")?,
+        &row.get::<_, String>("slug")?,
+        ContentFormats {
+            markdown: row.get("content")?,
+            mobiledoc: row.get("mobiledoc")?,
+            lexical: row.get("lexical")?,
+            plaintext: row.get("plaintext")?,
+            html: row.get("html")?,
+        },
+        raw_html,
+        shortcodes,
+    );
+
+    let excerpt = non_empty_column(row, "excerpt")?;
+
+    Ok(Post {
+        title: row.get("title")?,
+        content,
+        description: description_or_excerpt(row.get("description")?, excerpt.as_deref()),
+        date: row.get("date")?,
+        updated: row.get("updated")?,
+        aliases: Vec::new(),
+        // the creation time is only a fallback for the publication date, so it needn't be valid
+        created: row.get("created").unwrap_or_else(|err| {
+            log::debug!("ignoring unreadable creation time: {}", err);
+            None
+        }),
+        status: row.get("status")?,
+        slug: row.get("slug")?,
+        page: row.get::<_, Option<bool>>("page")?.unwrap_or_default(),
+        keys: KeyMapping::default(),
+        extra: Extra {
+            id: row.get("id")?,
+            uuid: non_empty_column(row, "uuid")?,
+            language: row
+                .get::<_, Option<String>>("language")?
+                .unwrap_or_default(),
+            author_name: row
+                .get::<_, Option<String>>("author_name")?
+                .unwrap_or_default(),
+            authors: Vec::new(),
+            feature_image: non_empty_column(row, "feature_image")?,
+            featured: row.get::<_, Option<bool>>("featured")?.unwrap_or_default(),
+            visibility: non_empty_column(row, "visibility")?,
+            excerpt,
+            canonical_url: non_empty_column(row, "canonical_url")?,
+            codeinjection_head: non_empty_column(row, "codeinjection_head")?,
+            codeinjection_foot: non_empty_column(row, "codeinjection_foot")?,
+            // computed later, if at all
+            words: None,
+            reading_time: None,
+            plaintext_fallback: source == ContentSource::Plaintext,
+            raw_html: source == ContentSource::Html,
+            // separated from the tags later, if at all
+            internal_tags: Vec::new(),
+            custom: BTreeMap::new(),
+            social: Social {
+                og_image: non_empty_column(row, "og_image")?,
+                og_title: non_empty_column(row, "og_title")?,
+                og_description: non_empty_column(row, "og_description")?,
+                twitter_image: non_empty_column(row, "twitter_image")?,
+                twitter_title: non_empty_column(row, "twitter_title")?,
+                twitter_description: non_empty_column(row, "twitter_description")?,
+            },
+        },
+        taxonomies: Taxonomies::default(),
+    })
+}
+
+/// read an optional text column, treating blank text as absent
+fn non_empty_column(row: &rusqlite::Row, column: &str) -> Result<Option<String>, rusqlite::Error> {
+    Ok(non_empty(row.get(column)?))
+}
+
+/// whether the database has a table of this name
+fn has_table(conn: &Connection, name: &str) -> Result<bool, rusqlite::Error> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+        params![name],
+        |row| row.get(0),
+    )
+}
+
+pub(crate) fn has_column(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+) -> Result<bool, rusqlite::Error> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2)",
+        params![table, column],
+        |row| row.get(0),
+    )
+}
+
+/// A connection which a cursor either borrows, or owns.
+enum Conn<'conn> {
+    Borrowed(&'conn Connection),
+    Owned(Connection),
+}
+
+/// A cursor over the posts in a Ghost database.
+///
+/// Rather than holding a statement open across the whole table, each step fetches the
+/// single next post by id. This keeps memory use bounded by the size of one post, no matter
+/// how large the blog is.
+pub struct Posts<'conn> {
+    conn: Conn<'conn>,
+    sql: String,
+    links: Option<LinkRewriter>,
+    raw_html: RawHtmlPolicy,
+    shortcodes: Shortcodes,
+    /// whether to read authors from `posts_authors`, once known
+    posts_authors: Option<bool>,
+    /// whether `posts_tags` records the order of each post's tags, once known
+    tags_sorted: Option<bool>,
+    last_id: PostId,
+    done: bool,
+}
+
+impl<'conn> Posts<'conn> {
+    fn fetch_next(&mut self) -> Result<Option<Post>, Error> {
+        let conn = match &self.conn {
+            Conn::Borrowed(conn) => *conn,
+            Conn::Owned(conn) => conn,
+        };
+        let mut stmt = conn.prepare_cached(&self.sql)?;
+        let mut post = match stmt
+            .query_row(params![self.last_id], |row| {
+                Ok(post_from_row(row, self.raw_html, &self.shortcodes)
+                    .map_err(|err| row_error(row, err)))
+            })
+            .optional()?
+        {
+            Some(post) => post?,
+            None => return Ok(None),
+        };
+        self.last_id = post.extra.id.clone();
+
+        let tags_sorted = match self.tags_sorted {
+            Some(tags_sorted) => tags_sorted,
+            None => {
+                let tags_sorted = has_column(conn, "posts_tags", "sort_order")?;
+                self.tags_sorted = Some(tags_sorted);
+                tags_sorted
+            }
+        };
+        post.update_tags(conn, tags_sorted)
+            .map_err(|err| post_error(&post, Stage::Tags, err))?;
+        let posts_authors = match self.posts_authors {
+            Some(posts_authors) => posts_authors,
+            None => {
+                let posts_authors = has_table(conn, "posts_authors")?;
+                self.posts_authors = Some(posts_authors);
+                posts_authors
+            }
+        };
+        if posts_authors {
+            post.update_authors(conn)
+                .map_err(|err| post_error(&post, Stage::Authors, err))?;
+        }
+        if post.extra.authors.is_empty() && !post.extra.author_name.is_empty() {
+            post.extra.authors.push(post.extra.author_name.clone());
+        }
+        if let Some(links) = &self.links {
+            post.rewrite_links(links);
+        }
+        Ok(Some(post))
+    }
+}
+
+impl<'conn> Iterator for Posts<'conn> {
+    type Item = Result<Post, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let next = self.fetch_next().transpose();
+        // stop at the end of the table, or at the first error: we can't know where to resume
+        if !matches!(next, Some(Ok(_))) {
+            self.done = true;
+        }
+        next
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_model::tests::test_db;
+
+    #[test]
+    fn filters_posts() {
+        let conn = test_db();
+        let posts = PostQuery::new()
+            .filter("posts.status = 'published'")
+            .query(&conn)
+            .unwrap();
+        assert_eq!(posts.len(), 2);
+        assert_eq!(posts[0].title, "Second");
+    }
+
+    #[test]
+    fn filters_posts_by_date_tag_author_and_status() {
+        let conn = test_db();
+        conn.execute_batch(
+            "
+            UPDATE posts SET published_at = '2019-06-01 10:00:00' WHERE id = 7;
+            UPDATE posts SET published_at = '2020-01-02 10:00:00' WHERE id = 9;
+            INSERT INTO users (id, name, slug) VALUES (2, 'Ann O''Neil', 'ann');
+            UPDATE posts SET author_id = 2 WHERE id = 9;
+            ",
+        )
+        .unwrap();
+        let ids = |post_filter: PostFilter| {
+            PostQuery::new()
+                .columns(PostColumns::detect(&conn).unwrap())
+                .post_filter(post_filter)
+                .query(&conn)
+                .unwrap()
+                .into_iter()
+                .map(|post| post.extra.id)
+                .collect::<Vec<_>>()
+        };
+        let date = |s: &str| Some(s.parse().unwrap());
+
+        assert_eq!(
+            ids(PostFilter::default()),
+            vec![3.into(), 7.into(), 9.into()]
+        );
+        assert_eq!(
+            ids(PostFilter {
+                since: date("2019-06-01"),
+                ..PostFilter::default()
+            }),
+            vec![7.into(), 9.into()]
+        );
+        assert_eq!(
+            ids(PostFilter {
+                since: date("2019-01-01"),
+                until: date("2019-06-01"),
+                ..PostFilter::default()
+            }),
+            vec![7.into()]
+        );
+        assert_eq!(
+            ids(PostFilter {
+                tags: vec!["Rust".into()],
+                ..PostFilter::default()
+            }),
+            vec![7.into()]
+        );
+        assert_eq!(
+            ids(PostFilter {
+                authors: vec!["ann o'neil".into(), "nobody".into()],
+                ..PostFilter::default()
+            }),
+            vec![9.into()]
+        );
+        assert_eq!(
+            ids(PostFilter {
+                statuses: vec!["draft".into()],
+                ..PostFilter::default()
+            }),
+            vec![3.into()]
+        );
+    }
+
+    #[test]
+    fn limits_posts() {
+        let conn = test_db();
+        let query = PostQuery::new().filter("posts.status = 'published'");
+        let first = query.choose_ids(&conn, 1, false).unwrap();
+        assert_eq!(first, vec![7.into()]);
+        let posts = query.clone().ids(&first).query(&conn).unwrap();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].title, "Second");
+
+        let sample = query.choose_ids(&conn, 5, true).unwrap();
+        assert_eq!(sample, vec![7.into(), 9.into()]);
+        assert!(query.ids(&[]).query(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn query_without_rewriting_links() {
+        let conn = test_db();
+        let posts = PostQuery::new()
+            .join_authors(false)
+            .rewrite_links(false)
+            .query(&conn)
+            .unwrap();
+        assert_eq!(posts[1].content, "![](/content/images/2020/01/a.png)");
+        assert!(posts[1].extra.author_name.is_empty());
+    }
+
+    #[test]
+    fn falls_back_to_plaintext() {
+        let conn = test_db();
+        let posts = PostQuery::new()
+            .columns(PostColumns::detect(&conn).unwrap())
+            .query(&conn)
+            .unwrap();
+        assert_eq!(posts[1].content, "![](/blog/2020/01/a.png)");
+        assert!(!posts[1].extra.plaintext_fallback);
+        assert_eq!(posts[2].content, "only plain");
+        assert!(posts[2].extra.plaintext_fallback);
+    }
+
+    #[test]
+    fn reports_the_post_which_failed() {
+        let conn = test_db();
+        conn.execute_batch(
+            "
+            INSERT INTO posts (id, title, slug, markdown, status, author_id, updated_at)
+                VALUES (1234, 'Broken', 'my-slug', 'oops', 'published', 1, 'yesterday');
+            ",
+        )
+        .unwrap();
+        let err = Post::query(&conn).unwrap_err();
+        assert!(matches!(
+            &err,
+            Error::Post { id, stage: Stage::Column(column), .. }
+                if *id == 1234.into() && column == "updated"
+        ));
+        assert_eq!(err.to_string(), "post 1234 (my-slug): invalid updated");
+    }
+
+    #[test]
+    fn passes_through_html() {
+        let conn = test_db();
+        let query = PostQuery::new().columns(PostColumns::detect(&conn).unwrap());
+
+        let posts = query
+            .clone()
+            .raw_html(RawHtmlPolicy::Fallback)
+            .query(&conn)
+            .unwrap();
+        assert_eq!(posts[1].content, "![](/blog/2020/01/a.png)");
+        assert!(!posts[1].extra.raw_html);
+        assert_eq!(
+            posts[2].content,
+            "<div class=\"ghost-raw-html\">\n<p>only</p>\n<!-- -->\n<p>plain</p>\n</div>\n"
+        );
+        assert!(posts[2].extra.raw_html);
+        assert!(!posts[2].extra.plaintext_fallback);
+
+        // posts without html are never passed through
+        let posts = query.raw_html(RawHtmlPolicy::Always).query(&conn).unwrap();
+        assert!(!posts[1].extra.raw_html);
+        assert!(posts[2].extra.raw_html);
+    }
+
+    #[test]
+    fn detects_missing_columns() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "
+            CREATE TABLE posts (
+                id integer not null primary key autoincrement,
+                title varchar(150) not null,
+                slug varchar(150) not null,
+                mobiledoc text null,
+                plaintext text null,
+                status varchar(150) not null default 'draft',
+                locale varchar(6) null,
+                canonical_url text null,
+                custom_excerpt varchar(300) null,
+                codeinjection_head text null,
+                codeinjection_foot text null,
+                type varchar(50) not null default 'post',
+                author_id integer not null,
+                updated_at datetime null,
+                published_at datetime null
+            );
+            CREATE TABLE users (id integer primary key, name varchar(150) not null);
+            CREATE TABLE tags (id integer primary key, name varchar(150) not null);
+            CREATE TABLE posts_tags (post_id integer not null, tag_id integer not null);
+            CREATE TABLE posts_meta (
+                id integer primary key,
+                post_id integer not null,
+                meta_description varchar(500) null,
+                og_image text null,
+                og_title varchar(300) null
+            );
+
+            INSERT INTO users (id, name) VALUES (1, 'Pete');
+            INSERT INTO posts_meta (post_id, og_image, og_title)
+                VALUES (1, '/content/images/2020/01/og.png', 'Modern!');
+            INSERT INTO posts (
+                id, title, slug, plaintext, locale, canonical_url, custom_excerpt, codeinjection_head,
+                author_id
+            )
+                VALUES (
+                    1, 'Modern', 'modern', 'plain', 'de', 'https://example.com/modern', 'In short',
+                    '<style>
+p { color: red; }
+</style>', 1
+                );
+            ",
+        )
+        .unwrap();
+        let columns = PostColumns::detect(&conn).unwrap();
+        assert_eq!(columns.content, "NULL");
+        assert_eq!(
+            columns.description,
+            "(SELECT posts_meta.meta_description FROM posts_meta WHERE posts_meta.post_id = posts.id)"
+        );
+        assert_eq!(columns.twitter_image, "NULL");
+        assert_eq!(columns.language, "posts.locale");
+        assert_eq!(columns.date, "posts.published_at");
+        assert_eq!(columns.page, "posts.type = 'page'");
+
+        let posts = PostQuery::new().columns(columns).query(&conn).unwrap();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].content, "plain");
+        assert_eq!(posts[0].extra.language, "de");
+        assert_eq!(
+            posts[0].extra.canonical_url.as_deref(),
+            Some("https://example.com/modern")
+        );
+        assert_eq!(posts[0].description, "In short");
+        assert_eq!(posts[0].extra.excerpt.as_deref(), Some("In short"));
+        assert_eq!(
+            posts[0].extra.social.og_image.as_deref(),
+            Some("/blog/2020/01/og.png")
+        );
+        assert_eq!(posts[0].extra.social.og_title.as_deref(), Some("Modern!"));
+        assert_eq!(posts[0].extra.codeinjection_foot, None);
+        assert!(posts[0]
+            .to_string()
+            .contains("codeinjection_head = \"\"\"\n<style>\np { color: red; }\n</style>\"\"\"\n"));
+    }
+}
@@ -0,0 +1,59 @@
+//! Rendering a post into the document written for it.
+//!
+//! The built-in renderer writes the frontmatter which the chosen [`Target`] expects. Sites with
+//! another generator, or a frontmatter layout which [`FrontmatterTemplate`](crate::FrontmatterTemplate)
+//! can't express, can render posts themselves, and reuse the rest of the extraction.
+
+use std::fmt;
+use std::io::Write;
+
+use crate::{data_model::Post, Error, ExtractOptions, FrontmatterFormat, Target};
+
+/// Renders a post's frontmatter and content, as the document written to its path.
+///
+/// The post is complete when it's rendered: its links, images, and path are final, and the
+/// options' transforms have been run on it. Its path is chosen by the options regardless, so a
+/// renderer should write a document which the target can read from there.
+pub trait PostRenderer: Send + Sync {
+    /// write the document for a post
+    fn render(
+        &self,
+        post: &Post,
+        writer: &mut dyn Write,
+        options: &ExtractOptions,
+    ) -> Result<(), Error>;
+}
+
+impl fmt::Debug for dyn PostRenderer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PostRenderer")
+    }
+}
+
+/// The built-in renderer: the options' frontmatter template if they have one, or else the
+/// frontmatter which their target expects, in their frontmatter format.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DefaultRenderer;
+
+impl PostRenderer for DefaultRenderer {
+    fn render(
+        &self,
+        post: &Post,
+        mut writer: &mut dyn Write,
+        options: &ExtractOptions,
+    ) -> Result<(), Error> {
+        if let Some(template) = &options.frontmatter_template {
+            let format = match options.target {
+                // jekyll only reads yaml
+                Target::Jekyll => FrontmatterFormat::Yaml,
+                Target::Zola | Target::Hugo => options.frontmatter,
+            };
+            return post.render_template_to(&mut writer, template, format);
+        }
+        match options.target {
+            Target::Zola => post.render_to(&mut writer, options.frontmatter),
+            Target::Hugo => post.render_hugo_to(&mut writer, options.frontmatter),
+            Target::Jekyll => post.render_jekyll_to(&mut writer),
+        }
+    }
+}
@@ -0,0 +1,274 @@
+//! An overview of a ghost blog, to plan its migration.
+//!
+//! Before migrating a blog, it helps to know how much there is to move: how many drafts will need a
+//! decision, which tags are worth a taxonomy, and how large the images directory will be.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+
+use chrono::Datelike;
+use rusqlite::Connection;
+
+use crate::{
+    archive::{copy_db, find_ghost_paths_in},
+    data_model::{count_words, is_internal_tag, PostId},
+    extract::select_posts,
+    progress::human_bytes,
+    query::PostColumns,
+    Error, ExtractOptions,
+};
+
+/// The number of the largest posts which are listed.
+const LARGEST_POSTS: usize = 10;
+
+/// A post's size, to find those which will take longest to check once migrated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PostSize {
+    pub id: PostId,
+    pub slug: String,
+    pub title: String,
+    /// the number of words in the post's content
+    pub words: usize,
+}
+
+/// How much a ghost blog contains.
+///
+/// Only the posts which the options select are counted, so the statistics describe what an
+/// extraction with the same options would write.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Stats {
+    /// the number of posts with each status, i.e. `published` or `draft`
+    pub posts: BTreeMap<String, usize>,
+    /// the number of pages with each status
+    pub pages: BTreeMap<String, usize>,
+    /// the number of posts and pages published in each year
+    pub years: BTreeMap<i32, usize>,
+    /// the number of posts and pages which were never published
+    pub undated: usize,
+    /// each public tag, with the number of posts and pages which have it, most frequent first
+    pub tags: Vec<(String, usize)>,
+    /// each author, with the number of posts and pages they wrote, most prolific first
+    pub authors: Vec<(String, usize)>,
+    /// the number of images in the archive's image directories
+    pub images: usize,
+    /// the total size of those images, in bytes
+    pub image_bytes: u64,
+    /// the largest posts and pages, by their number of words, largest first
+    pub largest: Vec<PostSize>,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let statuses = |counts: &BTreeMap<String, usize>| {
+            let n: usize = counts.values().sum();
+            let statuses: Vec<_> = counts
+                .iter()
+                .map(|(status, count)| format!("{} {}", count, status))
+                .collect();
+            if statuses.is_empty() {
+                n.to_string()
+            } else {
+                format!("{} ({})", n, statuses.join(", "))
+            }
+        };
+        writeln!(f, "posts: {}", statuses(&self.posts))?;
+        writeln!(f, "pages: {}", statuses(&self.pages))?;
+        writeln!(f, "by year:")?;
+        for (year, count) in &self.years {
+            writeln!(f, "  {}: {}", year, count)?;
+        }
+        if self.undated > 0 {
+            writeln!(f, "  unpublished: {}", self.undated)?;
+        }
+        writeln!(f, "tags:")?;
+        for (tag, count) in &self.tags {
+            writeln!(f, "  {}: {}", tag, count)?;
+        }
+        writeln!(f, "authors:")?;
+        for (author, count) in &self.authors {
+            writeln!(f, "  {}: {}", author, count)?;
+        }
+        writeln!(
+            f,
+            "images: {} ({})",
+            self.images,
+            human_bytes(self.image_bytes as f64)
+        )?;
+        writeln!(f, "largest posts:")?;
+        for post in &self.largest {
+            writeln!(
+                f,
+                "  {} words: {} ({}) {:?}",
+                post.words, post.id, post.slug, post.title
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// count what a ghost blog within an archive contains
+///
+/// The archive is read once, for its database and the sizes of the images in the directories of the
+/// options' image sources; nothing is written besides a temporary copy of the database. The
+/// database and images are found as [`extract_archive`](crate::extract_archive) finds them.
+pub fn archive_stats<AP>(archive_path: AP, options: &ExtractOptions) -> Result<Stats, Error>
+where
+    AP: AsRef<Path>,
+{
+    let archive_path = archive_path.as_ref();
+    let ghost_paths = find_ghost_paths_in(
+        archive_path,
+        options.prefix.clone(),
+        options.db_path.clone(),
+        options.images_prefix.clone(),
+    )?;
+    let images_bases: Vec<_> = options
+        .image_sources
+        .iter()
+        .filter_map(|source| ghost_paths.source_dir(source))
+        .collect();
+
+    let (mut images, mut image_bytes) = (0, 0);
+    let database = copy_db(archive_path, &ghost_paths.db, |path, entry| {
+        if entry.header().entry_type() == tar::EntryType::Regular
+            && images_bases.iter().any(|base| path.starts_with(base))
+        {
+            images += 1;
+            image_bytes += entry.size();
+        }
+    })?;
+
+    let conn =
+        Connection::open_with_flags(database.path(), rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    Ok(Stats {
+        images,
+        image_bytes,
+        ..database_stats(&conn, options)?
+    })
+}
+
+/// count the posts, tags, and authors in a ghost database
+fn database_stats(conn: &Connection, options: &ExtractOptions) -> Result<Stats, Error> {
+    let mut stats = Stats::default();
+    let mut tags = HashMap::new();
+    let mut authors = HashMap::new();
+    let columns = PostColumns::detect(conn)?;
+    for post in select_posts(columns, options).iter(conn) {
+        let post = post?;
+        let status = if post.status.draft() {
+            "draft"
+        } else {
+            "published"
+        };
+        let statuses = if post.page {
+            &mut stats.pages
+        } else {
+            &mut stats.posts
+        };
+        *statuses.entry(status.to_string()).or_default() += 1;
+        match post.date {
+            Some(date) => *stats.years.entry(date.year()).or_default() += 1,
+            None => stats.undated += 1,
+        }
+        for tag in post
+            .taxonomies
+            .tags
+            .iter()
+            .filter(|tag| !is_internal_tag(tag))
+        {
+            *tags.entry(tag.clone()).or_default() += 1;
+        }
+        for author in &post.extra.authors {
+            *authors.entry(author.clone()).or_default() += 1;
+        }
+        stats.largest.push(PostSize {
+            words: count_words(&post.content),
+            id: post.extra.id,
+            slug: post.slug,
+            title: post.title,
+        });
+    }
+    stats.tags = most_frequent(tags);
+    stats.authors = most_frequent(authors);
+    stats
+        .largest
+        .sort_by_key(|post| std::cmp::Reverse(post.words));
+    stats.largest.truncate(LARGEST_POSTS);
+    Ok(stats)
+}
+
+/// sort counts with the most frequent first, and ties by name
+fn most_frequent(counts: HashMap<String, usize>) -> Vec<(String, usize)> {
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_posts_tags_and_authors() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "
+            CREATE TABLE posts (
+                id integer not null primary key,
+                title varchar(150) not null,
+                slug varchar(150) not null,
+                markdown text null,
+                page boolean not null default '0',
+                status varchar(150) not null default 'draft',
+                author_id integer not null,
+                published_at datetime null
+            );
+            CREATE TABLE users (id integer not null primary key, name varchar(150) not null);
+            CREATE TABLE tags (
+                id integer not null primary key,
+                name varchar(150) not null,
+                slug varchar(150) not null default ''
+            );
+            CREATE TABLE posts_tags (
+                id integer not null primary key autoincrement,
+                post_id integer not null,
+                tag_id integer not null,
+                sort_order integer not null default '0'
+            );
+            INSERT INTO users (id, name) VALUES (1, 'Pete'), (2, 'Ann');
+            INSERT INTO tags (id, name, slug) VALUES
+                (1, 'rust', 'rust'), (2, 'zola', 'zola'), (3, '#hidden', 'hash-hidden');
+            INSERT INTO posts (id, title, slug, markdown, page, status, author_id, published_at) VALUES
+                (1, 'Hello', 'hello', 'one two three', 0, 'published', 1, '2019-01-02 10:00:00'),
+                (2, 'Again', 'again', 'one two', 0, 'published', 2, '2020-01-03 10:00:00'),
+                (3, 'Later', 'later', 'one two three four', 0, 'draft', 1, NULL),
+                (4, 'About', 'about', 'one', 1, 'published', 1, '2020-02-01 10:00:00');
+            INSERT INTO posts_tags (post_id, tag_id) VALUES (1, 1), (1, 3), (2, 1), (2, 2), (3, 2);
+            ",
+        )
+        .unwrap();
+
+        let stats = database_stats(&conn, &ExtractOptions::default()).unwrap();
+        assert_eq!(stats.posts["published"], 2);
+        assert_eq!(stats.posts["draft"], 1);
+        assert_eq!(stats.pages["published"], 1);
+        assert_eq!(stats.years[&2019], 1);
+        assert_eq!(stats.years[&2020], 2);
+        assert_eq!(stats.undated, 1);
+        assert_eq!(
+            stats.tags,
+            vec![("rust".to_string(), 2), ("zola".to_string(), 2)]
+        );
+        assert_eq!(
+            stats.authors,
+            vec![("Pete".to_string(), 3), ("Ann".to_string(), 1)]
+        );
+        let largest: Vec<_> = stats.largest.iter().map(|post| post.words).collect();
+        assert_eq!(largest, vec![4, 3, 2, 1]);
+
+        let printed = stats.to_string();
+        assert!(printed.starts_with("posts: 3 (1 draft, 2 published)\npages: 1 (1 published)\n"));
+        assert!(printed.contains("\n  4 words: 3 (later) \"Later\"\n"));
+    }
+}
@@ -0,0 +1,184 @@
+//! A machine-readable summary of an extraction.
+//!
+//! Scripts which migrate a blog, i.e. in CI, can check it to decide whether the migration is
+//! complete, rather than scraping the log.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::{data_model::PostId, Error, ExtractOptions, ReportFormat};
+
+/// Why a post was left out of the extraction, or extracted without its markdown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Reason {
+    /// drafts are skipped by [`DraftPolicy::Skip`](crate::DraftPolicy::Skip)
+    Draft,
+    /// its content is Ghost's plaintext rendering
+    Plaintext,
+    /// its content is its pre-rendered html, by [`RawHtmlPolicy::Fallback`](crate::RawHtmlPolicy::Fallback)
+    RawHtml,
+    /// it has no content at all, i.e. its editor document couldn't be rendered
+    Empty,
+    /// some of its cards are left as html
+    UnconvertedCards,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Reason::Draft => "a draft",
+            Reason::Plaintext => "converted from plaintext",
+            Reason::RawHtml => "passed through as html",
+            Reason::Empty => "empty",
+            Reason::UnconvertedCards => "cards left as html",
+        })
+    }
+}
+
+/// A post which was left out of the extraction, or extracted without its markdown.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct NotedPost {
+    pub(crate) id: PostId,
+    pub(crate) slug: String,
+    pub(crate) title: String,
+    pub(crate) reason: Reason,
+}
+
+impl fmt::Display for NotedPost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}) {:?}: {}",
+            self.id, self.slug, self.title, self.reason
+        )
+    }
+}
+
+/// A post with images which have no alt text, for the author to describe once it's migrated.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct PostWithoutAlt {
+    pub(crate) id: PostId,
+    pub(crate) slug: String,
+    pub(crate) title: String,
+    /// the urls of the images without alt text, as they're linked in the post
+    pub(crate) images: Vec<String>,
+}
+
+impl fmt::Display for PostWithoutAlt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}) {:?}: {}",
+            self.id,
+            self.slug,
+            self.title,
+            self.images.join(", ")
+        )
+    }
+}
+
+/// What an extraction did, and what it couldn't.
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct Summary {
+    /// the number of posts and pages written
+    pub(crate) posts: usize,
+    /// the posts left out of the extraction
+    pub(crate) skipped: Vec<NotedPost>,
+    /// the posts written without their markdown, whose content should be checked
+    pub(crate) without_markdown: Vec<NotedPost>,
+    /// the posts some of whose cards couldn't be converted, and are left as html
+    pub(crate) unconverted_cards: Vec<NotedPost>,
+    /// the posts with images which have no alt text
+    pub(crate) without_alt_text: Vec<PostWithoutAlt>,
+    /// the number of images extracted from the archive, or downloaded from the api
+    pub(crate) images: usize,
+    /// the number of images downloaded from elsewhere
+    pub(crate) external_images: usize,
+    /// the number of images converted into another format
+    pub(crate) converted_images: usize,
+    /// the number of images left in the archive as orphans
+    pub(crate) pruned_images: usize,
+    /// the internal links which don't resolve to an extracted file, by the path of each post
+    /// containing them, relative to the extract path
+    pub(crate) dangling_links: BTreeMap<String, Vec<String>>,
+    /// the problems with the frontmatter of the generated files which the target would reject,
+    /// by the path of each, relative to the extract path; only checked if the options say to
+    pub(crate) invalid_frontmatter: BTreeMap<String, Vec<String>>,
+    /// the posts which an incremental update rewrote, as they were new or changed, by the path
+    /// of each, relative to the extract path
+    pub(crate) updated: Vec<String>,
+    /// the number of posts which an incremental update left alone, as they hadn't changed
+    pub(crate) unchanged: usize,
+    /// the number of section indices written
+    pub(crate) indices: u32,
+    /// the other warnings logged once the extraction completes, one per post or image concerned
+    pub(crate) warnings: Vec<String>,
+}
+
+impl Summary {
+    /// write the summary in the format which the options choose, if any
+    ///
+    /// It's written to the options' report path, or else to stdout.
+    pub(crate) fn write(&self, options: &ExtractOptions) -> Result<(), Error> {
+        let format = match options.report {
+            Some(format) => format,
+            None => return Ok(()),
+        };
+        let mut writer: Box<dyn Write> = match &options.report_path {
+            Some(path) => Box::new(std::io::BufWriter::new(std::fs::File::create(path)?)),
+            None => Box::new(std::io::stdout().lock()),
+        };
+        match format {
+            ReportFormat::Json => {
+                serde_json::to_writer_pretty(&mut writer, self)?;
+                writeln!(writer)?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_json_reports() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.json");
+        let options = ExtractOptions {
+            report: Some(ReportFormat::Json),
+            report_path: Some(path.clone()),
+            ..ExtractOptions::default()
+        };
+        let summary = Summary {
+            posts: 2,
+            skipped: vec![NotedPost {
+                id: 3.into(),
+                slug: "draft".into(),
+                title: "Draft".into(),
+                reason: Reason::Draft,
+            }],
+            images: 4,
+            indices: 5,
+            warnings: vec!["post 6 (hello) had no publication date".into()],
+            ..Summary::default()
+        };
+        summary.write(&options).unwrap();
+
+        let report: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(report["posts"], 2);
+        assert_eq!(report["skipped"][0]["id"], 3);
+        assert_eq!(report["skipped"][0]["reason"], "draft");
+        assert_eq!(report["without_markdown"].as_array().unwrap().len(), 0);
+        assert_eq!(report["images"], 4);
+        assert_eq!(report["indices"], 5);
+        assert_eq!(report["warnings"].as_array().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,102 @@
+//! Conversion of extracted raster images into modern formats.
+//!
+//! Ghost serves its uploads as they were uploaded, which is usually as png or jpeg. WebP and AVIF
+//! images are much smaller, and every current browser displays them.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::{data_model::url_path, extract::claim_output, ConflictPolicy, Error, ImageFormat};
+
+/// The extensions of the raster images which are converted.
+///
+/// Gifs are left alone, as they may be animated.
+const RASTER_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "bmp", "tif", "tiff"];
+
+/// convert the images into the given format, each beside its original
+///
+/// Returns the path of each converted image relative to the images directory, with `/`
+/// separators, mapped to the path of its conversion. The originals are removed, unless they are
+/// to be kept. Images which can't be converted are logged, and left alone. A conversion which
+/// already exists is left alone if the conflict policy says to, but is used all the same.
+pub(crate) fn transcode_images(
+    images: &[PathBuf],
+    images_path: &Path,
+    format: ImageFormat,
+    keep_originals: bool,
+    on_conflict: ConflictPolicy,
+) -> Result<HashMap<String, String>, Error> {
+    let mut converted = HashMap::new();
+    for image in images {
+        let relative = match image.strip_prefix(images_path) {
+            Ok(relative) => relative,
+            Err(_) => continue,
+        };
+        let is_raster = image
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                RASTER_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+            });
+        if !is_raster {
+            continue;
+        }
+        let to = image.with_extension(format.extension());
+        if claim_output(&to, on_conflict)? {
+            if let Err(err) = transcode(image, &to, format) {
+                log::warn!("failed to convert {}: {}", image.display(), err);
+                continue;
+            }
+        }
+        if !keep_originals {
+            std::fs::remove_file(image)?;
+        }
+        converted.insert(
+            url_path(relative),
+            url_path(&relative.with_extension(format.extension())),
+        );
+    }
+    Ok(converted)
+}
+
+/// convert one image
+fn transcode(from: &Path, to: &Path, format: ImageFormat) -> Result<(), image::ImageError> {
+    let image = image::open(from)?;
+    let format = match format {
+        ImageFormat::Webp => image::ImageFormat::WebP,
+        ImageFormat::Avif => image::ImageFormat::Avif,
+    };
+    // lossless webp can't encode every pixel format which jpegs and pngs decode to
+    image.to_rgba8().save_with_format(to, format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_raster_images() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("2020/01")).unwrap();
+        let png = dir.path().join("2020/01/a.png");
+        image::RgbImage::from_pixel(2, 2, image::Rgb([200, 100, 50]))
+            .save(&png)
+            .unwrap();
+        let svg = dir.path().join("2020/01/b.svg");
+        std::fs::write(&svg, "<svg/>").unwrap();
+
+        let converted = transcode_images(
+            &[png.clone(), svg.clone()],
+            dir.path(),
+            ImageFormat::Webp,
+            false,
+            ConflictPolicy::default(),
+        )
+        .unwrap();
+        assert_eq!(converted.len(), 1);
+        assert_eq!(converted["2020/01/a.png"], "2020/01/a.webp");
+        assert!(dir.path().join("2020/01/a.webp").is_file());
+        assert!(!png.exists());
+        assert!(svg.exists());
+    }
+}
@@ -0,0 +1,956 @@
+//! Checks that an extraction's output would build.
+//!
+//! Links to images and between posts are rewritten as each post is written, but the files they
+//! point to may be renamed or moved into page bundles afterwards, or may never have been
+//! extracted at all. Zola refuses to build a site with a dangling internal link, or with
+//! frontmatter it can't parse, so both are checked before the user ever runs `zola build`.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, SecondsFormat, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
+use rusqlite::Connection;
+
+use crate::{
+    data_model::{is_internal_tag, PostId, SECTION},
+    extract::{is_skipped, resolve_undated, select_posts},
+    query::PostColumns,
+    Error, ExtractOptions, Target,
+};
+
+lazy_static! {
+    // zola's internal links, i.e. `@/blog/2020/01/02/hello.md#top`
+    static ref ZOLA_LINK: Regex = Regex::new(&format!(r"@/{}/([^)\s#?]+)", SECTION)).unwrap();
+    // hugo's internal links, i.e. `{{< ref "/blog/2020/01/02/hello.md" >}}`
+    static ref HUGO_LINK: Regex =
+        Regex::new(&format!(r##"\bref\s+"/{}/([^"#?]+)"##, SECTION)).unwrap();
+    // jekyll's links to posts, i.e. `{% post_url 2020-01-02-hello %}`
+    static ref JEKYLL_POST_LINK: Regex = Regex::new(r"\{%\s*post_url\s+(\S+)\s*%\}").unwrap();
+    // jekyll's links to other pages, i.e. `{% link _drafts/hello.md %}`
+    static ref JEKYLL_LINK: Regex = Regex::new(r"\{%\s*link\s+(\S+)\s*%\}").unwrap();
+    // the target of a markdown link or image, or the `src` or `href` of an html element
+    static ref LINK_TARGET: Regex =
+        Regex::new(r#"\]\(([^)\s]+)|(?i)\b(?:src|href)\s*=\s*["']([^"']+)"#).unwrap();
+    // a toml table's header, i.e. `[extra]` or `[[extra.links]]`
+    static ref TOML_TABLE: Regex = Regex::new(r"^\s*\[\[?([^\]]+)\]\]?\s*(?:#.*)?$").unwrap();
+    // the key of a toml key/value pair, i.e. `title = "Hello"`
+    static ref TOML_KEY: Regex = Regex::new(r#"^\s*("[^"]*"|'[^']*'|[\w.-]+)\s*="#).unwrap();
+}
+
+/// The files to which a site's urls resolve.
+#[derive(Debug)]
+pub(crate) struct LinkChecker {
+    extract_path: PathBuf,
+    target: Target,
+    /// url prefixes, each with the directory from which it's served, i.e. the images url
+    served: Vec<(String, PathBuf)>,
+    /// matches the links under any of the served urls; those which are part of another link, i.e.
+    /// an internal link or an absolute url, are matched with a prefix, and left alone
+    served_link: Option<Regex>,
+    /// whether posts are page bundles, whose links to their images are relative
+    bundles: bool,
+}
+
+impl LinkChecker {
+    pub(crate) fn new(extract_path: &Path, options: &ExtractOptions) -> Result<Self, Error> {
+        let mut served = Vec::new();
+        // images which aren't extracted may be migrated separately, so links to them can't be
+        // checked
+        if options.extract_images {
+            served.push((options.images_url(), options.images_path(extract_path)?));
+        }
+        let bundles = options.page_bundles && options.target != Target::Jekyll;
+        if bundles {
+            // the links in the frontmatter to a bundle's images are absolute
+            served.push((options.target.content_url(), extract_path.to_path_buf()));
+        }
+        served.retain(|(url, _)| !url.is_empty() && url != "/");
+        let served_link = if served.is_empty() {
+            None
+        } else {
+            let prefixes: Vec<_> = served.iter().map(|(url, _)| regex::escape(url)).collect();
+            Some(
+                Regex::new(&format!(
+                    r#"(@|\bref\s+"|[\w.:/%-])?(?:{})/[^\s"'()\[\]<>?#]+"#,
+                    prefixes.join("|")
+                ))
+                .expect("escaped prefixes form a valid regex"),
+            )
+        };
+        Ok(LinkChecker {
+            extract_path: extract_path.to_path_buf(),
+            target: options.target,
+            served,
+            served_link,
+            bundles,
+        })
+    }
+
+    /// the internal links of a written document which don't resolve to an existing file
+    ///
+    /// `path` is the document's path relative to the extract path.
+    pub(crate) fn dangling(&self, path: &Path, document: &str) -> Vec<String> {
+        let mut dangling = Vec::new();
+        let mut check = |link: &str, candidates: Vec<PathBuf>| {
+            let resolves = candidates.iter().any(|candidate| candidate.is_file());
+            if !resolves && !dangling.iter().any(|dangling| dangling == link) {
+                dangling.push(link.to_string());
+            }
+        };
+
+        match self.target {
+            Target::Zola => {
+                for link in ZOLA_LINK.captures_iter(document) {
+                    check(&link[0], vec![self.extract_path.join(&link[1])]);
+                }
+            }
+            Target::Hugo => {
+                for link in HUGO_LINK.captures_iter(document) {
+                    check(&link[0], vec![self.extract_path.join(&link[1])]);
+                }
+            }
+            Target::Jekyll => {
+                for link in JEKYLL_POST_LINK.captures_iter(document) {
+                    let post = format!("{}.md", &link[1]);
+                    check(&link[0], vec![self.extract_path.join("_posts").join(post)]);
+                }
+                for link in JEKYLL_LINK.captures_iter(document) {
+                    check(&link[0], vec![self.extract_path.join(&link[1])]);
+                }
+            }
+        }
+
+        if let Some(served_link) = &self.served_link {
+            for link in served_link.captures_iter(document) {
+                if link.get(1).is_some() {
+                    continue;
+                }
+                let url = &link[0];
+                let candidates = self
+                    .served
+                    .iter()
+                    .filter_map(|(prefix, dir)| {
+                        let rest = url.strip_prefix(prefix.as_str())?.strip_prefix('/')?;
+                        Some(dir.join(rest))
+                    })
+                    .collect();
+                check(url, candidates);
+            }
+        }
+
+        if self.bundles {
+            let dir = self
+                .extract_path
+                .join(path.parent().unwrap_or_else(|| Path::new("")));
+            for link in LINK_TARGET.captures_iter(document) {
+                let target = match link.get(1).or_else(|| link.get(2)) {
+                    Some(target) => target.as_str(),
+                    None => continue,
+                };
+                let file = target.split(['?', '#']).next().unwrap_or_default();
+                // only the bundle's own images are linked relatively
+                if file.is_empty()
+                    || file.ends_with('/')
+                    || file.starts_with(['/', '@', '{', '.'])
+                    || file.contains(':')
+                {
+                    continue;
+                }
+                check(target, vec![dir.join(file)]);
+            }
+        }
+
+        dangling
+    }
+
+    /// the dangling internal links of each of the written posts, given relative to the extract
+    /// path
+    pub(crate) fn check(&self, posts: &[PathBuf]) -> Result<Vec<(PathBuf, Vec<String>)>, Error> {
+        let mut dangling = Vec::new();
+        for path in posts {
+            let document = std::fs::read_to_string(self.extract_path.join(path))?;
+            let links = self.dangling(path, &document);
+            if !links.is_empty() {
+                dangling.push((path.clone(), links));
+            }
+        }
+        Ok(dangling)
+    }
+}
+
+/// The keys which zola allows in a page's frontmatter.
+const PAGE_KEYS: &[&str] = &[
+    "title",
+    "description",
+    "updated",
+    "date",
+    "draft",
+    "slug",
+    "path",
+    "aliases",
+    "authors",
+    "in_search_index",
+    "template",
+    "taxonomies",
+    "weight",
+    "extra",
+];
+
+/// The keys which zola allows in a section's frontmatter.
+const SECTION_KEYS: &[&str] = &[
+    "title",
+    "description",
+    "draft",
+    "sort_by",
+    "weight",
+    "template",
+    "page_template",
+    "paginate_by",
+    "paginate_path",
+    "paginate_reversed",
+    "insert_anchor_links",
+    "in_search_index",
+    "render",
+    "redirect_to",
+    "aliases",
+    "transparent",
+    "generate_feed",
+    "generate_feeds",
+    "extra",
+];
+
+/// The keys of each type which zola checks, of pages and sections alike.
+const STRING_KEYS: &[&str] = &[
+    "title",
+    "description",
+    "slug",
+    "path",
+    "template",
+    "sort_by",
+    "page_template",
+    "paginate_path",
+    "insert_anchor_links",
+    "redirect_to",
+];
+const BOOL_KEYS: &[&str] = &[
+    "draft",
+    "in_search_index",
+    "paginate_reversed",
+    "render",
+    "transparent",
+    "generate_feed",
+    "generate_feeds",
+];
+const INTEGER_KEYS: &[&str] = &["weight", "paginate_by"];
+const STRING_ARRAY_KEYS: &[&str] = &["aliases", "authors"];
+
+/// The keys whose values are dates, for each target.
+fn date_keys(target: Target) -> &'static [&'static str] {
+    match target {
+        Target::Zola => &["date", "updated"],
+        Target::Hugo => &["date", "lastmod", "publishDate", "expiryDate"],
+        Target::Jekyll => &["date", "last_modified_at"],
+    }
+}
+
+/// the markdown files beneath a directory, relative to it, in order
+///
+/// Hidden directories are passed over.
+pub(crate) fn markdown_files(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(subdir) = dirs.pop() {
+        for entry in std::fs::read_dir(&subdir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|extension| extension == "md") {
+                files.push(path.strip_prefix(dir)?.to_path_buf());
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// whether a markdown file is a section's index, rather than a page
+fn is_section(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with("_index"))
+}
+
+/// check that every markdown file beneath the extract path has frontmatter which the target would
+/// accept, returning the problems with each file which has any, relative to the extract path
+pub(crate) fn validate_tree(
+    extract_path: &Path,
+    options: &ExtractOptions,
+) -> Result<Vec<(PathBuf, Vec<String>)>, Error> {
+    let mut invalid = Vec::new();
+    for path in markdown_files(extract_path)? {
+        let document = std::fs::read_to_string(extract_path.join(&path))?;
+        let problems = validate_frontmatter(&document, is_section(&path), options.target);
+        if !problems.is_empty() {
+            invalid.push((path, problems));
+        }
+    }
+    Ok(invalid)
+}
+
+/// How an extracted post differs from the post in the database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Drift {
+    /// the post would be extracted, but no file has its id
+    Missing { id: PostId, slug: String },
+    /// a file has the id of a post which isn't in the database
+    Removed { id: PostId, path: PathBuf },
+    /// a field of a post's file differs from what would be extracted now
+    Changed {
+        id: PostId,
+        path: PathBuf,
+        /// the frontmatter key, i.e. `title`
+        field: String,
+        /// the value from the database, or `None` if it would be left out
+        database: Option<String>,
+        /// the value in the file, or `None` if it has none
+        file: Option<String>,
+    },
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let or_none = |value: &Option<String>| value.clone().unwrap_or_else(|| "unset".into());
+        match self {
+            Drift::Missing { id, slug } => write!(f, "post {} ({}) was not extracted", id, slug),
+            Drift::Removed { id, path } => write!(
+                f,
+                "{} is post {}, which is not in the database",
+                path.display(),
+                id
+            ),
+            Drift::Changed {
+                path,
+                field,
+                database,
+                file,
+                ..
+            } => write!(
+                f,
+                "{}: {} is {}, but {} in the database",
+                path.display(),
+                field,
+                or_none(file),
+                or_none(database)
+            ),
+        }
+    }
+}
+
+/// compare the posts extracted beneath the extract path with those in a ghost database
+///
+/// Each markdown file with a post's id in its frontmatter is matched with the post; files without
+/// one, i.e. indices, are passed over. The posts which the options select are read as they would be
+/// extracted, and their titles, dates, and tags compared with their files'. Posts which would be
+/// extracted but have no file, and files of posts which are no longer in the database, are
+/// reported too. Keys which [`ExtractOptions::frontmatter_keys`] renames are followed; those it
+/// drops aren't compared.
+pub fn verify_extraction(
+    db_path: &Path,
+    extract_path: &Path,
+    options: &ExtractOptions,
+) -> Result<Vec<Drift>, Error> {
+    let conn = Connection::open_with_flags(db_path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let taxonomies = match options.target {
+        Target::Zola => "taxonomies.",
+        Target::Hugo | Target::Jekyll => "",
+    };
+    let key = |key: String| renamed_key(options, &key);
+
+    let mut files = BTreeMap::new();
+    let id_key = match id_key(options) {
+        Some(id_key) => id_key,
+        None => return Ok(Vec::new()),
+    };
+    for path in markdown_files(extract_path)? {
+        if is_section(&path) {
+            continue;
+        }
+        let document = std::fs::read_to_string(extract_path.join(&path))?;
+        let frontmatter = match parse_frontmatter(&document) {
+            Ok(Some(frontmatter)) => frontmatter,
+            _ => continue,
+        };
+        let id = match file_id(&frontmatter, &id_key) {
+            Some(id) => id,
+            None => continue,
+        };
+        files.entry(id).or_insert((path, frontmatter));
+    }
+
+    let mut drift = Vec::new();
+    let columns = PostColumns::detect(&conn)?.without_content();
+    for post in select_posts(columns, options).iter(&conn) {
+        let mut post = post?;
+        resolve_undated(&mut post, options.undated_policy)?;
+        if is_skipped(&post, options) {
+            continue;
+        }
+        let id = post.extra.id.clone();
+        let (path, frontmatter) = match files.remove(&id) {
+            Some(file) => file,
+            None => {
+                drift.push(Drift::Missing {
+                    id,
+                    slug: post.slug,
+                });
+                continue;
+            }
+        };
+        let mut compare = |field: String, database: Option<String>| {
+            let field = match key(field) {
+                Some(field) => field,
+                None => return,
+            };
+            let file = lookup(&frontmatter, &field).map(|value| match value {
+                toml::Value::String(value) => value.clone(),
+                toml::Value::Array(values) => values
+                    .iter()
+                    .map(|value| {
+                        value
+                            .as_str()
+                            .map_or_else(|| value.to_string(), str::to_string)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                value => value.to_string(),
+            });
+            let (database, file) = match (database, file) {
+                (Some(database), Some(file)) if field.ends_with("date") => {
+                    normalize_dates(database, file)
+                }
+                (database, file) => (database, file),
+            };
+            if database != file {
+                drift.push(Drift::Changed {
+                    id: id.clone(),
+                    path: path.clone(),
+                    field,
+                    database,
+                    file,
+                });
+            }
+        };
+
+        compare("title".into(), Some(post.title.clone()));
+        compare(
+            "date".into(),
+            post.date
+                .map(|date| date.to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+        );
+
+        if let Some(taxonomy) = &options.taxonomies.tags {
+            let tags: Vec<_> = std::mem::take(&mut post.taxonomies.tags)
+                .into_iter()
+                .filter(|tag| !is_internal_tag(tag))
+                .collect();
+            post.taxonomies.categories = tags.iter().take(1).cloned().collect();
+            post.taxonomies.tags = tags;
+            post.taxonomies.authors = post.extra.authors.clone();
+            post.taxonomies.mapping = options.taxonomies.clone();
+            let terms = post
+                .taxonomies
+                .terms()
+                .into_iter()
+                .find(|(name, _)| name == taxonomy)
+                .map(|(_, terms)| terms)
+                .unwrap_or_default();
+            compare(
+                format!("{}{}", taxonomies, taxonomy),
+                Some(terms.join(", ")),
+            );
+        }
+    }
+
+    for (id, (path, _)) in files {
+        let in_database: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM posts WHERE id = ?1)",
+            [&id],
+            |row| row.get(0),
+        )?;
+        if !in_database {
+            drift.push(Drift::Removed { id, path });
+        }
+    }
+    Ok(drift)
+}
+
+/// the dotted key under which each file has its post's id, following the options' renames
+///
+/// `None` if the options drop it.
+pub(crate) fn id_key(options: &ExtractOptions) -> Option<String> {
+    renamed_key(
+        options,
+        match options.target {
+            Target::Hugo => "params.id",
+            Target::Zola | Target::Jekyll => "extra.id",
+        },
+    )
+}
+
+/// the key under which each file has the time its post was last updated, following the options'
+/// renames
+///
+/// `None` if the options drop it.
+pub(crate) fn updated_key(options: &ExtractOptions) -> Option<String> {
+    renamed_key(
+        options,
+        match options.target {
+            Target::Zola => "updated",
+            Target::Hugo => "lastmod",
+            Target::Jekyll => "last_modified_at",
+        },
+    )
+}
+
+/// a dotted key of the frontmatter, as the options rename it; `None` if they drop it
+fn renamed_key(options: &ExtractOptions, key: &str) -> Option<String> {
+    match options.frontmatter_keys.get(key) {
+        Some(renamed) => renamed.map(str::to_string),
+        None => Some(key.to_string()),
+    }
+}
+
+/// the id of the post whose file has this frontmatter, under the given dotted key
+pub(crate) fn file_id(frontmatter: &toml::value::Table, id_key: &str) -> Option<PostId> {
+    match lookup(frontmatter, id_key)? {
+        toml::Value::Integer(id) => Some(PostId::Int(*id)),
+        toml::Value::String(id) => Some(PostId::Text(id.clone())),
+        _ => None,
+    }
+}
+
+/// the value of a key of the frontmatter, which may be dotted, i.e. `extra.id`
+pub(crate) fn lookup<'a>(
+    frontmatter: &'a toml::value::Table,
+    key: &str,
+) -> Option<&'a toml::Value> {
+    let mut keys = key.split('.');
+    let mut value = frontmatter.get(keys.next()?)?;
+    for key in keys {
+        value = value.get(key)?;
+    }
+    Some(value)
+}
+
+/// a date from the database, and one from a file, in the same form if they can be
+///
+/// A datetime in the file in another offset or precision is rewritten in utc, as the database's
+/// is; a date without a time matches any time that day.
+fn normalize_dates(database: String, file: String) -> (Option<String>, Option<String>) {
+    if let Ok(date) = DateTime::parse_from_rfc3339(&file) {
+        let file = date
+            .with_timezone(&Utc)
+            .to_rfc3339_opts(SecondsFormat::AutoSi, true);
+        return (Some(database), Some(file));
+    }
+    if NaiveDate::parse_from_str(&file, "%Y-%m-%d").is_ok() {
+        let day = database.get(..10).unwrap_or(&database).to_string();
+        return (Some(day), Some(file));
+    }
+    (Some(database), Some(file))
+}
+
+/// the problems with a document's frontmatter which would stop the target from building it
+///
+/// The frontmatter must parse, without duplicate keys, and its dates must be dates. Zola is
+/// stricter: each page needs a title, and every key must be one zola knows, with a value of the
+/// type it expects. `section` says whether the document is a zola section's `_index.md`.
+pub(crate) fn validate_frontmatter(document: &str, section: bool, target: Target) -> Vec<String> {
+    let mut problems = Vec::new();
+    let frontmatter = match parse_frontmatter(document) {
+        Ok(Some(frontmatter)) => frontmatter,
+        // hugo and jekyll pass files without frontmatter through as they are
+        Ok(None) if target != Target::Zola => return problems,
+        Ok(None) => {
+            problems.push("no frontmatter, delimited by `+++` or `---`".into());
+            return problems;
+        }
+        Err(problem) => {
+            problems.push(problem);
+            return problems;
+        }
+    };
+
+    for key in date_keys(target) {
+        if let Some(value) = frontmatter.get(*key) {
+            if !is_date(value) {
+                problems.push(format!("`{}` is not a date: {}", key, value));
+            }
+        }
+    }
+    if target != Target::Zola {
+        return problems;
+    }
+
+    let known = if section { SECTION_KEYS } else { PAGE_KEYS };
+    for (key, value) in &frontmatter {
+        let key = key.as_str();
+        if !known.contains(&key) {
+            problems.push(format!("unknown key `{}`", key));
+            continue;
+        }
+        let expected = if STRING_KEYS.contains(&key) {
+            value.is_str().then_some(()).ok_or("a string")
+        } else if BOOL_KEYS.contains(&key) {
+            value.is_bool().then_some(()).ok_or("a boolean")
+        } else if INTEGER_KEYS.contains(&key) {
+            value.is_integer().then_some(()).ok_or("an integer")
+        } else if STRING_ARRAY_KEYS.contains(&key) {
+            is_string_array(value)
+                .then_some(())
+                .ok_or("an array of strings")
+        } else if key == "extra" {
+            value.is_table().then_some(()).ok_or("a table")
+        } else if key == "taxonomies" {
+            let taxonomies = value.as_table();
+            taxonomies
+                .filter(|taxonomies| taxonomies.values().all(is_string_array))
+                .map(|_| ())
+                .ok_or("a table of arrays of strings")
+        } else {
+            Ok(())
+        };
+        if let Err(expected) = expected {
+            problems.push(format!("`{}` is not {}: {}", key, expected, value));
+        }
+    }
+    if !section && !frontmatter.contains_key("title") {
+        problems.push("no `title`".into());
+    }
+    problems
+}
+
+/// parse a document's frontmatter, be it toml between `+++` or yaml between `---`, into a table
+///
+/// `None` if it has none; the problem if it can't be parsed.
+pub(crate) fn parse_frontmatter(document: &str) -> Result<Option<toml::value::Table>, String> {
+    let document = document.trim_start_matches('\u{feff}');
+    let (delimiter, yaml) = if document.starts_with("+++") {
+        ("+++", false)
+    } else if document.starts_with("---") {
+        ("---", true)
+    } else {
+        return Ok(None);
+    };
+    let mut lines = document.split_inclusive('\n');
+    if lines.next().map(str::trim_end) != Some(delimiter) {
+        return Ok(None);
+    }
+    let mut frontmatter = String::new();
+    let mut terminated = false;
+    for line in lines {
+        if line.trim_end() == delimiter {
+            terminated = true;
+            break;
+        }
+        frontmatter.push_str(line);
+    }
+    if !terminated {
+        return Err(format!("frontmatter is not closed by `{}`", delimiter));
+    }
+    if yaml {
+        let value: serde_yaml::Value = serde_yaml::from_str(&frontmatter)
+            .map_err(|err| format!("invalid yaml frontmatter: {}", err))?;
+        if value.is_null() {
+            return Ok(Some(toml::value::Table::new()));
+        }
+        match toml::Value::try_from(value) {
+            Ok(toml::Value::Table(table)) => Ok(Some(table)),
+            Ok(_) => Err("yaml frontmatter is not a mapping".into()),
+            Err(err) => Err(format!("unsupported yaml frontmatter: {}", err)),
+        }
+    } else {
+        if let Some(key) = duplicate_key(&frontmatter) {
+            return Err(format!("invalid toml frontmatter: duplicate key `{}`", key));
+        }
+        toml::from_str(&frontmatter)
+            .map(Some)
+            .map_err(|err| format!("invalid toml frontmatter: {}", err))
+    }
+}
+
+/// the first key set twice in the same table of a toml document, if any
+///
+/// The toml parser takes the last of them, where zola's rejects the document.
+fn duplicate_key(toml: &str) -> Option<String> {
+    let mut keys = std::collections::HashSet::new();
+    let mut table = String::new();
+    let mut multiline: Option<&str> = None;
+    for line in toml.lines() {
+        if let Some(quotes) = multiline {
+            if line.matches(quotes).count() % 2 == 1 {
+                multiline = None;
+            }
+            continue;
+        }
+        if let Some(header) = TOML_TABLE.captures(line) {
+            table = header[1].trim().to_string();
+            // each of an array of tables is a table of its own
+            if line.trim_start().starts_with("[[") {
+                keys.retain(|(key_table, _): &(String, String)| key_table != &table);
+            }
+            continue;
+        }
+        if let Some(key) = TOML_KEY.captures(line) {
+            // `"title"` is the same key as `title`
+            let key = key[1].trim_matches(['"', '\'']).to_string();
+            if !keys.insert((table.clone(), key.clone())) {
+                return Some(key);
+            }
+        }
+        multiline = ["\"\"\"", "'''"]
+            .iter()
+            .copied()
+            .find(|quotes| line.matches(quotes).count() % 2 == 1);
+    }
+    None
+}
+
+/// whether a frontmatter value is a date or datetime, of the forms which zola parses
+///
+/// Dates in yaml frontmatter are strings.
+fn is_date(value: &toml::Value) -> bool {
+    let date = match value {
+        toml::Value::Datetime(datetime) => datetime.to_string(),
+        toml::Value::String(date) => date.clone(),
+        _ => return false,
+    };
+    DateTime::parse_from_rfc3339(&date).is_ok()
+        || NaiveDateTime::parse_from_str(&date, "%Y-%m-%dT%H:%M:%S%.f").is_ok()
+        || NaiveDateTime::parse_from_str(&date, "%Y-%m-%d %H:%M:%S%.f").is_ok()
+        || NaiveDate::parse_from_str(&date, "%Y-%m-%d").is_ok()
+}
+
+fn is_string_array(value: &toml::Value) -> bool {
+    value
+        .as_array()
+        .is_some_and(|values| values.iter().all(toml::Value::is_str))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_dangling_links() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("2020/01/02")).unwrap();
+        std::fs::write(dir.path().join("2020/01/02/hello.md"), "").unwrap();
+        std::fs::write(dir.path().join("2020/01/a.png"), "").unwrap();
+        let checker = LinkChecker::new(dir.path(), &ExtractOptions::default()).unwrap();
+
+        let document = "+++\n[extra]\nfeature_image = \"/blog/2020/01/hero.png\"\n+++\n\
+                        [hi](@/blog/2020/01/02/hello.md#top) [bye](@/blog/2020/01/03/bye.md)\n\
+                        ![a](/blog/2020/01/a.png?v=1) <img src=\"/blog/2020/01/b.png\">\n\
+                        [elsewhere](https://example.com/blog/2020/01/c.png)";
+        assert_eq!(
+            checker.dangling(Path::new("2020/01/04/post.md"), document),
+            vec![
+                "@/blog/2020/01/03/bye.md",
+                "/blog/2020/01/hero.png",
+                "/blog/2020/01/b.png",
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_dangling_bundle_images() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("2020/01/02/hello")).unwrap();
+        std::fs::write(dir.path().join("2020/01/02/hello/a.png"), "").unwrap();
+        let options = ExtractOptions {
+            page_bundles: true,
+            ..ExtractOptions::default()
+        };
+        let checker = LinkChecker::new(dir.path(), &options).unwrap();
+
+        let document = "+++\n[extra]\nfeature_image = \"/blog/2020/01/02/hello/a.png\"\n+++\n\
+                        ![a](a.png) ![b](b.png) [up](#top) [site](/about/)";
+        assert_eq!(
+            checker.dangling(Path::new("2020/01/02/hello/index.md"), document),
+            vec!["b.png"]
+        );
+    }
+
+    #[test]
+    fn finds_dangling_jekyll_links() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("_posts")).unwrap();
+        std::fs::write(dir.path().join("_posts/2020-01-02-hello.md"), "").unwrap();
+        let options = ExtractOptions {
+            target: Target::Jekyll,
+            ..ExtractOptions::default()
+        };
+        let checker = LinkChecker::new(dir.path(), &options).unwrap();
+
+        let document = "[hi]({% post_url 2020-01-02-hello %}) [bye]({% link _drafts/bye.md %})";
+        assert_eq!(
+            checker.dangling(Path::new("_posts/2020-01-04-post.md"), document),
+            vec!["{% link _drafts/bye.md %}"]
+        );
+    }
+
+    #[test]
+    fn validates_zola_frontmatter() {
+        let page = "+++\ntitle = \"Hello\"\ndate = 2020-01-02T10:00:00Z\n\n[extra]\nid = 1\n\n\
+                    [taxonomies]\ntags = [\"rust\"]\n+++\n\nHi";
+        assert!(validate_frontmatter(page, false, Target::Zola).is_empty());
+        let section = "+++\nsort_by = \"date\"\npaginate_by = 10\n+++\n";
+        assert!(validate_frontmatter(section, true, Target::Zola).is_empty());
+        assert_eq!(
+            validate_frontmatter(section, false, Target::Zola),
+            vec![
+                "unknown key `sort_by`",
+                "unknown key `paginate_by`",
+                "no `title`"
+            ]
+        );
+
+        let duplicate = "+++\ntitle = \"a\"\ntitle = \"b\"\n+++\n";
+        let problems = validate_frontmatter(duplicate, false, Target::Zola);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("invalid toml frontmatter"));
+
+        let bad = "+++\ntitle = 1\ndate = \"yesterday\"\ndraft = \"no\"\n\
+                   [taxonomies]\ntags = \"rust\"\n+++\n";
+        let problems = validate_frontmatter(bad, false, Target::Zola);
+        assert_eq!(
+            problems[..3],
+            [
+                "`date` is not a date: \"yesterday\"",
+                "`title` is not a string: 1",
+                "`draft` is not a boolean: \"no\"",
+            ]
+        );
+        assert!(problems[3].starts_with("`taxonomies` is not a table of arrays of strings"));
+        assert_eq!(problems.len(), 4);
+
+        assert_eq!(
+            validate_frontmatter("+++\ntitle = \"a\"\n", false, Target::Zola),
+            vec!["frontmatter is not closed by `+++`"]
+        );
+        assert_eq!(validate_frontmatter("Hi", false, Target::Zola).len(), 1);
+    }
+
+    #[test]
+    fn finds_duplicate_toml_keys() {
+        assert_eq!(duplicate_key("a = 1\n[extra]\na = 2\nb = 3\n"), None);
+        assert_eq!(
+            duplicate_key("a = 1\n[extra]\nb = 2\n\"b\" = 3\n").as_deref(),
+            Some("b")
+        );
+        assert_eq!(
+            duplicate_key("[extra]\nhead = \"\"\"\na = 1\na = 1\n\"\"\"\na = 2\n"),
+            None
+        );
+        assert_eq!(
+            duplicate_key("[[extra.links]]\nurl = 1\n[[extra.links]]\nurl = 2\n"),
+            None
+        );
+        assert_eq!(duplicate_key("a = 1\nb = 2\na = 3\n").as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn validates_yaml_frontmatter() {
+        let page =
+            "---\ntitle: Hello\ndate: 2020-01-02T10:00:00Z\ntaxonomies:\n  tags:\n  - rust\n---\n";
+        assert!(validate_frontmatter(page, false, Target::Zola).is_empty());
+        let post = "---\nlayout: post\ntitle: Hello\ndate: 2020-01-02T10:00:00Z\n---\n";
+        assert!(validate_frontmatter(post, false, Target::Jekyll).is_empty());
+        assert!(validate_frontmatter("Hi", false, Target::Jekyll).is_empty());
+        let undated = "---\ntitle: Hello\ndate: soon\n---\n";
+        assert_eq!(
+            validate_frontmatter(undated, false, Target::Jekyll),
+            vec!["`date` is not a date: \"soon\""]
+        );
+    }
+
+    #[test]
+    fn finds_drift_from_the_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("ghost.db");
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch(
+                "
+                CREATE TABLE posts (
+                    id integer not null primary key,
+                    title varchar(150) not null,
+                    slug varchar(150) not null,
+                    markdown text null,
+                    status varchar(150) not null default 'draft',
+                    author_id integer not null,
+                    published_at datetime null
+                );
+                CREATE TABLE users (id integer not null primary key, name varchar(150) not null);
+                CREATE TABLE tags (
+                    id integer not null primary key,
+                    name varchar(150) not null,
+                    slug varchar(150) not null default ''
+                );
+                CREATE TABLE posts_tags (
+                    id integer not null primary key autoincrement,
+                    post_id integer not null,
+                    tag_id integer not null,
+                    sort_order integer not null default '0'
+                );
+                INSERT INTO users (id, name) VALUES (1, 'Pete');
+                INSERT INTO tags (id, name, slug) VALUES (1, 'rust', 'rust'), (2, '#hidden', 'hash-hidden');
+                INSERT INTO posts (id, title, slug, status, author_id, published_at) VALUES
+                    (1, 'Hello', 'hello', 'published', 1, '2020-01-02 10:00:00'),
+                    (2, 'Again', 'again', 'published', 1, '2020-01-03 10:00:00'),
+                    (3, 'Gone', 'gone', 'published', 1, '2020-01-04 10:00:00');
+                INSERT INTO posts_tags (post_id, tag_id) VALUES (1, 1), (1, 2), (2, 1);
+                ",
+            )
+            .unwrap();
+
+        let blog = dir.path().join("blog");
+        std::fs::create_dir_all(blog.join("2020")).unwrap();
+        std::fs::write(
+            blog.join("2020/hello.md"),
+            "+++\ntitle = \"Hello\"\ndate = 2020-01-02T12:00:00+02:00\n\n[extra]\nid = 1\n\n\
+             [taxonomies]\ntags = [\"rust\"]\n+++\n",
+        )
+        .unwrap();
+        std::fs::write(
+            blog.join("2020/again.md"),
+            "+++\ntitle = \"Edited\"\ndate = 2020-01-03\n\n[extra]\nid = 2\n+++\n",
+        )
+        .unwrap();
+        std::fs::write(
+            blog.join("2020/old.md"),
+            "+++\ntitle = \"Old\"\n\n[extra]\nid = 4\n+++\n",
+        )
+        .unwrap();
+        std::fs::write(blog.join("_index.md"), "+++\nsort_by = \"date\"\n+++\n").unwrap();
+
+        let drift = verify_extraction(&db_path, &blog, &ExtractOptions::default()).unwrap();
+        let drift: Vec<_> = drift.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            drift,
+            vec![
+                "2020/again.md: title is Edited, but Again in the database",
+                "2020/again.md: taxonomies.tags is unset, but rust in the database",
+                "post 3 (gone) was not extracted",
+                "2020/old.md is post 4, which is not in the database",
+            ]
+        );
+    }
+}